@@ -80,11 +80,30 @@ struct UiBlameMeta {
     agent_version: Option<String>,
 }
 
+#[derive(Serialize)]
+struct UiBlameSpan {
+    /// Character offsets within the line (end exclusive).
+    start: usize,
+    end: usize,
+    meta: Option<UiBlameMeta>,
+}
+
 #[derive(Serialize)]
 struct UiLineBlame {
     line_no: usize,
     text: String,
     meta: Option<UiBlameMeta>,
+    spans: Vec<UiBlameSpan>,
+}
+
+fn to_ui_meta(m: ai_blame::blame::BlameMeta) -> UiBlameMeta {
+    UiBlameMeta {
+        timestamp: m.timestamp.to_rfc3339(),
+        model: m.model,
+        session_id: m.session_id,
+        agent_tool: m.agent_tool,
+        agent_version: m.agent_version,
+    }
 }
 
 #[derive(Serialize)]
@@ -110,6 +129,7 @@ struct UiTranscriptSummary {
     message_count: usize,
     files_touched: usize,
     primary_model: Option<String>,
+    cost: Option<f64>,
     source_file: String,
 }
 
@@ -259,6 +279,15 @@ fn resolve_trace_dir_for_project(project_dir: Option<PathBuf>) -> PathBuf {
     ai_blame::paths::resolve_claude_trace_dir(&resolved_home, &resolved_target)
 }
 
+/// Prices from the project's `.ai-blame.yaml`, or the built-in ones without a project.
+fn load_prices(project_dir: Option<&Path>) -> Result<ai_blame::pricing::PriceTable, String> {
+    match project_dir {
+        Some(dir) => ai_blame::pricing::PriceTable::load(Some(dir))
+            .map_err(|e| format!("Failed to load prices: {e}")),
+        None => Ok(ai_blame::pricing::PriceTable::builtin()),
+    }
+}
+
 fn should_skip_dir(name: &str) -> bool {
     matches!(
         name,
@@ -435,19 +464,28 @@ async fn blame_file(project_dir: String, file_path: String) -> Result<BlameFileR
 
         let blamed = ai_blame::blame::compute_line_blame(&current_content, &edits_for_file)
             .map_err(|e| format!("Failed to compute blame for {file_path}: {e}"))?;
+        let spans = ai_blame::blame::compute_span_blame(
+            &current_content,
+            &edits_for_file,
+            ai_blame::blame::SpanGranularity::Word,
+        )
+        .map_err(|e| format!("Failed to compute blame for {file_path}: {e}"))?;
 
         let lines: Vec<UiLineBlame> = blamed
             .into_iter()
-            .map(|l| UiLineBlame {
+            .zip(spans)
+            .map(|(l, spans)| UiLineBlame {
                 line_no: l.line_no,
                 text: l.text,
-                meta: l.meta.map(|m| UiBlameMeta {
-                    timestamp: m.timestamp.to_rfc3339(),
-                    model: m.model,
-                    session_id: m.session_id,
-                    agent_tool: m.agent_tool,
-                    agent_version: m.agent_version,
-                }),
+                meta: l.meta.map(to_ui_meta),
+                spans: spans
+                    .into_iter()
+                    .map(|s| UiBlameSpan {
+                        start: s.start,
+                        end: s.end,
+                        meta: s.meta.map(to_ui_meta),
+                    })
+                    .collect(),
             })
             .collect();
 
@@ -477,6 +515,7 @@ fn convert_content_block(block: &ai_blame::transcript::ContentBlock) -> UiConten
             tool_use_id,
             content,
             is_error,
+            ..
         } => UiContentBlock::ToolResult {
             tool_use_id: tool_use_id.clone(),
             content: content.clone(),
@@ -620,6 +659,7 @@ async fn list_transcripts(
         let project_dir = project_dir
             .and_then(|s| if s.trim().is_empty() { None } else { Some(s) })
             .map(PathBuf::from);
+        let prices = load_prices(project_dir.as_deref())?;
         let trace_dir = resolve_trace_dir_for_project(project_dir);
 
         let transcripts = ai_blame::transcript::parse_transcripts_from_directory(&trace_dir)
@@ -632,7 +672,7 @@ async fn list_transcripts(
         let summaries: Vec<UiTranscriptSummary> = display_transcripts
             .iter()
             .map(|t| {
-                let summary = t.summary();
+                let summary = t.summary(&prices);
                 UiTranscriptSummary {
                     session_id: summary.session_id,
                     agent_tool: summary.agent_tool,
@@ -642,6 +682,7 @@ async fn list_transcripts(
                     message_count: summary.message_count,
                     files_touched: summary.files_touched,
                     primary_model: summary.primary_model,
+                    cost: summary.cost,
                     source_file: summary.source_file,
                 }
             })
@@ -692,11 +733,23 @@ fn find_transcript_by_session(
 async fn get_transcript(
     session_or_path: String,
     project_dir: Option<String>,
+    redact: Option<bool>,
 ) -> Result<UiTranscript, String> {
     // Move CPU-bound work to a blocking thread
     tauri::async_runtime::spawn_blocking(move || {
+        // Secrets are redacted unless the caller asks for the raw transcript
+        let redactor = if redact.unwrap_or(true) {
+            let start_dir = project_dir.as_deref().map(PathBuf::from);
+            Some(
+                ai_blame::redact::Redactor::load(start_dir.as_deref())
+                    .map_err(|e| format!("Failed to load redaction config: {e:#}"))?,
+            )
+        } else {
+            None
+        };
+
         // First try to interpret as a direct file path (with validation)
-        let transcript =
+        let mut transcript =
             if let Ok(safe_path) = ai_blame::utils::validate_safe_path(&session_or_path) {
                 if safe_path.exists() {
                     ai_blame::transcript::parse_transcript(&safe_path)
@@ -710,6 +763,9 @@ async fn get_transcript(
                 find_transcript_by_session(&session_or_path, project_dir)?
             };
 
+        if let Some(redactor) = redactor {
+            redactor.redact_transcript(&mut transcript);
+        }
         Ok(convert_transcript(transcript))
     })
     .await
@@ -717,12 +773,21 @@ async fn get_transcript(
 }
 
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 async fn search_transcripts(
     project_dir: Option<String>,
     query: Option<String>,
     session_id_pattern: Option<String>,
     agent_tool: Option<String>,
     model: Option<String>,
+    touched_file: Option<String>,
+    used_tool: Option<String>,
+    failed_command: Option<bool>,
+    tool_error: Option<bool>,
+    git_branch: Option<String>,
+    cwd: Option<String>,
+    min_tokens: Option<u64>,
+    max_tokens: Option<u64>,
     limit: Option<usize>,
 ) -> Result<SearchTranscriptsResult, String> {
     // Move CPU-bound work to a blocking thread
@@ -730,6 +795,7 @@ async fn search_transcripts(
         let project_dir = project_dir
             .and_then(|s| if s.trim().is_empty() { None } else { Some(s) })
             .map(PathBuf::from);
+        let prices = load_prices(project_dir.as_deref())?;
         let trace_dir = resolve_trace_dir_for_project(project_dir);
 
         let criteria = ai_blame::transcript::TranscriptSearchCriteria {
@@ -741,11 +807,23 @@ async fn search_transcripts(
             model: model.filter(|s| !s.is_empty()),
             since: None,
             until: None,
+            touched_file: touched_file.filter(|s| !s.is_empty()),
+            used_tool: used_tool.filter(|s| !s.is_empty()),
+            failed_command: failed_command.unwrap_or(false),
+            tool_error: tool_error.unwrap_or(false),
+            git_branch: git_branch.filter(|s| !s.is_empty()),
+            cwd: cwd.filter(|s| !s.is_empty()),
+            min_tokens,
+            max_tokens,
         };
 
-        let search_result =
-            ai_blame::transcript::search_transcripts(&trace_dir, &criteria, limit.unwrap_or(50))
-                .map_err(|e| format!("Search failed: {e}"))?;
+        let search_result = ai_blame::transcript::search_transcripts(
+            &trace_dir,
+            &criteria,
+            limit.unwrap_or(50),
+            &prices,
+        )
+        .map_err(|e| format!("Search failed: {e}"))?;
 
         let ui_matches: Vec<UiSearchMatch> = search_result
             .matching_transcripts
@@ -761,6 +839,7 @@ async fn search_transcripts(
                     message_count: summary.message_count,
                     files_touched: summary.files_touched,
                     primary_model: summary.primary_model.clone(),
+                    cost: summary.cost,
                     source_file: summary.source_file.clone(),
                 };
                 let ui_snippets: Vec<UiMatchSnippet> = search_match
@@ -0,0 +1,194 @@
+//! Snapshots of the files an `annotate` run writes, so the run can be undone.
+//!
+//! Each run gets its own directory under `.ai-blame/backup/<run-id>/` holding a
+//! `manifest.json` and a copy of every file as it was before the run touched it.
+
+use crate::utils::write_atomic;
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+/// Backup location, relative to the project root.
+pub const BACKUP_DIR: &str = ".ai-blame/backup";
+
+const MANIFEST_FILE: &str = "manifest.json";
+const FILES_DIR: &str = "files";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupEntry {
+    /// Path of the written file, relative to the project root when it lives inside it.
+    pub path: PathBuf,
+    /// Whether the file existed before the run (new sidecars are removed on undo).
+    pub existed: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupManifest {
+    pub run_id: String,
+    pub created_at: DateTime<Utc>,
+    #[serde(default)]
+    pub entries: Vec<BackupEntry>,
+}
+
+/// Outcome of [`undo_run`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct UndoSummary {
+    /// Files restored to their pre-run contents.
+    pub restored: usize,
+    /// Files created by the run and deleted again.
+    pub removed: usize,
+}
+
+/// An in-progress backup for a single `annotate` run.
+pub struct BackupRun {
+    root: PathBuf,
+    dir: PathBuf,
+    manifest: BackupManifest,
+}
+
+fn absolute(path: &Path) -> Result<PathBuf> {
+    if path.is_absolute() {
+        Ok(path.to_path_buf())
+    } else {
+        Ok(std::env::current_dir()?.join(path))
+    }
+}
+
+/// Where a file's copy lives inside the run directory (absolute paths lose their root).
+fn stored_path(path: &Path) -> PathBuf {
+    path.components()
+        .filter(|c| matches!(c, Component::Normal(_)))
+        .collect()
+}
+
+fn run_dir(root: &Path, run_id: &str) -> Result<PathBuf> {
+    let mut components = Path::new(run_id).components();
+    if !matches!(
+        (components.next(), components.next()),
+        (Some(Component::Normal(_)), None)
+    ) {
+        bail!("Invalid backup run id: {:?}", run_id);
+    }
+    Ok(root.join(BACKUP_DIR).join(run_id))
+}
+
+impl BackupRun {
+    /// Start a new backup run under `{root}/.ai-blame/backup/`.
+    ///
+    /// Run ids are UTC timestamps (`20251201T080342Z`), suffixed when two runs share a second.
+    pub fn create(root: &Path) -> Result<Self> {
+        let root = absolute(root)?;
+        let created_at = Utc::now();
+        let base_id = created_at.format("%Y%m%dT%H%M%SZ").to_string();
+
+        let mut run_id = base_id.clone();
+        let mut suffix = 1;
+        while run_dir(&root, &run_id)?.exists() {
+            suffix += 1;
+            run_id = format!("{}-{}", base_id, suffix);
+        }
+
+        let dir = run_dir(&root, &run_id)?;
+        fs::create_dir_all(&dir)
+            .with_context(|| format!("Failed to create backup directory: {:?}", dir))?;
+
+        let run = Self {
+            root,
+            dir,
+            manifest: BackupManifest {
+                run_id,
+                created_at,
+                entries: Vec::new(),
+            },
+        };
+        run.save_manifest()?;
+        Ok(run)
+    }
+
+    pub fn run_id(&self) -> &str {
+        &self.manifest.run_id
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Record `path` before it is written. Copies it if it exists; snapshotting the same
+    /// path twice keeps the first (original) copy.
+    pub fn snapshot(&mut self, path: &Path) -> Result<()> {
+        let abs = absolute(path)?;
+        let rel = abs.strip_prefix(&self.root).unwrap_or(&abs).to_path_buf();
+        if self.manifest.entries.iter().any(|e| e.path == rel) {
+            return Ok(());
+        }
+
+        let existed = abs.is_file();
+        if existed {
+            let copy = self.dir.join(FILES_DIR).join(stored_path(&rel));
+            if let Some(parent) = copy.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::copy(&abs, &copy)
+                .with_context(|| format!("Failed to back up {:?} to {:?}", abs, copy))?;
+        }
+
+        self.manifest
+            .entries
+            .push(BackupEntry { path: rel, existed });
+        // Saved after every file so an interrupted run can still be undone.
+        self.save_manifest()
+    }
+
+    fn save_manifest(&self) -> Result<()> {
+        let path = self.dir.join(MANIFEST_FILE);
+        write_atomic(&path, serde_json::to_string_pretty(&self.manifest)?)
+            .with_context(|| format!("Failed to write backup manifest: {:?}", path))
+    }
+}
+
+/// Load the manifest of a previous run.
+pub fn load_manifest(root: &Path, run_id: &str) -> Result<BackupManifest> {
+    let path = run_dir(root, run_id)?.join(MANIFEST_FILE);
+    if !path.exists() {
+        bail!(
+            "No backup found for run {:?} (looked in {:?})",
+            run_id,
+            path
+        );
+    }
+    let content = fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read backup manifest: {:?}", path))?;
+    serde_json::from_str(&content)
+        .with_context(|| format!("Failed to parse backup manifest: {:?}", path))
+}
+
+/// Restore every file written by run `run_id` to its pre-run state.
+pub fn undo_run(root: &Path, run_id: &str) -> Result<UndoSummary> {
+    let root = absolute(root)?;
+    let manifest = load_manifest(&root, run_id)?;
+    let dir = run_dir(&root, run_id)?;
+    let mut summary = UndoSummary::default();
+
+    for entry in &manifest.entries {
+        let target = root.join(&entry.path);
+        if entry.existed {
+            let copy = dir.join(FILES_DIR).join(stored_path(&entry.path));
+            let content = fs::read(&copy)
+                .with_context(|| format!("Failed to read backup copy: {:?}", copy))?;
+            if let Some(parent) = target.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            write_atomic(&target, content)
+                .with_context(|| format!("Failed to restore file: {:?}", target))?;
+            summary.restored += 1;
+        } else if target.exists() {
+            fs::remove_file(&target)
+                .with_context(|| format!("Failed to remove file: {:?}", target))?;
+            summary.removed += 1;
+        }
+    }
+
+    Ok(summary)
+}
@@ -1,10 +1,14 @@
+use crate::cache::EditsByFile;
+use crate::diff::{align, Step};
 use crate::models::EditRecord;
 use anyhow::Result;
 use chrono::{DateTime, Utc};
 use regex::Regex;
+use serde::Serialize;
+use std::collections::HashMap;
 use std::sync::OnceLock;
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
 pub struct BlameMeta {
     pub timestamp: DateTime<Utc>,
     pub model: String,
@@ -20,6 +24,27 @@ pub struct LineBlame {
     pub text: String,
     /// Attribution for this line (None means unknown/unattributed).
     pub meta: Option<BlameMeta>,
+    /// Set by [`detect_moves`] when the line was moved or copied from elsewhere; `meta`
+    /// then points at the original author.
+    pub moved: Option<MoveInfo>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MoveKind {
+    /// Cut from another place (the source edit removed it).
+    Moved,
+    /// Duplicated from text an earlier edit wrote.
+    Copied,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct MoveInfo {
+    pub kind: MoveKind,
+    /// The edit that pasted the block.
+    pub by: BlameMeta,
+    /// File the block came from (as recorded in the trace).
+    pub from_file: String,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -90,6 +115,7 @@ fn to_meta(edit: &EditRecord) -> BlameMeta {
 
 /// Compute per-line blame for `current_content` using edits (typically from traces) via a
 /// reverse-apply strategy. This approximates `git blame` for the current working tree.
+/// Notebook cell edits are ignored.
 pub fn compute_line_blame(current_content: &str, edits: &[EditRecord]) -> Result<Vec<LineBlame>> {
     let original_lines = normalize_lines(current_content);
     let mut blame: Vec<Option<BlameMeta>> = vec![None; original_lines.len()];
@@ -99,7 +125,7 @@ pub fn compute_line_blame(current_content: &str, edits: &[EditRecord]) -> Result
     let mut mapping: Vec<Option<usize>> = (0..original_lines.len()).map(Some).collect();
 
     // Process newest -> oldest.
-    let mut edits_desc: Vec<&EditRecord> = edits.iter().collect();
+    let mut edits_desc: Vec<&EditRecord> = edits.iter().filter(|e| !e.is_cell_edit()).collect();
     edits_desc.sort_by_key(|e| e.timestamp);
     edits_desc.reverse();
 
@@ -163,6 +189,7 @@ pub fn compute_line_blame(current_content: &str, edits: &[EditRecord]) -> Result
             line_no: i + 1,
             text,
             meta: blame[i].clone(),
+            moved: None,
         })
         .collect())
 }
@@ -195,3 +222,434 @@ pub fn group_blocks(lines: &[LineBlame]) -> Vec<BlameBlock> {
     });
     blocks
 }
+
+/// A line that is gone from the file, with the edit that removed it (reverse blame).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineRemoval {
+    /// 1-based line number in the content it was looked up from (snippet or git revision).
+    pub line_no: usize,
+    pub text: String,
+    /// The most recent edit whose `old_string` dropped this line (None if not found).
+    pub meta: Option<BlameMeta>,
+}
+
+fn count_trimmed(lines: &[String], needle: &str) -> usize {
+    lines.iter().filter(|l| l.trim() == needle).count()
+}
+
+/// Lines of `old_content` that no longer appear in `current_content`, with their
+/// 1-based line numbers. Lines are compared ignoring indentation; blank lines are skipped.
+pub fn removed_lines(old_content: &str, current_content: &str) -> Vec<(usize, String)> {
+    let mut remaining: HashMap<String, usize> = HashMap::new();
+    for line in normalize_lines(current_content) {
+        *remaining.entry(line.trim().to_string()).or_default() += 1;
+    }
+
+    let mut removed = Vec::new();
+    for (i, line) in normalize_lines(old_content).into_iter().enumerate() {
+        let key = line.trim();
+        if key.is_empty() {
+            continue;
+        }
+        match remaining.get_mut(key) {
+            Some(n) if *n > 0 => *n -= 1,
+            _ => removed.push((i + 1, line)),
+        }
+    }
+    removed
+}
+
+/// Reverse blame: for each line, find the most recent edit that removed it, i.e. whose
+/// `old_string` contained the line more often than its `new_string` (ignoring indentation).
+pub fn compute_reverse_blame(lines: &[(usize, String)], edits: &[EditRecord]) -> Vec<LineRemoval> {
+    let mut edits_desc: Vec<&EditRecord> = edits
+        .iter()
+        .filter(|e| !e.is_create && !e.is_cell_edit())
+        .collect();
+    edits_desc.sort_by_key(|e| std::cmp::Reverse(e.timestamp));
+
+    let normalized: Vec<(Vec<String>, Vec<String>)> = edits_desc
+        .iter()
+        .map(|e| {
+            (
+                normalize_lines(e.old_string.as_deref().unwrap_or("")),
+                normalize_lines(e.new_string.as_deref().unwrap_or("")),
+            )
+        })
+        .collect();
+
+    lines
+        .iter()
+        .map(|(line_no, text)| {
+            let needle = text.trim();
+            let meta = if needle.is_empty() {
+                None
+            } else {
+                edits_desc
+                    .iter()
+                    .zip(&normalized)
+                    .find(|(_, (old, new))| count_trimmed(old, needle) > count_trimmed(new, needle))
+                    .map(|(edit, _)| to_meta(edit))
+            };
+            LineRemoval {
+                line_no: *line_no,
+                text: text.clone(),
+                meta,
+            }
+        })
+        .collect()
+}
+
+/// Default minimum block size (non-blank lines) for [`detect_moves`].
+pub const DEFAULT_MOVE_MIN_LINES: usize = 3;
+
+fn trimmed_lines(s: Option<&str>) -> Vec<String> {
+    normalize_lines(s.unwrap_or(""))
+        .into_iter()
+        .map(|l| l.trim().to_string())
+        .collect()
+}
+
+fn contains_block(haystack: &[String], block: &[String]) -> bool {
+    !block.is_empty()
+        && haystack.len() >= block.len()
+        && haystack.windows(block.len()).any(|w| w == block)
+}
+
+struct BlockSource<'a> {
+    edit: &'a EditRecord,
+    /// Lines the edit replaced (its `old_string`).
+    removed: Vec<String>,
+    /// Lines the edit wrote (its `new_string`, or the created content).
+    written: Vec<String>,
+}
+
+fn same_edit(a: &EditRecord, b: &EditRecord) -> bool {
+    a.timestamp == b.timestamp && a.session_id == b.session_id && a.file_path == b.file_path
+}
+
+/// Where `block` (pasted by `mover`) came from: the original author (if known), how it
+/// got here, and the file it came from.
+fn find_block_origin(
+    block: &[String],
+    mover: &EditRecord,
+    sources: &[BlockSource],
+) -> Option<(Option<BlameMeta>, MoveKind, String)> {
+    let others = || sources.iter().filter(|s| !same_edit(s.edit, mover));
+
+    // Moved: another edit cut the block (it's in its old_string but not its new_string).
+    // The cut and the paste can happen in either order, so take the one nearest in time.
+    if let Some(cut) = others()
+        .filter(|s| contains_block(&s.removed, block) && !contains_block(&s.written, block))
+        .min_by_key(|s| (s.edit.timestamp - mover.timestamp).num_seconds().abs())
+    {
+        let before = cut.edit.timestamp.min(mover.timestamp);
+        let author = others()
+            .filter(|s| s.edit.timestamp < before && contains_block(&s.written, block))
+            .max_by_key(|s| s.edit.timestamp)
+            .map(|s| to_meta(s.edit));
+        return Some((author, MoveKind::Moved, cut.edit.file_path.clone()));
+    }
+
+    // Copied: an earlier edit wrote the same block, and it is still there.
+    others()
+        .filter(|s| s.edit.timestamp < mover.timestamp && contains_block(&s.written, block))
+        .max_by_key(|s| s.edit.timestamp)
+        .map(|s| {
+            (
+                Some(to_meta(s.edit)),
+                MoveKind::Copied,
+                s.edit.file_path.clone(),
+            )
+        })
+}
+
+/// Move/copy detection, like `git blame -M -C`.
+///
+/// For lines blamed on an edit that pasted a block (at least `min_lines` non-blank lines)
+/// which another edit — in this file or any file in `sources` — cut or wrote earlier,
+/// restore the original attribution and record the pasting edit in `moved`. Text that was
+/// already in the pasting edit's own `old_string` is left alone (unchanged context).
+pub fn detect_moves(
+    lines: &mut [LineBlame],
+    edits: &[EditRecord],
+    sources: &EditsByFile,
+    min_lines: usize,
+) {
+    let min_lines = min_lines.max(1);
+    let block_sources: Vec<BlockSource> = edits
+        .iter()
+        .chain(sources.values().flatten())
+        .filter(|edit| !edit.is_cell_edit())
+        .map(|edit| BlockSource {
+            edit,
+            removed: trimmed_lines(edit.old_string.as_deref()),
+            written: trimmed_lines(
+                edit.new_string
+                    .as_deref()
+                    .or(edit.create_content.as_deref()),
+            ),
+        })
+        .collect();
+
+    let mut i = 0;
+    while i < lines.len() {
+        let Some(meta) = lines[i].meta.clone() else {
+            i += 1;
+            continue;
+        };
+        let mut end = i;
+        while end < lines.len() && lines[end].meta.as_ref() == Some(&meta) {
+            end += 1;
+        }
+        let Some(mover) = edits.iter().find(|e| to_meta(e) == meta) else {
+            i = end;
+            continue;
+        };
+        let mover_removed = trimmed_lines(mover.old_string.as_deref());
+
+        let trimmed: Vec<String> = lines[i..end]
+            .iter()
+            .map(|l| l.text.trim().to_string())
+            .collect();
+        let mut k = 0;
+        while k < trimmed.len() {
+            if trimmed[k].is_empty() {
+                k += 1;
+                continue;
+            }
+            // Smallest block starting here with `min_lines` non-blank lines...
+            let mut len = 0;
+            let mut non_blank = 0;
+            while k + len < trimmed.len() && non_blank < min_lines {
+                if !trimmed[k + len].is_empty() {
+                    non_blank += 1;
+                }
+                len += 1;
+            }
+            let found = if non_blank < min_lines {
+                None
+            } else {
+                find_block_origin(&trimmed[k..k + len], mover, &block_sources)
+            };
+            let Some(mut origin) =
+                found.filter(|_| !contains_block(&mover_removed, &trimmed[k..k + len]))
+            else {
+                k += 1;
+                continue;
+            };
+            // ...then grow it while it still has the same origin.
+            while k + len < trimmed.len() {
+                match find_block_origin(&trimmed[k..=k + len], mover, &block_sources) {
+                    Some(next) if next == origin => {
+                        len += 1;
+                        origin = next;
+                    }
+                    _ => break,
+                }
+            }
+
+            let (author, kind, from_file) = origin;
+            for line in &mut lines[i + k..i + k + len] {
+                line.meta = author.clone();
+                line.moved = Some(MoveInfo {
+                    kind,
+                    by: meta.clone(),
+                    from_file: from_file.clone(),
+                });
+            }
+            k += len;
+        }
+        i = end;
+    }
+}
+
+/// How finely [`compute_span_blame`] attributes text within a line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpanGranularity {
+    /// Identifiers/numbers, whitespace runs and single punctuation characters.
+    Word,
+    /// Single characters.
+    Char,
+}
+
+/// A run of characters within one line attributed to the same edit.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BlameSpan {
+    /// 0-based character (not byte) offset of the first character in the line.
+    pub start: usize,
+    /// Character offset one past the last character.
+    pub end: usize,
+    pub meta: Option<BlameMeta>,
+}
+
+/// Split `chars` into tokens, returned as character lengths.
+fn token_lengths(chars: &[char], granularity: SpanGranularity) -> Vec<usize> {
+    // 0 = punctuation/newline (always a token on its own), 1 = word, 2 = inline whitespace
+    let class = |c: char| {
+        if c.is_alphanumeric() || c == '_' {
+            1
+        } else if c.is_whitespace() && c != '\n' {
+            2
+        } else {
+            0
+        }
+    };
+    let mut lengths = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let mut len = 1;
+        let kind = class(chars[i]);
+        if granularity == SpanGranularity::Word && kind != 0 {
+            while i + len < chars.len() && class(chars[i + len]) == kind {
+                len += 1;
+            }
+        }
+        lengths.push(len);
+        i += len;
+    }
+    lengths
+}
+
+fn tokens(chars: &[char], granularity: SpanGranularity) -> Vec<(usize, &[char])> {
+    let mut start = 0;
+    token_lengths(chars, granularity)
+        .into_iter()
+        .map(|len| {
+            let token = (start, &chars[start..start + len]);
+            start += len;
+            token
+        })
+        .collect()
+}
+
+fn find_char_run(haystack: &[char], needle: &[char], hint_line: Option<usize>) -> Option<usize> {
+    if needle.is_empty() || haystack.len() < needle.len() {
+        return None;
+    }
+    let hint = hint_line.map(|l| l.saturating_sub(1));
+    let mut best: Option<(usize, usize)> = None;
+    let mut line = 0usize;
+    for i in 0..=(haystack.len() - needle.len()) {
+        if i > 0 && haystack[i - 1] == '\n' {
+            line += 1;
+        }
+        if haystack[i..i + needle.len()] != *needle {
+            continue;
+        }
+        let Some(hint) = hint else {
+            return Some(i);
+        };
+        let distance = line.abs_diff(hint);
+        if best.is_none_or(|(_, d)| distance < d) {
+            best = Some((i, distance));
+        }
+    }
+    best.map(|(i, _)| i)
+}
+
+fn chars_of(s: &str) -> Vec<char> {
+    s.replace("\r\n", "\n").chars().collect()
+}
+
+/// Attribute spans within each line of `current_content` to the edits that wrote them.
+///
+/// Works like [`compute_line_blame`], but each edit's `old_string` and `new_string` are
+/// diffed at `granularity`, so an edit only claims the text it actually changed; the rest
+/// of the snippet is left for older edits. Returns one list of spans per line (in the same
+/// order as `compute_line_blame`), covering the whole line.
+pub fn compute_span_blame(
+    current_content: &str,
+    edits: &[EditRecord],
+    granularity: SpanGranularity,
+) -> Result<Vec<Vec<BlameSpan>>> {
+    let normalized = current_content.replace("\r\n", "\n");
+    let original: Vec<char> = normalized.trim_end_matches('\n').chars().collect();
+    let mut blame: Vec<Option<BlameMeta>> = vec![None; original.len()];
+
+    // Working characters and a mapping from working index -> original character index.
+    let mut working = original.clone();
+    let mut mapping: Vec<Option<usize>> = (0..original.len()).map(Some).collect();
+
+    let mut edits_desc: Vec<&EditRecord> = edits.iter().filter(|e| !e.is_cell_edit()).collect();
+    edits_desc.sort_by_key(|e| e.timestamp);
+    edits_desc.reverse();
+
+    for edit in edits_desc {
+        let meta = to_meta(edit);
+
+        if edit.is_create {
+            for slot in blame.iter_mut() {
+                if slot.is_none() {
+                    *slot = Some(meta.clone());
+                }
+            }
+            break;
+        }
+
+        let new_chars = match edit.new_string.as_deref() {
+            Some(s) if !s.trim().is_empty() => chars_of(s),
+            _ => continue,
+        };
+        let hint_line = parse_new_start_line(&edit.structured_patch);
+        let Some(start) = find_char_run(&working, &new_chars, hint_line) else {
+            continue;
+        };
+        let old_chars = chars_of(edit.old_string.as_deref().unwrap_or(""));
+
+        let region = mapping[start..start + new_chars.len()].to_vec();
+        let mut old_mapping = vec![None; old_chars.len()];
+        let old_tokens = tokens(&old_chars, granularity);
+        let new_tokens = tokens(&new_chars, granularity);
+        let old_text: Vec<&[char]> = old_tokens.iter().map(|(_, t)| *t).collect();
+        let new_text: Vec<&[char]> = new_tokens.iter().map(|(_, t)| *t).collect();
+        for step in align(&old_text, &new_text) {
+            match step {
+                Step::Same(i, j) => {
+                    let (old_at, token) = old_tokens[i];
+                    let new_at = new_tokens[j].0;
+                    old_mapping[old_at..old_at + token.len()]
+                        .copy_from_slice(&region[new_at..new_at + token.len()]);
+                }
+                Step::Added(j) => {
+                    let (new_at, token) = new_tokens[j];
+                    for orig in region[new_at..new_at + token.len()].iter().flatten() {
+                        if blame[*orig].is_none() {
+                            blame[*orig] = Some(meta.clone());
+                        }
+                    }
+                }
+                Step::Removed(_) => {}
+            }
+        }
+
+        // Reverse-apply: the unchanged tokens carry their mapping back into old_string.
+        working.splice(start..start + new_chars.len(), old_chars);
+        mapping.splice(start..start + new_chars.len(), old_mapping);
+    }
+
+    let mut lines: Vec<Vec<BlameSpan>> = if original.is_empty() {
+        Vec::new()
+    } else {
+        vec![Vec::new()]
+    };
+    let mut col = 0;
+    for (c, meta) in original.iter().zip(blame) {
+        if *c == '\n' {
+            lines.push(Vec::new());
+            col = 0;
+            continue;
+        }
+        let spans = lines.last_mut().expect("at least one line");
+        match spans.last_mut() {
+            Some(span) if span.meta == meta => span.end = col + 1,
+            _ => spans.push(BlameSpan {
+                start: col,
+                end: col + 1,
+                meta,
+            }),
+        }
+        col += 1;
+    }
+    Ok(lines)
+}
@@ -0,0 +1,271 @@
+//! Versioned schema migrations for the DuckDB cache.
+//!
+//! Each [`Migration`] upgrades the schema by exactly one version and runs in its own
+//! transaction together with the version bump, so an interrupted upgrade never leaves a
+//! half-migrated cache behind. Caches that cannot be migrated (written by a newer release,
+//! missing their version, or failing a step) are deleted and rebuilt from scratch by
+//! [`CacheManager::open`](super::CacheManager::open); the cache only holds derived data, so
+//! rebuilding costs a reparse and nothing else.
+
+use super::types::SchemaUpgrade;
+use anyhow::{anyhow, Context, Result};
+use duckdb::Connection;
+
+/// Schema version written by this release.
+pub const CACHE_VERSION: u32 = 2;
+
+/// One schema step, upgrading the cache from `version - 1` to `version`.
+struct Migration {
+    version: u32,
+    description: &'static str,
+    up: fn(&Connection) -> Result<()>,
+}
+
+/// All migrations in version order. Append a new entry (and bump [`CACHE_VERSION`]) to
+/// change the schema; never edit a step that has shipped.
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "trace_files and edit_records tables",
+        up: create_initial_schema,
+    },
+    Migration {
+        version: 2,
+        description: "edit record columns, parse_state and transcript tables",
+        up: add_parse_state_and_transcripts,
+    },
+];
+
+/// What the version row of an existing database says.
+enum StoredVersion {
+    /// No tables at all: a freshly created database file.
+    Empty,
+    Known(u32),
+    /// Tables exist but the version is missing or not a number.
+    Unreadable(String),
+}
+
+/// Bring the schema up to [`CACHE_VERSION`].
+///
+/// Returns an error when the cache cannot be migrated; the caller rebuilds it from an empty
+/// database in that case.
+pub(crate) fn migrate(conn: &Connection) -> Result<SchemaUpgrade> {
+    match stored_version(conn)? {
+        StoredVersion::Empty => {
+            apply(conn, 0)?;
+            Ok(SchemaUpgrade::Created(CACHE_VERSION))
+        }
+        StoredVersion::Known(v) if v == CACHE_VERSION => Ok(SchemaUpgrade::UpToDate(v)),
+        StoredVersion::Known(v) if v > CACHE_VERSION => Err(anyhow!(
+            "cache version {} is newer than supported version {}",
+            v,
+            CACHE_VERSION
+        )),
+        StoredVersion::Known(v) => {
+            apply(conn, v)?;
+            Ok(SchemaUpgrade::Migrated {
+                from: v,
+                to: CACHE_VERSION,
+            })
+        }
+        StoredVersion::Unreadable(reason) => Err(anyhow!(reason)),
+    }
+}
+
+/// Read the schema version recorded in `cache_metadata`.
+fn stored_version(conn: &Connection) -> Result<StoredVersion> {
+    let version: Option<String> = conn
+        .query_row(
+            "SELECT value FROM cache_metadata WHERE key = 'version'",
+            [],
+            |row| row.get(0),
+        )
+        .ok();
+
+    if let Some(v) = version {
+        return Ok(match v.trim().parse::<u32>() {
+            Ok(n) => StoredVersion::Known(n),
+            Err(_) => StoredVersion::Unreadable(format!("unrecognized cache version {:?}", v)),
+        });
+    }
+
+    if user_objects(conn)? == 0 {
+        Ok(StoredVersion::Empty)
+    } else {
+        Ok(StoredVersion::Unreadable(
+            "cache has no version record".to_string(),
+        ))
+    }
+}
+
+/// Run every migration newer than `from`, each in its own transaction.
+fn apply(conn: &Connection, from: u32) -> Result<()> {
+    for migration in MIGRATIONS.iter().filter(|m| m.version > from) {
+        conn.execute("BEGIN TRANSACTION", [])?;
+        let step = (migration.up)(conn).and_then(|()| {
+            conn.execute(
+                "INSERT OR REPLACE INTO cache_metadata (key, value) VALUES ('version', ?1)",
+                [migration.version.to_string()],
+            )?;
+            Ok(())
+        });
+        if let Err(e) = step {
+            let _ = conn.execute("ROLLBACK", []);
+            return Err(e).with_context(|| {
+                format!(
+                    "Cache migration to version {} ({}) failed",
+                    migration.version, migration.description
+                )
+            });
+        }
+        conn.execute("COMMIT", [])?;
+    }
+    Ok(())
+}
+
+/// Number of tables and views in the main schema.
+fn user_objects(conn: &Connection) -> Result<i64> {
+    Ok(conn.query_row(
+        "SELECT COUNT(*) FROM information_schema.tables
+         WHERE table_catalog = current_database() AND table_schema = 'main'",
+        [],
+        |row| row.get(0),
+    )?)
+}
+
+fn create_initial_schema(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS cache_metadata (
+            key TEXT PRIMARY KEY,
+            value TEXT NOT NULL
+        );
+
+        CREATE TABLE IF NOT EXISTS trace_files (
+            file_path TEXT PRIMARY KEY,
+            provider TEXT NOT NULL,
+            file_mtime_ns TEXT NOT NULL,
+            file_size_bytes TEXT NOT NULL,
+            last_parsed_at TEXT NOT NULL,
+            record_count INTEGER NOT NULL DEFAULT 0,
+            parse_duration_ms INTEGER
+        );
+        CREATE INDEX IF NOT EXISTS idx_trace_files_provider ON trace_files(provider);
+        CREATE INDEX IF NOT EXISTS idx_trace_files_mtime ON trace_files(file_mtime_ns);
+
+        CREATE TABLE IF NOT EXISTS edit_records (
+            trace_file_path TEXT NOT NULL,
+            file_path TEXT NOT NULL,
+            timestamp TEXT NOT NULL,
+            model TEXT NOT NULL,
+            session_id TEXT NOT NULL,
+            is_create BOOLEAN NOT NULL,
+            change_size INTEGER NOT NULL,
+            agent_tool TEXT NOT NULL,
+            agent_version TEXT,
+            old_string TEXT,
+            new_string TEXT,
+            structured_patch TEXT,
+            create_content TEXT
+        );
+        CREATE INDEX IF NOT EXISTS idx_edits_file_path ON edit_records(file_path);
+        CREATE INDEX IF NOT EXISTS idx_edits_timestamp ON edit_records(timestamp);
+        CREATE INDEX IF NOT EXISTS idx_edits_session ON edit_records(session_id);
+        CREATE INDEX IF NOT EXISTS idx_edits_trace_file ON edit_records(trace_file_path);",
+    )?;
+    Ok(())
+}
+
+fn add_parse_state_and_transcripts(conn: &Connection) -> Result<()> {
+    // Cached edits predate notebook edits, subagent IDs, diff hunks and replace_all; parse
+    // the traces again.
+    conn.execute_batch(
+        "ALTER TABLE edit_records ADD COLUMN IF NOT EXISTS cell_id TEXT;
+        ALTER TABLE edit_records ADD COLUMN IF NOT EXISTS agent_id TEXT;
+        ALTER TABLE edit_records ADD COLUMN IF NOT EXISTS replace_all BOOLEAN DEFAULT false;
+        DELETE FROM trace_files;
+        DELETE FROM edit_records;
+
+        CREATE TABLE IF NOT EXISTS parse_state (
+            trace_file_path TEXT PRIMARY KEY,
+            provider TEXT NOT NULL,
+            byte_offset TEXT NOT NULL,
+            state TEXT NOT NULL
+        );
+
+        CREATE TABLE IF NOT EXISTS transcript_files (
+            file_path TEXT PRIMARY KEY,
+            file_mtime_ns TEXT NOT NULL,
+            file_size_bytes TEXT NOT NULL,
+            last_parsed_at TEXT NOT NULL,
+            is_transcript BOOLEAN NOT NULL
+        );
+
+        CREATE TABLE IF NOT EXISTS transcripts (
+            source_file TEXT PRIMARY KEY,
+            session_id TEXT NOT NULL,
+            agent_tool TEXT NOT NULL,
+            agent_version TEXT,
+            cwd TEXT,
+            git_branch TEXT,
+            slug TEXT,
+            start_time TEXT NOT NULL,
+            end_time TEXT,
+            message_count INTEGER NOT NULL,
+            user_message_count INTEGER NOT NULL,
+            assistant_message_count INTEGER NOT NULL,
+            tool_use_count INTEGER NOT NULL,
+            files_touched INTEGER NOT NULL,
+            total_input_tokens BIGINT,
+            total_output_tokens BIGINT,
+            agent_id TEXT,
+            parent_session_id TEXT,
+            continues_from TEXT
+        );
+        CREATE INDEX IF NOT EXISTS idx_transcripts_session ON transcripts(session_id);
+
+        CREATE TABLE IF NOT EXISTS transcript_messages (
+            source_file TEXT NOT NULL,
+            seq INTEGER NOT NULL,
+            id TEXT NOT NULL,
+            role TEXT NOT NULL,
+            timestamp TEXT NOT NULL,
+            model TEXT,
+            input_tokens BIGINT,
+            output_tokens BIGINT,
+            cache_read_tokens BIGINT,
+            cache_creation_tokens BIGINT
+        );
+        CREATE INDEX IF NOT EXISTS idx_transcript_messages_source
+            ON transcript_messages(source_file);
+
+        CREATE TABLE IF NOT EXISTS transcript_blocks (
+            source_file TEXT NOT NULL,
+            message_seq INTEGER NOT NULL,
+            seq INTEGER NOT NULL,
+            block_type TEXT NOT NULL,
+            data TEXT NOT NULL,
+            block_key BIGINT NOT NULL,
+            role TEXT NOT NULL,
+            tool_name TEXT,
+            file_path TEXT,
+            tokens TEXT NOT NULL,
+            term_count INTEGER NOT NULL,
+            exit_code INTEGER,
+            is_error BOOLEAN
+        );
+        CREATE INDEX IF NOT EXISTS idx_transcript_blocks_source
+            ON transcript_blocks(source_file);
+        CREATE INDEX IF NOT EXISTS idx_transcript_blocks_key ON transcript_blocks(block_key);
+
+        CREATE TABLE IF NOT EXISTS transcript_terms (
+            source_file TEXT NOT NULL,
+            block_key BIGINT NOT NULL,
+            term TEXT NOT NULL,
+            tf INTEGER NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS idx_transcript_terms_term ON transcript_terms(term);
+        CREATE INDEX IF NOT EXISTS idx_transcript_terms_source
+            ON transcript_terms(source_file);",
+    )?;
+    Ok(())
+}
@@ -1,5 +1,13 @@
+pub mod migrations;
+pub mod query;
+mod transcripts;
 pub mod types;
-pub use types::{FileMetadata, StalenessReport};
+pub use migrations::CACHE_VERSION;
+pub use transcripts::is_transcript_stale;
+pub use types::{
+    CacheInfo, FileMetadata, ParseStateUpdate, ProviderStats, SchemaUpgrade, StalenessReport,
+    TranscriptHit, VacuumReport,
+};
 
 use crate::models::EditRecord;
 use anyhow::{Context, Result};
@@ -15,6 +23,7 @@ pub type EditsByFile = HashMap<String, Vec<EditRecord>>;
 pub struct CacheManager {
     conn: Arc<Mutex<Connection>>,
     db_path: PathBuf,
+    schema_upgrade: SchemaUpgrade,
 }
 
 impl CacheManager {
@@ -27,8 +36,8 @@ impl CacheManager {
     /// Open or create cache database at project root.
     ///
     /// Creates a DuckDB cache file at `{project_root}/.ai-blame.ddb` to store
-    /// parsed trace data. If the cache is corrupted, it will be automatically
-    /// rebuilt.
+    /// parsed trace data. Older schemas are migrated in place; if the cache is
+    /// corrupted or cannot be migrated, it will be automatically rebuilt.
     ///
     /// # Arguments
     ///
@@ -55,9 +64,9 @@ impl CacheManager {
     /// # Ok::<(), Box<dyn std::error::Error>>(())
     /// ```
     pub fn open(project_root: &Path) -> Result<Self> {
-        let db_path = project_root.join(".ai-blame.ddb");
+        let db_path = Self::cache_path(project_root);
 
-        let conn = match Connection::open(&db_path) {
+        let mut conn = match Connection::open(&db_path) {
             Ok(c) => c,
             Err(e) => {
                 eprintln!("Warning: Cache corrupted ({}), rebuilding...", e);
@@ -66,119 +75,66 @@ impl CacheManager {
             }
         };
 
-        // Initialize schema if needed
-        Self::initialize_schema(&conn)?;
+        let schema_upgrade = match migrations::migrate(&conn) {
+            Ok(upgrade) => upgrade,
+            Err(e) => {
+                let reason = format!("{:#}", e);
+                eprintln!("Warning: Cache not migratable ({}), rebuilding...", reason);
+                drop(conn);
+                Self::remove(project_root)?;
+                conn = Connection::open(&db_path)?;
+                migrations::migrate(&conn)?;
+                SchemaUpgrade::Rebuilt {
+                    reason,
+                    to: CACHE_VERSION,
+                }
+            }
+        };
 
         Ok(Self {
             conn: Arc::new(Mutex::new(conn)),
             db_path,
+            schema_upgrade,
         })
     }
 
-    /// Initialize database schema if not already present
-    fn initialize_schema(conn: &Connection) -> Result<()> {
-        // Try to get version
-        let version: Option<String> = conn
-            .query_row(
-                "SELECT value FROM cache_metadata WHERE key = 'version'",
-                [],
-                |row| row.get(0),
-            )
-            .ok();
-
-        match version.as_deref() {
-            Some("1") => {
-                // Current version, schema already exists
-                Ok(())
-            }
-            Some(v) => {
-                // Unknown version, rebuild cache
-                Err(anyhow::anyhow!("Unsupported cache version: {}", v))
-            }
-            None => {
-                // Fresh database, create schema
-                // Create cache_metadata table
-                conn.execute(
-                    "CREATE TABLE IF NOT EXISTS cache_metadata (
-                        key TEXT PRIMARY KEY,
-                        value TEXT NOT NULL
-                    )",
-                    [],
-                )?;
-
-                // Insert version (safe because table just created)
-                conn.execute(
-                    "INSERT INTO cache_metadata (key, value) VALUES ('version', '1')",
-                    [],
-                )?;
-
-                // Create trace_files table
-                conn.execute(
-                    "CREATE TABLE IF NOT EXISTS trace_files (
-                        file_path TEXT PRIMARY KEY,
-                        provider TEXT NOT NULL,
-                        file_mtime_ns TEXT NOT NULL,
-                        file_size_bytes TEXT NOT NULL,
-                        last_parsed_at TEXT NOT NULL,
-                        record_count INTEGER NOT NULL DEFAULT 0,
-                        parse_duration_ms INTEGER
-                    )",
-                    [],
-                )?;
-
-                conn.execute(
-                    "CREATE INDEX IF NOT EXISTS idx_trace_files_provider ON trace_files(provider)",
-                    [],
-                )?;
-
-                conn.execute(
-                    "CREATE INDEX IF NOT EXISTS idx_trace_files_mtime ON trace_files(file_mtime_ns)",
-                    [],
-                )?;
-
-                // Create edit_records table
-                conn.execute(
-                    "CREATE TABLE IF NOT EXISTS edit_records (
-                        trace_file_path TEXT NOT NULL,
-                        file_path TEXT NOT NULL,
-                        timestamp TEXT NOT NULL,
-                        model TEXT NOT NULL,
-                        session_id TEXT NOT NULL,
-                        is_create BOOLEAN NOT NULL,
-                        change_size INTEGER NOT NULL,
-                        agent_tool TEXT NOT NULL,
-                        agent_version TEXT,
-                        old_string TEXT,
-                        new_string TEXT,
-                        structured_patch TEXT,
-                        create_content TEXT
-                    )",
-                    [],
-                )?;
-
-                conn.execute(
-                    "CREATE INDEX IF NOT EXISTS idx_edits_file_path ON edit_records(file_path)",
-                    [],
-                )?;
-
-                conn.execute(
-                    "CREATE INDEX IF NOT EXISTS idx_edits_timestamp ON edit_records(timestamp)",
-                    [],
-                )?;
-
-                conn.execute(
-                    "CREATE INDEX IF NOT EXISTS idx_edits_session ON edit_records(session_id)",
-                    [],
-                )?;
+    /// Open a throwaway cache that lives only in memory.
+    ///
+    /// Used when the on-disk cache is disabled or unavailable but a cache-backed feature
+    /// (such as the transcript search index) is still needed for a single run.
+    pub fn open_in_memory() -> Result<Self> {
+        let conn = Connection::open_in_memory()?;
+        migrations::migrate(&conn)?;
+        Ok(Self {
+            conn: Arc::new(Mutex::new(conn)),
+            db_path: PathBuf::from(":memory:"),
+            schema_upgrade: SchemaUpgrade::Created(CACHE_VERSION),
+        })
+    }
 
-                conn.execute(
-                    "CREATE INDEX IF NOT EXISTS idx_edits_trace_file ON edit_records(trace_file_path)",
-                    [],
-                )?;
+    /// Path of the cache database for a trace directory.
+    pub fn cache_path(project_root: &Path) -> PathBuf {
+        project_root.join(".ai-blame.ddb")
+    }
 
-                Ok(())
+    /// Delete the cache database (and its write-ahead log) for a trace directory.
+    ///
+    /// Returns `false` if there was no cache to delete.
+    pub fn remove(project_root: &Path) -> Result<bool> {
+        let db_path = Self::cache_path(project_root);
+        let existed = db_path.exists();
+        for path in [db_path.clone(), wal_path(&db_path)] {
+            if path.exists() {
+                std::fs::remove_file(&path)
+                    .with_context(|| format!("Failed to delete cache file {:?}", path))?;
             }
         }
+        Ok(existed)
+    }
+
+    /// How the schema was brought up to date when this cache was opened.
+    pub fn schema_upgrade(&self) -> &SchemaUpgrade {
+        &self.schema_upgrade
     }
 
     /// Get cached edits for a specific trace file (if fresh)
@@ -203,7 +159,7 @@ impl CacheManager {
         let mut stmt = conn.prepare(
             "SELECT file_path, timestamp, model, session_id, is_create, change_size,
                     agent_tool, agent_version, old_string, new_string,
-                    structured_patch, create_content
+                    structured_patch, create_content, cell_id, agent_id, replace_all
              FROM edit_records
              WHERE trace_file_path = ?1
              ORDER BY timestamp",
@@ -222,6 +178,9 @@ impl CacheManager {
             new_string: Option<String>,
             structured_patch: Option<String>,
             create_content: Option<String>,
+            cell_id: Option<String>,
+            agent_id: Option<String>,
+            replace_all: bool,
         }
 
         let rows = stmt.query_map([trace_file.to_string_lossy().as_ref()], |row| {
@@ -238,6 +197,9 @@ impl CacheManager {
                 new_string: row.get(9)?,
                 structured_patch: row.get(10)?,
                 create_content: row.get(11)?,
+                cell_id: row.get(12)?,
+                agent_id: row.get(13)?,
+                replace_all: row.get(14)?,
             })
         })?;
 
@@ -260,6 +222,9 @@ impl CacheManager {
                 new_string: row.new_string,
                 structured_patch: row.structured_patch,
                 create_content: row.create_content,
+                cell_id: row.cell_id,
+                agent_id: row.agent_id,
+                replace_all: row.replace_all,
             });
         }
 
@@ -275,75 +240,47 @@ impl CacheManager {
         parse_duration_ms: u64,
     ) -> Result<()> {
         let conn = self.lock_conn()?;
-
-        // Get file metadata
-        let meta = std::fs::metadata(trace_file)?;
-        let mtime_ns = meta
-            .modified()?
-            .duration_since(std::time::UNIX_EPOCH)?
-            .as_nanos() as i64;
-        let size_bytes = meta.len() as i64;
-
-        // Begin transaction
         conn.execute("BEGIN TRANSACTION", [])?;
+        write_edits(&conn, trace_file, provider, edits, parse_duration_ms)?;
+        conn.execute("COMMIT", [])?;
+        Ok(())
+    }
 
-        // Delete old trace_files entry if exists
-        conn.execute(
-            "DELETE FROM trace_files WHERE file_path = ?1",
-            [trace_file.to_string_lossy().as_ref()],
-        )?;
-
-        // Insert/update trace_files
-        conn.execute(
-            "INSERT INTO trace_files
-             (file_path, provider, file_mtime_ns, file_size_bytes, last_parsed_at, record_count, parse_duration_ms)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
-            duckdb::params![
-                trace_file.to_string_lossy(),
-                provider,
-                mtime_ns.to_string(),
-                size_bytes.to_string(),
-                Utc::now().to_rfc3339(),
-                edits.len() as i64,
-                parse_duration_ms as i64,
-            ],
-        )?;
-
-        let trace_file_str = trace_file.to_string_lossy();
-
-        // Delete old edits for this trace file
-        conn.execute(
-            "DELETE FROM edit_records WHERE trace_file_path = ?1",
-            [trace_file_str.as_ref()],
-        )?;
-
-        // Insert new edits
-        let mut stmt = conn.prepare(
-            "INSERT INTO edit_records
-             (trace_file_path, file_path, timestamp, model, session_id, is_create,
-              change_size, agent_tool, agent_version, old_string, new_string,
-              structured_patch, create_content)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
-        )?;
-
-        for edit in edits {
-            stmt.execute(duckdb::params![
-                &trace_file_str,
-                &edit.file_path,
-                edit.timestamp.to_rfc3339(),
-                &edit.model,
-                &edit.session_id,
-                edit.is_create,
-                edit.change_size as i64,
-                &edit.agent_tool,
-                &edit.agent_version,
-                &edit.old_string,
-                &edit.new_string,
-                &edit.structured_patch,
-                &edit.create_content,
-            ])?;
+    /// Store an all-or-nothing directory parse in one transaction
+    ///
+    /// `edits` are cached under the first trace file and the others are marked as checked
+    /// with no edits. `parse_states` are saved in the same transaction, so a failed write
+    /// never leaves a saved offset ahead of the cached edits.
+    pub fn store_directory_edits(
+        &self,
+        trace_files: &[PathBuf],
+        provider: &str,
+        edits: &[EditRecord],
+        parse_duration_ms: u64,
+        parse_states: &[ParseStateUpdate],
+    ) -> Result<()> {
+        let conn = self.lock_conn()?;
+        conn.execute("BEGIN TRANSACTION", [])?;
+        let result = (|| -> Result<()> {
+            for (i, trace_file) in trace_files.iter().enumerate() {
+                let file_edits = if i == 0 { edits } else { &[] };
+                write_edits(&conn, trace_file, provider, file_edits, parse_duration_ms)?;
+            }
+            for update in parse_states {
+                write_parse_state(
+                    &conn,
+                    &update.trace_file,
+                    provider,
+                    update.byte_offset,
+                    &update.state,
+                )?;
+            }
+            Ok(())
+        })();
+        if let Err(e) = result {
+            let _ = conn.execute("ROLLBACK", []);
+            return Err(e);
         }
-
         conn.execute("COMMIT", [])?;
         Ok(())
     }
@@ -356,7 +293,7 @@ impl CacheManager {
             format!(
                 "SELECT file_path, timestamp, model, session_id, is_create, change_size,
                         agent_tool, agent_version, old_string, new_string,
-                        structured_patch, create_content
+                        structured_patch, create_content, cell_id, agent_id, replace_all
                  FROM edit_records
                  WHERE file_path LIKE '%{}%'
                  ORDER BY file_path, timestamp",
@@ -365,7 +302,7 @@ impl CacheManager {
         } else {
             "SELECT file_path, timestamp, model, session_id, is_create, change_size,
                     agent_tool, agent_version, old_string, new_string,
-                    structured_patch, create_content
+                    structured_patch, create_content, cell_id, agent_id, replace_all
              FROM edit_records
              ORDER BY file_path, timestamp"
                 .to_string()
@@ -387,6 +324,9 @@ impl CacheManager {
             new_string: Option<String>,
             structured_patch: Option<String>,
             create_content: Option<String>,
+            cell_id: Option<String>,
+            agent_id: Option<String>,
+            replace_all: bool,
         }
 
         let rows = stmt.query_map([], |row| {
@@ -403,6 +343,9 @@ impl CacheManager {
                 new_string: row.get(9)?,
                 structured_patch: row.get(10)?,
                 create_content: row.get(11)?,
+                cell_id: row.get(12)?,
+                agent_id: row.get(13)?,
+                replace_all: row.get(14)?,
             })
         })?;
 
@@ -424,6 +367,9 @@ impl CacheManager {
                 new_string: row.new_string,
                 structured_patch: row.structured_patch,
                 create_content: row.create_content,
+                cell_id: row.cell_id,
+                agent_id: row.agent_id,
+                replace_all: row.replace_all,
             };
             edits_by_file.entry(row.file_path).or_default().push(edit);
         }
@@ -450,12 +396,69 @@ impl CacheManager {
                 "DELETE FROM trace_files WHERE file_path = ?1",
                 [file_path_str.as_ref()],
             )?;
+
+            // Delete saved parser state
+            conn.execute(
+                "DELETE FROM parse_state WHERE trace_file_path = ?1",
+                [file_path_str.as_ref()],
+            )?;
+
+            // Delete cached transcript data
+            for (table, key) in transcripts::TRANSCRIPT_TABLES {
+                conn.execute(
+                    &format!("DELETE FROM {} WHERE {} = ?1", table, key),
+                    [file_path_str.as_ref()],
+                )?;
+            }
         }
 
         conn.execute("COMMIT", [])?;
         Ok(())
     }
 
+    /// Get the parser state saved for a trace file (parser-specific JSON), if any.
+    pub fn get_parse_state(&self, trace_file: &Path) -> Result<Option<String>> {
+        let conn = self.lock_conn()?;
+        conn.query_row(
+            "SELECT state FROM parse_state WHERE trace_file_path = ?1",
+            [trace_file.to_string_lossy().as_ref()],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| anyhow::anyhow!("Failed to query parse state: {}", e))
+    }
+
+    /// Save the parser state for a trace file, replacing any previous state.
+    ///
+    /// `byte_offset` is where parsing stopped; it is stored alongside the parser's own
+    /// `state` JSON so it can be inspected without decoding it.
+    pub fn store_parse_state(
+        &self,
+        trace_file: &Path,
+        provider: &str,
+        byte_offset: u64,
+        state: &str,
+    ) -> Result<()> {
+        let conn = self.lock_conn()?;
+        conn.execute("BEGIN TRANSACTION", [])?;
+        write_parse_state(&conn, trace_file, provider, byte_offset, state)?;
+        conn.execute("COMMIT", [])?;
+        Ok(())
+    }
+
+    /// Trace files that have saved parser state for `provider`.
+    pub fn parse_state_files(&self, provider: &str) -> Result<Vec<PathBuf>> {
+        let conn = self.lock_conn()?;
+        let mut stmt =
+            conn.prepare("SELECT trace_file_path FROM parse_state WHERE provider = ?1 ORDER BY 1")?;
+        let rows = stmt.query_map([provider], |row| row.get::<_, String>(0))?;
+        let mut files = Vec::new();
+        for row in rows {
+            files.push(PathBuf::from(row?));
+        }
+        Ok(files)
+    }
+
     /// Get file metadata from cache
     pub fn get_file_metadata(&self, trace_file: &Path) -> Result<Option<FileMetadata>> {
         let conn = self.lock_conn()?;
@@ -497,6 +500,218 @@ impl CacheManager {
     pub fn db_path(&self) -> &Path {
         &self.db_path
     }
+
+    /// Summarize the cache: size, schema version, counts and per-provider totals.
+    pub fn info(&self) -> Result<CacheInfo> {
+        let conn = self.lock_conn()?;
+        conn.execute("CHECKPOINT", [])?;
+
+        let count = |sql: &str| -> Result<u64> {
+            let n: i64 = conn.query_row(sql, [], |row| row.get(0))?;
+            Ok(n.max(0) as u64)
+        };
+        let trace_files = count("SELECT COUNT(*) FROM trace_files")?;
+        let edit_records = count("SELECT COUNT(*) FROM edit_records")?;
+        let transcripts = count("SELECT COUNT(*) FROM transcripts")?;
+
+        let mut stmt = conn.prepare(
+            "SELECT provider,
+                    COUNT(*),
+                    CAST(COALESCE(SUM(record_count), 0) AS BIGINT),
+                    CAST(COALESCE(SUM(TRY_CAST(file_size_bytes AS BIGINT)), 0) AS BIGINT),
+                    CAST(COALESCE(SUM(parse_duration_ms), 0) AS BIGINT),
+                    MAX(last_parsed_at)
+             FROM trace_files
+             GROUP BY provider
+             ORDER BY provider",
+        )?;
+        let providers = stmt
+            .query_map([], |row| {
+                Ok(ProviderStats {
+                    provider: row.get(0)?,
+                    trace_files: row.get::<_, i64>(1)?.max(0) as u64,
+                    record_count: row.get::<_, i64>(2)?.max(0) as u64,
+                    trace_bytes: row.get::<_, i64>(3)?.max(0) as u64,
+                    parse_duration_ms: row.get::<_, i64>(4)?.max(0) as u64,
+                    last_parsed_at: row.get(5)?,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        Ok(CacheInfo {
+            db_path: self.db_path.clone(),
+            size_bytes: file_size(&self.db_path),
+            schema_version: CACHE_VERSION,
+            trace_files,
+            edit_records,
+            transcripts,
+            providers,
+        })
+    }
+
+    /// Drop entries for trace files that no longer exist, then compact the database.
+    ///
+    /// DuckDB reuses freed blocks but never shrinks its file, so compaction copies the
+    /// live data into a fresh database and swaps it in place of the old one.
+    pub fn vacuum(self) -> Result<VacuumReport> {
+        let missing: Vec<PathBuf> = {
+            let conn = self.lock_conn()?;
+            let mut stmt = conn.prepare(
+                "SELECT file_path FROM trace_files
+                 UNION SELECT trace_file_path FROM parse_state
+                 UNION SELECT file_path FROM transcript_files",
+            )?;
+            let paths = stmt
+                .query_map([], |row| row.get::<_, String>(0))?
+                .collect::<std::result::Result<Vec<_>, _>>()?;
+            paths
+                .into_iter()
+                .map(PathBuf::from)
+                .filter(|p| !p.exists())
+                .collect()
+        };
+        self.invalidate_files(&missing)?;
+
+        let conn = self.lock_conn()?;
+        conn.execute("CHECKPOINT", [])?;
+        let size_before = file_size(&self.db_path);
+
+        let compact_path = self.db_path.with_extension("ddb.compact");
+        let _ = std::fs::remove_file(&compact_path);
+        let database: String = conn.query_row("SELECT current_database()", [], |row| row.get(0))?;
+        conn.execute_batch(&format!(
+            "ATTACH '{}' AS ai_blame_compact;
+             COPY FROM DATABASE \"{}\" TO ai_blame_compact;
+             DETACH ai_blame_compact;",
+            compact_path.to_string_lossy().replace('\'', "''"),
+            database.replace('"', "\"\""),
+        ))
+        .with_context(|| format!("Failed to compact cache {:?}", self.db_path))?;
+        drop(conn);
+
+        // Close the connection before replacing the file underneath it.
+        let db_path = self.db_path.clone();
+        drop(self);
+        let _ = std::fs::remove_file(wal_path(&db_path));
+        std::fs::rename(&compact_path, &db_path)
+            .with_context(|| format!("Failed to replace cache {:?}", db_path))?;
+
+        Ok(VacuumReport {
+            pruned_files: missing.len(),
+            size_before,
+            size_after: file_size(&db_path),
+        })
+    }
+}
+
+/// DuckDB's write-ahead log sits next to the database file.
+fn wal_path(db_path: &Path) -> PathBuf {
+    let mut name = db_path.as_os_str().to_os_string();
+    name.push(".wal");
+    PathBuf::from(name)
+}
+
+fn file_size(path: &Path) -> u64 {
+    std::fs::metadata(path).map(|m| m.len()).unwrap_or(0)
+}
+
+/// Replace the cached edits and metadata of one trace file (inside a transaction).
+fn write_edits(
+    conn: &Connection,
+    trace_file: &Path,
+    provider: &str,
+    edits: &[EditRecord],
+    parse_duration_ms: u64,
+) -> Result<()> {
+    // Get file metadata
+    let meta = std::fs::metadata(trace_file)?;
+    let mtime_ns = meta
+        .modified()?
+        .duration_since(std::time::UNIX_EPOCH)?
+        .as_nanos() as i64;
+    let size_bytes = meta.len() as i64;
+
+    // Delete old trace_files entry if exists
+    conn.execute(
+        "DELETE FROM trace_files WHERE file_path = ?1",
+        [trace_file.to_string_lossy().as_ref()],
+    )?;
+
+    // Insert/update trace_files
+    conn.execute(
+        "INSERT INTO trace_files
+         (file_path, provider, file_mtime_ns, file_size_bytes, last_parsed_at, record_count, parse_duration_ms)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        duckdb::params![
+            trace_file.to_string_lossy(),
+            provider,
+            mtime_ns.to_string(),
+            size_bytes.to_string(),
+            Utc::now().to_rfc3339(),
+            edits.len() as i64,
+            parse_duration_ms as i64,
+        ],
+    )?;
+
+    let trace_file_str = trace_file.to_string_lossy();
+
+    // Delete old edits for this trace file
+    conn.execute(
+        "DELETE FROM edit_records WHERE trace_file_path = ?1",
+        [trace_file_str.as_ref()],
+    )?;
+
+    // Insert new edits
+    let mut stmt = conn.prepare(
+        "INSERT INTO edit_records
+         (trace_file_path, file_path, timestamp, model, session_id, is_create,
+          change_size, agent_tool, agent_version, old_string, new_string,
+          structured_patch, create_content, cell_id, agent_id, replace_all)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16)",
+    )?;
+
+    for edit in edits {
+        stmt.execute(duckdb::params![
+            &trace_file_str,
+            &edit.file_path,
+            edit.timestamp.to_rfc3339(),
+            &edit.model,
+            &edit.session_id,
+            edit.is_create,
+            edit.change_size as i64,
+            &edit.agent_tool,
+            &edit.agent_version,
+            &edit.old_string,
+            &edit.new_string,
+            &edit.structured_patch,
+            &edit.create_content,
+            &edit.cell_id,
+            &edit.agent_id,
+            edit.replace_all,
+        ])?;
+    }
+    Ok(())
+}
+
+/// Replace the saved parser state of one trace file (inside a transaction).
+fn write_parse_state(
+    conn: &Connection,
+    trace_file: &Path,
+    provider: &str,
+    byte_offset: u64,
+    state: &str,
+) -> Result<()> {
+    let trace_file_str = trace_file.to_string_lossy();
+    conn.execute(
+        "DELETE FROM parse_state WHERE trace_file_path = ?1",
+        [trace_file_str.as_ref()],
+    )?;
+    conn.execute(
+        "INSERT INTO parse_state (trace_file_path, provider, byte_offset, state)
+         VALUES (?1, ?2, ?3, ?4)",
+        duckdb::params![trace_file_str, provider, byte_offset.to_string(), state],
+    )?;
+    Ok(())
 }
 
 #[cfg(test)]
@@ -525,6 +740,9 @@ mod tests {
             new_string: None,
             structured_patch: None,
             create_content: Some("test".to_string()),
+            cell_id: None,
+            agent_id: None,
+            replace_all: false,
         }];
 
         cache.store_edits(&trace_file, "test", &edits, 50)?;
@@ -0,0 +1,252 @@
+//! Ad-hoc SQL over one or more trace caches.
+//!
+//! Each cache is attached read-only to an in-memory DuckDB database, and its tables are
+//! exposed as `edit_records` and `trace_files` views spanning all attached caches. A few
+//! predefined views ([`VIEWS`]) cover the questions that come up most often.
+
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, NaiveDate, NaiveTime};
+use duckdb::types::Value;
+use duckdb::Connection;
+use serde_json::Value as JsonValue;
+use std::path::{Path, PathBuf};
+
+/// A view available to every query, defined over `edit_records`.
+pub struct PredefinedView {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub sql: &'static str,
+}
+
+/// Views created in every query session.
+pub const VIEWS: &[PredefinedView] = &[
+    PredefinedView {
+        name: "edits_per_day",
+        description: "Edits, sessions and characters changed per day and model",
+        sql: "SELECT CAST(substr(timestamp, 1, 10) AS DATE) AS day,
+                     model,
+                     COUNT(*) AS edits,
+                     COUNT(DISTINCT session_id) AS sessions,
+                     SUM(change_size) AS chars_changed
+              FROM edit_records
+              GROUP BY day, model
+              ORDER BY day, model",
+    },
+    PredefinedView {
+        name: "file_agent_share",
+        description: "Each agent's share of the edits to each file",
+        sql: "SELECT file_path,
+                     agent_tool,
+                     COUNT(*) AS edits,
+                     SUM(change_size) AS chars_changed,
+                     ROUND(COUNT(*) / SUM(COUNT(*)) OVER (PARTITION BY file_path), 3)
+                         AS edit_share,
+                     MAX(timestamp) AS last_edit
+              FROM edit_records
+              GROUP BY file_path, agent_tool
+              ORDER BY file_path, edits DESC, agent_tool",
+    },
+    PredefinedView {
+        name: "session_sizes",
+        description: "Sessions ranked by characters changed",
+        sql: "SELECT session_id,
+                     agent_tool,
+                     string_agg(DISTINCT model, ', ' ORDER BY model) AS models,
+                     COUNT(*) AS edits,
+                     COUNT(DISTINCT file_path) AS files,
+                     SUM(change_size) AS chars_changed,
+                     MIN(timestamp) AS started,
+                     MAX(timestamp) AS ended
+              FROM edit_records
+              GROUP BY session_id, agent_tool
+              ORDER BY chars_changed DESC, session_id",
+    },
+];
+
+/// Tables every cache has, unioned across caches under the same name.
+const CACHE_TABLES: &[&str] = &["edit_records", "trace_files"];
+
+/// Columns and rows of a query result, with values converted to JSON.
+#[derive(Debug, Clone, Default)]
+pub struct QueryResult {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<JsonValue>>,
+}
+
+/// An in-memory DuckDB session with the caches attached.
+pub struct QuerySession {
+    conn: Connection,
+}
+
+impl QuerySession {
+    /// Attach the given cache databases read-only and create the query views.
+    pub fn open(cache_paths: &[PathBuf]) -> Result<Self> {
+        if cache_paths.is_empty() {
+            bail!("No cache to query");
+        }
+        let conn = Connection::open_in_memory()?;
+
+        let mut aliases = Vec::new();
+        for (i, path) in cache_paths.iter().enumerate() {
+            let alias = format!("cache_{}", i);
+            conn.execute(
+                &format!(
+                    "ATTACH {} AS {} (READ_ONLY)",
+                    sql_string(&path.to_string_lossy()),
+                    alias
+                ),
+                [],
+            )
+            .with_context(|| format!("Failed to attach cache {:?}", path))?;
+            aliases.push(alias);
+        }
+
+        for table in CACHE_TABLES {
+            let union = aliases
+                .iter()
+                .map(|alias| format!("SELECT * FROM {}.main.{}", alias, table))
+                .collect::<Vec<_>>()
+                .join(" UNION ALL BY NAME ");
+            conn.execute(&format!("CREATE TEMP VIEW {} AS {}", table, union), [])
+                .with_context(|| format!("Failed to create view {}", table))?;
+        }
+        for view in VIEWS {
+            conn.execute(
+                &format!("CREATE TEMP VIEW {} AS {}", view.name, view.sql),
+                [],
+            )
+            .with_context(|| format!("Failed to create view {}", view.name))?;
+        }
+
+        Ok(Self { conn })
+    }
+
+    /// Run a SQL statement and collect its result.
+    pub fn run(&self, sql: &str) -> Result<QueryResult> {
+        let mut stmt = self.conn.prepare(sql)?;
+        let mut rows = stmt.query([])?;
+
+        let columns = rows
+            .as_ref()
+            .map(|stmt| stmt.column_names())
+            .unwrap_or_default();
+        let mut result = QueryResult {
+            columns,
+            rows: Vec::new(),
+        };
+        while let Some(row) = rows.next()? {
+            let values = (0..result.columns.len())
+                .map(|i| row.get::<_, Value>(i).map(to_json))
+                .collect::<duckdb::Result<Vec<_>>>()?;
+            result.rows.push(values);
+        }
+        Ok(result)
+    }
+
+    /// Write the result of a query to a Parquet file.
+    pub fn write_parquet(&self, sql: &str, output: &Path) -> Result<()> {
+        let query = sql.trim().trim_end_matches(';');
+        self.conn
+            .execute(
+                &format!(
+                    "COPY ({}) TO {} (FORMAT PARQUET)",
+                    query,
+                    sql_string(&output.to_string_lossy())
+                ),
+                [],
+            )
+            .with_context(|| format!("Failed to write Parquet file {:?}", output))?;
+        Ok(())
+    }
+}
+
+/// Quote a string literal for DuckDB.
+fn sql_string(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "''"))
+}
+
+/// Convert a DuckDB value to JSON; temporal values become ISO 8601 strings.
+pub(crate) fn to_json(value: Value) -> JsonValue {
+    match value {
+        Value::Null => JsonValue::Null,
+        Value::Boolean(b) => b.into(),
+        Value::TinyInt(n) => n.into(),
+        Value::SmallInt(n) => n.into(),
+        Value::Int(n) => n.into(),
+        Value::BigInt(n) => n.into(),
+        Value::HugeInt(n) => i64::try_from(n)
+            .map(JsonValue::from)
+            .unwrap_or_else(|_| n.to_string().into()),
+        Value::UTinyInt(n) => n.into(),
+        Value::USmallInt(n) => n.into(),
+        Value::UInt(n) => n.into(),
+        Value::UBigInt(n) => n.into(),
+        Value::Float(f) => float(f as f64),
+        Value::Double(f) => float(f),
+        Value::Decimal(d) => d
+            .to_string()
+            .parse::<f64>()
+            .map(float)
+            .unwrap_or_else(|_| d.to_string().into()),
+        Value::Timestamp(unit, v) => DateTime::from_timestamp_micros(unit.to_micros(v))
+            .map(|ts| ts.to_rfc3339().into())
+            .unwrap_or(JsonValue::Null),
+        Value::Text(s) | Value::Enum(s) => s.into(),
+        Value::Blob(bytes) => bytes
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect::<String>()
+            .into(),
+        Value::Date32(days) => NaiveDate::from_num_days_from_ce_opt(days + EPOCH_DAYS_FROM_CE)
+            .map(|d| d.to_string().into())
+            .unwrap_or(JsonValue::Null),
+        Value::Time64(unit, v) => {
+            let micros = unit.to_micros(v);
+            NaiveTime::from_num_seconds_from_midnight_opt(
+                (micros / 1_000_000) as u32,
+                ((micros % 1_000_000) * 1_000) as u32,
+            )
+            .map(|t| t.to_string().into())
+            .unwrap_or(JsonValue::Null)
+        }
+        Value::Interval {
+            months,
+            days,
+            nanos,
+        } => format!("{} months {} days {} ns", months, days, nanos).into(),
+        Value::List(items) | Value::Array(items) => {
+            JsonValue::Array(items.into_iter().map(to_json).collect())
+        }
+        Value::Struct(fields) => JsonValue::Object(
+            fields
+                .iter()
+                .map(|(k, v)| (k.clone(), to_json(v.clone())))
+                .collect(),
+        ),
+        Value::Map(entries) => JsonValue::Array(
+            entries
+                .iter()
+                .map(|(k, v)| JsonValue::Array(vec![to_json(k.clone()), to_json(v.clone())]))
+                .collect(),
+        ),
+        Value::Union(inner) => to_json(*inner),
+    }
+}
+
+/// Days from 0001-01-01 (chrono's day 1) to the Unix epoch.
+const EPOCH_DAYS_FROM_CE: i32 = 719_163;
+
+fn float(f: f64) -> JsonValue {
+    serde_json::Number::from_f64(f)
+        .map(JsonValue::Number)
+        .unwrap_or(JsonValue::Null)
+}
+
+/// Render a JSON value as plain text for table and CSV output (`NULL` becomes empty).
+pub fn display_value(value: &JsonValue) -> String {
+    match value {
+        JsonValue::Null => String::new(),
+        JsonValue::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
@@ -1,11 +1,11 @@
--- Cache metadata and versioning
+-- Cache metadata and versioning (see migrations.rs for how older versions are upgraded)
 CREATE TABLE IF NOT EXISTS cache_metadata (
     key TEXT PRIMARY KEY,
     value TEXT NOT NULL
 );
 
 -- Initialize metadata if table is new
-INSERT OR IGNORE INTO cache_metadata (key, value) VALUES ('version', '1');
+INSERT OR IGNORE INTO cache_metadata (key, value) VALUES ('version', '2');
 INSERT OR IGNORE INTO cache_metadata (key, value) VALUES ('created_at', CAST(NOW() AS VARCHAR));
 
 -- Per-file metadata for staleness detection
@@ -39,6 +39,9 @@ CREATE TABLE IF NOT EXISTS edit_records (
     new_string TEXT,
     structured_patch TEXT,
     create_content TEXT,
+    cell_id TEXT,
+    agent_id TEXT,                           -- Subagent that made the edit (Claude sidechains)
+    replace_all BOOLEAN DEFAULT false,       -- Edit replaced every match of old_string
     FOREIGN KEY (trace_file_id) REFERENCES trace_files(id)
 );
 
@@ -46,3 +49,92 @@ CREATE INDEX IF NOT EXISTS idx_edits_file_path ON edit_records(file_path);
 CREATE INDEX IF NOT EXISTS idx_edits_timestamp ON edit_records(timestamp);
 CREATE INDEX IF NOT EXISTS idx_edits_session ON edit_records(session_id);
 CREATE INDEX IF NOT EXISTS idx_edits_trace_file ON edit_records(trace_file_id);
+
+-- Per-file parser state for incremental parsing of append-only traces
+CREATE TABLE IF NOT EXISTS parse_state (
+    trace_file_path TEXT PRIMARY KEY,
+    provider TEXT NOT NULL,
+    byte_offset TEXT NOT NULL,               -- Bytes parsed so far
+    state TEXT NOT NULL                      -- Parser-specific JSON (e.g. UUID -> model maps)
+);
+
+-- Transcript files seen, with the same staleness fields as trace_files
+CREATE TABLE IF NOT EXISTS transcript_files (
+    file_path TEXT PRIMARY KEY,
+    file_mtime_ns TEXT NOT NULL,
+    file_size_bytes TEXT NOT NULL,
+    last_parsed_at TEXT NOT NULL,
+    is_transcript BOOLEAN NOT NULL           -- FALSE if no transcript parser understood the file
+);
+
+-- Cached transcripts: metadata and statistics, one row per transcript file
+CREATE TABLE IF NOT EXISTS transcripts (
+    source_file TEXT PRIMARY KEY,
+    session_id TEXT NOT NULL,
+    agent_tool TEXT NOT NULL,
+    agent_version TEXT,
+    cwd TEXT,
+    git_branch TEXT,
+    slug TEXT,
+    start_time TEXT NOT NULL,                -- ISO8601 timestamp
+    end_time TEXT,
+    message_count INTEGER NOT NULL,
+    user_message_count INTEGER NOT NULL,
+    assistant_message_count INTEGER NOT NULL,
+    tool_use_count INTEGER NOT NULL,
+    files_touched INTEGER NOT NULL,
+    total_input_tokens BIGINT,
+    total_output_tokens BIGINT,
+    agent_id TEXT,                           -- Set for subagent (sidechain) transcripts
+    parent_session_id TEXT,                  -- Session that started the subagent
+    continues_from TEXT                      -- Message in an earlier file this one continues
+);
+
+CREATE INDEX IF NOT EXISTS idx_transcripts_session ON transcripts(session_id);
+
+-- Messages of cached transcripts, in transcript order
+CREATE TABLE IF NOT EXISTS transcript_messages (
+    source_file TEXT NOT NULL,
+    seq INTEGER NOT NULL,                    -- Position within the transcript
+    id TEXT NOT NULL,
+    role TEXT NOT NULL,                      -- 'user', 'assistant' or 'system'
+    timestamp TEXT NOT NULL,
+    model TEXT,
+    input_tokens BIGINT,
+    output_tokens BIGINT,
+    cache_read_tokens BIGINT,
+    cache_creation_tokens BIGINT
+);
+
+CREATE INDEX IF NOT EXISTS idx_transcript_messages_source ON transcript_messages(source_file);
+
+-- Content blocks of cached messages
+CREATE TABLE IF NOT EXISTS transcript_blocks (
+    source_file TEXT NOT NULL,
+    message_seq INTEGER NOT NULL,
+    seq INTEGER NOT NULL,                    -- Position within the message
+    block_type TEXT NOT NULL,                -- 'text', 'tool_use', ...
+    data TEXT NOT NULL,                      -- The ContentBlock as JSON
+    block_key BIGINT NOT NULL,               -- Unique block id, referenced by transcript_terms
+    role TEXT NOT NULL,                      -- Role of the owning message
+    tool_name TEXT,                          -- Tool name of a tool call
+    file_path TEXT,                          -- Path of a file operation or tool call
+    tokens TEXT NOT NULL,                    -- Search tokens in order, space-separated
+    term_count INTEGER NOT NULL,             -- Number of search tokens (BM25 length)
+    exit_code INTEGER,                       -- Exit code of a command
+    is_error BOOLEAN                         -- Whether a tool result was an error
+);
+
+CREATE INDEX IF NOT EXISTS idx_transcript_blocks_source ON transcript_blocks(source_file);
+CREATE INDEX IF NOT EXISTS idx_transcript_blocks_key ON transcript_blocks(block_key);
+
+-- Inverted index over block tokens for transcript search
+CREATE TABLE IF NOT EXISTS transcript_terms (
+    source_file TEXT NOT NULL,
+    block_key BIGINT NOT NULL,
+    term TEXT NOT NULL,
+    tf INTEGER NOT NULL                      -- Occurrences of the term in the block
+);
+
+CREATE INDEX IF NOT EXISTS idx_transcript_terms_term ON transcript_terms(term);
+CREATE INDEX IF NOT EXISTS idx_transcript_terms_source ON transcript_terms(source_file);
@@ -0,0 +1,752 @@
+//! Cached transcripts: metadata, statistics, messages and content blocks.
+//!
+//! Transcripts are cached per JSONL file with the same mtime/size staleness check as trace
+//! files. Files that no transcript parser understands are recorded too, so they are not
+//! re-read on every listing.
+//!
+//! Every content block is also indexed for search: its tokens go into `transcript_terms`
+//! (an inverted index of term frequencies per block), and queries are compiled to SQL over
+//! that table and ranked with BM25.
+
+use super::{CacheManager, FileMetadata, TranscriptHit};
+use crate::transcript::query::{block_search_text, tokenize, FieldFilter, QueryNode};
+use crate::transcript::{
+    ContentBlock, Role, TokenUsage, Transcript, TranscriptMessage, TranscriptMeta,
+    TranscriptSearchCriteria, TranscriptStats,
+};
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Tables holding cached transcript data, keyed by transcript file.
+pub(super) const TRANSCRIPT_TABLES: &[(&str, &str)] = &[
+    ("transcript_terms", "source_file"),
+    ("transcript_blocks", "source_file"),
+    ("transcript_messages", "source_file"),
+    ("transcripts", "source_file"),
+    ("transcript_files", "file_path"),
+];
+
+impl CacheManager {
+    /// Staleness metadata for every cached transcript file.
+    pub fn transcript_file_metadata(&self) -> Result<HashMap<PathBuf, FileMetadata>> {
+        let conn = self.lock_conn()?;
+        let mut stmt = conn.prepare(
+            "SELECT file_path, file_mtime_ns, file_size_bytes, last_parsed_at
+             FROM transcript_files",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, String>(3)?,
+            ))
+        })?;
+
+        let mut metadata = HashMap::new();
+        for row in rows {
+            let (path, mtime_ns, size_bytes, last_parsed_at) = row?;
+            let (Ok(file_mtime_ns), Ok(file_size_bytes)) = (mtime_ns.parse(), size_bytes.parse())
+            else {
+                // Unreadable entries are simply treated as stale.
+                continue;
+            };
+            metadata.insert(
+                PathBuf::from(path),
+                FileMetadata {
+                    file_mtime_ns,
+                    file_size_bytes,
+                    last_parsed_at,
+                },
+            );
+        }
+        Ok(metadata)
+    }
+
+    /// Load cached transcripts for the given files.
+    ///
+    /// Files missing from the result are not cached. A `None` value means the file was
+    /// cached as not being a transcript.
+    pub fn get_cached_transcripts(
+        &self,
+        files: &[PathBuf],
+    ) -> Result<HashMap<PathBuf, Option<Transcript>>> {
+        let conn = self.lock_conn()?;
+        conn.execute_batch("CREATE OR REPLACE TEMP TABLE wanted_transcripts (file_path TEXT)")?;
+        let mut insert = conn.prepare("INSERT INTO wanted_transcripts VALUES (?1)")?;
+        for file in files {
+            insert.execute([file.to_string_lossy().as_ref()])?;
+        }
+
+        let mut result: HashMap<PathBuf, Option<Transcript>> = HashMap::new();
+        let mut stmt = conn.prepare(
+            "SELECT file_path FROM transcript_files
+             WHERE NOT is_transcript AND file_path IN (SELECT file_path FROM wanted_transcripts)",
+        )?;
+        for path in stmt.query_map([], |row| row.get::<_, String>(0))? {
+            result.insert(PathBuf::from(path?), None);
+        }
+
+        let mut transcripts: HashMap<String, Transcript> = HashMap::new();
+        let mut stmt = conn.prepare(
+            "SELECT source_file, session_id, agent_tool, agent_version, cwd, git_branch, slug,
+                    start_time, end_time, message_count, user_message_count,
+                    assistant_message_count, tool_use_count, files_touched,
+                    total_input_tokens, total_output_tokens, agent_id, parent_session_id,
+                    continues_from
+             FROM transcripts
+             WHERE source_file IN (SELECT file_path FROM wanted_transcripts)",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            let count = |i: usize| row.get::<_, i64>(i).map(|n| n.max(0) as usize);
+            let tokens = |i: usize| {
+                row.get::<_, Option<i64>>(i)
+                    .map(|n| n.map(|n| n.max(0) as u64))
+            };
+            Ok((
+                row.get::<_, String>(0)?,
+                TranscriptMeta {
+                    session_id: row.get(1)?,
+                    agent_tool: row.get(2)?,
+                    agent_version: row.get(3)?,
+                    cwd: row.get(4)?,
+                    git_branch: row.get(5)?,
+                    slug: row.get(6)?,
+                    start_time: DateTime::<Utc>::UNIX_EPOCH,
+                    end_time: None,
+                    source_file: None,
+                    agent_id: row.get(16)?,
+                    parent_session_id: row.get(17)?,
+                    parent_tool_use_id: None,
+                    continues_from: row.get(18)?,
+                    continuations: Vec::new(),
+                },
+                row.get::<_, String>(7)?,
+                row.get::<_, Option<String>>(8)?,
+                TranscriptStats {
+                    message_count: count(9)?,
+                    user_message_count: count(10)?,
+                    assistant_message_count: count(11)?,
+                    tool_use_count: count(12)?,
+                    files_touched: count(13)?,
+                    total_input_tokens: tokens(14)?,
+                    total_output_tokens: tokens(15)?,
+                },
+            ))
+        })?;
+        for row in rows {
+            let (source_file, mut meta, start_time, end_time, stats) = row?;
+            meta.start_time = parse_timestamp(&start_time)?;
+            meta.end_time = end_time.as_deref().map(parse_timestamp).transpose()?;
+            meta.source_file = Some(source_file.clone());
+            transcripts.insert(
+                source_file,
+                Transcript {
+                    meta,
+                    messages: Vec::new(),
+                    stats,
+                    subagents: Vec::new(),
+                },
+            );
+        }
+
+        let mut stmt = conn.prepare(
+            "SELECT source_file, id, role, timestamp, model, input_tokens, output_tokens,
+                    cache_read_tokens, cache_creation_tokens
+             FROM transcript_messages
+             WHERE source_file IN (SELECT file_path FROM wanted_transcripts)
+             ORDER BY source_file, seq",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            let tokens = |i: usize| {
+                row.get::<_, Option<i64>>(i)
+                    .map(|n| n.map(|n| n.max(0) as u64))
+            };
+            let usage = TokenUsage {
+                input_tokens: tokens(5)?,
+                output_tokens: tokens(6)?,
+                cache_read_tokens: tokens(7)?,
+                cache_creation_tokens: tokens(8)?,
+            };
+            let has_usage = usage.input_tokens.is_some()
+                || usage.output_tokens.is_some()
+                || usage.cache_read_tokens.is_some()
+                || usage.cache_creation_tokens.is_some();
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, String>(3)?,
+                row.get::<_, Option<String>>(4)?,
+                has_usage.then_some(usage),
+            ))
+        })?;
+        for row in rows {
+            let (source_file, id, role, timestamp, model, usage) = row?;
+            let Some(transcript) = transcripts.get_mut(&source_file) else {
+                continue;
+            };
+            transcript.messages.push(TranscriptMessage {
+                id,
+                role: parse_role(&role)?,
+                timestamp: parse_timestamp(&timestamp)?,
+                content: Vec::new(),
+                model,
+                usage,
+            });
+        }
+
+        let mut stmt = conn.prepare(
+            "SELECT source_file, message_seq, data
+             FROM transcript_blocks
+             WHERE source_file IN (SELECT file_path FROM wanted_transcripts)
+             ORDER BY source_file, message_seq, seq",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, i64>(1)?,
+                row.get::<_, String>(2)?,
+            ))
+        })?;
+        for row in rows {
+            let (source_file, message_seq, data) = row?;
+            let Some(transcript) = transcripts.get_mut(&source_file) else {
+                continue;
+            };
+            let message = transcript
+                .messages
+                .get_mut(message_seq as usize)
+                .ok_or_else(|| anyhow!("Cached content block without a message"))?;
+            let block: ContentBlock = serde_json::from_str(&data)
+                .map_err(|e| anyhow!("Invalid cached content block: {}", e))?;
+            message.content.push(block);
+        }
+
+        for (source_file, transcript) in transcripts {
+            result.insert(PathBuf::from(source_file), Some(transcript));
+        }
+        Ok(result)
+    }
+
+    /// Store parsed transcripts, replacing whatever was cached for their files.
+    ///
+    /// Pass `None` for files that turned out not to be transcripts. Content blocks are
+    /// indexed for [`search_transcript_index`](Self::search_transcript_index) as they are
+    /// stored.
+    pub fn store_transcripts(&self, entries: &[(&Path, Option<&Transcript>)]) -> Result<()> {
+        let conn = self.lock_conn()?;
+        let parsed_at = Utc::now().to_rfc3339();
+
+        conn.execute("BEGIN TRANSACTION", [])?;
+        for (file, transcript) in entries {
+            let file_str = file.to_string_lossy();
+            let file_meta = std::fs::metadata(file)?;
+            let mtime_ns = file_meta
+                .modified()?
+                .duration_since(std::time::UNIX_EPOCH)?
+                .as_nanos() as i64;
+
+            for (table, key) in TRANSCRIPT_TABLES {
+                conn.execute(
+                    &format!("DELETE FROM {} WHERE {} = ?1", table, key),
+                    [file_str.as_ref()],
+                )?;
+            }
+            conn.execute(
+                "INSERT INTO transcript_files
+                 (file_path, file_mtime_ns, file_size_bytes, last_parsed_at, is_transcript)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                duckdb::params![
+                    file_str,
+                    mtime_ns.to_string(),
+                    file_meta.len().to_string(),
+                    &parsed_at,
+                    transcript.is_some(),
+                ],
+            )?;
+
+            let Some(transcript) = transcript else {
+                continue;
+            };
+            let (meta, stats) = (&transcript.meta, &transcript.stats);
+            conn.execute(
+                "INSERT INTO transcripts VALUES
+                 (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18,
+                  ?19)",
+                duckdb::params![
+                    file_str,
+                    &meta.session_id,
+                    &meta.agent_tool,
+                    &meta.agent_version,
+                    &meta.cwd,
+                    &meta.git_branch,
+                    &meta.slug,
+                    meta.start_time.to_rfc3339(),
+                    meta.end_time.map(|t| t.to_rfc3339()),
+                    stats.message_count as i64,
+                    stats.user_message_count as i64,
+                    stats.assistant_message_count as i64,
+                    stats.tool_use_count as i64,
+                    stats.files_touched as i64,
+                    stats.total_input_tokens.map(|n| n as i64),
+                    stats.total_output_tokens.map(|n| n as i64),
+                    &meta.agent_id,
+                    &meta.parent_session_id,
+                    &meta.continues_from,
+                ],
+            )?;
+        }
+
+        // Messages, blocks and terms go through appenders: a year of sessions is millions
+        // of rows, far too many for one INSERT each.
+        let mut next_block_key: i64 = conn.query_row(
+            "SELECT COALESCE(MAX(block_key), 0) + 1 FROM transcript_blocks",
+            [],
+            |row| row.get(0),
+        )?;
+        let mut messages = conn.appender("transcript_messages")?;
+        let mut blocks = conn.appender("transcript_blocks")?;
+        let mut terms = conn.appender("transcript_terms")?;
+        for (file, transcript) in entries {
+            let Some(transcript) = transcript else {
+                continue;
+            };
+            let file_str = file.to_string_lossy();
+            for (seq, message) in transcript.messages.iter().enumerate() {
+                let usage = message.usage.as_ref();
+                let tokens =
+                    |f: fn(&TokenUsage) -> Option<u64>| usage.and_then(f).map(|n| n as i64);
+                let role = message.role.to_string();
+                messages.append_row(duckdb::params![
+                    file_str,
+                    seq as i64,
+                    &message.id,
+                    &role,
+                    message.timestamp.to_rfc3339(),
+                    &message.model,
+                    tokens(|u| u.input_tokens),
+                    tokens(|u| u.output_tokens),
+                    tokens(|u| u.cache_read_tokens),
+                    tokens(|u| u.cache_creation_tokens),
+                ])?;
+                for (block_seq, block) in message.content.iter().enumerate() {
+                    let block_key = next_block_key;
+                    next_block_key += 1;
+                    let block_tokens = tokenize(&block_search_text(block));
+                    blocks.append_row(duckdb::params![
+                        file_str,
+                        seq as i64,
+                        block_seq as i64,
+                        block_type(block),
+                        serde_json::to_string(block)?,
+                        block_key,
+                        &role,
+                        tool_name(block),
+                        block_file_path(block),
+                        block_tokens.join(" "),
+                        block_tokens.len() as i64,
+                        block_exit_code(block),
+                        block_is_error(block),
+                    ])?;
+
+                    let mut frequencies: HashMap<&str, i64> = HashMap::new();
+                    for token in &block_tokens {
+                        *frequencies.entry(token.as_str()).or_default() += 1;
+                    }
+                    for (term, tf) in frequencies {
+                        terms.append_row(duckdb::params![file_str, block_key, term, tf])?;
+                    }
+                }
+            }
+        }
+        messages.flush()?;
+        blocks.flush()?;
+        terms.flush()?;
+        drop((messages, blocks, terms));
+        conn.execute("COMMIT", [])?;
+        Ok(())
+    }
+
+    /// Search indexed transcripts, best match first.
+    ///
+    /// With a query, transcripts are ranked by the BM25 score of their matching blocks and
+    /// each hit carries up to `blocks_per_transcript` of its best blocks. Without one, every
+    /// transcript passing the metadata filters of `criteria` is returned, newest first.
+    /// The `query`, `use_regex` and `case_sensitive` fields of `criteria` are ignored.
+    pub fn search_transcript_index(
+        &self,
+        query: Option<&QueryNode>,
+        criteria: &TranscriptSearchCriteria,
+        blocks_per_transcript: usize,
+    ) -> Result<Vec<TranscriptHit>> {
+        let mut sql = SqlQuery::default();
+        let conn = self.lock_conn()?;
+
+        let Some(query) = query else {
+            let filters = sql.session_filters(criteria);
+            let statement = format!(
+                "SELECT t.source_file FROM transcripts t
+                 WHERE {}
+                 ORDER BY CAST(t.start_time AS TIMESTAMP) DESC, t.source_file",
+                filters
+            );
+            let mut stmt = conn.prepare(&statement)?;
+            let rows = stmt.query_map(duckdb::params_from_iter(&sql.params), |row| {
+                row.get::<_, String>(0)
+            })?;
+            return rows
+                .map(|row| {
+                    Ok(TranscriptHit {
+                        source_file: PathBuf::from(row?),
+                        score: 0.0,
+                        blocks: Vec::new(),
+                    })
+                })
+                .collect();
+        };
+
+        let matches = sql.compile(query);
+        let filters = sql.session_filters(criteria);
+        let ranked = sql.ranking_terms(query);
+        sql.params.push(blocks_per_transcript.to_string());
+        let statement = format!(
+            "WITH matched AS (
+                SELECT b.block_key, b.source_file, b.message_seq, b.seq, b.term_count
+                FROM transcript_blocks b
+                JOIN transcripts t ON t.source_file = b.source_file
+                WHERE ({}) AND {}
+            ),
+            corpus AS (
+                SELECT COUNT(*)::DOUBLE AS n, GREATEST(AVG(term_count), 1)::DOUBLE AS avgdl
+                FROM transcript_blocks
+            ),
+            query_terms AS (
+                SELECT term, COUNT(*)::DOUBLE AS df FROM transcript_terms
+                WHERE {}
+                GROUP BY term
+            ),
+            hits AS (
+                SELECT tt.block_key, tt.tf, q.df
+                FROM transcript_terms tt JOIN query_terms q ON q.term = tt.term
+            ),
+            scored AS (
+                SELECT m.source_file, m.message_seq, m.seq,
+                    COALESCE(SUM(
+                        ln(1 + (c.n - h.df + 0.5) / (h.df + 0.5))
+                        * h.tf * ({k1} + 1)
+                        / (h.tf + {k1} * (1 - {b} + {b} * m.term_count / c.avgdl))
+                    ), 0) AS score
+                FROM matched m
+                CROSS JOIN corpus c
+                LEFT JOIN hits h ON h.block_key = m.block_key
+                GROUP BY m.block_key, m.source_file, m.message_seq, m.seq
+            )
+            SELECT s.source_file, s.message_seq, s.seq,
+                SUM(s.score) OVER (PARTITION BY s.source_file) AS total
+            FROM scored s
+            JOIN transcripts t ON t.source_file = s.source_file
+            QUALIFY row_number() OVER (
+                PARTITION BY s.source_file ORDER BY s.score DESC, s.message_seq, s.seq
+            ) <= CAST(? AS BIGINT)
+            ORDER BY total DESC, CAST(t.start_time AS TIMESTAMP) DESC, s.source_file,
+                s.score DESC, s.message_seq, s.seq",
+            matches,
+            filters,
+            ranked,
+            k1 = BM25_K1,
+            b = BM25_B,
+        );
+
+        let mut stmt = conn.prepare(&statement)?;
+        let rows = stmt.query_map(duckdb::params_from_iter(&sql.params), |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, i64>(1)?,
+                row.get::<_, i64>(2)?,
+                row.get::<_, f64>(3)?,
+            ))
+        })?;
+        let mut hits: Vec<TranscriptHit> = Vec::new();
+        for row in rows {
+            let (source_file, message_seq, seq, score) = row?;
+            let position = (message_seq.max(0) as usize, seq.max(0) as usize);
+            match hits.last_mut() {
+                Some(hit) if hit.source_file.as_os_str() == source_file.as_str() => {
+                    hit.blocks.push(position)
+                }
+                _ => hits.push(TranscriptHit {
+                    source_file: PathBuf::from(source_file),
+                    score,
+                    blocks: vec![position],
+                }),
+            }
+        }
+        Ok(hits)
+    }
+}
+
+/// BM25 term-frequency saturation.
+const BM25_K1: f64 = 1.2;
+/// BM25 block-length normalization.
+const BM25_B: f64 = 0.75;
+
+/// SQL text and positional parameters built up from a search query.
+#[derive(Default)]
+struct SqlQuery {
+    params: Vec<String>,
+}
+
+impl SqlQuery {
+    fn param(&mut self, value: impl Into<String>) -> &'static str {
+        self.params.push(value.into());
+        "?"
+    }
+
+    /// Condition on a block `b` for a query node.
+    fn compile(&mut self, node: &QueryNode) -> String {
+        match node {
+            QueryNode::Term(term) => format!(
+                "b.block_key IN (SELECT block_key FROM transcript_terms WHERE term = {})",
+                self.param(term.as_str())
+            ),
+            QueryNode::Prefix(prefix) => format!(
+                "b.block_key IN (SELECT block_key FROM transcript_terms WHERE starts_with(term, {}))",
+                self.param(prefix.as_str())
+            ),
+            QueryNode::Phrase(tokens) => {
+                // Every token through the index, then the exact sequence on the block.
+                let mut parts: Vec<String> = tokens
+                    .iter()
+                    .map(|t| self.compile(&QueryNode::Term(t.clone())))
+                    .collect();
+                parts.push(format!(
+                    "contains(' ' || b.tokens || ' ', {})",
+                    self.param(format!(" {} ", tokens.join(" ")))
+                ));
+                parts.join(" AND ")
+            }
+            QueryNode::Field(FieldFilter::Role(role)) => {
+                format!("b.role = {}", self.param(role.to_string()))
+            }
+            QueryNode::Field(FieldFilter::Tool(name)) => format!(
+                "lower(COALESCE(b.tool_name, '')) = {}",
+                self.param(name.as_str())
+            ),
+            QueryNode::Field(FieldFilter::File(path)) => format!(
+                "contains(lower(COALESCE(b.file_path, '')), {})",
+                self.param(path.as_str())
+            ),
+            QueryNode::Field(FieldFilter::Block(block_type)) => {
+                format!("b.block_type = {}", self.param(*block_type))
+            }
+            QueryNode::And(nodes) => self.join(nodes, " AND "),
+            QueryNode::Or(nodes) => self.join(nodes, " OR "),
+            QueryNode::Not(node) => format!("NOT ({})", self.compile(node)),
+        }
+    }
+
+    fn join(&mut self, nodes: &[QueryNode], separator: &str) -> String {
+        let parts: Vec<String> = nodes
+            .iter()
+            .map(|n| format!("({})", self.compile(n)))
+            .collect();
+        parts.join(separator)
+    }
+
+    /// Condition selecting the index terms that count towards a query's score.
+    fn ranking_terms(&mut self, query: &QueryNode) -> String {
+        let mut parts = Vec::new();
+        for node in query.ranking_terms() {
+            match node {
+                QueryNode::Term(term) => {
+                    parts.push(format!("term = {}", self.param(term.as_str())))
+                }
+                QueryNode::Prefix(prefix) => parts.push(format!(
+                    "starts_with(term, {})",
+                    self.param(prefix.as_str())
+                )),
+                QueryNode::Phrase(tokens) => {
+                    for token in tokens {
+                        parts.push(format!("term = {}", self.param(token.as_str())));
+                    }
+                }
+                _ => {}
+            }
+        }
+        if parts.is_empty() {
+            "FALSE".to_string()
+        } else {
+            parts.join(" OR ")
+        }
+    }
+
+    /// Conditions on a transcript `t` from the session filters of `criteria`.
+    fn session_filters(&mut self, criteria: &TranscriptSearchCriteria) -> String {
+        let mut parts = vec!["TRUE".to_string()];
+        if let Some(pattern) = &criteria.session_id_pattern {
+            parts.push(format!(
+                "contains(t.session_id, {})",
+                self.param(pattern.as_str())
+            ));
+        }
+        if let Some(tool) = &criteria.agent_tool {
+            parts.push(format!(
+                "contains(lower(t.agent_tool), {})",
+                self.param(tool.to_lowercase())
+            ));
+        }
+        if let Some(since) = criteria.since {
+            parts.push(format!(
+                "CAST(t.start_time AS TIMESTAMP) >= CAST({} AS TIMESTAMP)",
+                self.param(since.to_rfc3339())
+            ));
+        }
+        if let Some(until) = criteria.until {
+            parts.push(format!(
+                "CAST(t.start_time AS TIMESTAMP) <= CAST({} AS TIMESTAMP)",
+                self.param(until.to_rfc3339())
+            ));
+        }
+        if let Some(model) = &criteria.model {
+            parts.push(format!(
+                "EXISTS (SELECT 1 FROM transcript_messages m
+                         WHERE m.source_file = t.source_file
+                           AND contains(lower(COALESCE(m.model, '')), {}))",
+                self.param(model.to_lowercase())
+            ));
+        }
+        if let Some(branch) = &criteria.git_branch {
+            parts.push(format!(
+                "contains(COALESCE(t.git_branch, ''), {})",
+                self.param(branch.as_str())
+            ));
+        }
+        if let Some(cwd) = &criteria.cwd {
+            parts.push(format!(
+                "contains(COALESCE(t.cwd, ''), {})",
+                self.param(cwd.as_str())
+            ));
+        }
+        let total_tokens = "COALESCE(t.total_input_tokens, 0) + COALESCE(t.total_output_tokens, 0)";
+        if let Some(min) = criteria.min_tokens {
+            parts.push(format!(
+                "{} >= CAST({} AS BIGINT)",
+                total_tokens,
+                self.param(min.to_string())
+            ));
+        }
+        if let Some(max) = criteria.max_tokens {
+            parts.push(format!(
+                "{} <= CAST({} AS BIGINT)",
+                total_tokens,
+                self.param(max.to_string())
+            ));
+        }
+
+        // Filters on the blocks of the transcript.
+        let mut block_conditions = Vec::new();
+        if let Some(path) = &criteria.touched_file {
+            block_conditions.push(format!(
+                "fb.block_type = 'file_operation' AND contains(lower(fb.file_path), {})",
+                self.param(path.to_lowercase())
+            ));
+        }
+        if let Some(tool) = &criteria.used_tool {
+            block_conditions.push(format!(
+                "fb.block_type = 'tool_use' AND lower(fb.tool_name) = {}",
+                self.param(tool.to_lowercase())
+            ));
+        }
+        if criteria.failed_command {
+            block_conditions.push("fb.exit_code <> 0".to_string());
+        }
+        if criteria.tool_error {
+            block_conditions.push("fb.is_error".to_string());
+        }
+        for condition in block_conditions {
+            parts.push(format!(
+                "EXISTS (SELECT 1 FROM transcript_blocks fb
+                         WHERE fb.source_file = t.source_file AND {})",
+                condition
+            ));
+        }
+        parts.join(" AND ")
+    }
+}
+
+/// Whether `file` changed since it was cached (or was never cached).
+pub fn is_transcript_stale(file: &Path, cached: Option<&FileMetadata>) -> bool {
+    let Some(cached) = cached else {
+        return true;
+    };
+    let Ok(meta) = std::fs::metadata(file) else {
+        return true;
+    };
+    let mtime_ns = meta
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|d| d.as_nanos() as i64);
+    mtime_ns != Some(cached.file_mtime_ns) || meta.len() as i64 != cached.file_size_bytes
+}
+
+fn block_type(block: &ContentBlock) -> &'static str {
+    match block {
+        ContentBlock::Text { .. } => "text",
+        ContentBlock::Thinking { .. } => "thinking",
+        ContentBlock::ToolUse { .. } => "tool_use",
+        ContentBlock::ToolResult { .. } => "tool_result",
+        ContentBlock::Code { .. } => "code",
+        ContentBlock::FileOperation { .. } => "file_operation",
+        ContentBlock::Command { .. } => "command",
+    }
+}
+
+fn tool_name(block: &ContentBlock) -> Option<&str> {
+    match block {
+        ContentBlock::ToolUse { name, .. } => Some(name),
+        _ => None,
+    }
+}
+
+/// The file a block is about: the path of a file operation or a tool call's path argument.
+fn block_file_path(block: &ContentBlock) -> Option<&str> {
+    match block {
+        ContentBlock::FileOperation { file_path, .. } => Some(file_path),
+        ContentBlock::ToolUse { input, .. } => ["file_path", "notebook_path", "path"]
+            .iter()
+            .find_map(|key| input.get(key).and_then(|v| v.as_str())),
+        _ => None,
+    }
+}
+
+fn block_exit_code(block: &ContentBlock) -> Option<i32> {
+    match block {
+        ContentBlock::Command { exit_code, .. } => *exit_code,
+        _ => None,
+    }
+}
+
+fn block_is_error(block: &ContentBlock) -> Option<bool> {
+    match block {
+        ContentBlock::ToolResult { is_error, .. } => Some(*is_error),
+        _ => None,
+    }
+}
+
+fn parse_role(role: &str) -> Result<Role> {
+    match role {
+        "user" => Ok(Role::User),
+        "assistant" => Ok(Role::Assistant),
+        "system" => Ok(Role::System),
+        other => Err(anyhow!("Invalid cached message role '{}'", other)),
+    }
+}
+
+fn parse_timestamp(s: &str) -> Result<DateTime<Utc>> {
+    s.parse()
+        .map_err(|e| anyhow!("Invalid cached timestamp '{}': {}", s, e))
+}
@@ -8,9 +8,69 @@ pub struct FileMetadata {
     pub last_parsed_at: String, // ISO8601 timestamp of when it was cached
 }
 
+/// Parser state to save for a trace file together with its cached edits
+#[derive(Debug, Clone)]
+pub struct ParseStateUpdate {
+    pub trace_file: PathBuf,
+    pub byte_offset: u64, // Where parsing stopped
+    pub state: String,    // Parser-specific JSON
+}
+
 /// Report of which files are stale vs fresh
 #[derive(Debug, Clone)]
 pub struct StalenessReport {
     pub stale_files: Vec<PathBuf>, // Files that need reparsing
     pub fresh_files: Vec<PathBuf>, // Files that can use cached data
 }
+
+/// Outcome of bringing an opened cache up to the current schema version
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SchemaUpgrade {
+    /// The cache was already at this version
+    UpToDate(u32),
+    /// A new, empty cache was created at this version
+    Created(u32),
+    /// The schema was migrated in place, keeping cached data
+    Migrated { from: u32, to: u32 },
+    /// The cache could not be migrated and was deleted and recreated
+    Rebuilt { reason: String, to: u32 },
+}
+
+/// A transcript matched by the transcript search index
+#[derive(Debug, Clone)]
+pub struct TranscriptHit {
+    pub source_file: PathBuf,
+    pub score: f64,                  // BM25 relevance summed over matching blocks
+    pub blocks: Vec<(usize, usize)>, // (message, block) positions of the best blocks, best first
+}
+
+/// Summary of a cache database for `ai-blame cache info`
+#[derive(Debug, Clone)]
+pub struct CacheInfo {
+    pub db_path: PathBuf,
+    pub size_bytes: u64,
+    pub schema_version: u32,
+    pub trace_files: u64,
+    pub edit_records: u64,
+    pub transcripts: u64,
+    pub providers: Vec<ProviderStats>,
+}
+
+/// Per-provider totals from the `trace_files` table
+#[derive(Debug, Clone)]
+pub struct ProviderStats {
+    pub provider: String,
+    pub trace_files: u64,
+    pub record_count: u64,
+    pub trace_bytes: u64,
+    pub parse_duration_ms: u64,
+    pub last_parsed_at: Option<String>,
+}
+
+/// Result of compacting a cache database
+#[derive(Debug, Clone)]
+pub struct VacuumReport {
+    pub pruned_files: usize, // Cached trace files that no longer exist on disk
+    pub size_before: u64,    // Database size in bytes before compaction
+    pub size_after: u64,     // Database size in bytes after compaction
+}
@@ -1,16 +1,21 @@
-use crate::blame::{compute_line_blame, group_blocks, BlameBlock, BlameMeta, LineBlame};
+use crate::backup::{undo_run, BackupRun};
+use crate::blame::{
+    compute_line_blame, compute_span_blame, detect_moves, group_blocks, BlameBlock, BlameMeta,
+    BlameSpan, LineBlame, MoveInfo, MoveKind, SpanGranularity,
+};
+use crate::cache::{CacheInfo, CacheManager, SchemaUpgrade};
 use crate::config::{
     find_config, get_default_config, load_config, resolve_sidecar_path, write_seed_config,
     SeedFlavor,
 };
 use crate::extractor::{apply_filters, convert_to_file_histories};
 use crate::models::*;
-use crate::updater::{apply_rule, preview_update};
-use anyhow::{anyhow, Result};
+use crate::updater::{apply_rule, output_path, preview_update};
+use anyhow::{anyhow, Context, Result};
 use chrono::{DateTime, Utc};
 use clap::{CommandFactory, Parser, Subcommand, ValueEnum};
 use clap_complete::{generate, Shell};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::path::{Path, PathBuf};
 use std::process::Command;
 
@@ -20,6 +25,7 @@ enum BlameColumn {
     Model,
     Timestamp,
     Line,
+    Moved,
     Code,
 }
 
@@ -30,7 +36,9 @@ enum TranscriptColumn {
     Timestamp,
     Messages,
     Files,
+    Duration,
     Models,
+    Cost,
     LastMessage,
 }
 
@@ -53,6 +61,13 @@ struct BlameConfig {
     agent_alias: Vec<(String, String)>,
     model_alias: Vec<(String, String)>,
     no_header: bool,
+    reverse: bool,
+    snippet: Option<String>,
+    rev: Option<String>,
+    detect_moves: bool,
+    move_min_lines: usize,
+    format: BlameFormat,
+    spans: Option<SpanLevel>,
 }
 
 #[derive(Parser)]
@@ -137,6 +152,14 @@ enum Commands {
         /// Rebuild cache (invalidate all cached data)
         #[arg(long)]
         rebuild_cache: bool,
+
+        /// Read histories from a provenance store (directory, .jsonl or .ddb) instead of traces
+        #[arg(long)]
+        store: Option<PathBuf>,
+
+        /// How the cost-to-produce columns divide a session's cost between the files it changed
+        #[arg(long, value_enum, default_value = "change-size")]
+        cost_weight: CostWeight,
     },
 
     /// Annotate files or write sidecars/comments using output rules (writes by default)
@@ -183,6 +206,14 @@ enum Commands {
         /// Rebuild cache (invalidate all cached data)
         #[arg(long)]
         rebuild_cache: bool,
+
+        /// Snapshot every file before writing it, into .ai-blame/backup/<run-id>/
+        #[arg(long)]
+        backup: bool,
+
+        /// Restore the files written by a previous `--backup` run, then exit
+        #[arg(long, value_name = "RUN_ID", conflicts_with_all = ["backup", "dry_run"])]
+        undo: Option<String>,
     },
 
     /// Show statistics about available traces
@@ -279,6 +310,120 @@ enum Commands {
         /// Suppress the file metadata header (creation date, etc.)
         #[arg(long)]
         no_header: bool,
+
+        /// Reverse blame: find the edits that removed lines (needs --snippet or --rev)
+        #[arg(long)]
+        reverse: bool,
+
+        /// With --reverse: text whose lines to trace (each non-blank line is looked up)
+        #[arg(long, requires = "reverse")]
+        snippet: Option<String>,
+
+        /// Detect moved and copied blocks (within and across files, like `git blame -M -C`)
+        /// and keep their original attribution
+        #[arg(short = 'M', long, conflicts_with = "reverse")]
+        detect_moves: bool,
+
+        /// Minimum non-blank lines for a block to count as moved or copied
+        #[arg(long, default_value_t = crate::blame::DEFAULT_MOVE_MIN_LINES)]
+        move_min_lines: usize,
+
+        /// With --reverse: trace lines present at this git revision but gone now
+        /// (narrow with --lines, which then refers to the revision's line numbers)
+        #[arg(long, requires = "reverse", conflicts_with = "snippet")]
+        rev: Option<String>,
+
+        /// Output format (table, json)
+        #[arg(long, value_enum, default_value = "table")]
+        format: BlameFormat,
+
+        /// Attribute text within each line at word or character level (JSON output only)
+        #[arg(long, value_enum, conflicts_with = "reverse")]
+        spans: Option<SpanLevel>,
+    },
+
+    /// Show each agent edit to a file as a unified diff (like `git log -p`)
+    Log {
+        /// File to show edits for (path relative to cwd, or absolute)
+        file: String,
+
+        /// Claude trace directory (overrides --dir and --home)
+        #[arg(short = 't', long)]
+        trace_dir: Option<PathBuf>,
+
+        /// Target project directory (default: cwd)
+        #[arg(short = 'd', long)]
+        dir: Option<PathBuf>,
+
+        /// Home directory where .claude/ lives (default: ~)
+        #[arg(long)]
+        home: Option<PathBuf>,
+
+        /// Only show edits on or after this date (YYYY-MM-DD or RFC 3339)
+        #[arg(long)]
+        since: Option<String>,
+
+        /// Only show edits on or before this date (YYYY-MM-DD or RFC 3339)
+        #[arg(long)]
+        until: Option<String>,
+
+        /// Only show edits from sessions whose id starts with this prefix
+        #[arg(long)]
+        session: Option<String>,
+
+        /// Colorize the diff output
+        #[arg(long, value_enum, default_value = "auto")]
+        color: ColorChoice,
+
+        /// Show secrets and personal data instead of redacting them
+        #[arg(long)]
+        no_redact: bool,
+
+        /// Disable cache (always reparse traces)
+        #[arg(long)]
+        no_cache: bool,
+
+        /// Rebuild cache (invalidate all cached data)
+        #[arg(long)]
+        rebuild_cache: bool,
+    },
+
+    /// Reconstruct a file as it was at a point in the agent's history
+    Show {
+        /// File to reconstruct (path relative to cwd, or absolute; need not exist anymore)
+        file: String,
+
+        /// Timestamp (YYYY-MM-DD or RFC 3339) or session id prefix to reconstruct at
+        #[arg(long)]
+        at: String,
+
+        /// Claude trace directory (overrides --dir and --home)
+        #[arg(short = 't', long)]
+        trace_dir: Option<PathBuf>,
+
+        /// Target project directory (default: cwd)
+        #[arg(short = 'd', long)]
+        dir: Option<PathBuf>,
+
+        /// Home directory where .claude/ lives (default: ~)
+        #[arg(long)]
+        home: Option<PathBuf>,
+
+        /// Exit with an error if any step of the replay diverges
+        #[arg(long)]
+        strict: bool,
+
+        /// Show secrets and personal data instead of redacting them
+        #[arg(long)]
+        no_redact: bool,
+
+        /// Disable cache (always reparse traces)
+        #[arg(long)]
+        no_cache: bool,
+
+        /// Rebuild cache (invalidate all cached data)
+        #[arg(long)]
+        rebuild_cache: bool,
     },
 
     /// Show timeline of actions in the repository
@@ -322,6 +467,156 @@ enum Commands {
         action: TranscriptAction,
     },
 
+    /// Run SQL against the trace cache (tables: edit_records, trace_files; see --list-views)
+    Query {
+        /// SQL statement to run
+        #[arg(required_unless_present = "list_views")]
+        sql: Option<String>,
+
+        /// Claude trace directory (only this directory; default: Claude and Codex trace directories)
+        #[arg(short = 't', long)]
+        trace_dir: Option<PathBuf>,
+
+        /// Target project directory (default: cwd)
+        #[arg(short = 'd', long)]
+        dir: Option<PathBuf>,
+
+        /// Home directory where .claude/ lives (default: ~)
+        #[arg(long)]
+        home: Option<PathBuf>,
+
+        /// Output format (parquet requires --output)
+        #[arg(long, value_enum, default_value = "table")]
+        format: QueryFormat,
+
+        /// Write the result to a file instead of stdout
+        #[arg(short = 'o', long)]
+        output: Option<PathBuf>,
+
+        /// List the predefined views and exit
+        #[arg(long)]
+        list_views: bool,
+
+        /// Query the cache as is, without parsing new trace data first
+        #[arg(long)]
+        no_refresh: bool,
+    },
+
+    /// Export edits, timeline events or transcript summaries for analytics pipelines
+    Export {
+        /// What to export
+        #[arg(long, value_enum, default_value = "edits")]
+        data: ExportData,
+
+        /// File format
+        #[arg(long, value_enum, default_value = "parquet")]
+        format: ExportFileFormat,
+
+        /// File to write
+        #[arg(short = 'o', long)]
+        output: PathBuf,
+
+        /// Leave out old_string, new_string, structured_patch and create_content (edits only)
+        #[arg(long)]
+        no_payloads: bool,
+
+        /// Claude trace directory (overrides --dir and --home)
+        #[arg(short = 't', long)]
+        trace_dir: Option<PathBuf>,
+
+        /// Target project directory (default: cwd)
+        #[arg(short = 'd', long)]
+        dir: Option<PathBuf>,
+
+        /// Home directory where .claude/ lives (default: ~)
+        #[arg(long)]
+        home: Option<PathBuf>,
+
+        /// Filter files by path pattern (edits and timeline)
+        #[arg(short = 'p', long, default_value = "")]
+        pattern: String,
+
+        /// Skip Codex/Copilot traces
+        #[arg(long)]
+        skip_codex: bool,
+
+        /// Export without redacting secrets and personal data
+        #[arg(long)]
+        no_redact: bool,
+    },
+
+    /// Estimate agent spend from transcript token usage
+    ///
+    /// Costs use the built-in price table, overridden by the `pricing` section of
+    /// .ai-blame.yaml (see --list-prices).
+    Cost {
+        /// Group spend by session, day, model, agent or file touched
+        #[arg(long, value_enum, default_value = "session")]
+        by: CostBy,
+
+        /// How --by file divides a session's cost between the files it changed
+        #[arg(long, value_enum, default_value = "change-size")]
+        weight: CostWeight,
+
+        /// Claude trace directory (only this directory; default: Claude and Codex trace directories)
+        #[arg(short = 't', long)]
+        trace_dir: Option<PathBuf>,
+
+        /// Target project directory (default: cwd)
+        #[arg(short = 'd', long)]
+        dir: Option<PathBuf>,
+
+        /// Home directory where .claude/ lives (default: ~)
+        #[arg(long)]
+        home: Option<PathBuf>,
+
+        /// Config file with a pricing section (default: auto-find .ai-blame.yaml)
+        #[arg(short = 'c', long)]
+        config: Option<PathBuf>,
+
+        /// Only include sessions since this date (YYYY-MM-DD or RFC 3339)
+        #[arg(long)]
+        since: Option<String>,
+
+        /// Only include sessions until this date (YYYY-MM-DD or RFC 3339)
+        #[arg(long)]
+        until: Option<String>,
+
+        /// Skip Codex/Copilot traces
+        #[arg(long)]
+        skip_codex: bool,
+
+        /// Limit to the N most expensive groups (0 for all)
+        #[arg(short = 'n', long, default_value = "0")]
+        limit: usize,
+
+        /// Output format
+        #[arg(long, value_enum, default_value = "table")]
+        format: CostFormat,
+
+        /// Print the price table in effect and exit
+        #[arg(long)]
+        list_prices: bool,
+    },
+
+    /// Inspect and maintain the trace cache (.ai-blame.ddb)
+    Cache {
+        #[command(subcommand)]
+        action: CacheAction,
+
+        /// Claude trace directory (only this directory; default: Claude and Codex trace directories)
+        #[arg(short = 't', long, global = true)]
+        trace_dir: Option<PathBuf>,
+
+        /// Target project directory (default: cwd)
+        #[arg(short = 'd', long, global = true)]
+        dir: Option<PathBuf>,
+
+        /// Home directory where .claude/ lives (default: ~)
+        #[arg(long, global = true)]
+        home: Option<PathBuf>,
+    },
+
     /// Generate shell completion scripts
     Completions {
         /// Shell to generate completions for
@@ -330,6 +625,8 @@ enum Commands {
     },
 }
 
+// Parsed once per run, so the size of the `Search` variant doesn't matter.
+#[allow(clippy::large_enum_variant)]
 #[derive(Subcommand)]
 enum TranscriptAction {
     /// List all available transcripts
@@ -354,7 +651,7 @@ enum TranscriptAction {
         #[arg(long, value_enum, default_value = "table")]
         format: TranscriptFormat,
 
-        /// Columns to display: S=Session, A=Agent, T=Timestamp, M=Messages, F=Files, O=mOdels, L=Last message.
+        /// Columns to display: S=Session, A=Agent, T=Timestamp, M=Messages, F=Files, D=Duration, O=mOdels, C=Cost, L=Last message.
         /// Default is 'SATM' for table format.
         #[arg(long)]
         columns: Option<String>,
@@ -377,7 +674,7 @@ enum TranscriptAction {
         #[arg(long)]
         home: Option<PathBuf>,
 
-        /// Output format (text, json, markdown)
+        /// Output format (text, json, markdown, html)
         #[arg(long, value_enum, default_value = "text")]
         format: TranscriptViewFormat,
 
@@ -392,12 +689,22 @@ enum TranscriptAction {
         /// Show tool use details
         #[arg(long)]
         show_tools: bool,
+
+        /// Show secrets and personal data instead of redacting them
+        #[arg(long)]
+        no_redact: bool,
     },
 
     /// Search across all transcripts for matching content
+    ///
+    /// Queries use the full-text index and are ranked by relevance. Syntax: words (all
+    /// required), "exact phrases", prefix*, OR, NOT or -word, parentheses, and the filters
+    /// role:user|assistant, tool:NAME, file:PATH and block:TYPE (text, thinking, tool,
+    /// result, code, file, command).
     Search {
-        /// Search query (text or regex pattern)
-        query: String,
+        /// Search query (see above), or a regex pattern with --regex; omit to list the
+        /// transcripts matching the filters
+        query: Option<String>,
 
         /// Claude trace directory (overrides --dir and --home)
         #[arg(short = 't', long)]
@@ -411,11 +718,11 @@ enum TranscriptAction {
         #[arg(long)]
         home: Option<PathBuf>,
 
-        /// Treat query as a regex pattern
+        /// Treat query as a regex pattern (scans every message, unranked)
         #[arg(short = 'e', long)]
         regex: bool,
 
-        /// Case-sensitive search (default: case-insensitive)
+        /// Case-sensitive substring search (scans every message, unranked)
         #[arg(short = 's', long)]
         case_sensitive: bool,
 
@@ -431,14 +738,46 @@ enum TranscriptAction {
         #[arg(long)]
         model: Option<String>,
 
-        /// Only include transcripts since this date (YYYY-MM-DD)
+        /// Only include transcripts since this date (YYYY-MM-DD or RFC 3339)
         #[arg(long)]
         since: Option<String>,
 
-        /// Only include transcripts until this date (YYYY-MM-DD)
+        /// Only include transcripts until this date (YYYY-MM-DD or RFC 3339)
         #[arg(long)]
         until: Option<String>,
 
+        /// Only transcripts that created, edited or read a file whose path contains this
+        #[arg(long, value_name = "PATH")]
+        file: Option<String>,
+
+        /// Only transcripts that called this tool (e.g., "Bash", "Edit")
+        #[arg(long)]
+        tool: Option<String>,
+
+        /// Only transcripts with a command that exited non-zero
+        #[arg(long)]
+        failed_commands: bool,
+
+        /// Only transcripts with a tool result reported as an error
+        #[arg(long)]
+        tool_errors: bool,
+
+        /// Filter by git branch pattern
+        #[arg(long)]
+        branch: Option<String>,
+
+        /// Filter by working directory pattern
+        #[arg(long)]
+        cwd: Option<String>,
+
+        /// Only transcripts using at least N tokens (input + output)
+        #[arg(long, value_name = "N")]
+        min_tokens: Option<u64>,
+
+        /// Only transcripts using at most N tokens (input + output)
+        #[arg(long, value_name = "N")]
+        max_tokens: Option<u64>,
+
         /// Limit to N matching transcripts (0 for all)
         #[arg(short = 'n', long, default_value = "20")]
         limit: usize,
@@ -446,20 +785,165 @@ enum TranscriptAction {
         /// Output format (table, json)
         #[arg(long, value_enum, default_value = "table")]
         format: TranscriptFormat,
+
+        /// Show secrets and personal data instead of redacting them
+        #[arg(long)]
+        no_redact: bool,
     },
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum QueryFormat {
+    Table,
+    Csv,
+    Json,
+    Parquet,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum ExportData {
+    /// One row per edit
+    Edits,
+    /// One row per timeline event
+    Timeline,
+    /// One row per transcript, with its statistics
+    Transcripts,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum CostBy {
+    Session,
+    Day,
+    Model,
+    Agent,
+    /// Files the session changed, sharing its cost as --weight says
+    File,
+}
+
+impl CostBy {
+    fn group(self, weight: CostWeight) -> crate::pricing::CostGroup {
+        match self {
+            CostBy::Session => crate::pricing::CostGroup::Session,
+            CostBy::Day => crate::pricing::CostGroup::Day,
+            CostBy::Model => crate::pricing::CostGroup::Model,
+            CostBy::Agent => crate::pricing::CostGroup::Agent,
+            CostBy::File => crate::pricing::CostGroup::File(weight.into()),
+        }
+    }
+}
+
+/// How a session's cost is divided between the files it changed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum CostWeight {
+    /// In proportion to the characters changed in each file
+    ChangeSize,
+    /// Each response's cost goes to the edit it led to
+    Messages,
+}
+
+impl From<CostWeight> for crate::pricing::Attribution {
+    fn from(weight: CostWeight) -> Self {
+        match weight {
+            CostWeight::ChangeSize => crate::pricing::Attribution::ChangeSize,
+            CostWeight::Messages => crate::pricing::Attribution::Messages,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum CostFormat {
+    Table,
+    Json,
+    Csv,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum ExportFileFormat {
+    Parquet,
+    Csv,
+    Jsonl,
+}
+
+impl From<ExportFileFormat> for crate::export::ExportFormat {
+    fn from(format: ExportFileFormat) -> Self {
+        match format {
+            ExportFileFormat::Parquet => crate::export::ExportFormat::Parquet,
+            ExportFileFormat::Csv => crate::export::ExportFormat::Csv,
+            ExportFileFormat::Jsonl => crate::export::ExportFormat::Jsonl,
+        }
+    }
+}
+
+#[derive(Subcommand, Clone, Copy, PartialEq, Eq)]
+enum CacheAction {
+    /// Show cache location, size, schema version and per-provider statistics
+    Info,
+
+    /// Drop entries for deleted trace files and compact the cache database
+    Vacuum,
+
+    /// Delete the cache (it is rebuilt on the next run)
+    Clear,
+
+    /// Upgrade the cache schema to the current version
+    Migrate,
+}
+
 #[derive(Debug, Clone, Copy, ValueEnum)]
 enum TranscriptFormat {
     Table,
     Json,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum BlameFormat {
+    Table,
+    Json,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum SpanLevel {
+    Word,
+    Char,
+}
+
+impl From<SpanLevel> for SpanGranularity {
+    fn from(level: SpanLevel) -> Self {
+        match level {
+            SpanLevel::Word => SpanGranularity::Word,
+            SpanLevel::Char => SpanGranularity::Char,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, ValueEnum)]
 enum TranscriptViewFormat {
     Text,
     Json,
     Markdown,
+    /// Standalone page with collapsible tool calls; shows everything
+    Html,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum ColorChoice {
+    /// Color when writing to a terminal and NO_COLOR is unset
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    fn enabled(self) -> bool {
+        use std::io::IsTerminal;
+        match self {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => {
+                std::env::var_os("NO_COLOR").is_none() && std::io::stdout().is_terminal()
+            }
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy, ValueEnum)]
@@ -474,6 +958,7 @@ const AGENT_WIDTH: usize = 18;
 const MODEL_WIDTH: usize = 20;
 const TIMESTAMP_WIDTH: usize = 16;
 const LINE_WIDTH: usize = 5;
+const MOVED_WIDTH: usize = 23;
 const MIN_HEADER_LINE_LENGTH: usize = 10;
 
 fn parse_alias_pair(s: &str) -> Result<(String, String), String> {
@@ -502,11 +987,12 @@ fn parse_column_spec(spec: Option<&str>, show_agent: bool) -> Result<Vec<BlameCo
                 'M' | 'm' => Some(BlameColumn::Model),
                 'T' | 't' => Some(BlameColumn::Timestamp),
                 'L' | 'l' => Some(BlameColumn::Line),
+                'V' | 'v' => Some(BlameColumn::Moved),
                 'C' | 'c' => Some(BlameColumn::Code),
                 _ if ch.is_whitespace() => None,
                 _ => {
                     return Err(format!(
-                        "invalid column specifier '{}'; allowed: A,M,T,L,C",
+                        "invalid column specifier '{}'; allowed: A,M,T,L,V,C",
                         ch
                     ))
                 }
@@ -571,6 +1057,7 @@ fn column_label(column: BlameColumn) -> &'static str {
         BlameColumn::Model => "model",
         BlameColumn::Timestamp => "timestamp",
         BlameColumn::Line => "line",
+        BlameColumn::Moved => "moved",
         BlameColumn::Code => "code",
     }
 }
@@ -581,6 +1068,7 @@ fn column_width_and_alignment(column: BlameColumn) -> (Option<usize>, bool) {
         BlameColumn::Model => (Some(MODEL_WIDTH), false),
         BlameColumn::Timestamp => (Some(TIMESTAMP_WIDTH), false),
         BlameColumn::Line => (Some(LINE_WIDTH), true),
+        BlameColumn::Moved => (Some(MOVED_WIDTH), false),
         BlameColumn::Code => (None, false),
     }
 }
@@ -625,6 +1113,19 @@ fn format_timestamp(meta: Option<&BlameMeta>) -> String {
     }
 }
 
+fn format_moved(line: &LineBlame) -> String {
+    match &line.moved {
+        Some(m) => {
+            let verb = match m.kind {
+                MoveKind::Moved => "moved",
+                MoveKind::Copied => "copied",
+            };
+            format!("{} {}", verb, m.by.timestamp.format("%Y-%m-%d %H:%M"))
+        }
+        None => "-".to_string(),
+    }
+}
+
 fn format_row(columns: &[BlameColumn], line: &LineBlame, aliases: &AliasConfig) -> String {
     let mut parts = Vec::new();
     for col in columns {
@@ -639,6 +1140,7 @@ fn format_row(columns: &[BlameColumn], line: &LineBlame, aliases: &AliasConfig)
                 push_cell(&mut parts, *col, format_timestamp(line.meta.as_ref()))
             }
             BlameColumn::Line => push_cell(&mut parts, *col, line.line_no.to_string()),
+            BlameColumn::Moved => push_cell(&mut parts, *col, format_moved(line)),
             BlameColumn::Code => parts.push(format!("| {}", line.text)),
         }
     }
@@ -676,7 +1178,7 @@ fn format_block_label(
                     BlameColumn::Agent => parts.push(format_agent(Some(meta), aliases)),
                     BlameColumn::Model => parts.push(format_model(Some(meta), aliases)),
                     BlameColumn::Timestamp => parts.push(format_timestamp(Some(meta))),
-                    BlameColumn::Line | BlameColumn::Code => {}
+                    BlameColumn::Line | BlameColumn::Moved | BlameColumn::Code => {}
                 }
             }
             if parts.is_empty() {
@@ -763,68 +1265,56 @@ fn get_file_creation_date(file_path: &Path) -> Option<DateTime<Utc>> {
         .map(|dt| dt.with_timezone(&Utc))
 }
 
-fn blame_command(config: BlameConfig) -> Result<()> {
-    let trace_dir = resolve_trace_dir(config.trace_dir, config.target_dir, config.home_dir);
-    if !trace_dir.exists() {
-        eprintln!("Trace directory not found: {:?}", trace_dir);
-        std::process::exit(1);
-    }
-
-    let file_path = PathBuf::from(&config.file);
-    let file_path = if file_path.exists() {
-        file_path
-    } else {
-        std::env::current_dir()
-            .unwrap_or_else(|_| PathBuf::from("."))
-            .join(&config.file)
-    };
-    if !file_path.exists() {
-        eprintln!("File not found: {:?}", file_path);
-        std::process::exit(1);
-    }
-
-    let current_content = std::fs::read_to_string(&file_path)?;
-
-    // Extract edits from both Claude and Codex traces, using a loose substring filter to reduce scan cost.
-    let pattern = Path::new(&config.file)
+/// Edits for one file from the Claude and Codex traces, picking the best-matching trace path.
+fn trace_edits_for_file(trace_dir: &Path, file: &str, rel: &str) -> Result<Vec<EditRecord>> {
+    // Use a loose substring filter to reduce scan cost.
+    let pattern = Path::new(file)
         .file_name()
         .and_then(|n| n.to_str())
-        .unwrap_or(&config.file)
+        .unwrap_or(file)
         .to_string();
+    let edits_by_file = extract_trace_edits(trace_dir, Some(pattern))?;
+    Ok(best_matching_edits(&edits_by_file, file, rel))
+}
+
+/// Edits from the Claude and Codex traces under `trace_dir`, optionally filtered by path substring.
+fn extract_trace_edits(
+    trace_dir: &Path,
+    file_pattern: Option<String>,
+) -> Result<crate::cache::EditsByFile> {
     let filter_config = FilterConfig {
-        file_pattern: Some(pattern),
+        file_pattern,
         ..Default::default()
     };
-    let all_trace_dirs = crate::extractor::get_all_trace_dirs(&trace_dir);
+    let all_trace_dirs = crate::extractor::get_all_trace_dirs(trace_dir);
     let trace_dir_refs: Vec<&Path> = all_trace_dirs.iter().map(|p| p.as_path()).collect();
 
     // Determine repo root from file path
     let repo_root = std::env::current_dir().ok();
-    let edits_by_file = crate::extractor::extract_edit_history_from_dirs(
+    crate::extractor::extract_edit_history_from_dirs(
         &trace_dir_refs,
         &filter_config,
         repo_root.as_deref(),
-    )?;
-
-    // Pick the best-matching trace path.
-    let rel = file_path
-        .strip_prefix(std::env::current_dir().unwrap_or_else(|_| PathBuf::from(".")))
-        .ok()
-        .and_then(|p| p.to_str())
-        .map(|s| s.to_string())
-        .unwrap_or_else(|| config.file.clone());
+    )
+}
 
+/// The edits of the trace path that best matches `file` (as given) or `rel` (cwd-relative).
+fn best_matching_edits(
+    edits_by_file: &crate::cache::EditsByFile,
+    file: &str,
+    rel: &str,
+) -> Vec<EditRecord> {
     let mut best: Option<(&String, &Vec<EditRecord>, usize)> = None;
-    for (k, v) in &edits_by_file {
+    for (k, v) in edits_by_file {
         let norm = crate::extractor::normalize_path(k, None);
-        let score = if norm == rel || norm == config.file {
+        let score = if norm == rel || norm == file {
             0
-        } else if norm.ends_with(&rel) || norm.ends_with(&config.file) || rel.ends_with(&norm) {
+        } else if norm.ends_with(rel) || norm.ends_with(file) || rel.ends_with(&norm) {
             1
         } else if Path::new(&norm)
             .file_name()
             .and_then(|n| n.to_str())
-            .map(|n| config.file.ends_with(n) || rel.ends_with(n))
+            .map(|n| file.ends_with(n) || rel.ends_with(n))
             .unwrap_or(false)
         {
             2
@@ -836,8 +1326,158 @@ fn blame_command(config: BlameConfig) -> Result<()> {
         }
     }
 
-    let edits = best.map(|b| b.1.clone()).unwrap_or_default();
-    let blamed = compute_line_blame(&current_content, &edits)?;
+    best.map(|b| b.1.clone()).unwrap_or_default()
+}
+
+/// `blame --reverse`: report which edit removed each line of a snippet or of an older revision.
+fn reverse_blame_command(config: BlameConfig) -> Result<()> {
+    let trace_dir = resolve_trace_dir(config.trace_dir, config.target_dir, config.home_dir);
+    if !trace_dir.exists() {
+        eprintln!("Trace directory not found: {:?}", trace_dir);
+        std::process::exit(1);
+    }
+
+    let cwd = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
+    let file_path = cwd.join(&config.file);
+    let rel = file_path
+        .strip_prefix(&cwd)
+        .ok()
+        .and_then(|p| p.to_str())
+        .map(|s| s.to_string())
+        .unwrap_or_else(|| config.file.clone());
+
+    let mut lines: Vec<(usize, String)> = if let Some(snippet) = &config.snippet {
+        snippet
+            .lines()
+            .enumerate()
+            .filter(|(_, l)| !l.trim().is_empty())
+            .map(|(i, l)| (i + 1, l.to_string()))
+            .collect()
+    } else if let Some(rev) = &config.rev {
+        let output = Command::new("git")
+            .arg("show")
+            .arg(format!("{}:./{}", rev, rel))
+            .output()?;
+        if !output.status.success() {
+            eprintln!(
+                "Could not read {} at {}: {}",
+                rel,
+                rev,
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+            std::process::exit(1);
+        }
+        let old_content = String::from_utf8_lossy(&output.stdout);
+        // A deleted file counts as empty: every line of the revision is gone.
+        let current = std::fs::read_to_string(&file_path).unwrap_or_default();
+        crate::blame::removed_lines(&old_content, &current)
+    } else {
+        eprintln!("--reverse needs --snippet <TEXT> or --rev <REV>");
+        std::process::exit(2);
+    };
+
+    if let Some(spec) = config.lines.as_deref() {
+        let Some((start, end)) = parse_line_range(spec) else {
+            eprintln!("Invalid --lines value (expected N-M): {}", spec);
+            std::process::exit(2);
+        };
+        lines.retain(|(n, _)| *n >= start && *n <= end);
+    }
+    if lines.is_empty() {
+        println!("(no removed lines to trace)");
+        return Ok(());
+    }
+
+    let edits = trace_edits_for_file(&trace_dir, &config.file, &rel)?;
+    let removals = crate::blame::compute_reverse_blame(&lines, &edits);
+
+    // Group lines by the edit that removed them, oldest removal first.
+    let mut groups: Vec<(Option<BlameMeta>, Vec<&crate::blame::LineRemoval>)> = Vec::new();
+    for removal in &removals {
+        match groups.iter_mut().find(|(meta, _)| *meta == removal.meta) {
+            Some((_, members)) => members.push(removal),
+            None => groups.push((removal.meta.clone(), vec![removal])),
+        }
+    }
+    groups.sort_by_key(|(meta, _)| (meta.is_none(), meta.as_ref().map(|m| m.timestamp)));
+
+    for (i, (meta, members)) in groups.iter().enumerate() {
+        if i > 0 {
+            println!();
+        }
+        match meta {
+            Some(m) => {
+                let agent = match &m.agent_version {
+                    Some(v) => format!("{}@{}", m.agent_tool, v),
+                    None => m.agent_tool.clone(),
+                };
+                println!(
+                    "Removed {} by {} ({}) in session {}:",
+                    m.timestamp.format("%Y-%m-%d %H:%M:%S UTC"),
+                    m.model,
+                    agent,
+                    m.session_id
+                );
+            }
+            None => println!("No recorded edit removed:"),
+        }
+        for removal in members {
+            println!("{:>6} | {}", removal.line_no, removal.text);
+        }
+    }
+    Ok(())
+}
+
+fn blame_command(config: BlameConfig) -> Result<()> {
+    if config.format == BlameFormat::Json && config.reverse {
+        anyhow::bail!("--format json is not supported with --reverse");
+    }
+    if config.spans.is_some() && config.format != BlameFormat::Json {
+        anyhow::bail!("--spans requires --format json");
+    }
+    if config.reverse {
+        return reverse_blame_command(config);
+    }
+
+    let file_path = PathBuf::from(&config.file);
+    let file_path = if file_path.exists() {
+        file_path
+    } else {
+        std::env::current_dir()
+            .unwrap_or_else(|_| PathBuf::from("."))
+            .join(&config.file)
+    };
+    if !file_path.exists() {
+        eprintln!("File not found: {:?}", file_path);
+        std::process::exit(1);
+    }
+
+    let current_content = std::fs::read_to_string(&file_path)?;
+
+    let rel = file_path
+        .strip_prefix(std::env::current_dir().unwrap_or_else(|_| PathBuf::from(".")))
+        .ok()
+        .and_then(|p| p.to_str())
+        .map(|s| s.to_string())
+        .unwrap_or_else(|| config.file.clone());
+
+    let mut all_edits = crate::cache::EditsByFile::new();
+    let trace_dir = resolve_trace_dir(config.trace_dir, config.target_dir, config.home_dir);
+    if !trace_dir.exists() {
+        eprintln!("Trace directory not found: {:?}", trace_dir);
+        std::process::exit(1);
+    }
+    let edits = if config.detect_moves {
+        // Moves can come from any file, so extract everything.
+        all_edits = extract_trace_edits(&trace_dir, None)?;
+        best_matching_edits(&all_edits, &config.file, &rel)
+    } else {
+        trace_edits_for_file(&trace_dir, &config.file, &rel)?
+    };
+    let mut blamed = compute_line_blame(&current_content, &edits)?;
+    if config.detect_moves {
+        detect_moves(&mut blamed, &edits, &all_edits, config.move_min_lines);
+    }
 
     let (mut start_line, mut end_line) = (1usize, blamed.len().max(1));
     if let Some(spec) = config.lines.as_deref() {
@@ -857,14 +1497,41 @@ fn blame_command(config: BlameConfig) -> Result<()> {
         return Ok(());
     }
 
+    if config.format == BlameFormat::Json {
+        let spans = match config.spans {
+            Some(level) => Some(compute_span_blame(&current_content, &edits, level.into())?),
+            None => None,
+        };
+        let lines: Vec<JsonBlameLine> = blamed[(start_line - 1)..end_line]
+            .iter()
+            .map(|line| JsonBlameLine {
+                line: line.line_no,
+                text: &line.text,
+                meta: line.meta.as_ref(),
+                moved: line.moved.as_ref(),
+                spans: spans
+                    .as_ref()
+                    .and_then(|s| s.get(line.line_no - 1))
+                    .map(Vec::as_slice),
+            })
+            .collect();
+        let output = JsonBlame { file: &rel, lines };
+        println!("{}", serde_json::to_string_pretty(&output)?);
+        return Ok(());
+    }
+
     let blocks_vec = if config.blocks {
         group_blocks(&blamed)
     } else {
         Vec::new()
     };
 
-    let columns =
+    let mut columns =
         parse_column_spec(config.columns.as_deref(), config.show_agent).map_err(|e| anyhow!(e))?;
+    if config.detect_moves && config.columns.is_none() {
+        let code_idx = columns.len() - 1;
+        columns.insert(code_idx, BlameColumn::Moved);
+    }
     let alias_config = AliasConfig {
         agent_aliases: config.agent_alias.into_iter().collect(),
         model_aliases: config.model_alias.into_iter().collect(),
@@ -897,9 +1564,67 @@ fn blame_command(config: BlameConfig) -> Result<()> {
         println!("{}", format_row(&columns, line, &alias_config));
     }
 
+    print_moved_blocks(&blamed[(start_line - 1)..end_line]);
+
     Ok(())
 }
 
+/// `blame --format json` output.
+#[derive(serde::Serialize)]
+struct JsonBlame<'a> {
+    file: &'a str,
+    lines: Vec<JsonBlameLine<'a>>,
+}
+
+/// One line of `blame --format json`.
+#[derive(serde::Serialize)]
+struct JsonBlameLine<'a> {
+    line: usize,
+    text: &'a str,
+    meta: Option<&'a BlameMeta>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    moved: Option<&'a MoveInfo>,
+    /// Present with `--spans`: character ranges of the line and who wrote each.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    spans: Option<&'a [BlameSpan]>,
+}
+
+/// Footer for `blame -M`: one line per moved/copied block, with where it came from.
+fn print_moved_blocks(lines: &[LineBlame]) {
+    let mut blocks: Vec<(usize, usize, &MoveInfo)> = Vec::new();
+    for line in lines {
+        let Some(info) = &line.moved else { continue };
+        match blocks.last_mut() {
+            Some((_, end, prev)) if *end + 1 == line.line_no && *prev == info => {
+                *end = line.line_no
+            }
+            _ => blocks.push((line.line_no, line.line_no, info)),
+        }
+    }
+    if blocks.is_empty() {
+        return;
+    }
+
+    println!();
+    println!("Moved/copied blocks:");
+    for (start, end, info) in blocks {
+        let verb = match info.kind {
+            MoveKind::Moved => "moved",
+            MoveKind::Copied => "copied",
+        };
+        println!(
+            "  lines {}-{} {} from {} by {} in session {} ({})",
+            start,
+            end,
+            verb,
+            crate::extractor::normalize_path(&info.from_file, None),
+            info.by.model,
+            info.by.session_id,
+            info.by.timestamp.format("%Y-%m-%d %H:%M")
+        );
+    }
+}
+
 #[cfg(test)]
 #[allow(clippy::items_after_test_module)]
 mod tests {
@@ -933,6 +1658,24 @@ mod tests {
         assert!(cols_with_agent.starts_with(&[BlameColumn::Agent, BlameColumn::Model]));
     }
 
+    #[test]
+    fn test_format_log_entry_has_no_line_diff_for_notebook_cells() {
+        let edit = EditRecord {
+            file_path: "analysis.ipynb".to_string(),
+            timestamp: Utc.with_ymd_and_hms(2025, 12, 1, 9, 0, 0).unwrap(),
+            model: "claude-3-opus".to_string(),
+            session_id: "s1".to_string(),
+            agent_tool: "claude-code".to_string(),
+            new_string: Some("import pandas as pd".to_string()),
+            cell_id: Some("cell-1".to_string()),
+            ..Default::default()
+        };
+        let entry = format_log_entry(&edit, "analysis.ipynb", false);
+        assert!(entry.contains("Cell:    cell-1\n"));
+        assert!(entry.ends_with("    (notebook cell edit; no line diff)\n"));
+        assert!(!entry.contains("+import pandas"));
+    }
+
     #[test]
     fn test_parse_column_spec_rejects_unknown() {
         let err = parse_column_spec(Some("AMX"), false).unwrap_err();
@@ -946,6 +1689,7 @@ mod tests {
             line_no: 42,
             text: "print('hi')".to_string(),
             meta: Some(meta),
+            moved: None,
         };
         let aliases = AliasConfig {
             agent_aliases: HashMap::from([("claude-code".to_string(), "CC".to_string())]),
@@ -975,13 +1719,32 @@ mod tests {
     }
 }
 
-fn print_summary_table(histories: &HistoriesByFile) {
+fn print_summary_table(histories: &HistoriesByFile, costs: &HashMap<String, f64>) {
+    let cost_column = |cost: Option<&f64>| {
+        if costs.is_empty() {
+            String::new()
+        } else {
+            format!(
+                " | {:>10}",
+                cost.map(|c| format_cost(*c)).unwrap_or_default()
+            )
+        }
+    };
+
     println!("\n=== Summary ===");
     println!(
-        "{:<50} | {:>5} | {:<20} | {:<20}",
-        "File", "Edits", "First Edit", "Last Edit"
+        "{:<50} | {:>5} | {:<20} | {:<20}{}",
+        "File",
+        "Edits",
+        "First Edit",
+        "Last Edit",
+        if costs.is_empty() {
+            String::new()
+        } else {
+            format!(" | {:>10}", "Cost")
+        }
     );
-    println!("{}", "-".repeat(105));
+    println!("{}", "-".repeat(if costs.is_empty() { 105 } else { 118 }));
 
     let mut sorted_paths: Vec<_> = histories.keys().collect();
     sorted_paths.sort();
@@ -1002,9 +1765,78 @@ fn print_summary_table(histories: &HistoriesByFile) {
             .last_edit()
             .map(|dt| dt.format("%Y-%m-%d %H:%M").to_string())
             .unwrap_or_else(|| "N/A".to_string());
-        println!("{:<50} | {:>5} | {:<20} | {:<20}", name, count, first, last);
+        println!(
+            "{:<50} | {:>5} | {:<20} | {:<20}{}",
+            name,
+            count,
+            first,
+            last,
+            cost_column(costs.get(path))
+        );
+    }
+
+    println!();
+}
+
+/// Cost to produce each file of `histories`: the share of agent spend attributed to it
+/// (see [`crate::pricing::Attribution`]) across the sessions in the trace directories.
+/// Files without attributed spend are left out.
+fn file_costs(
+    trace_dir: &Path,
+    skip_codex: bool,
+    prices: &crate::pricing::PriceTable,
+    weight: CostWeight,
+    histories: &HistoriesByFile,
+) -> Result<HashMap<String, f64>> {
+    let mut trace_dirs = crate::extractor::get_all_trace_dirs(trace_dir);
+    if skip_codex {
+        trace_dirs.retain(|d| !d.to_string_lossy().contains("codex"));
+    }
+    let mut transcripts = Vec::new();
+    for dir in trace_dirs.iter().filter(|d| d.exists()) {
+        transcripts.extend(crate::transcript::parse_transcripts_from_directory(dir)?);
+    }
+
+    let group = crate::pricing::CostGroup::File(weight.into());
+    Ok(crate::pricing::cost_report(&transcripts, prices, group)
+        .into_iter()
+        .filter(|row| row.cost > 0.0 && histories.contains_key(&row.key))
+        .map(|row| (row.key, row.cost))
+        .collect())
+}
+
+/// Cost to produce each directory: the total of the files below it.
+fn print_directory_costs(costs: &HashMap<String, f64>) {
+    if costs.is_empty() {
+        return;
+    }
+
+    let mut dirs: BTreeMap<String, (usize, f64)> = BTreeMap::new();
+    for (path, cost) in costs {
+        for dir in Path::new(path).ancestors().skip(1) {
+            let dir = dir.to_string_lossy();
+            let key = if dir.is_empty() {
+                "./".to_string()
+            } else {
+                format!("{}/", dir.trim_end_matches('/'))
+            };
+            let entry = dirs.entry(key).or_default();
+            entry.0 += 1;
+            entry.1 += cost;
+        }
     }
 
+    println!("=== Cost to Produce by Directory ===");
+    println!("{:<50} | {:>5} | {:>10}", "Directory", "Files", "Cost");
+    println!("{}", "-".repeat(71));
+    for (dir, (files, cost)) in &dirs {
+        println!(
+            "{:<50} | {:>5} | {:>10}",
+            tail_chars(dir, 50),
+            files,
+            format_cost(*cost)
+        );
+    }
     println!();
 }
 
@@ -1029,22 +1861,30 @@ fn print_yaml_previews(histories: &HistoriesByFile, limit: usize) -> Result<()>
     Ok(())
 }
 
-fn load_output_config(config_file: Option<PathBuf>) -> Result<OutputConfig> {
-    if let Some(cf) = config_file {
+/// Load the output config, with `store` paths resolved against the project root
+/// (`--dir`, or the current directory).
+fn load_output_config(
+    config_file: Option<PathBuf>,
+    target_dir: Option<&Path>,
+) -> Result<OutputConfig> {
+    let mut config = if let Some(cf) = config_file {
         if !cf.exists() {
             eprintln!("Config file not found: {:?}", cf);
             std::process::exit(1);
         }
         println!("Using config: {:?}", cf);
-        return load_config(&cf);
-    }
-
-    if let Some(found_config) = find_config(None) {
+        load_config(&cf)?
+    } else if let Some(found_config) = find_config(None) {
         println!("Using config: {:?}", found_config);
-        return load_config(&found_config);
-    }
+        load_config(&found_config)?
+    } else {
+        get_default_config()
+    };
 
-    Ok(get_default_config())
+    if let Some(root) = target_dir {
+        crate::store::resolve_store_paths(&mut config, root);
+    }
+    Ok(config)
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -1086,6 +1926,7 @@ fn build_histories_verbose(
     verbose: u8,
     skip_codex: bool,
 ) -> Result<(PathBuf, OutputConfig, HistoriesByFile)> {
+    let project_root = target_dir.clone();
     let trace_dir = resolve_trace_dir(trace_dir, target_dir, home_dir);
 
     if !trace_dir.exists() {
@@ -1100,7 +1941,7 @@ fn build_histories_verbose(
     }
 
     // Load output config
-    let output_config = load_output_config(config_file)?;
+    let output_config = load_output_config(config_file, project_root.as_deref())?;
 
     // Build filter config
     let filter_config = FilterConfig {
@@ -1193,6 +2034,40 @@ fn build_histories_verbose(
     Ok((trace_dir, output_config, histories))
 }
 
+/// Histories read back from a provenance store, with the same target/pattern/initial-and-recent
+/// filtering `build_histories` applies to trace-derived histories.
+fn load_store_histories(
+    store: &Path,
+    target: Option<String>,
+    initial_and_recent: bool,
+    file_pattern: &str,
+) -> Result<HistoriesByFile> {
+    let mut histories = crate::store::read_all(store)?;
+    if !file_pattern.is_empty() {
+        histories.retain(|k, _| k.contains(file_pattern));
+    }
+    if initial_and_recent {
+        for history in histories.values_mut() {
+            if history.events.len() > 2 {
+                let last = history.events.pop();
+                history.events.truncate(1);
+                history.events.extend(last);
+            }
+        }
+    }
+    if let Some(target_str) = target {
+        histories.retain(|k, _| k.contains(&target_str));
+        if histories.is_empty() {
+            println!("No history found for: {}", target_str);
+            std::process::exit(1);
+        }
+    }
+    if histories.is_empty() {
+        println!("No histories found in store.");
+    }
+    Ok(histories)
+}
+
 fn print_output_plan(output_config: &OutputConfig, histories: &HistoriesByFile) {
     println!("\n=== Output Plan ===");
     println!("{:<50} | {:<10} | destination", "File", "Policy");
@@ -1210,6 +2085,14 @@ fn print_output_plan(output_config: &OutputConfig, histories: &HistoriesByFile)
                 OutputPolicy::Skip => ("skip".to_string(), "(skipped)".to_string()),
                 OutputPolicy::Append => ("append".to_string(), "in-place".to_string()),
                 OutputPolicy::Comment => ("comment".to_string(), "in-place".to_string()),
+                OutputPolicy::Store => {
+                    let store = crate::store::store_path_for_rule(&r);
+                    let dest = match crate::store::entry_path(&store, path) {
+                        Some(entry) => entry.to_string_lossy().to_string(),
+                        None => "(path cannot be stored)".to_string(),
+                    };
+                    ("store".to_string(), dest)
+                }
                 OutputPolicy::Sidecar => {
                     let mut file_path = PathBuf::from(path);
                     if !file_path.exists() {
@@ -1248,11 +2131,18 @@ fn report_command(
     file_pattern: String,
     verbose: u8,
     skip_codex: bool,
+    store: Option<PathBuf>,
+    cost_weight: CostWeight,
 ) -> Result<()> {
     if verbose > 0 {
         eprintln!("[*] Extracting edit history...");
     }
-    let (_trace_dir, output_config, histories) = if verbose >= 2 {
+    let (trace_dir, output_config, histories) = if let Some(store) = store {
+        println!("Reading provenance store: {:?}", store);
+        let output_config = load_output_config(config_file, target_dir.as_deref())?;
+        let histories = load_store_histories(&store, target, initial_and_recent, &file_pattern)?;
+        (None, output_config, histories)
+    } else if verbose >= 2 {
         build_histories_verbose(
             target,
             trace_dir,
@@ -1264,7 +2154,8 @@ fn report_command(
             file_pattern,
             verbose,
             skip_codex,
-        )?
+        )
+        .map(|(dir, config, histories)| (Some(dir), config, histories))?
     } else {
         build_histories(
             target,
@@ -1276,14 +2167,29 @@ fn report_command(
             min_change_size,
             file_pattern,
             skip_codex,
-        )?
+        )
+        .map(|(dir, config, histories)| (Some(dir), config, histories))?
     };
 
     if histories.is_empty() {
         return Ok(());
     }
 
-    print_summary_table(&histories);
+    // Store histories carry no session transcripts to price.
+    let costs = match &trace_dir {
+        Some(dir) => {
+            let prices = crate::pricing::PriceTable::from_config(&output_config);
+            // Costs are extra; a transcript that fails to parse only drops the column.
+            file_costs(dir, skip_codex, &prices, cost_weight, &histories).unwrap_or_else(|e| {
+                eprintln!("Warning: Could not estimate costs: {:#}", e);
+                HashMap::new()
+            })
+        }
+        None => HashMap::new(),
+    };
+
+    print_summary_table(&histories, &costs);
+    print_directory_costs(&costs);
     print_output_plan(&output_config, &histories);
 
     let limit = if show_all { histories.len() } else { 5 };
@@ -1300,10 +2206,14 @@ fn annotate_command(
     home_dir: Option<PathBuf>,
     config_file: Option<PathBuf>,
     dry_run: bool,
+    backup: bool,
     initial_and_recent: bool,
     min_change_size: usize,
     file_pattern: String,
 ) -> Result<()> {
+    let project_root = target_dir
+        .clone()
+        .unwrap_or_else(|| std::env::current_dir().unwrap_or_else(|_| PathBuf::from(".")));
     let (_trace_dir, output_config, histories) = build_histories(
         target,
         trace_dir,
@@ -1326,6 +2236,15 @@ fn annotate_command(
         println!("\nApplying changes...");
     }
 
+    let mut backup_run = if backup && !dry_run {
+        Some(BackupRun::create(&project_root)?)
+    } else {
+        None
+    };
+
+    // Store-policy histories, written once per store after the loop.
+    let mut store_batches: BTreeMap<PathBuf, Vec<&FileHistory>> = BTreeMap::new();
+
     let cwd = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
     for (rel_path, history) in &histories {
         // Find the actual file
@@ -1352,6 +2271,23 @@ fn annotate_command(
             continue;
         }
 
+        if let (Some(run), Some(output)) =
+            (backup_run.as_mut(), output_path(&file_path, history, &rule))
+        {
+            if let Err(e) = run.snapshot(&output) {
+                println!("  Error: {} (file not modified)", e);
+                continue;
+            }
+        }
+
+        if rule.policy == OutputPolicy::Store {
+            store_batches
+                .entry(crate::store::store_path_for_rule(&rule))
+                .or_default()
+                .push(history);
+            continue;
+        }
+
         match apply_rule(&file_path, history, &rule, dry_run) {
             Ok((true, msg)) => println!("  {}", msg),
             Ok((false, msg)) => println!("  Failed: {}", msg),
@@ -1359,22 +2295,263 @@ fn annotate_command(
         }
     }
 
+    for (store, batch) in &store_batches {
+        match crate::store::write_histories(store, batch, dry_run) {
+            Ok(results) => {
+                for (ok, msg) in results {
+                    if ok {
+                        println!("  {}", msg);
+                    } else {
+                        println!("  Failed: {}", msg);
+                    }
+                }
+            }
+            Err(e) => println!("  Error: {}", e),
+        }
+    }
+
+    if let Some(run) = backup_run {
+        println!(
+            "\nBackup saved to {:?} (undo with: ai-blame annotate --undo {})",
+            run.dir(),
+            run.run_id()
+        );
+    }
+
     Ok(())
 }
 
-fn timeline_command(
+fn undo_command(target_dir: Option<PathBuf>, run_id: &str) -> Result<()> {
+    let project_root = target_dir
+        .unwrap_or_else(|| std::env::current_dir().unwrap_or_else(|_| PathBuf::from(".")));
+    let summary = undo_run(&project_root, run_id)?;
+    println!(
+        "Undid run {}: restored {} file(s), removed {} file(s)",
+        run_id, summary.restored, summary.removed
+    );
+    Ok(())
+}
+
+/// Parse a `--since`/`--until` value: `YYYY-MM-DD` (start or end of that day, UTC) or RFC 3339.
+fn parse_date_bound(value: &str, flag: &str, end_of_day: bool) -> Result<DateTime<Utc>> {
+    if let Ok(dt) = DateTime::parse_from_rfc3339(value) {
+        return Ok(dt.with_timezone(&Utc));
+    }
+    let date = chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .map_err(|e| anyhow!("Invalid {} date '{}': {}", flag, value, e))?;
+    let time = if end_of_day {
+        date.and_hms_opt(23, 59, 59)
+    } else {
+        date.and_hms_opt(0, 0, 0)
+    };
+    Ok(time.ok_or_else(|| anyhow!("Invalid date"))?.and_utc())
+}
+
+struct LogConfig {
+    file: String,
     trace_dir: Option<PathBuf>,
     target_dir: Option<PathBuf>,
     home_dir: Option<PathBuf>,
-    file_pattern: String,
-    verbose: u8,
-    skip_codex: bool,
-    limit: usize,
-) -> Result<()> {
-    let trace_dir = resolve_trace_dir(trace_dir, target_dir, home_dir);
+    since: Option<String>,
+    until: Option<String>,
+    session: Option<String>,
+    color: bool,
+    redact: bool,
+}
 
-    if verbose > 0 {
-        eprintln!("[*] Collecting timeline events...");
+fn log_command(config: LogConfig) -> Result<()> {
+    let trace_dir = resolve_trace_dir(config.trace_dir, config.target_dir, config.home_dir);
+    if !trace_dir.exists() {
+        eprintln!("Trace directory not found: {:?}", trace_dir);
+        std::process::exit(1);
+    }
+
+    let since = config
+        .since
+        .as_deref()
+        .map(|s| parse_date_bound(s, "--since", false))
+        .transpose()?;
+    let until = config
+        .until
+        .as_deref()
+        .map(|s| parse_date_bound(s, "--until", true))
+        .transpose()?;
+
+    let file_path = PathBuf::from(&config.file);
+    let rel = std::env::current_dir()
+        .ok()
+        .and_then(|cwd| file_path.strip_prefix(cwd).ok().map(|p| p.to_path_buf()))
+        .and_then(|p| p.to_str().map(|s| s.to_string()))
+        .unwrap_or_else(|| config.file.clone());
+
+    let mut edits = trace_edits_for_file(&trace_dir, &config.file, &rel)?;
+    edits.retain(|e| {
+        since.map(|s| e.timestamp >= s).unwrap_or(true)
+            && until.map(|u| e.timestamp <= u).unwrap_or(true)
+            && config
+                .session
+                .as_deref()
+                .map(|p| e.session_id.starts_with(p))
+                .unwrap_or(true)
+    });
+    // Newest first, like `git log`.
+    edits.sort_by_key(|e| std::cmp::Reverse(e.timestamp));
+
+    if edits.is_empty() {
+        println!("No edits found for: {}", config.file);
+        return Ok(());
+    }
+    if config.redact {
+        let redactor = crate::redact::Redactor::load(None)?;
+        report_redactions(edits.iter_mut().map(|e| redactor.redact_edit(e)).sum());
+    }
+
+    for (i, edit) in edits.iter().enumerate() {
+        if i > 0 {
+            println!();
+        }
+        print!("{}", format_log_entry(edit, &rel, config.color));
+    }
+    Ok(())
+}
+
+/// One `ai-blame log` entry: a header describing the edit, then its diff.
+fn format_log_entry(edit: &EditRecord, path: &str, color: bool) -> String {
+    let title = format!("edit {}", edit.session_id);
+    let mut out = if color {
+        format!("\x1b[33m{}\x1b[0m\n", title)
+    } else {
+        format!("{}\n", title)
+    };
+    out.push_str(&format!(
+        "Date:    {}\n",
+        edit.timestamp.format("%Y-%m-%d %H:%M:%S UTC")
+    ));
+    out.push_str(&format!("Model:   {}\n", edit.model));
+    match &edit.agent_version {
+        Some(version) => out.push_str(&format!("Agent:   {}@{}\n", edit.agent_tool, version)),
+        None => out.push_str(&format!("Agent:   {}\n", edit.agent_tool)),
+    }
+    out.push_str(&format!(
+        "Action:  {}\n",
+        if edit.is_create { "created" } else { "edited" }
+    ));
+    if let Some(cell_id) = &edit.cell_id {
+        out.push_str(&format!("Cell:    {}\n", cell_id));
+    }
+    if let Some(agent_id) = &edit.agent_id {
+        out.push_str(&format!("Subagent: {}\n", agent_id));
+    }
+    out.push('\n');
+
+    let hunks = crate::diff::edit_hunks(edit);
+    if edit.is_cell_edit() {
+        out.push_str("    (notebook cell edit; no line diff)\n");
+    } else if hunks.is_empty() {
+        out.push_str("    (no diff recorded in trace)\n");
+    } else {
+        out.push_str(&crate::diff::render_patch(
+            path,
+            edit.is_create,
+            &hunks,
+            color,
+        ));
+    }
+    out
+}
+
+fn show_command(trace_dir: &Path, file: &str, at: &str, strict: bool, redact: bool) -> Result<()> {
+    use crate::replay::{reconstruct, resolve_cutoff, ReplayPoint, ReplayStrategy};
+
+    if !trace_dir.exists() {
+        eprintln!("Trace directory not found: {:?}", trace_dir);
+        std::process::exit(1);
+    }
+
+    let file_path = PathBuf::from(file);
+    let file_path = if file_path.is_absolute() {
+        file_path
+    } else {
+        std::env::current_dir()
+            .unwrap_or_else(|_| PathBuf::from("."))
+            .join(file)
+    };
+    let rel = std::env::current_dir()
+        .ok()
+        .and_then(|cwd| file_path.strip_prefix(cwd).ok().map(|p| p.to_path_buf()))
+        .and_then(|p| p.to_str().map(|s| s.to_string()))
+        .unwrap_or_else(|| file.to_string());
+    // The file may have been deleted since; that only rules out rewinding from it.
+    let current = std::fs::read_to_string(&file_path).ok();
+
+    let edits = trace_edits_for_file(trace_dir, file, &rel)?;
+    if edits.is_empty() {
+        eprintln!("No edits found for: {}", file);
+        std::process::exit(1);
+    }
+
+    let point = match parse_date_bound(at, "--at", true) {
+        Ok(t) => ReplayPoint::Time(t),
+        Err(_) => ReplayPoint::Session(at.to_string()),
+    };
+    let cutoff = resolve_cutoff(&edits, &point)?;
+    let replay = reconstruct(current.as_deref(), &edits, cutoff);
+
+    // Content goes to stdout (so it can be redirected); the replay report goes to stderr.
+    let strategy = match replay.strategy {
+        ReplayStrategy::Forward => "replayed forward from creation",
+        ReplayStrategy::Backward => "rewound from the working tree",
+    };
+    eprintln!(
+        "{} at {} ({}, {} edit(s) applied)",
+        rel,
+        cutoff.format("%Y-%m-%d %H:%M:%S UTC"),
+        strategy,
+        replay.applied
+    );
+    for d in &replay.divergences {
+        eprintln!(
+            "  diverged: {} session {}: {}",
+            d.timestamp.format("%Y-%m-%d %H:%M:%S"),
+            d.session_id,
+            d.reason
+        );
+    }
+    if replay.matches_working_tree == Some(false) {
+        eprintln!("  note: replaying all edits does not reproduce the current file (edited outside recorded sessions)");
+    }
+
+    match replay.content {
+        Some(mut content) => {
+            // Redact the output only: replay needs the edits' exact text to match.
+            if redact {
+                let redactor = crate::redact::Redactor::load(None)?;
+                report_redactions(redactor.redact_text(&mut content));
+            }
+            print!("{}", content)
+        }
+        None => eprintln!("  {} did not exist yet at that point", rel),
+    }
+
+    if strict && !replay.divergences.is_empty() {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+fn timeline_command(
+    trace_dir: Option<PathBuf>,
+    target_dir: Option<PathBuf>,
+    home_dir: Option<PathBuf>,
+    file_pattern: String,
+    verbose: u8,
+    skip_codex: bool,
+    limit: usize,
+) -> Result<()> {
+    let trace_dir = resolve_trace_dir(trace_dir, target_dir, home_dir);
+
+    if verbose > 0 {
+        eprintln!("[*] Collecting timeline events...");
     }
 
     if !trace_dir.exists() {
@@ -1449,7 +2626,7 @@ fn timeline_command(
     }
 
     // Sort by timestamp (most recent first)
-    all_edits.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
+    all_edits.sort_by_key(|e| std::cmp::Reverse(e.timestamp));
 
     // Apply limit if specified
     let display_edits = if limit > 0 && all_edits.len() > limit {
@@ -1646,12 +2823,14 @@ fn parse_transcript_column_spec(spec: Option<&str>) -> Result<Vec<TranscriptColu
                 'T' | 't' => Some(TranscriptColumn::Timestamp),
                 'M' | 'm' => Some(TranscriptColumn::Messages),
                 'F' | 'f' => Some(TranscriptColumn::Files),
+                'D' | 'd' => Some(TranscriptColumn::Duration),
                 'O' | 'o' => Some(TranscriptColumn::Models),
+                'C' | 'c' => Some(TranscriptColumn::Cost),
                 'L' | 'l' => Some(TranscriptColumn::LastMessage),
                 _ if ch.is_whitespace() => None,
                 _ => {
                     return Err(format!(
-                        "invalid column specifier '{}'; allowed: S,A,T,M,F,O,L",
+                        "invalid column specifier '{}'; allowed: S,A,T,M,F,D,O,C,L",
                         ch
                     ))
                 }
@@ -1684,6 +2863,7 @@ fn transcript_list_command(
     format: TranscriptFormat,
     columns: Option<String>,
 ) -> Result<()> {
+    let prices = crate::pricing::PriceTable::load(target_dir.as_deref())?;
     let trace_dir = resolve_trace_dir(trace_dir, target_dir, home_dir);
 
     if !trace_dir.exists() {
@@ -1691,7 +2871,10 @@ fn transcript_list_command(
         std::process::exit(1);
     }
 
-    let transcripts = crate::transcript::parse_transcripts_from_directory(&trace_dir)?;
+    // Resumed and compacted sessions are listed as one conversation.
+    let transcripts = crate::transcript::link_continuations(
+        crate::transcript::parse_transcripts_from_directory(&trace_dir)?,
+    );
 
     if transcripts.is_empty() {
         println!("No transcripts found in {:?}", trace_dir);
@@ -1707,10 +2890,13 @@ fn transcript_list_command(
     match format {
         TranscriptFormat::Table => {
             let cols = parse_transcript_column_spec(columns.as_deref()).map_err(|e| anyhow!(e))?;
-            print_transcript_table(display_transcripts, &cols, &transcripts, limit);
+            print_transcript_table(display_transcripts, &cols, &transcripts, limit, &prices);
         }
         TranscriptFormat::Json => {
-            let summaries: Vec<_> = display_transcripts.iter().map(|t| t.summary()).collect();
+            let summaries: Vec<_> = display_transcripts
+                .iter()
+                .map(|t| t.summary(&prices))
+                .collect();
             println!("{}", serde_json::to_string_pretty(&summaries)?);
         }
     }
@@ -1723,6 +2909,7 @@ fn print_transcript_table(
     columns: &[TranscriptColumn],
     all_transcripts: &[crate::transcript::Transcript],
     limit: usize,
+    prices: &crate::pricing::PriceTable,
 ) {
     println!("\n=== Transcripts ===");
 
@@ -1752,10 +2939,18 @@ fn print_transcript_table(
                 widths.push(8);
                 headers.push("Files");
             }
+            TranscriptColumn::Duration => {
+                widths.push(9);
+                headers.push("Duration");
+            }
             TranscriptColumn::Models => {
                 widths.push(30);
                 headers.push("Models");
             }
+            TranscriptColumn::Cost => {
+                widths.push(10);
+                headers.push("Cost");
+            }
             TranscriptColumn::LastMessage => {
                 widths.push(50);
                 headers.push("Last Message");
@@ -1777,7 +2972,7 @@ fn print_transcript_table(
 
     // Print rows
     for transcript in display_transcripts {
-        let summary = transcript.summary();
+        let summary = transcript.summary(prices);
         let row_values: Vec<String> = columns
             .iter()
             .map(|col| match col {
@@ -1792,6 +2987,7 @@ fn print_transcript_table(
                 }
                 TranscriptColumn::Messages => format!("{}", summary.message_count),
                 TranscriptColumn::Files => format!("{}", summary.files_touched),
+                TranscriptColumn::Duration => format_duration(summary.duration_secs),
                 TranscriptColumn::Models => {
                     if summary.all_models.is_empty() {
                         "(unknown)".to_string()
@@ -1799,6 +2995,7 @@ fn print_transcript_table(
                         summary.all_models.join(", ")
                     }
                 }
+                TranscriptColumn::Cost => summary.cost.map(format_cost).unwrap_or_default(),
                 TranscriptColumn::LastMessage => summary
                     .last_message_preview
                     .as_ref()
@@ -1814,7 +3011,10 @@ fn print_transcript_table(
             .map(|(idx, (v, w))| {
                 if matches!(
                     columns.get(idx),
-                    Some(TranscriptColumn::Messages) | Some(TranscriptColumn::Files)
+                    Some(TranscriptColumn::Messages)
+                        | Some(TranscriptColumn::Files)
+                        | Some(TranscriptColumn::Duration)
+                        | Some(TranscriptColumn::Cost)
                 ) {
                     format!("{:>width$}", v, width = w)
                 } else {
@@ -1842,6 +3042,7 @@ struct TranscriptDisplayOptions {
     full: bool,
     show_thinking: bool,
     show_tools: bool,
+    redact: bool,
 }
 
 /// Directory resolution context
@@ -1851,14 +3052,26 @@ struct DirectoryContext {
     home_dir: Option<PathBuf>,
 }
 
+/// Tell the user on stderr that values were redacted and how to see them.
+fn report_redactions(count: usize) {
+    if count > 0 {
+        eprintln!(
+            "Redacted {} value{} that looked like secrets or personal data (--no-redact shows them)",
+            count,
+            if count == 1 { "" } else { "s" }
+        );
+    }
+}
+
 fn transcript_view_command(
     session: String,
     dirs: DirectoryContext,
     options: TranscriptDisplayOptions,
 ) -> Result<()> {
+    let prices = crate::pricing::PriceTable::load(dirs.target_dir.as_deref())?;
     // Check if session is a file path
-    let transcript = if PathBuf::from(&session).exists() {
-        crate::transcript::parse_transcript(&PathBuf::from(&session))?
+    let mut transcript = if PathBuf::from(&session).exists() {
+        crate::transcript::parse_conversation(&PathBuf::from(&session))?
     } else {
         // Search for transcript by session ID
         let trace_dir = resolve_trace_dir(dirs.trace_dir, dirs.target_dir, dirs.home_dir);
@@ -1867,25 +3080,42 @@ fn transcript_view_command(
             std::process::exit(1);
         }
 
-        let transcripts = crate::transcript::parse_transcripts_from_directory(&trace_dir)?;
-        transcripts
-            .into_iter()
-            .find(|t| {
-                t.meta.session_id.contains(&session)
-                    || t.meta
-                        .slug
-                        .as_ref()
-                        .map(|s| s.contains(&session))
-                        .unwrap_or(false)
-                    || t.meta
-                        .source_file
-                        .as_ref()
-                        .map(|s| s.contains(&session))
-                        .unwrap_or(false)
-            })
-            .ok_or_else(|| anyhow!("Transcript not found: {}", session))?
+        let transcripts = crate::transcript::link_continuations(crate::transcript::link_subagents(
+            crate::transcript::parse_transcripts_from_directory(&trace_dir)?,
+        ));
+        let matches = |t: &crate::transcript::Transcript| {
+            t.meta.session_id.contains(&session)
+                || t.meta.continuations.iter().any(|c| {
+                    c.session_id.contains(&session)
+                        || c.source_file.as_ref().is_some_and(|s| s.contains(&session))
+                })
+                || t.meta
+                    .slug
+                    .as_ref()
+                    .map(|s| s.contains(&session))
+                    .unwrap_or(false)
+                || t.meta
+                    .source_file
+                    .as_ref()
+                    .map(|s| s.contains(&session))
+                    .unwrap_or(false)
+        };
+        // Sessions first; a subagent can still be viewed on its own by its file name.
+        match transcripts.iter().position(&matches) {
+            Some(i) => transcripts.into_iter().nth(i).expect("index is in range"),
+            None => transcripts
+                .into_iter()
+                .flat_map(|t| t.subagents)
+                .find(|t| matches(t))
+                .ok_or_else(|| anyhow!("Transcript not found: {}", session))?,
+        }
     };
 
+    if options.redact {
+        let redactor = crate::redact::Redactor::load(None)?;
+        report_redactions(redactor.redact_transcript(&mut transcript));
+    }
+
     match options.format {
         TranscriptViewFormat::Json => {
             println!("{}", serde_json::to_string_pretty(&transcript)?);
@@ -1906,6 +3136,12 @@ fn transcript_view_command(
                 options.show_tools,
             );
         }
+        TranscriptViewFormat::Html => {
+            print!(
+                "{}",
+                crate::transcript::html::render_html(&transcript, &prices)
+            );
+        }
     }
 
     Ok(())
@@ -1917,8 +3153,6 @@ fn print_transcript_text(
     show_thinking: bool,
     show_tools: bool,
 ) {
-    use crate::transcript::{ContentBlock, Role};
-
     // Header
     println!("\n{}", "=".repeat(80));
     println!("Session: {}", transcript.meta.session_id);
@@ -1942,17 +3176,57 @@ fn print_transcript_text(
     if let Some(end) = transcript.meta.end_time {
         println!("End: {}", end.format("%Y-%m-%d %H:%M:%S"));
     }
+    for resumed in &transcript.meta.continuations {
+        println!(
+            "Resumed: {} as session {}",
+            resumed.start_time.format("%Y-%m-%d %H:%M:%S"),
+            resumed.session_id
+        );
+    }
+    println!(
+        "Duration: {}",
+        format_duration(transcript.duration().num_seconds())
+    );
     println!("Messages: {}", transcript.stats.message_count);
     println!("Files Touched: {}", transcript.stats.files_touched);
     println!("{}", "=".repeat(80));
 
-    // Messages
+    print_messages_text(transcript, None, full, show_thinking, show_tools);
+
+    println!("\n{}", "=".repeat(80));
+}
+
+/// Print a transcript's messages, each subagent after the tool call that started it.
+/// `agent` labels the messages of a subagent.
+fn print_messages_text(
+    transcript: &crate::transcript::Transcript,
+    agent: Option<&str>,
+    full: bool,
+    show_thinking: bool,
+    show_tools: bool,
+) {
+    use crate::transcript::{ContentBlock, Role};
+
+    let mut resumes = transcript.meta.continuations.iter().peekable();
     for message in &transcript.messages {
+        while let Some(resumed) = resumes.next_if(|c| c.start_time <= message.timestamp) {
+            println!(
+                "\n{} Resumed as session {} at {} {}",
+                "=".repeat(10),
+                resumed.session_id,
+                resumed.start_time.format("%Y-%m-%d %H:%M:%S"),
+                "=".repeat(10)
+            );
+        }
         let role_str = match message.role {
             Role::User => "[USER]",
             Role::Assistant => "[ASSISTANT]",
             Role::System => "[SYSTEM]",
         };
+        let role_str = match agent {
+            Some(id) => format!("{} @ {}]", role_str.trim_end_matches(']'), id),
+            None => role_str.to_string(),
+        };
 
         let time_str = message.timestamp.format("%H:%M:%S").to_string();
         let model_str = message
@@ -2009,6 +3283,9 @@ fn print_transcript_text(
                     } else {
                         println!("[Tool: {}]", name);
                     }
+                    for subagent in transcript.subagents_started_by(id) {
+                        print_subagent_text(subagent, full, show_thinking, show_tools);
+                    }
                 }
                 ContentBlock::ToolResult {
                     content, is_error, ..
@@ -2065,7 +3342,26 @@ fn print_transcript_text(
         }
     }
 
-    println!("\n{}", "=".repeat(80));
+    for subagent in transcript.unplaced_subagents() {
+        print_subagent_text(subagent, full, show_thinking, show_tools);
+    }
+}
+
+fn print_subagent_text(
+    subagent: &crate::transcript::Transcript,
+    full: bool,
+    show_thinking: bool,
+    show_tools: bool,
+) {
+    let id = subagent.meta.agent_id.as_deref().unwrap_or("?");
+    println!(
+        "\n>>> Subagent {} ({} messages, {})",
+        id,
+        subagent.stats.message_count,
+        subagent.meta.start_time.format("%H:%M:%S")
+    );
+    print_messages_text(subagent, Some(id), full, show_thinking, show_tools);
+    println!("\n<<< End of subagent {}", id);
 }
 
 fn print_transcript_markdown(
@@ -2074,8 +3370,6 @@ fn print_transcript_markdown(
     show_thinking: bool,
     show_tools: bool,
 ) {
-    use crate::transcript::{ContentBlock, Role};
-
     // Header
     println!("# Transcript: {}", transcript.meta.session_id);
     println!();
@@ -2099,14 +3393,46 @@ fn print_transcript_markdown(
     if let Some(end) = transcript.meta.end_time {
         println!("**End:** {}", end.format("%Y-%m-%d %H:%M:%S"));
     }
+    for resumed in &transcript.meta.continuations {
+        println!(
+            "**Resumed:** {} as session `{}`",
+            resumed.start_time.format("%Y-%m-%d %H:%M:%S"),
+            resumed.session_id
+        );
+    }
+    println!(
+        "**Duration:** {}",
+        format_duration(transcript.duration().num_seconds())
+    );
     println!("**Messages:** {}", transcript.stats.message_count);
     println!("**Files Touched:** {}", transcript.stats.files_touched);
     println!();
     println!("---");
     println!();
 
-    // Messages
+    print_messages_markdown(transcript, 0, full, show_thinking, show_tools);
+}
+
+/// Markdown counterpart of [`print_messages_text`]; `depth` is the subagent nesting level.
+fn print_messages_markdown(
+    transcript: &crate::transcript::Transcript,
+    depth: usize,
+    full: bool,
+    show_thinking: bool,
+    show_tools: bool,
+) {
+    use crate::transcript::{ContentBlock, Role};
+
+    let mut resumes = transcript.meta.continuations.iter().peekable();
     for message in &transcript.messages {
+        while let Some(resumed) = resumes.next_if(|c| c.start_time <= message.timestamp) {
+            println!(
+                "> **Resumed** as session `{}` at {}",
+                resumed.session_id,
+                resumed.start_time.format("%Y-%m-%d %H:%M:%S")
+            );
+            println!();
+        }
         let role_str = match message.role {
             Role::User => "User",
             Role::Assistant => "Assistant",
@@ -2120,7 +3446,13 @@ fn print_transcript_markdown(
             .map(|m| format!(" _{}_", m))
             .unwrap_or_default();
 
-        println!("## {} ({}){}", role_str, time_str, model_str);
+        println!(
+            "{} {} ({}){}",
+            "#".repeat((depth + 2).min(6)),
+            role_str,
+            time_str,
+            model_str
+        );
         println!();
 
         for content in &message.content {
@@ -2174,6 +3506,15 @@ fn print_transcript_markdown(
                         println!("> **Tool:** `{}`", name);
                         println!();
                     }
+                    for subagent in transcript.subagents_started_by(id) {
+                        print_subagent_markdown(
+                            subagent,
+                            depth + 1,
+                            full,
+                            show_thinking,
+                            show_tools,
+                        );
+                    }
                 }
                 ContentBlock::ToolResult {
                     content, is_error, ..
@@ -2243,11 +3584,34 @@ fn print_transcript_markdown(
         println!("---");
         println!();
     }
+
+    for subagent in transcript.unplaced_subagents() {
+        print_subagent_markdown(subagent, depth + 1, full, show_thinking, show_tools);
+    }
+}
+
+fn print_subagent_markdown(
+    subagent: &crate::transcript::Transcript,
+    depth: usize,
+    full: bool,
+    show_thinking: bool,
+    show_tools: bool,
+) {
+    println!("<details>");
+    println!(
+        "<summary>Subagent <code>{}</code> · {} messages</summary>",
+        subagent.meta.agent_id.as_deref().unwrap_or("?"),
+        subagent.stats.message_count
+    );
+    println!();
+    print_messages_markdown(subagent, depth, full, show_thinking, show_tools);
+    println!("</details>");
+    println!();
 }
 
 /// Search criteria for transcript search command
 struct TranscriptSearchConfig {
-    query: String,
+    query: Option<String>,
     use_regex: bool,
     case_sensitive: bool,
     session_id_pattern: Option<String>,
@@ -2255,13 +3619,21 @@ struct TranscriptSearchConfig {
     model: Option<String>,
     since: Option<String>,
     until: Option<String>,
+    touched_file: Option<String>,
+    used_tool: Option<String>,
+    failed_command: bool,
+    tool_error: bool,
+    git_branch: Option<String>,
+    cwd: Option<String>,
+    min_tokens: Option<u64>,
+    max_tokens: Option<u64>,
     limit: usize,
     format: TranscriptFormat,
+    redact: bool,
 }
 
 fn transcript_search_command(dirs: DirectoryContext, config: TranscriptSearchConfig) -> Result<()> {
-    use chrono::NaiveDate;
-
+    let prices = crate::pricing::PriceTable::load(dirs.target_dir.as_deref())?;
     let trace_dir = resolve_trace_dir(dirs.trace_dir, dirs.target_dir, dirs.home_dir);
 
     if !trace_dir.exists() {
@@ -2270,32 +3642,19 @@ fn transcript_search_command(dirs: DirectoryContext, config: TranscriptSearchCon
     }
 
     // Parse date filters
-    let since = if let Some(ref date_str) = config.since {
-        let date = NaiveDate::parse_from_str(date_str, "%Y-%m-%d")
-            .map_err(|e| anyhow!("Invalid --since date '{}': {}", date_str, e))?;
-        Some(
-            date.and_hms_opt(0, 0, 0)
-                .ok_or_else(|| anyhow!("Invalid date"))?
-                .and_utc(),
-        )
-    } else {
-        None
-    };
-
-    let until = if let Some(ref date_str) = config.until {
-        let date = NaiveDate::parse_from_str(date_str, "%Y-%m-%d")
-            .map_err(|e| anyhow!("Invalid --until date '{}': {}", date_str, e))?;
-        Some(
-            date.and_hms_opt(23, 59, 59)
-                .ok_or_else(|| anyhow!("Invalid date"))?
-                .and_utc(),
-        )
-    } else {
-        None
-    };
+    let since = config
+        .since
+        .as_deref()
+        .map(|s| parse_date_bound(s, "--since", false))
+        .transpose()?;
+    let until = config
+        .until
+        .as_deref()
+        .map(|s| parse_date_bound(s, "--until", true))
+        .transpose()?;
 
     let criteria = crate::transcript::TranscriptSearchCriteria {
-        query: Some(config.query.clone()),
+        query: config.query.clone(),
         use_regex: config.use_regex,
         case_sensitive: config.case_sensitive,
         session_id_pattern: config.session_id_pattern,
@@ -2303,13 +3662,26 @@ fn transcript_search_command(dirs: DirectoryContext, config: TranscriptSearchCon
         model: config.model,
         since,
         until,
+        touched_file: config.touched_file,
+        used_tool: config.used_tool,
+        failed_command: config.failed_command,
+        tool_error: config.tool_error,
+        git_branch: config.git_branch,
+        cwd: config.cwd,
+        min_tokens: config.min_tokens,
+        max_tokens: config.max_tokens,
     };
 
-    let result = crate::transcript::search_transcripts(&trace_dir, &criteria, config.limit)?;
+    let mut result =
+        crate::transcript::search_transcripts(&trace_dir, &criteria, config.limit, &prices)?;
+    if config.redact {
+        let redactor = crate::redact::Redactor::load(None)?;
+        report_redactions(redactor.redact_search_result(&mut result));
+    }
 
     match config.format {
         TranscriptFormat::Table => {
-            print_search_results_table(&result, &config.query);
+            print_search_results_table(&result, config.query.as_deref());
         }
         TranscriptFormat::Json => {
             println!("{}", serde_json::to_string_pretty(&result)?);
@@ -2319,13 +3691,17 @@ fn transcript_search_command(dirs: DirectoryContext, config: TranscriptSearchCon
     Ok(())
 }
 
-fn print_search_results_table(result: &crate::transcript::SearchResult, query: &str) {
+fn print_search_results_table(result: &crate::transcript::SearchResult, query: Option<&str>) {
+    let searched = match query {
+        Some(query) => format!("'{}'", query),
+        None => "the filters".to_string(),
+    };
     if result.matching_transcripts.is_empty() {
-        println!("\nNo transcripts found matching '{}'", query);
+        println!("\nNo transcripts found matching {}", searched);
         return;
     }
 
-    println!("\n=== Search Results for '{}' ===", query);
+    println!("\n=== Search Results for {} ===", searched);
     println!(
         "Found {} matching transcript{}",
         result.total_matches,
@@ -2366,6 +3742,632 @@ fn print_search_results_table(result: &crate::transcript::SearchResult, query: &
     println!("Use 'ai-blame transcript view <session-id>' to view a transcript");
 }
 
+struct QueryOptions {
+    format: QueryFormat,
+    output: Option<PathBuf>,
+    list_views: bool,
+    no_refresh: bool,
+}
+
+fn query_command(sql: Option<String>, dirs: DirectoryContext, opts: QueryOptions) -> Result<()> {
+    if opts.list_views {
+        for view in crate::cache::query::VIEWS {
+            println!("{:<18} {}", view.name, view.description);
+        }
+        return Ok(());
+    }
+    let sql = sql.ok_or_else(|| anyhow!("No SQL statement given"))?;
+    if opts.format == QueryFormat::Parquet && opts.output.is_none() {
+        return Err(anyhow!("--format parquet requires --output"));
+    }
+
+    let trace_dirs: Vec<PathBuf> = cache_trace_dirs(dirs)
+        .into_iter()
+        .filter(|d| d.exists())
+        .collect();
+    if !opts.no_refresh && std::env::var("AI_BLAME_NO_CACHE").is_err() {
+        // Parsing brings each cache up to date with the traces (and migrates its schema).
+        let refs: Vec<&Path> = trace_dirs.iter().map(|p| p.as_path()).collect();
+        let repo_root = std::env::current_dir().ok();
+        crate::extractor::extract_edit_history_from_dirs(
+            &refs,
+            &FilterConfig::default(),
+            repo_root.as_deref(),
+        )?;
+    }
+
+    let caches: Vec<PathBuf> = dirs_with_cache(trace_dirs)
+        .iter()
+        .map(|d| CacheManager::cache_path(d))
+        .collect();
+    if caches.is_empty() {
+        return Err(anyhow!(
+            "No cache found. Run a command that reads traces (e.g. `ai-blame stats`) first."
+        ));
+    }
+    let session = crate::cache::query::QuerySession::open(&caches)?;
+
+    if opts.format == QueryFormat::Parquet {
+        let output = opts.output.as_deref().unwrap_or(Path::new(""));
+        session.write_parquet(&sql, output)?;
+        eprintln!("Wrote {:?}", output);
+        return Ok(());
+    }
+
+    let result = session.run(&sql)?;
+    let rendered = match opts.format {
+        QueryFormat::Csv => render_query_csv(&result),
+        QueryFormat::Json => render_query_json(&result),
+        _ => render_query_table(&result),
+    };
+    match &opts.output {
+        Some(path) => {
+            std::fs::write(path, rendered).with_context(|| format!("Failed to write {:?}", path))?
+        }
+        None => print!("{}", rendered),
+    }
+    Ok(())
+}
+
+fn render_query_table(result: &crate::cache::query::QueryResult) -> String {
+    use crate::cache::query::display_value;
+    const MAX_CELL: usize = 60;
+
+    let cells: Vec<Vec<String>> = result
+        .rows
+        .iter()
+        .map(|row| {
+            row.iter()
+                .map(|v| {
+                    let text = display_value(v).replace('\n', " ");
+                    crate::utils::safe_truncate(&text, MAX_CELL)
+                })
+                .collect()
+        })
+        .collect();
+    let numeric: Vec<bool> = (0..result.columns.len())
+        .map(|i| {
+            result
+                .rows
+                .iter()
+                .all(|row| row[i].is_number() || row[i].is_null())
+                && result.rows.iter().any(|row| row[i].is_number())
+        })
+        .collect();
+    let widths: Vec<usize> = result
+        .columns
+        .iter()
+        .enumerate()
+        .map(|(i, name)| {
+            cells
+                .iter()
+                .map(|row| row[i].chars().count())
+                .chain(std::iter::once(name.chars().count()))
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+
+    let format_row = |values: Vec<&str>| -> String {
+        values
+            .iter()
+            .enumerate()
+            .map(|(i, v)| {
+                if numeric[i] {
+                    format!("{:>width$}", v, width = widths[i])
+                } else {
+                    format!("{:<width$}", v, width = widths[i])
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("  ")
+            .trim_end()
+            .to_string()
+    };
+
+    let mut out = String::new();
+    out.push_str(&format_row(
+        result.columns.iter().map(|c| c.as_str()).collect(),
+    ));
+    out.push('\n');
+    out.push_str(
+        &widths
+            .iter()
+            .map(|w| "-".repeat(*w))
+            .collect::<Vec<_>>()
+            .join("  "),
+    );
+    out.push('\n');
+    for row in &cells {
+        out.push_str(&format_row(row.iter().map(|c| c.as_str()).collect()));
+        out.push('\n');
+    }
+    out.push_str(&format!(
+        "({} row{})\n",
+        result.rows.len(),
+        if result.rows.len() == 1 { "" } else { "s" }
+    ));
+    out
+}
+
+fn render_query_csv(result: &crate::cache::query::QueryResult) -> String {
+    let mut out = String::new();
+    let header: Vec<String> = result.columns.iter().map(|c| csv_field(c)).collect();
+    out.push_str(&header.join(","));
+    out.push('\n');
+    for row in &result.rows {
+        let fields: Vec<String> = row
+            .iter()
+            .map(|v| csv_field(&crate::cache::query::display_value(v)))
+            .collect();
+        out.push_str(&fields.join(","));
+        out.push('\n');
+    }
+    out
+}
+
+/// One JSON object per row, keeping the query's column order.
+fn render_query_json(result: &crate::cache::query::QueryResult) -> String {
+    let rows: Vec<String> = result
+        .rows
+        .iter()
+        .map(|row| {
+            let fields: Vec<String> = result
+                .columns
+                .iter()
+                .zip(row)
+                .map(|(name, value)| {
+                    format!("{}: {}", serde_json::Value::from(name.as_str()), value)
+                })
+                .collect();
+            format!("  {{{}}}", fields.join(", "))
+        })
+        .collect();
+    if rows.is_empty() {
+        "[]\n".to_string()
+    } else {
+        format!("[\n{}\n]\n", rows.join(",\n"))
+    }
+}
+
+struct ExportOptions {
+    data: ExportData,
+    format: ExportFileFormat,
+    output: PathBuf,
+    no_payloads: bool,
+    pattern: String,
+    skip_codex: bool,
+    redact: bool,
+}
+
+fn export_command(dirs: DirectoryContext, opts: ExportOptions) -> Result<()> {
+    use crate::export::Export;
+
+    let trace_dir = resolve_trace_dir(dirs.trace_dir, dirs.target_dir, dirs.home_dir);
+    if !trace_dir.exists() {
+        eprintln!("Trace directory not found: {:?}", trace_dir);
+        std::process::exit(1);
+    }
+
+    let export = match opts.data {
+        ExportData::Edits | ExportData::Timeline => {
+            let config = FilterConfig {
+                file_pattern: if opts.pattern.is_empty() {
+                    None
+                } else {
+                    Some(opts.pattern.clone())
+                },
+                ..Default::default()
+            };
+            let mut all_trace_dirs = crate::extractor::get_all_trace_dirs(&trace_dir);
+            if opts.skip_codex {
+                all_trace_dirs.retain(|d| !d.to_string_lossy().contains("codex"));
+            }
+            let trace_dir_refs: Vec<&Path> = all_trace_dirs.iter().map(|p| p.as_path()).collect();
+            let repo_root = std::env::current_dir().ok();
+            let mut edits_by_file = crate::extractor::extract_edit_history_from_dirs(
+                &trace_dir_refs,
+                &config,
+                repo_root.as_deref(),
+            )?;
+            if opts.skip_codex {
+                for edits in edits_by_file.values_mut() {
+                    edits.retain(|edit| {
+                        !edit.agent_tool.to_lowercase().contains("copilot")
+                            && !edit.agent_tool.to_lowercase().contains("codex")
+                    });
+                }
+            }
+
+            if opts.data == ExportData::Timeline {
+                Export::timeline(&crate::extractor::timeline_events(&edits_by_file))?
+            } else {
+                let mut edits: Vec<EditRecord> = edits_by_file.into_values().flatten().collect();
+                if opts.redact && !opts.no_payloads {
+                    let redactor = crate::redact::Redactor::load(None)?;
+                    report_redactions(edits.iter_mut().map(|e| redactor.redact_edit(e)).sum());
+                }
+                edits.sort_by(|a, b| {
+                    a.timestamp
+                        .cmp(&b.timestamp)
+                        .then_with(|| a.file_path.cmp(&b.file_path))
+                });
+                Export::edits(&edits, !opts.no_payloads)?
+            }
+        }
+        ExportData::Transcripts => {
+            let mut transcripts = crate::transcript::parse_transcripts_from_directory(&trace_dir)?;
+            if opts.redact {
+                let redactor = crate::redact::Redactor::load(None)?;
+                report_redactions(
+                    transcripts
+                        .iter_mut()
+                        .map(|t| redactor.redact_transcript_meta(&mut t.meta))
+                        .sum(),
+                );
+            }
+            Export::transcripts(&transcripts)?
+        }
+    };
+
+    export.write(opts.format.into(), &opts.output)?;
+    let rows = export.row_count()?;
+    eprintln!(
+        "Wrote {} row{} to {:?}",
+        rows,
+        if rows == 1 { "" } else { "s" },
+        opts.output
+    );
+    Ok(())
+}
+
+struct CostOptions {
+    by: CostBy,
+    weight: CostWeight,
+    config: Option<PathBuf>,
+    since: Option<String>,
+    until: Option<String>,
+    skip_codex: bool,
+    limit: usize,
+    format: CostFormat,
+    list_prices: bool,
+}
+
+fn cost_command(dirs: DirectoryContext, opts: CostOptions) -> Result<()> {
+    use crate::pricing::{cost_report, PriceTable};
+
+    let prices = match &opts.config {
+        Some(path) => PriceTable::from_config(&load_config(path)?),
+        None => PriceTable::load(dirs.target_dir.as_deref())?,
+    };
+    if opts.list_prices {
+        print_price_table(&prices);
+        return Ok(());
+    }
+
+    let since = opts
+        .since
+        .as_deref()
+        .map(|s| parse_date_bound(s, "--since", false))
+        .transpose()?;
+    let until = opts
+        .until
+        .as_deref()
+        .map(|s| parse_date_bound(s, "--until", true))
+        .transpose()?;
+
+    let mut trace_dirs = cache_trace_dirs(dirs);
+    if opts.skip_codex {
+        trace_dirs.retain(|d| !d.to_string_lossy().contains("codex"));
+    }
+    let mut transcripts = Vec::new();
+    for dir in trace_dirs.iter().filter(|d| d.exists()) {
+        transcripts.extend(crate::transcript::parse_transcripts_from_directory(dir)?);
+    }
+    transcripts.retain(|t| {
+        since.is_none_or(|s| t.meta.start_time >= s) && until.is_none_or(|u| t.meta.start_time <= u)
+    });
+
+    let rows = cost_report(&transcripts, &prices, opts.by.group(opts.weight));
+    let total = rows.iter().map(|r| r.cost).sum::<f64>();
+    let shown = if opts.limit > 0 && rows.len() > opts.limit {
+        &rows[..opts.limit]
+    } else {
+        &rows[..]
+    };
+
+    match opts.format {
+        CostFormat::Json => println!("{}", serde_json::to_string_pretty(shown)?),
+        CostFormat::Csv => {
+            println!(
+                "key,sessions,input_tokens,output_tokens,cache_read_tokens,\
+                 cache_creation_tokens,cost,unpriced_tokens"
+            );
+            for row in shown {
+                println!(
+                    "{},{},{},{},{},{},{:.6},{}",
+                    csv_field(&row.key),
+                    row.sessions,
+                    row.input_tokens,
+                    row.output_tokens,
+                    row.cache_read_tokens,
+                    row.cache_creation_tokens,
+                    row.cost,
+                    row.unpriced_tokens
+                );
+            }
+        }
+        CostFormat::Table => {
+            let sessions: HashSet<&str> = transcripts
+                .iter()
+                .map(|t| t.meta.session_id.as_str())
+                .collect();
+            print_cost_table(shown, rows.len(), total, sessions.len(), opts.by)
+        }
+    }
+    Ok(())
+}
+
+fn print_cost_table(
+    rows: &[crate::pricing::CostRow],
+    group_count: usize,
+    total: f64,
+    session_count: usize,
+    by: CostBy,
+) {
+    let heading = match by {
+        CostBy::Session => "Session",
+        CostBy::Day => "Day",
+        CostBy::Model => "Model",
+        CostBy::Agent => "Agent",
+        CostBy::File => "File",
+    };
+    let key_width = rows
+        .iter()
+        .map(|r| r.key.chars().count())
+        .max()
+        .unwrap_or(0)
+        .clamp(heading.len(), 60);
+
+    println!(
+        "{:<kw$} {:>8} {:>12} {:>12} {:>12} {:>12} {:>10}",
+        heading,
+        "Sessions",
+        "Input",
+        "Output",
+        "Cache Read",
+        "Cache Write",
+        "Cost",
+        kw = key_width
+    );
+    println!("{}", "-".repeat(key_width + 72));
+    for row in rows {
+        let key = if row.key.chars().count() > key_width {
+            format!("…{}", tail_chars(&row.key, key_width - 1))
+        } else {
+            row.key.clone()
+        };
+        println!(
+            "{:<kw$} {:>8} {:>12} {:>12} {:>12} {:>12} {:>10}",
+            key,
+            row.sessions,
+            row.input_tokens,
+            row.output_tokens,
+            row.cache_read_tokens,
+            row.cache_creation_tokens,
+            format_cost(row.cost),
+            kw = key_width
+        );
+    }
+    if group_count > rows.len() {
+        println!(
+            "\n... and {} more (use -n 0 to show all)",
+            group_count - rows.len()
+        );
+    }
+
+    println!(
+        "\nTotal: {} across {} session{}",
+        format_cost(total),
+        session_count,
+        if session_count == 1 { "" } else { "s" }
+    );
+
+    let mut unpriced: Vec<&str> = rows
+        .iter()
+        .flat_map(|r| r.unpriced_models.iter().map(|m| m.as_str()))
+        .collect();
+    unpriced.sort();
+    unpriced.dedup();
+    if !unpriced.is_empty() {
+        let tokens: u64 = rows.iter().map(|r| r.unpriced_tokens).sum();
+        println!(
+            "Not priced: {} tokens from {} (add them under pricing in .ai-blame.yaml)",
+            tokens,
+            unpriced.join(", ")
+        );
+    }
+}
+
+fn print_price_table(prices: &crate::pricing::PriceTable) {
+    println!(
+        "{:<24} {:>10} {:>10} {:>11} {:>12}",
+        "Model", "Input", "Output", "Cache Read", "Cache Write"
+    );
+    println!("{}", "-".repeat(71));
+    for price in prices.prices() {
+        println!(
+            "{:<24} {:>10.3} {:>10.3} {:>11.3} {:>12.3}",
+            price.model,
+            price.input,
+            price.output,
+            price.cache_read.unwrap_or(price.input),
+            price.cache_creation.unwrap_or(price.input)
+        );
+    }
+    println!("\nUSD per million tokens; models match by name prefix.");
+}
+
+fn format_cost(cost: f64) -> String {
+    format!("${:.2}", cost)
+}
+
+/// A duration in seconds as `1h 05m`, `12m 30s` or `45s`.
+fn format_duration(secs: i64) -> String {
+    let secs = secs.max(0);
+    if secs >= 3600 {
+        format!("{}h {:02}m", secs / 3600, secs % 3600 / 60)
+    } else if secs >= 60 {
+        format!("{}m {:02}s", secs / 60, secs % 60)
+    } else {
+        format!("{}s", secs)
+    }
+}
+
+/// The last `n` characters of `s`.
+fn tail_chars(s: &str, n: usize) -> &str {
+    let skip = s.chars().count().saturating_sub(n);
+    s.char_indices().nth(skip).map_or("", |(i, _)| &s[i..])
+}
+
+/// Quote a CSV field if it contains a separator, quote or line break.
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Trace directories the `cache` and `query` commands operate on.
+///
+/// An explicit `--trace-dir` selects just that directory; otherwise the Claude trace
+/// directory and any Codex directories are included.
+fn cache_trace_dirs(dirs: DirectoryContext) -> Vec<PathBuf> {
+    let explicit = dirs.trace_dir.is_some();
+    let claude_dir = resolve_trace_dir(dirs.trace_dir, dirs.target_dir, dirs.home_dir);
+    if explicit {
+        vec![claude_dir]
+    } else {
+        crate::extractor::get_all_trace_dirs(&claude_dir)
+    }
+}
+
+/// The subset of `trace_dirs` that already has a cache.
+fn dirs_with_cache(trace_dirs: Vec<PathBuf>) -> Vec<PathBuf> {
+    trace_dirs
+        .into_iter()
+        .filter(|d| CacheManager::cache_path(d).exists())
+        .collect()
+}
+
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
+
+fn describe_schema_upgrade(upgrade: &SchemaUpgrade) -> String {
+    match upgrade {
+        SchemaUpgrade::UpToDate(v) => format!("already at version {}", v),
+        SchemaUpgrade::Created(v) => format!("created at version {}", v),
+        SchemaUpgrade::Migrated { from, to } => format!("migrated from version {} to {}", from, to),
+        SchemaUpgrade::Rebuilt { reason, to } => {
+            format!("rebuilt at version {} ({})", to, reason)
+        }
+    }
+}
+
+fn cache_command(action: CacheAction, dirs: DirectoryContext) -> Result<()> {
+    let trace_dirs = dirs_with_cache(cache_trace_dirs(dirs));
+    if trace_dirs.is_empty() {
+        println!("No cache found.");
+        return Ok(());
+    }
+
+    for (i, trace_dir) in trace_dirs.iter().enumerate() {
+        if i > 0 && action == CacheAction::Info {
+            println!();
+        }
+        let db_path = CacheManager::cache_path(trace_dir);
+        match action {
+            CacheAction::Clear => {
+                CacheManager::remove(trace_dir)?;
+                println!("Deleted cache: {:?}", db_path);
+            }
+            CacheAction::Migrate => {
+                let cache = CacheManager::open(trace_dir)
+                    .with_context(|| format!("Failed to open cache {:?}", db_path))?;
+                println!(
+                    "{:?}: {}",
+                    db_path,
+                    describe_schema_upgrade(cache.schema_upgrade())
+                );
+            }
+            CacheAction::Vacuum => {
+                let cache = CacheManager::open(trace_dir)
+                    .with_context(|| format!("Failed to open cache {:?}", db_path))?;
+                let report = cache.vacuum()?;
+                println!(
+                    "{:?}: pruned {} deleted trace file(s), {} -> {}",
+                    db_path,
+                    report.pruned_files,
+                    format_bytes(report.size_before),
+                    format_bytes(report.size_after)
+                );
+            }
+            CacheAction::Info => {
+                let cache = CacheManager::open(trace_dir)
+                    .with_context(|| format!("Failed to open cache {:?}", db_path))?;
+                print_cache_info(&cache.info()?, cache.schema_upgrade());
+            }
+        }
+    }
+    Ok(())
+}
+
+fn print_cache_info(info: &CacheInfo, upgrade: &SchemaUpgrade) {
+    println!("Cache:          {:?}", info.db_path);
+    println!("Size:           {}", format_bytes(info.size_bytes));
+    print!("Schema version: {}", info.schema_version);
+    if !matches!(upgrade, SchemaUpgrade::UpToDate(_)) {
+        print!(" ({})", describe_schema_upgrade(upgrade));
+    }
+    println!();
+    println!("Trace files:    {}", info.trace_files);
+    println!("Edit records:   {}", info.edit_records);
+    println!("Transcripts:    {}", info.transcripts);
+
+    if info.providers.is_empty() {
+        return;
+    }
+    println!();
+    println!(
+        "{:<10} {:>7} {:>9} {:>11} {:>10}  LAST PARSED",
+        "PROVIDER", "FILES", "RECORDS", "TRACE SIZE", "PARSE MS"
+    );
+    for p in &info.providers {
+        println!(
+            "{:<10} {:>7} {:>9} {:>11} {:>10}  {}",
+            p.provider,
+            p.trace_files,
+            p.record_count,
+            format_bytes(p.trace_bytes),
+            p.parse_duration_ms,
+            p.last_parsed_at.as_deref().unwrap_or("-")
+        );
+    }
+}
+
 pub fn run() -> Result<()> {
     let cli = Cli::parse();
 
@@ -2403,6 +4405,8 @@ pub fn run() -> Result<()> {
             only_claude,
             no_cache,
             rebuild_cache,
+            store,
+            cost_weight,
         } => {
             if rebuild_cache {
                 if let Ok(cwd) = std::env::current_dir() {
@@ -2424,6 +4428,8 @@ pub fn run() -> Result<()> {
                 pattern,
                 verbose,
                 skip_codex || only_claude,
+                store,
+                cost_weight,
             )
         }
         Commands::Annotate {
@@ -2438,7 +4444,12 @@ pub fn run() -> Result<()> {
             pattern,
             no_cache,
             rebuild_cache,
+            backup,
+            undo,
         } => {
+            if let Some(run_id) = undo {
+                return undo_command(dir, &run_id);
+            }
             if rebuild_cache {
                 if let Ok(cwd) = std::env::current_dir() {
                     let _ = std::fs::remove_file(cwd.join(".ai-blame.ddb"));
@@ -2454,6 +4465,7 @@ pub fn run() -> Result<()> {
                 home,
                 config,
                 dry_run,
+                backup,
                 initial_and_recent,
                 min_change_size,
                 pattern,
@@ -2493,6 +4505,13 @@ pub fn run() -> Result<()> {
             no_cache,
             rebuild_cache,
             no_header,
+            reverse,
+            snippet,
+            rev,
+            detect_moves,
+            move_min_lines,
+            format,
+            spans,
         } => {
             if rebuild_cache {
                 if let Ok(cwd) = std::env::current_dir() {
@@ -2514,8 +4533,70 @@ pub fn run() -> Result<()> {
                 agent_alias,
                 model_alias,
                 no_header,
+                reverse,
+                snippet,
+                rev,
+                detect_moves,
+                move_min_lines,
+                format,
+                spans,
             })
         }
+        Commands::Log {
+            file,
+            trace_dir,
+            dir,
+            home,
+            since,
+            until,
+            session,
+            color,
+            no_redact,
+            no_cache,
+            rebuild_cache,
+        } => {
+            if rebuild_cache {
+                CacheManager::remove(&resolve_trace_dir(
+                    trace_dir.clone(),
+                    dir.clone(),
+                    home.clone(),
+                ))?;
+            }
+            if no_cache {
+                std::env::set_var("AI_BLAME_NO_CACHE", "1");
+            }
+            log_command(LogConfig {
+                file,
+                trace_dir,
+                target_dir: dir,
+                home_dir: home,
+                since,
+                until,
+                session,
+                color: color.enabled(),
+                redact: !no_redact,
+            })
+        }
+        Commands::Show {
+            file,
+            at,
+            trace_dir,
+            dir,
+            home,
+            strict,
+            no_redact,
+            no_cache,
+            rebuild_cache,
+        } => {
+            let trace_dir = resolve_trace_dir(trace_dir, dir, home);
+            if rebuild_cache {
+                CacheManager::remove(&trace_dir)?;
+            }
+            if no_cache {
+                std::env::set_var("AI_BLAME_NO_CACHE", "1");
+            }
+            show_command(&trace_dir, &file, &at, strict, !no_redact)
+        }
         Commands::Timeline {
             trace_dir,
             dir,
@@ -2552,6 +4633,7 @@ pub fn run() -> Result<()> {
                 full,
                 show_thinking,
                 show_tools,
+                no_redact,
             } => transcript_view_command(
                 session,
                 DirectoryContext {
@@ -2564,6 +4646,7 @@ pub fn run() -> Result<()> {
                     full,
                     show_thinking,
                     show_tools,
+                    redact: !no_redact,
                 },
             ),
             TranscriptAction::Search {
@@ -2578,8 +4661,17 @@ pub fn run() -> Result<()> {
                 model,
                 since,
                 until,
+                file,
+                tool,
+                failed_commands,
+                tool_errors,
+                branch,
+                cwd,
+                min_tokens,
+                max_tokens,
                 limit,
                 format,
+                no_redact,
             } => transcript_search_command(
                 DirectoryContext {
                     trace_dir,
@@ -2595,11 +4687,114 @@ pub fn run() -> Result<()> {
                     model,
                     since,
                     until,
+                    touched_file: file,
+                    used_tool: tool,
+                    failed_command: failed_commands,
+                    tool_error: tool_errors,
+                    git_branch: branch,
+                    cwd,
+                    min_tokens,
+                    max_tokens,
                     limit,
                     format,
+                    redact: !no_redact,
                 },
             ),
         },
+        Commands::Query {
+            sql,
+            trace_dir,
+            dir,
+            home,
+            format,
+            output,
+            list_views,
+            no_refresh,
+        } => query_command(
+            sql,
+            DirectoryContext {
+                trace_dir,
+                target_dir: dir,
+                home_dir: home,
+            },
+            QueryOptions {
+                format,
+                output,
+                list_views,
+                no_refresh,
+            },
+        ),
+        Commands::Export {
+            data,
+            format,
+            output,
+            no_payloads,
+            trace_dir,
+            dir,
+            home,
+            pattern,
+            skip_codex,
+            no_redact,
+        } => export_command(
+            DirectoryContext {
+                trace_dir,
+                target_dir: dir,
+                home_dir: home,
+            },
+            ExportOptions {
+                data,
+                format,
+                output,
+                no_payloads,
+                pattern,
+                skip_codex,
+                redact: !no_redact,
+            },
+        ),
+        Commands::Cost {
+            by,
+            weight,
+            trace_dir,
+            dir,
+            home,
+            config,
+            since,
+            until,
+            skip_codex,
+            limit,
+            format,
+            list_prices,
+        } => cost_command(
+            DirectoryContext {
+                trace_dir,
+                target_dir: dir,
+                home_dir: home,
+            },
+            CostOptions {
+                by,
+                weight,
+                config,
+                since,
+                until,
+                skip_codex,
+                limit,
+                format,
+                list_prices,
+            },
+        ),
+        Commands::Cache {
+            action,
+            trace_dir,
+            dir,
+            home,
+        } => cache_command(
+            action,
+            DirectoryContext {
+                trace_dir,
+                target_dir: dir,
+                home_dir: home,
+            },
+        ),
         Commands::Completions { shell } => {
             let mut cmd = Cli::command();
             generate(shell, &mut cmd, "ai-blame", &mut std::io::stdout());
@@ -38,7 +38,11 @@ pub fn get_default_config() -> OutputConfig {
             policy: OutputPolicy::Sidecar,
             format: "yaml".to_string(),
             comment_syntax: None,
+            template: None,
+            placement: CommentPlacement::Bottom,
             sidecar_pattern: Some("{stem}.history.yaml".to_string()),
+            store_path: None,
+            cell_history: false,
         }),
         rules: vec![
             FileRule {
@@ -46,23 +50,37 @@ pub fn get_default_config() -> OutputConfig {
                 policy: OutputPolicy::Append,
                 format: "yaml".to_string(),
                 comment_syntax: None,
+                template: None,
+                placement: CommentPlacement::Bottom,
                 sidecar_pattern: None,
+                store_path: None,
+                cell_history: false,
             },
             FileRule {
                 pattern: "*.yml".to_string(),
                 policy: OutputPolicy::Append,
                 format: "yaml".to_string(),
                 comment_syntax: None,
+                template: None,
+                placement: CommentPlacement::Bottom,
                 sidecar_pattern: None,
+                store_path: None,
+                cell_history: false,
             },
             FileRule {
                 pattern: "*.json".to_string(),
                 policy: OutputPolicy::Append,
                 format: "json".to_string(),
                 comment_syntax: None,
+                template: None,
+                placement: CommentPlacement::Bottom,
                 sidecar_pattern: None,
+                store_path: None,
+                cell_history: false,
             },
         ],
+        pricing: Vec::new(),
+        redaction: Default::default(),
     }
 }
 
@@ -81,6 +99,9 @@ defaults:
   # Sidecar mode: keep your source files untouched by writing companion history files.
   policy: sidecar
   sidecar_pattern: "{stem}.history.yaml"
+  # Or keep all provenance in one place, keyed by relative path:
+  # policy: store
+  # store_path: ".provenance"        # or "provenance.jsonl" / "provenance.ddb"
 
 rules:
   # Example: don't touch test outputs/fixtures
@@ -97,6 +118,7 @@ rules:
 #
 # In-place flavor:
 # - YAML/JSON: add an `edit_history` key directly to the file
+# - Jupyter notebooks: write history into notebook/cell metadata
 # - Common code/docs: append a comment block at end of file
 # - Everything else: fall back to sidecar (to avoid corrupting unknown formats)
 
@@ -115,35 +137,55 @@ rules:
   - pattern: "*.json"
     policy: append
     format: json
+  # Jupyter notebooks: history goes under `metadata.ai_blame` (nbformat-safe);
+  # `cell_history` also annotates each edited cell's metadata.
+  - pattern: "*.ipynb"
+    policy: append
+    format: ipynb
+    cell_history: true
+  # TOML can also be annotated natively as `[[tool.ai-blame.edit_history]]` tables:
+  # - pattern: "pyproject.toml"
+  #   policy: append
+  #   format: toml
+  # Markdown/Quarto/Jekyll pages can carry `edit_history` in their YAML front matter:
+  # - pattern: "*.qmd"
+  #   policy: append
+  #   format: front-matter
 
-  # Code/docs: append provenance as a comment block.
+  # Code/docs: append provenance as a comment block. The comment syntax is detected
+  # from the extension (`#`, `//`, `/* */`, `(* *)`, `--`, `%`, `<!-- -->`); set
+  # `comment_syntax` to override it, and `template` for a compact one-line-per-edit header.
   - pattern: "*.py"
     policy: comment
-    comment_syntax: hash
+    # template: "{date} {action} by {model} ({agent_tool})"
   - pattern: "*.sh"
     policy: comment
-    comment_syntax: hash
   - pattern: "*.toml"
     policy: comment
-    comment_syntax: hash
   - pattern: "*.md"
     policy: comment
-    comment_syntax: html
   - pattern: "*.rs"
     policy: comment
-    comment_syntax: slash
   - pattern: "*.js"
     policy: comment
-    comment_syntax: slash
   - pattern: "*.jsx"
     policy: comment
-    comment_syntax: slash
   - pattern: "*.ts"
     policy: comment
-    comment_syntax: slash
   - pattern: "*.tsx"
     policy: comment
-    comment_syntax: slash
+  - pattern: "*.go"
+    policy: comment
+  - pattern: "*.c"
+    policy: comment
+  - pattern: "*.css"
+    policy: comment
+  - pattern: "*.sql"
+    policy: comment
+  - pattern: "*.ml"
+    policy: comment
+  - pattern: "*.tex"
+    policy: comment
 
   # Example: don't touch test outputs/fixtures
   - pattern: "tests/**"
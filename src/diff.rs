@@ -0,0 +1,292 @@
+//! Unified diffs rebuilt from edit records, for `ai-blame log`.
+//!
+//! Traces don't carry a full before/after file, only the replaced snippet
+//! (`old_string` → `new_string`), the created content, and sometimes a
+//! `structured_patch`. A structured patch with hunk bodies is used as-is; otherwise
+//! the snippet pair is line-diffed, borrowing the patch's line numbers when it has them.
+
+use crate::models::EditRecord;
+use regex::Regex;
+use serde::Deserialize;
+use std::sync::OnceLock;
+
+/// Above this many old×new line pairs, skip the LCS and emit a plain replace.
+const MAX_LCS_CELLS: usize = 4_000_000;
+
+const RED: &str = "\x1b[31m";
+const GREEN: &str = "\x1b[32m";
+const CYAN: &str = "\x1b[36m";
+const BOLD: &str = "\x1b[1m";
+const RESET: &str = "\x1b[0m";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiffLine {
+    Context(String),
+    Removed(String),
+    Added(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hunk {
+    pub old_start: usize,
+    pub old_lines: usize,
+    pub new_start: usize,
+    pub new_lines: usize,
+    pub lines: Vec<DiffLine>,
+}
+
+impl Hunk {
+    fn from_lines(old_start: usize, new_start: usize, lines: Vec<DiffLine>) -> Self {
+        let old_lines = lines
+            .iter()
+            .filter(|l| !matches!(l, DiffLine::Added(_)))
+            .count();
+        let new_lines = lines
+            .iter()
+            .filter(|l| !matches!(l, DiffLine::Removed(_)))
+            .count();
+        Self {
+            old_start,
+            old_lines,
+            new_start,
+            new_lines,
+            lines,
+        }
+    }
+
+    /// `@@ -a,b +c,d @@`, omitting counts of 1 like `diff -u` does.
+    pub fn header(&self) -> String {
+        fn range(start: usize, count: usize) -> String {
+            if count == 1 {
+                start.to_string()
+            } else {
+                format!("{},{}", start, count)
+            }
+        }
+        format!(
+            "@@ -{} +{} @@",
+            range(self.old_start, self.old_lines),
+            range(self.new_start, self.new_lines)
+        )
+    }
+}
+
+/// One step of an alignment between two sequences, as indices into them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Step {
+    Same(usize, usize),
+    Removed(usize),
+    Added(usize),
+}
+
+/// Align `old` with `new` along a longest common subsequence.
+///
+/// Above [`MAX_LCS_CELLS`] pairs, everything old is removed and everything new added.
+pub(crate) fn align<T: PartialEq>(old: &[T], new: &[T]) -> Vec<Step> {
+    if old.len().saturating_mul(new.len()) > MAX_LCS_CELLS {
+        return (0..old.len())
+            .map(Step::Removed)
+            .chain((0..new.len()).map(Step::Added))
+            .collect();
+    }
+
+    // lcs[i][j] = LCS length of old[i..] and new[j..]
+    let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut out = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() && j < new.len() {
+        if old[i] == new[j] {
+            out.push(Step::Same(i, j));
+            i += 1;
+            j += 1;
+        } else if lcs[i + 1][j] >= lcs[i][j + 1] {
+            out.push(Step::Removed(i));
+            i += 1;
+        } else {
+            out.push(Step::Added(j));
+            j += 1;
+        }
+    }
+    out.extend((i..old.len()).map(Step::Removed));
+    out.extend((j..new.len()).map(Step::Added));
+    out
+}
+
+/// Line-level diff of `old` against `new` (longest common subsequence).
+pub fn diff_lines(old: &str, new: &str) -> Vec<DiffLine> {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+    align(&old, &new)
+        .into_iter()
+        .map(|step| match step {
+            Step::Same(i, _) => DiffLine::Context(old[i].to_string()),
+            Step::Removed(i) => DiffLine::Removed(old[i].to_string()),
+            Step::Added(j) => DiffLine::Added(new[j].to_string()),
+        })
+        .collect()
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct JsonHunk {
+    old_start: usize,
+    #[serde(default)]
+    old_lines: usize,
+    new_start: usize,
+    #[serde(default)]
+    new_lines: usize,
+    #[serde(default)]
+    lines: Vec<String>,
+}
+
+fn hunk_header_re() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| {
+        Regex::new(r"^@@ -(\d+)(?:,(\d+))? \+(\d+)(?:,(\d+))? @@").expect("valid hunk regex")
+    })
+}
+
+fn parse_body_line(line: &str) -> Option<DiffLine> {
+    if let Some(rest) = line.strip_prefix('+') {
+        Some(DiffLine::Added(rest.to_string()))
+    } else if let Some(rest) = line.strip_prefix('-') {
+        Some(DiffLine::Removed(rest.to_string()))
+    } else {
+        line.strip_prefix(' ')
+            .map(|rest| DiffLine::Context(rest.to_string()))
+    }
+}
+
+/// Parse a trace's `structured_patch`: either Claude's JSON hunk array or unified-diff text.
+///
+/// Hunks may come back with empty bodies when the patch only records line ranges.
+pub fn parse_structured_patch(patch: &str) -> Vec<Hunk> {
+    let trimmed = patch.trim();
+    if trimmed.starts_with('[') {
+        let hunks: Vec<JsonHunk> = serde_json::from_str(trimmed).unwrap_or_default();
+        return hunks
+            .into_iter()
+            .map(|h| Hunk {
+                old_start: h.old_start,
+                old_lines: h.old_lines,
+                new_start: h.new_start,
+                new_lines: h.new_lines,
+                lines: h.lines.iter().filter_map(|l| parse_body_line(l)).collect(),
+            })
+            .collect();
+    }
+
+    let mut hunks: Vec<Hunk> = Vec::new();
+    for line in trimmed.lines() {
+        if let Some(caps) = hunk_header_re().captures(line) {
+            let num = |i: usize, default: usize| {
+                caps.get(i)
+                    .and_then(|m| m.as_str().parse().ok())
+                    .unwrap_or(default)
+            };
+            hunks.push(Hunk {
+                old_start: num(1, 0),
+                old_lines: num(2, 1),
+                new_start: num(3, 0),
+                new_lines: num(4, 1),
+                lines: Vec::new(),
+            });
+        } else if let (Some(hunk), Some(body)) = (hunks.last_mut(), parse_body_line(line)) {
+            hunk.lines.push(body);
+        }
+    }
+    hunks
+}
+
+/// Hunks describing what `edit` changed; empty when the trace recorded no content or
+/// the edit targets a notebook cell.
+pub fn edit_hunks(edit: &EditRecord) -> Vec<Hunk> {
+    if edit.is_cell_edit() {
+        return Vec::new();
+    }
+    let patch_hunks = edit
+        .structured_patch
+        .as_deref()
+        .map(parse_structured_patch)
+        .unwrap_or_default();
+    if !patch_hunks.is_empty() && patch_hunks.iter().all(|h| !h.lines.is_empty()) {
+        return patch_hunks;
+    }
+
+    if edit.is_create {
+        let content = edit
+            .create_content
+            .as_deref()
+            .or(edit.new_string.as_deref());
+        return match content {
+            Some(content) => {
+                let lines = content
+                    .lines()
+                    .map(|l| DiffLine::Added(l.to_string()))
+                    .collect();
+                vec![Hunk::from_lines(0, 1, lines)]
+            }
+            None => Vec::new(),
+        };
+    }
+
+    if edit.old_string.is_none() && edit.new_string.is_none() {
+        return Vec::new();
+    }
+    let lines = diff_lines(
+        edit.old_string.as_deref().unwrap_or(""),
+        edit.new_string.as_deref().unwrap_or(""),
+    );
+    let (old_start, new_start) = patch_hunks
+        .first()
+        .map(|h| (h.old_start, h.new_start))
+        .unwrap_or((1, 1));
+    vec![Hunk::from_lines(old_start, new_start, lines)]
+}
+
+/// Render `hunks` as a unified diff for `path`, optionally with ANSI colors.
+pub fn render_patch(path: &str, is_create: bool, hunks: &[Hunk], color: bool) -> String {
+    let paint = |code: &str, text: String| {
+        if color {
+            format!("{}{}{}", code, text, RESET)
+        } else {
+            text
+        }
+    };
+
+    let mut out = String::new();
+    let old_name = if is_create {
+        "/dev/null".to_string()
+    } else {
+        format!("a/{}", path)
+    };
+    out.push_str(&paint(BOLD, format!("--- {}", old_name)));
+    out.push('\n');
+    out.push_str(&paint(BOLD, format!("+++ b/{}", path)));
+    out.push('\n');
+
+    for hunk in hunks {
+        out.push_str(&paint(CYAN, hunk.header()));
+        out.push('\n');
+        for line in &hunk.lines {
+            let rendered = match line {
+                DiffLine::Context(text) => format!(" {}", text),
+                DiffLine::Removed(text) => paint(RED, format!("-{}", text)),
+                DiffLine::Added(text) => paint(GREEN, format!("+{}", text)),
+            };
+            out.push_str(&rendered);
+            out.push('\n');
+        }
+    }
+    out
+}
@@ -0,0 +1,303 @@
+//! Export provenance data to Parquet, CSV or JSON Lines for analytics pipelines.
+//!
+//! Rows are loaded into an in-memory DuckDB table with a fixed schema (see [`Dataset::schema`]),
+//! so every export of a dataset has the same columns and types regardless of which records
+//! happen to be present. Parquet and CSV are written with DuckDB's `COPY`.
+
+use crate::models::{EditRecord, TimelineEvent};
+use crate::pricing::PriceTable;
+use crate::transcript::Transcript;
+use anyhow::{Context, Result};
+use duckdb::types::Value;
+use duckdb::Connection;
+use std::io::Write;
+use std::path::Path;
+
+/// File format of an export.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Parquet,
+    Csv,
+    /// One JSON object per line.
+    Jsonl,
+}
+
+/// What an export contains.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dataset {
+    /// One row per [`EditRecord`], including the edit payloads.
+    Edits,
+    /// One row per [`EditRecord`], without `old_string`, `new_string`, `structured_patch`
+    /// and `create_content`.
+    EditsWithoutPayloads,
+    /// One row per [`TimelineEvent`].
+    Timeline,
+    /// One row per transcript: its summary and statistics.
+    Transcripts,
+}
+
+impl Dataset {
+    /// Column definitions of the exported table, in output order.
+    pub fn schema(self) -> &'static str {
+        match self {
+            Dataset::Edits => EDITS_SCHEMA,
+            Dataset::EditsWithoutPayloads => EDITS_WITHOUT_PAYLOADS_SCHEMA,
+            Dataset::Timeline => TIMELINE_SCHEMA,
+            Dataset::Transcripts => TRANSCRIPTS_SCHEMA,
+        }
+    }
+}
+
+const EDITS_WITHOUT_PAYLOADS_SCHEMA: &str = "
+    file_path VARCHAR NOT NULL,
+    timestamp TIMESTAMPTZ NOT NULL,
+    model VARCHAR NOT NULL,
+    session_id VARCHAR NOT NULL,
+    agent_tool VARCHAR NOT NULL,
+    agent_version VARCHAR,
+    is_create BOOLEAN NOT NULL,
+    change_size BIGINT NOT NULL,
+    cell_id VARCHAR,
+    agent_id VARCHAR";
+
+const EDITS_SCHEMA: &str = "
+    file_path VARCHAR NOT NULL,
+    timestamp TIMESTAMPTZ NOT NULL,
+    model VARCHAR NOT NULL,
+    session_id VARCHAR NOT NULL,
+    agent_tool VARCHAR NOT NULL,
+    agent_version VARCHAR,
+    is_create BOOLEAN NOT NULL,
+    change_size BIGINT NOT NULL,
+    cell_id VARCHAR,
+    agent_id VARCHAR,
+    old_string VARCHAR,
+    new_string VARCHAR,
+    structured_patch VARCHAR,
+    create_content VARCHAR";
+
+const TIMELINE_SCHEMA: &str = "
+    timestamp TIMESTAMPTZ NOT NULL,
+    action VARCHAR NOT NULL,
+    file_path VARCHAR NOT NULL,
+    model VARCHAR NOT NULL,
+    agent_tool VARCHAR NOT NULL,
+    agent_version VARCHAR,
+    change_size BIGINT NOT NULL";
+
+const TRANSCRIPTS_SCHEMA: &str = "
+    session_id VARCHAR NOT NULL,
+    agent_tool VARCHAR NOT NULL,
+    agent_version VARCHAR,
+    slug VARCHAR,
+    cwd VARCHAR,
+    git_branch VARCHAR,
+    start_time TIMESTAMPTZ NOT NULL,
+    end_time TIMESTAMPTZ,
+    primary_model VARCHAR,
+    models VARCHAR,
+    message_count BIGINT NOT NULL,
+    user_message_count BIGINT NOT NULL,
+    assistant_message_count BIGINT NOT NULL,
+    tool_use_count BIGINT NOT NULL,
+    files_touched BIGINT NOT NULL,
+    total_input_tokens BIGINT,
+    total_output_tokens BIGINT,
+    source_file VARCHAR";
+
+/// Rows staged for export in an in-memory DuckDB table.
+pub struct Export {
+    conn: Connection,
+    dataset: Dataset,
+}
+
+impl Export {
+    fn new(dataset: Dataset) -> Result<Self> {
+        let conn = Connection::open_in_memory()?;
+        conn.execute(&format!("CREATE TABLE export ({})", dataset.schema()), [])?;
+        Ok(Self { conn, dataset })
+    }
+
+    /// Stage edit records, with or without their payloads.
+    pub fn edits(edits: &[EditRecord], include_payloads: bool) -> Result<Self> {
+        let dataset = if include_payloads {
+            Dataset::Edits
+        } else {
+            Dataset::EditsWithoutPayloads
+        };
+        let export = Self::new(dataset)?;
+        export.conn.execute("BEGIN TRANSACTION", [])?;
+        if include_payloads {
+            let mut stmt = export.conn.prepare(
+                "INSERT INTO export VALUES
+                 (?1, CAST(?2 AS TIMESTAMPTZ), ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)",
+            )?;
+            for edit in edits {
+                stmt.execute(duckdb::params![
+                    &edit.file_path,
+                    edit.timestamp.to_rfc3339(),
+                    &edit.model,
+                    &edit.session_id,
+                    &edit.agent_tool,
+                    &edit.agent_version,
+                    edit.is_create,
+                    edit.change_size as i64,
+                    &edit.cell_id,
+                    &edit.agent_id,
+                    &edit.old_string,
+                    &edit.new_string,
+                    &edit.structured_patch,
+                    &edit.create_content,
+                ])?;
+            }
+        } else {
+            let mut stmt = export.conn.prepare(
+                "INSERT INTO export VALUES
+                 (?1, CAST(?2 AS TIMESTAMPTZ), ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+            )?;
+            for edit in edits {
+                stmt.execute(duckdb::params![
+                    &edit.file_path,
+                    edit.timestamp.to_rfc3339(),
+                    &edit.model,
+                    &edit.session_id,
+                    &edit.agent_tool,
+                    &edit.agent_version,
+                    edit.is_create,
+                    edit.change_size as i64,
+                    &edit.cell_id,
+                    &edit.agent_id,
+                ])?;
+            }
+        }
+        export.conn.execute("COMMIT", [])?;
+        Ok(export)
+    }
+
+    /// Stage timeline events.
+    pub fn timeline(events: &[TimelineEvent]) -> Result<Self> {
+        let export = Self::new(Dataset::Timeline)?;
+        export.conn.execute("BEGIN TRANSACTION", [])?;
+        {
+            let mut stmt = export.conn.prepare(
+                "INSERT INTO export VALUES (CAST(?1 AS TIMESTAMPTZ), ?2, ?3, ?4, ?5, ?6, ?7)",
+            )?;
+            for event in events {
+                stmt.execute(duckdb::params![
+                    event.timestamp.to_rfc3339(),
+                    &event.action,
+                    &event.file_path,
+                    &event.model,
+                    &event.agent_tool,
+                    &event.agent_version,
+                    event.change_size as i64,
+                ])?;
+            }
+        }
+        export.conn.execute("COMMIT", [])?;
+        Ok(export)
+    }
+
+    /// Stage transcript summaries and statistics (messages are not exported).
+    pub fn transcripts(transcripts: &[Transcript]) -> Result<Self> {
+        let export = Self::new(Dataset::Transcripts)?;
+        export.conn.execute("BEGIN TRANSACTION", [])?;
+        {
+            let mut stmt = export.conn.prepare(
+                "INSERT INTO export VALUES
+                 (?1, ?2, ?3, ?4, ?5, ?6, CAST(?7 AS TIMESTAMPTZ), CAST(?8 AS TIMESTAMPTZ),
+                  ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18)",
+            )?;
+            for transcript in transcripts {
+                // Only the models are exported, so the prices don't matter.
+                let summary = transcript.summary(&PriceTable::builtin());
+                let meta = &transcript.meta;
+                let stats = &transcript.stats;
+                let models = if summary.all_models.is_empty() {
+                    None
+                } else {
+                    Some(summary.all_models.join(", "))
+                };
+                stmt.execute(duckdb::params![
+                    &meta.session_id,
+                    &meta.agent_tool,
+                    &meta.agent_version,
+                    &meta.slug,
+                    &meta.cwd,
+                    &meta.git_branch,
+                    meta.start_time.to_rfc3339(),
+                    meta.end_time.map(|t| t.to_rfc3339()),
+                    &summary.primary_model,
+                    models,
+                    stats.message_count as i64,
+                    stats.user_message_count as i64,
+                    stats.assistant_message_count as i64,
+                    stats.tool_use_count as i64,
+                    stats.files_touched as i64,
+                    stats.total_input_tokens.map(|n| n as i64),
+                    stats.total_output_tokens.map(|n| n as i64),
+                    &summary.source_file,
+                ])?;
+            }
+        }
+        export.conn.execute("COMMIT", [])?;
+        Ok(export)
+    }
+
+    /// The dataset this export holds.
+    pub fn dataset(&self) -> Dataset {
+        self.dataset
+    }
+
+    /// Number of staged rows.
+    pub fn row_count(&self) -> Result<usize> {
+        let count: i64 = self
+            .conn
+            .query_row("SELECT COUNT(*) FROM export", [], |row| row.get(0))?;
+        Ok(count as usize)
+    }
+
+    /// Write the staged rows to `output`.
+    pub fn write(&self, format: ExportFormat, output: &Path) -> Result<()> {
+        match format {
+            ExportFormat::Parquet => self.copy_to(output, "FORMAT PARQUET"),
+            ExportFormat::Csv => self.copy_to(output, "FORMAT CSV, HEADER"),
+            ExportFormat::Jsonl => self.write_jsonl(output),
+        }
+        .with_context(|| format!("Failed to write {:?}", output))
+    }
+
+    fn copy_to(&self, output: &Path, options: &str) -> Result<()> {
+        let target = format!("'{}'", output.to_string_lossy().replace('\'', "''"));
+        self.conn
+            .execute(&format!("COPY export TO {} ({})", target, options), [])?;
+        Ok(())
+    }
+
+    /// JSON Lines is written here rather than with `COPY`, which needs DuckDB's JSON extension.
+    fn write_jsonl(&self, output: &Path) -> Result<()> {
+        let mut stmt = self.conn.prepare("SELECT * FROM export")?;
+        let mut rows = stmt.query([])?;
+        let columns = rows
+            .as_ref()
+            .map(|stmt| stmt.column_names())
+            .unwrap_or_default();
+
+        // Objects are built by hand to keep the schema's column order.
+        let mut out = std::io::BufWriter::new(std::fs::File::create(output)?);
+        while let Some(row) = rows.next()? {
+            let mut fields = Vec::with_capacity(columns.len());
+            for (i, name) in columns.iter().enumerate() {
+                let value = crate::cache::query::to_json(row.get::<_, Value>(i)?);
+                fields.push(format!(
+                    "{}:{}",
+                    serde_json::Value::from(name.as_str()),
+                    value
+                ));
+            }
+            writeln!(out, "{{{}}}", fields.join(","))?;
+        }
+        out.flush()?;
+        Ok(())
+    }
+}
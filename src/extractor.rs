@@ -521,8 +521,12 @@ fn parse_trace_file_with_model_index(
             let is_create = tool_is_create(tool_result);
             let structured_patch = tool_result
                 .get("structuredPatch")
-                .and_then(|p| p.as_str())
-                .map(|s| s.to_string());
+                // Claude records hunks as a JSON array; keep them serialized for `ai-blame log`.
+                .and_then(|p| match p {
+                    serde_json::Value::String(s) => Some(s.clone()),
+                    serde_json::Value::Array(_) => Some(p.to_string()),
+                    _ => None,
+                });
             let old_string = tool_result
                 .get("oldString")
                 .and_then(|s| s.as_str())
@@ -621,6 +625,13 @@ fn parse_trace_file_with_model_index(
             new_string,
             structured_patch,
             create_content,
+            cell_id: None,
+            agent_id: crate::parsers::common::sidechain_agent_id(trace_path, &record),
+            replace_all: record
+                .get("toolUseResult")
+                .and_then(|tr| tr.get("replaceAll"))
+                .and_then(|r| r.as_bool())
+                .unwrap_or(false),
         };
 
         pending.push(PendingEdit {
@@ -761,6 +772,9 @@ pub fn parse_codex_cli_session(
                             new_string: None,
                             structured_patch: None,
                             create_content: Some(content),
+                            cell_id: None,
+                            agent_id: None,
+                            replace_all: false,
                         };
                         edits.push(edit);
                     }
@@ -804,6 +818,9 @@ pub fn parse_codex_cli_session(
                                 new_string: Some(curr),
                                 structured_patch: None,
                                 create_content: None,
+                                cell_id: None,
+                                agent_id: None,
+                                replace_all: false,
                             };
                             edits.push(edit);
                         }
@@ -880,9 +897,15 @@ pub fn extract_edit_history(trace_dir: &Path, config: &FilterConfig) -> Result<E
     extract_edit_history_from_dirs(&[trace_dir], config, None)
 }
 
+/// Recursively collect `.jsonl` files under `dir`, appending them to `files` in sorted
+/// order so parse results merge the same way on every run.
 pub fn collect_jsonl_files(dir: &Path, files: &mut Vec<PathBuf>) -> std::io::Result<()> {
     let mut visited = HashSet::new();
-    collect_jsonl_files_internal(dir, files, &mut visited)
+    let mut found = Vec::new();
+    let result = collect_jsonl_files_internal(dir, &mut found, &mut visited);
+    found.sort();
+    files.append(&mut found);
+    result
 }
 
 fn collect_jsonl_files_internal(
@@ -947,52 +970,48 @@ pub fn extract_edit_history_from_dirs_verbose(
 
         // Open cache for this specific trace directory (one cache per agent's traces)
         let cache = if cache_enabled {
-            crate::cache::CacheManager::open(trace_dir).ok()
+            match crate::cache::CacheManager::open(trace_dir) {
+                Ok(cache) => Some(cache),
+                Err(e) => {
+                    eprintln!(
+                        "Warning: Cache unavailable for {:?}, parsing without it: {:#}",
+                        trace_dir, e
+                    );
+                    None
+                }
+            }
         } else {
             None
         };
 
         // Detect which providers have traces in this directory
         let mut providers_in_dir = std::collections::HashSet::new();
-        if verbose >= 2 {
-            let mut trace_files = Vec::new();
-            if collect_jsonl_files(trace_dir, &mut trace_files).is_ok() {
-                let mut files_by_parser: std::collections::HashMap<&str, Vec<_>> =
-                    std::collections::HashMap::new();
-
-                for file in &trace_files {
-                    if let Ok(Some(parser)) =
-                        registry.find_parser(file).map(|p| p.map(|p| p.info().name))
-                    {
-                        files_by_parser
-                            .entry(parser)
-                            .or_insert_with(Vec::new)
-                            .push(file);
-                        providers_in_dir.insert(parser);
-                    }
-                }
+        let mut trace_files = Vec::new();
+        if collect_jsonl_files(trace_dir, &mut trace_files).is_ok() {
+            // Detection peeks at the first records of each file, so spread it over the pool.
+            let detected = crate::parallel::map_ordered(&trace_files, |file| {
+                registry
+                    .find_parser(file)
+                    .ok()
+                    .flatten()
+                    .map(|p| p.info().name)
+            });
+
+            let mut files_by_parser: std::collections::HashMap<&str, usize> =
+                std::collections::HashMap::new();
+            for parser in detected.into_iter().flatten() {
+                *files_by_parser.entry(parser).or_default() += 1;
+                providers_in_dir.insert(parser);
+            }
 
-                for (parser_name, files) in &files_by_parser {
+            if verbose >= 2 {
+                for (parser_name, count) in &files_by_parser {
                     eprintln!(
                         "[*] Found {} {} trace files in {:?}",
-                        files.len(),
-                        parser_name,
-                        trace_dir
+                        count, parser_name, trace_dir
                     );
                 }
             }
-        } else {
-            // Without verbose, still detect providers to only process relevant parsers
-            let mut trace_files = Vec::new();
-            if collect_jsonl_files(trace_dir, &mut trace_files).is_ok() {
-                for file in &trace_files {
-                    if let Ok(Some(parser)) =
-                        registry.find_parser(file).map(|p| p.map(|p| p.info().name))
-                    {
-                        providers_in_dir.insert(parser);
-                    }
-                }
-            }
         }
 
         // Only process providers that actually have traces in this directory
@@ -1014,6 +1033,9 @@ pub fn extract_edit_history_from_dirs_verbose(
                     eprintln!("[*] Checking {} cache...", provider);
                 }
 
+                // Cached edits cover whole trace files: parse without `file_pattern` and
+                // apply it as edits are read, so a narrow run caches every file's edits.
+
                 // Check which files are stale vs fresh
                 let staleness = match parser.check_directory_staleness(trace_dir, cache) {
                     Ok(report) => report,
@@ -1079,6 +1101,11 @@ pub fn extract_edit_history_from_dirs_verbose(
                                         continue;
                                     }
                                 }
+                                if !file_pattern.is_empty()
+                                    && !edit.file_path.contains(file_pattern)
+                                {
+                                    continue;
+                                }
 
                                 edits_by_file
                                     .entry(edit.file_path.clone())
@@ -1103,6 +1130,11 @@ pub fn extract_edit_history_from_dirs_verbose(
                                         continue;
                                     }
                                 }
+                                if !file_pattern.is_empty()
+                                    && !edit.file_path.contains(file_pattern)
+                                {
+                                    continue;
+                                }
 
                                 edits_by_file
                                     .entry(edit.file_path.clone())
@@ -1123,49 +1155,27 @@ pub fn extract_edit_history_from_dirs_verbose(
                         && staleness.fresh_files.is_empty();
 
                     if all_stale {
-                        // All files are stale: parse entire directory for correct cross-file resolution.
+                        // All files are stale: parse entire directory for correct cross-file resolution
+                        // (parsers with saved per-file state only parse what was appended).
                         // Cache the edits under the first stale file, and mark remaining files as checked with empty results.
                         let start = std::time::Instant::now();
-                        match parser.parse_directory_with_context(
-                            trace_dir,
-                            file_pattern,
-                            repo_root,
-                        ) {
-                            Ok(parsed_edits) => {
+                        match parser.parse_directory_with_cache(trace_dir, "", repo_root, cache) {
+                            Ok((parsed_edits, parse_states)) => {
                                 let elapsed = start.elapsed();
 
                                 // Cache parsed edits under FIRST file only to avoid duplication
-                                // When loading, we check the first file for all-or-nothing results
-                                if let Some(first_file) = staleness.stale_files.first() {
-                                    if let Err(e) = cache.store_edits(
-                                        first_file,
-                                        provider,
-                                        &parsed_edits,
-                                        elapsed.as_millis() as u64,
-                                    ) {
-                                        if verbose >= 1 {
-                                            eprintln!(
-                                                "Warning: Failed to cache {:?}: {}",
-                                                first_file, e
-                                            );
-                                        }
-                                    }
-                                }
-                                // For other stale files, store empty edits to mark them as checked
-                                for file in staleness.stale_files.iter().skip(1) {
-                                    if let Err(e) = cache.store_edits(
-                                        file,
-                                        provider,
-                                        &[],
-                                        elapsed.as_millis() as u64,
-                                    ) {
-                                        if verbose >= 1 {
-                                            eprintln!(
-                                                "Warning: Failed to update cache for {:?}: {}",
-                                                file, e
-                                            );
-                                        }
-                                    }
+                                // (other stale files are stored empty to mark them as checked).
+                                // When loading, we check the first file for all-or-nothing results.
+                                // Parser state is saved in the same transaction, so a failed
+                                // write leaves the next run to parse the same lines again.
+                                if let Err(e) = cache.store_directory_edits(
+                                    &staleness.stale_files,
+                                    provider,
+                                    &parsed_edits,
+                                    elapsed.as_millis() as u64,
+                                    &parse_states,
+                                ) {
+                                    eprintln!("Warning: Failed to cache {:?}: {}", trace_dir, e);
                                 }
 
                                 if verbose >= 2 && !parsed_edits.is_empty() {
@@ -1188,6 +1198,11 @@ pub fn extract_edit_history_from_dirs_verbose(
                                             continue;
                                         }
                                     }
+                                    if !file_pattern.is_empty()
+                                        && !edit.file_path.contains(file_pattern)
+                                    {
+                                        continue;
+                                    }
 
                                     edits_by_file
                                         .entry(edit.file_path.clone())
@@ -1213,13 +1228,23 @@ pub fn extract_edit_history_from_dirs_verbose(
                         }
                     } else {
                         // Only some files are stale: parse them individually (Codex per-file staleness)
-                        for file in &staleness.stale_files {
+                        let batch_reader = repo_root
+                            .and_then(|root| crate::git_batch::BatchGitReader::new(root).ok());
+                        let parsed = crate::parallel::map_ordered(&staleness.stale_files, |file| {
                             let start = std::time::Instant::now();
+                            let result = parser.parse_file_with_batch(
+                                file,
+                                "",
+                                repo_root,
+                                batch_reader.as_ref(),
+                            );
+                            (result, start.elapsed())
+                        });
 
-                            match parser.parse_file_with_context(file, file_pattern, repo_root) {
+                        // Cache writes and merging stay sequential, in file order.
+                        for (file, (result, elapsed)) in staleness.stale_files.iter().zip(parsed) {
+                            match result {
                                 Ok(edits) => {
-                                    let elapsed = start.elapsed();
-
                                     // Store in cache
                                     if let Err(e) = cache.store_edits(
                                         file,
@@ -1245,6 +1270,11 @@ pub fn extract_edit_history_from_dirs_verbose(
                                                 continue;
                                             }
                                         }
+                                        if !file_pattern.is_empty()
+                                            && !edit.file_path.contains(file_pattern)
+                                        {
+                                            continue;
+                                        }
 
                                         edits_by_file
                                             .entry(edit.file_path.clone())
@@ -1371,6 +1401,7 @@ pub fn convert_to_file_histories(
                     description: None,
                     agent_tool: Some(edit.agent_tool.clone()),
                     agent_version: edit.agent_version.clone(),
+                    cell_id: edit.cell_id.clone(),
                 }
             })
             .collect();
@@ -1401,7 +1432,26 @@ pub fn collect_timeline_events(
     // Get edits using existing library function
     let edits_by_file = extract_edit_history_from_dirs(trace_dirs, config, None)?;
 
-    // Flatten to timeline events
+    let mut events = timeline_events(&edits_by_file);
+
+    // Filter Codex/Copilot if requested
+    if skip_codex {
+        events.retain(|e| {
+            !e.agent_tool.to_lowercase().contains("copilot")
+                && !e.agent_tool.to_lowercase().contains("codex")
+        });
+    }
+
+    // Apply limit (0 means no limit)
+    if limit > 0 && events.len() > limit {
+        events.truncate(limit);
+    }
+
+    Ok(events)
+}
+
+/// Flatten edits into timeline events, most recent first.
+pub fn timeline_events(edits_by_file: &EditsByFile) -> Vec<TimelineEvent> {
     let mut events: Vec<TimelineEvent> = edits_by_file
         .values()
         .flat_map(|edits| {
@@ -1420,22 +1470,6 @@ pub fn collect_timeline_events(
             })
         })
         .collect();
-
-    // Filter Codex/Copilot if requested
-    if skip_codex {
-        events.retain(|e| {
-            !e.agent_tool.to_lowercase().contains("copilot")
-                && !e.agent_tool.to_lowercase().contains("codex")
-        });
-    }
-
-    // Sort by timestamp descending (most recent first)
-    events.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
-
-    // Apply limit (0 means no limit)
-    if limit > 0 && events.len() > limit {
-        events.truncate(limit);
-    }
-
-    Ok(events)
+    events.sort_by_key(|e| std::cmp::Reverse(e.timestamp));
+    events
 }
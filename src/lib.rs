@@ -1,13 +1,21 @@
+pub mod backup;
 pub mod blame;
 pub mod cache;
 #[cfg(feature = "cli")]
 pub mod cli;
 pub mod config;
+pub mod diff;
+pub mod export;
 pub mod extractor;
 pub mod git_batch;
 pub mod models;
+pub mod parallel;
 pub mod parsers;
 pub mod paths;
+pub mod pricing;
+pub mod redact;
+pub mod replay;
+pub mod store;
 pub mod transcript;
 pub mod updater;
 pub mod utils;
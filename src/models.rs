@@ -23,9 +23,12 @@ pub struct CurationEvent {
     pub agent_tool: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub agent_version: Option<String>,
+    /// Notebook cell touched by this event (Jupyter `NotebookEdit` operations only).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cell_id: Option<String>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct EditRecord {
     pub file_path: String,
     pub timestamp: DateTime<Utc>,
@@ -43,6 +46,20 @@ pub struct EditRecord {
     pub structured_patch: Option<String>,
     /// For create operations: the file content at creation time (if present in trace).
     pub create_content: Option<String>,
+    /// For notebook edits: the id of the cell that was edited, inserted or deleted.
+    pub cell_id: Option<String>,
+    /// For edits made by a subagent: its ID. `session_id` is the session that started it.
+    pub agent_id: Option<String>,
+    /// Whether the edit replaced every occurrence of `old_string` (Claude's `replaceAll`).
+    pub replace_all: bool,
+}
+
+impl EditRecord {
+    /// Whether this is a notebook cell edit. Its `new_string` is the cell source, not a
+    /// span of the `.ipynb` file, so line-level blame, diffs and replay can't use it.
+    pub fn is_cell_edit(&self) -> bool {
+        self.cell_id.is_some()
+    }
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -88,15 +105,106 @@ pub enum OutputPolicy {
     Append,
     Sidecar,
     Comment,
+    /// Write to a central provenance store instead of touching the file or its directory.
+    Store,
     Skip,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "lowercase")]
 pub enum CommentSyntax {
+    /// `# comment`
     Hash,
+    /// `// comment`
     Slash,
+    /// `<!-- comment -->`
     Html,
+    /// `/* comment */`
+    Block,
+    /// `(* comment *)`
+    Paren,
+    /// `-- comment`
+    Dash,
+    /// `% comment`
+    Percent,
+}
+
+impl CommentSyntax {
+    /// Built-in comment syntax for a file, based on its name or extension.
+    ///
+    /// Used by the `comment` policy when a rule doesn't set `comment_syntax`.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let name = path.file_name()?.to_str()?;
+        if matches!(
+            name,
+            "Dockerfile"
+                | "Makefile"
+                | "GNUmakefile"
+                | "Gemfile"
+                | "Rakefile"
+                | "Justfile"
+                | "justfile"
+                | "CMakeLists.txt"
+        ) {
+            return Some(Self::Hash);
+        }
+
+        let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+        let syntax = match ext.as_str() {
+            "py" | "pyi" | "sh" | "bash" | "zsh" | "fish" | "rb" | "pl" | "pm" | "r" | "jl"
+            | "ex" | "exs" | "nim" | "cr" | "tf" | "hcl" | "nix" | "ps1" | "cmake" | "mk"
+            | "toml" | "yaml" | "yml" | "cfg" | "conf" | "ini" | "dockerfile" => Self::Hash,
+            "rs" | "js" | "jsx" | "mjs" | "cjs" | "ts" | "tsx" | "mts" | "cts" | "go" | "java"
+            | "kt" | "kts" | "scala" | "swift" | "dart" | "c" | "h" | "cpp" | "cc" | "cxx"
+            | "hpp" | "hh" | "hxx" | "cs" | "fsx" | "php" | "groovy" | "gradle" | "proto"
+            | "zig" | "sol" | "jsonc" | "scss" | "less" => Self::Slash,
+            "html" | "htm" | "xhtml" | "xml" | "xsl" | "svg" | "md" | "markdown" | "vue" => {
+                Self::Html
+            }
+            "css" => Self::Block,
+            "ml" | "mli" | "sml" | "pas" | "wl" => Self::Paren,
+            "sql" | "lua" | "hs" | "elm" | "purs" | "ada" | "adb" | "ads" | "vhd" | "vhdl" => {
+                Self::Dash
+            }
+            "tex" | "sty" | "cls" | "ltx" | "erl" | "hrl" => Self::Percent,
+            _ => return None,
+        };
+        Some(syntax)
+    }
+
+    /// Prefix for line-comment syntaxes (`None` for block-only syntaxes).
+    pub fn line_prefix(&self) -> Option<&'static str> {
+        match self {
+            Self::Hash => Some("#"),
+            Self::Slash => Some("//"),
+            Self::Dash => Some("--"),
+            Self::Percent => Some("%"),
+            Self::Html | Self::Block | Self::Paren => None,
+        }
+    }
+
+    /// Opening and closing delimiters of the syntax's block comment, if it has one.
+    pub fn block_delimiters(&self) -> Option<(&'static str, &'static str)> {
+        match self {
+            Self::Html => Some(("<!--", "-->")),
+            Self::Block => Some(("/*", "*/")),
+            Self::Paren => Some(("(*", "*)")),
+            Self::Hash | Self::Slash | Self::Dash | Self::Percent => None,
+        }
+    }
+}
+
+/// Where the `comment` policy puts the `edit_history` block.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum CommentPlacement {
+    /// At the top, after any shebang, encoding cookie or Go build constraints.
+    Top,
+    /// At the end of the file.
+    #[default]
+    Bottom,
+    /// Like `top`, but also after a license/SPDX header and a `package` line.
+    AfterLicense,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -105,12 +213,29 @@ pub struct FileRule {
     pub pattern: String,
     #[serde(default = "default_policy")]
     pub policy: OutputPolicy,
+    /// In-place format for the `append` policy: `yaml`, `json`, `toml`, `front-matter` or `ipynb`.
     #[serde(default = "default_format")]
     pub format: String,
+    /// Comment syntax for the `comment` policy; detected from the file extension when unset.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub comment_syntax: Option<CommentSyntax>,
+    /// Per-event template for the `comment` policy (e.g. `{date} {action} by {model}`);
+    /// when unset the block contains the raw YAML history.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub template: Option<String>,
+    /// Where the `comment` policy places its block: `top`, `bottom` or `after-license`.
+    #[serde(default)]
+    pub placement: CommentPlacement,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub sidecar_pattern: Option<String>,
+    /// Location of the provenance store for the `store` policy (default `.provenance`).
+    /// A `.jsonl` path is a single JSON-lines file, `.ddb`/`.duckdb` a DuckDB database,
+    /// anything else a directory of per-file YAML.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub store_path: Option<String>,
+    /// For the `ipynb` format: also write per-cell history into each cell's metadata.
+    #[serde(default)]
+    pub cell_history: bool,
 }
 
 fn default_pattern() -> String {
@@ -131,6 +256,15 @@ pub struct OutputConfig {
     pub defaults: Option<FileRule>,
     #[serde(default)]
     pub rules: Vec<FileRule>,
+    /// Model prices overriding or extending the built-in table.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pricing: Vec<crate::pricing::ModelPrice>,
+    /// Redaction detectors to turn off or add.
+    #[serde(
+        default,
+        skip_serializing_if = "crate::redact::RedactionConfig::is_empty"
+    )]
+    pub redaction: crate::redact::RedactionConfig,
 }
 
 impl OutputConfig {
@@ -0,0 +1,63 @@
+//! Bounded worker pool for parsing trace files in parallel.
+//!
+//! Workers pull the next item from a shared counter, so a few very large trace files
+//! don't hold up a fixed partition of the work. Results come back in input order, which
+//! keeps merged output identical to a sequential run.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+/// Environment variable that caps the number of worker threads (`1` parses sequentially).
+pub const JOBS_ENV: &str = "AI_BLAME_JOBS";
+
+/// Number of worker threads: `AI_BLAME_JOBS` if set to a positive number, otherwise the
+/// machine's available parallelism.
+pub fn worker_count() -> usize {
+    std::env::var(JOBS_ENV)
+        .ok()
+        .and_then(|v| v.trim().parse::<usize>().ok())
+        .filter(|n| *n > 0)
+        .unwrap_or_else(|| {
+            std::thread::available_parallelism()
+                .map(|n| n.get())
+                .unwrap_or(1)
+        })
+}
+
+/// Apply `f` to every item on at most [`worker_count`] threads, returning the results in
+/// the same order as `items`.
+pub fn map_ordered<T, R, F>(items: &[T], f: F) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T) -> R + Sync,
+{
+    let workers = worker_count().min(items.len());
+    if workers <= 1 {
+        return items.iter().map(f).collect();
+    }
+
+    let next = AtomicUsize::new(0);
+    let slots: Vec<Mutex<Option<R>>> = items.iter().map(|_| Mutex::new(None)).collect();
+    std::thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| loop {
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                let Some(item) = items.get(i) else {
+                    break;
+                };
+                let result = f(item);
+                *slots[i].lock().expect("result slot lock poisoned") = Some(result);
+            });
+        }
+    });
+
+    slots
+        .into_iter()
+        .map(|slot| {
+            slot.into_inner()
+                .expect("result slot lock poisoned")
+                .expect("every item is processed before the scope ends")
+        })
+        .collect()
+}
@@ -3,16 +3,94 @@ use crate::models::EditRecord;
 use crate::parsers::{ParserInfo, TraceParser};
 use anyhow::{Context, Result};
 use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::collections::HashMap;
 use std::fs::File;
-use std::io::{BufRead, BufReader};
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom};
 use std::path::Path;
 use std::time::Instant;
 
 /// Parser for Claude Code trace files (.jsonl format)
 pub struct ClaudeParser;
 
+/// How far a Claude trace file has been parsed, and the model indices built from it.
+///
+/// Claude traces are append-only, so saving this in the cache lets the next run parse
+/// just the lines appended since (see [`TraceParser::parse_directory_with_cache`]).
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct ClaudeParseState {
+    /// Byte offset just past the last parsed line.
+    offset: u64,
+    /// Fingerprint of the bytes just before `offset`, to tell appends from rewrites.
+    fingerprint: u64,
+    models_by_uuid: HashMap<String, String>,
+    models_by_tool_use_id: HashMap<String, String>,
+    notebook_inputs: HashMap<String, Value>,
+}
+
+/// An edit whose model may still need resolving from its parent message.
+struct PendingEdit {
+    record: EditRecord,
+    parent_uuid: Option<String>,
+    tool_use_id: Option<String>,
+}
+
+/// Resolve unknown models using the file's own indices AND the cross-file index.
+fn resolve_pending(
+    pending: Vec<PendingEdit>,
+    state: &ClaudeParseState,
+    cross_file_models: &HashMap<String, String>,
+) -> Vec<EditRecord> {
+    let mut edits: Vec<EditRecord> = Vec::with_capacity(pending.len());
+    for mut p in pending {
+        if p.record.model == "unknown" {
+            if let Some(pu) = p.parent_uuid.as_deref() {
+                if let Some(model) = state
+                    .models_by_uuid
+                    .get(pu)
+                    .or_else(|| cross_file_models.get(pu))
+                {
+                    p.record.model = model.clone();
+                }
+            } else if let Some(tu) = p.tool_use_id.as_deref() {
+                if let Some(model) = state.models_by_tool_use_id.get(tu) {
+                    p.record.model = model.clone();
+                }
+            }
+        }
+        edits.push(p.record);
+    }
+    edits
+}
+
+/// Bytes hashed by [`fingerprint_before`].
+const FINGERPRINT_WINDOW: u64 = 1024;
+
+/// FNV-1a hash of up to [`FINGERPRINT_WINDOW`] bytes ending at `offset`.
+fn fingerprint_before(path: &Path, offset: u64) -> Result<u64> {
+    let start = offset.saturating_sub(FINGERPRINT_WINDOW);
+    let mut file =
+        File::open(path).with_context(|| format!("Failed to open trace file: {:?}", path))?;
+    file.seek(SeekFrom::Start(start))?;
+    let mut bytes = Vec::with_capacity((offset - start) as usize);
+    file.take(offset - start).read_to_end(&mut bytes)?;
+
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for b in bytes {
+        hash ^= u64::from(b);
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    Ok(hash)
+}
+
+/// A successful `NotebookEdit` tool call, resolved against its tool_use input.
+struct NotebookEdit {
+    notebook_path: String,
+    cell_id: Option<String>,
+    new_source: String,
+}
+
 impl ClaudeParser {
     pub fn new() -> Self {
         Self
@@ -35,6 +113,86 @@ impl ClaudeParser {
         None
     }
 
+    /// Collect `NotebookEdit` tool_use inputs from an assistant message, keyed by tool_use id.
+    ///
+    /// Older Claude Code versions omit `notebook_path` from the tool result, so the input is
+    /// needed to know which notebook was edited.
+    fn collect_notebook_inputs(record: &Value, inputs: &mut HashMap<String, Value>) {
+        let Some(content) = record
+            .get("message")
+            .and_then(|m| m.get("content"))
+            .and_then(|c| c.as_array())
+        else {
+            return;
+        };
+        for item in content {
+            if item.get("type").and_then(|t| t.as_str()) != Some("tool_use")
+                || item.get("name").and_then(|n| n.as_str()) != Some("NotebookEdit")
+            {
+                continue;
+            }
+            if let (Some(id), Some(input)) =
+                (item.get("id").and_then(|v| v.as_str()), item.get("input"))
+            {
+                inputs.insert(id.to_string(), input.clone());
+            }
+        }
+    }
+
+    /// Recognize a successful `NotebookEdit` tool result.
+    fn notebook_edit_from_record(
+        record: &Value,
+        notebook_inputs: &HashMap<String, Value>,
+    ) -> Option<NotebookEdit> {
+        if record.get("type").and_then(|t| t.as_str()) != Some("user") {
+            return None;
+        }
+        let tool_result = record.get("toolUseResult").filter(|tr| tr.is_object())?;
+        let new_source = tool_result.get("new_source").and_then(|s| s.as_str())?;
+        // NotebookEdit always reports `error`; only a non-empty message means failure.
+        if tool_result
+            .get("error")
+            .and_then(|e| e.as_str())
+            .is_some_and(|e| !e.is_empty())
+        {
+            return None;
+        }
+
+        let input = Self::extract_tool_use_id_from_tool_result_record(record)
+            .and_then(|id| notebook_inputs.get(&id));
+        let notebook_path = tool_result
+            .get("notebook_path")
+            .or_else(|| input.and_then(|i| i.get("notebook_path")))
+            .and_then(|p| p.as_str())
+            .filter(|p| !p.is_empty())?;
+        let edit_mode = tool_result
+            .get("edit_mode")
+            .or_else(|| input.and_then(|i| i.get("edit_mode")))
+            .and_then(|m| m.as_str())
+            .unwrap_or("replace");
+
+        // For inserts the input `cell_id` names the cell the new one was inserted after,
+        // so only the id reported by the result identifies the new cell.
+        let cell_id = tool_result
+            .get("cell_id")
+            .or_else(|| {
+                if edit_mode == "insert" {
+                    None
+                } else {
+                    input.and_then(|i| i.get("cell_id"))
+                }
+            })
+            .and_then(|c| c.as_str())
+            .filter(|c| !c.is_empty())
+            .map(|c| c.to_string());
+
+        Some(NotebookEdit {
+            notebook_path: notebook_path.to_string(),
+            cell_id,
+            new_source: new_source.to_string(),
+        })
+    }
+
     /// Check if a tool result is a create operation
     fn tool_is_create(tool_result: &Value) -> bool {
         let tool_type = tool_result
@@ -82,169 +240,284 @@ impl ClaudeParser {
         file_pattern: &str,
         cross_file_models: &std::collections::HashMap<String, String>,
     ) -> Result<Vec<EditRecord>> {
-        let file =
-            File::open(path).with_context(|| format!("Failed to open trace file: {:?}", path))?;
-        let reader = BufReader::new(file);
-
-        let mut models_by_uuid: HashMap<String, String> = HashMap::new();
-        let mut models_by_tool_use_id: HashMap<String, String> = HashMap::new();
+        let mut state = ClaudeParseState::default();
+        let pending = self.scan(path, file_pattern, &mut state)?;
+        Ok(resolve_pending(pending, &state, cross_file_models))
+    }
 
-        struct PendingEdit {
-            record: EditRecord,
-            parent_uuid: Option<String>,
-            tool_use_id: Option<String>,
-        }
+    /// Parse `path` from `state.offset` to the end, updating `state` as records are read.
+    ///
+    /// Returns edits whose model may still need resolving (see [`resolve_pending`]).
+    /// Only complete lines are consumed, plus a final unterminated line if it is already
+    /// valid JSON, so a line the agent is still writing is picked up on the next run.
+    fn scan(
+        &self,
+        path: &Path,
+        file_pattern: &str,
+        state: &mut ClaudeParseState,
+    ) -> Result<Vec<PendingEdit>> {
+        let mut file =
+            File::open(path).with_context(|| format!("Failed to open trace file: {:?}", path))?;
+        file.seek(SeekFrom::Start(state.offset))
+            .with_context(|| format!("Failed to seek in trace file: {:?}", path))?;
+        let mut reader = BufReader::new(file);
 
         let mut pending: Vec<PendingEdit> = Vec::new();
+        let mut buf = Vec::new();
+        loop {
+            buf.clear();
+            let read = reader.read_until(b'\n', &mut buf)?;
+            if read == 0 {
+                break;
+            }
+            // Partially written last line (possibly cut inside a character): leave it for
+            // the next run.
+            let complete = buf.last() == Some(&b'\n');
+            let line = match std::str::from_utf8(&buf) {
+                Ok(text) => text.trim(),
+                Err(_) if !complete => break,
+                Err(e) => {
+                    return Err(e)
+                        .with_context(|| format!("Invalid UTF-8 in trace file: {:?}", path))
+                }
+            };
+            if !complete && serde_json::from_str::<Value>(line).is_err() {
+                break;
+            }
+            state.offset += read as u64;
 
-        for line in reader.lines() {
-            let line = line?;
-            let line = line.trim();
             if line.is_empty() || !line.starts_with('{') {
                 continue;
             }
-
             let record = match serde_json::from_str::<Value>(line) {
                 Ok(v) => v,
                 Err(_) => continue,
             };
-
-            // Capture model from parent messages (for cross-file UUID resolution)
-            let model_for_record =
-                crate::parsers::common::extract_model_from_record(&record).map(|s| s.to_string());
-            if let Some(uuid) = record.get("uuid").and_then(|u| u.as_str()) {
-                if let Some(model) = model_for_record.as_deref() {
-                    models_by_uuid.insert(uuid.to_string(), model.to_string());
-                }
-            }
-            if let Some(model) = model_for_record.as_deref() {
-                for id in crate::parsers::common::extract_tool_use_ids_from_record(&record) {
-                    models_by_tool_use_id.insert(id, model.to_string());
-                }
+            if let Some(edit) = self.process_record(path, file_pattern, &record, state) {
+                pending.push(edit);
             }
+        }
 
-            // Check if this is a Codex-format edit (skip if so)
-            if crate::parsers::codex::is_codex_format_check(&record) {
-                continue;
-            }
+        state.fingerprint = fingerprint_before(path, state.offset)?;
+        Ok(pending)
+    }
 
-            if !Self::is_successful_edit(&record) {
-                continue;
+    /// Update `state` from one trace record and return the edit it records, if any.
+    fn process_record(
+        &self,
+        path: &Path,
+        file_pattern: &str,
+        record: &Value,
+        state: &mut ClaudeParseState,
+    ) -> Option<PendingEdit> {
+        // Capture model from parent messages (for cross-file UUID resolution)
+        let model_for_record =
+            crate::parsers::common::extract_model_from_record(record).map(|s| s.to_string());
+        if let Some(uuid) = record.get("uuid").and_then(|u| u.as_str()) {
+            if let Some(model) = model_for_record.as_deref() {
+                state
+                    .models_by_uuid
+                    .insert(uuid.to_string(), model.to_string());
             }
+        }
+        if let Some(model) = model_for_record.as_deref() {
+            for id in crate::parsers::common::extract_tool_use_ids_from_record(record) {
+                state.models_by_tool_use_id.insert(id, model.to_string());
+            }
+        }
 
-            let tool_result = match record.get("toolUseResult") {
-                Some(tr) => tr,
-                None => continue,
-            };
-
-            let file_path = tool_result
-                .get("filePath")
-                .and_then(|fp| fp.as_str())
-                .unwrap_or("");
+        // Check if this is a Codex-format edit (skip if so)
+        if crate::parsers::codex::is_codex_format_check(record) {
+            return None;
+        }
 
-            // Apply file pattern filter
-            if !file_pattern.is_empty() && !file_path.contains(file_pattern) {
-                continue;
-            }
+        Self::collect_notebook_inputs(record, &mut state.notebook_inputs);
+        let notebook_edit = Self::notebook_edit_from_record(record, &state.notebook_inputs);
+        // A tool use has one result: its input needn't be kept in the saved state after.
+        if let Some(id) = Self::extract_tool_use_id_from_tool_result_record(record) {
+            state.notebook_inputs.remove(&id);
+        }
 
-            let is_create = Self::tool_is_create(tool_result);
-            let structured_patch = tool_result
-                .get("structuredPatch")
-                .and_then(|p| p.as_str())
-                .map(|s| s.to_string());
-            let old_string = tool_result
-                .get("oldString")
-                .and_then(|s| s.as_str())
-                .map(|s| s.to_string());
-            let new_string = tool_result
-                .get("newString")
-                .and_then(|s| s.as_str())
-                .map(|s| s.to_string());
-            let create_content = if is_create {
-                tool_result
-                    .get("content")
-                    .and_then(|c| c.as_str())
-                    .or_else(|| tool_result.get("newString").and_then(|c| c.as_str()))
-                    .map(|s| s.to_string())
-            } else {
-                None
-            };
-            let change_size = Self::calculate_change_size(tool_result);
-
-            let parent_uuid = record
-                .get("parentUuid")
-                .and_then(|p| p.as_str())
-                .map(|s| s.to_string());
-            let tool_use_id = Self::extract_tool_use_id_from_tool_result_record(&record);
-
-            let agent_version = record
-                .get("version")
-                .and_then(|v| v.as_str())
-                .map(|s| s.to_string());
-
-            // Parse timestamp
-            let timestamp = match record.get("timestamp").and_then(|t| t.as_str()) {
-                Some(ts_str) => match DateTime::parse_from_rfc3339(ts_str) {
-                    Ok(dt) => dt.with_timezone(&Utc),
-                    Err(_) => continue,
-                },
-                None => continue,
-            };
+        if notebook_edit.is_none() && !Self::is_successful_edit(record) {
+            return None;
+        }
 
-            let session_id = record
-                .get("sessionId")
-                .or_else(|| record.get("session_id"))
-                .and_then(|s| s.as_str())
-                .unwrap_or("unknown")
-                .to_string();
+        let tool_result = record.get("toolUseResult")?;
 
-            let mut model = "unknown".to_string();
-            if let Some(m) = crate::parsers::common::extract_model_from_record(&record) {
-                model = m.to_string();
-            }
+        let file_path = match &notebook_edit {
+            Some(nb) => nb.notebook_path.clone(),
+            None => tool_result
+                .get("filePath")
+                .and_then(|fp| fp.as_str())
+                .unwrap_or("")
+                .to_string(),
+        };
 
-            let edit = EditRecord {
-                file_path: file_path.to_string(),
-                timestamp,
-                model,
-                session_id,
-                is_create,
-                change_size,
-                agent_tool: Self::infer_agent_tool(path, &record),
-                agent_version,
-                old_string,
-                new_string,
-                structured_patch,
-                create_content,
-            };
+        // Apply file pattern filter
+        if !file_pattern.is_empty() && !file_path.contains(file_pattern) {
+            return None;
+        }
 
-            pending.push(PendingEdit {
-                record: edit,
-                parent_uuid,
-                tool_use_id,
+        let is_create = Self::tool_is_create(tool_result);
+        let structured_patch = tool_result
+            .get("structuredPatch")
+            // Claude records hunks as a JSON array; keep them serialized for `ai-blame log`.
+            .and_then(|p| match p {
+                serde_json::Value::String(s) => Some(s.clone()),
+                serde_json::Value::Array(_) => Some(p.to_string()),
+                _ => None,
             });
+        let old_string = tool_result
+            .get("oldString")
+            .and_then(|s| s.as_str())
+            .map(|s| s.to_string());
+        let new_string = tool_result
+            .get("newString")
+            .and_then(|s| s.as_str())
+            .map(|s| s.to_string());
+        let create_content = if is_create {
+            tool_result
+                .get("content")
+                .and_then(|c| c.as_str())
+                .or_else(|| tool_result.get("newString").and_then(|c| c.as_str()))
+                .map(|s| s.to_string())
+        } else {
+            None
+        };
+        let mut change_size = Self::calculate_change_size(tool_result);
+        let mut new_string = new_string;
+        let mut cell_id = None;
+        if let Some(nb) = notebook_edit {
+            change_size = nb.new_source.len();
+            new_string = Some(nb.new_source);
+            cell_id = nb.cell_id;
         }
 
-        // Post-pass: resolve unknown models using collected indices AND cross-file index
-        let mut edits: Vec<EditRecord> = Vec::with_capacity(pending.len());
-        for mut p in pending {
-            if p.record.model == "unknown" {
-                if let Some(pu) = p.parent_uuid.as_deref() {
-                    if let Some(model) =
-                        models_by_uuid.get(pu).or_else(|| cross_file_models.get(pu))
-                    {
-                        p.record.model = model.clone();
-                    }
-                } else if let Some(tu) = p.tool_use_id.as_deref() {
-                    if let Some(model) = models_by_tool_use_id.get(tu) {
-                        p.record.model = model.clone();
+        let parent_uuid = record
+            .get("parentUuid")
+            .and_then(|p| p.as_str())
+            .map(|s| s.to_string());
+        let tool_use_id = Self::extract_tool_use_id_from_tool_result_record(record);
+
+        let agent_version = record
+            .get("version")
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string());
+
+        // Parse timestamp
+        let timestamp = DateTime::parse_from_rfc3339(record.get("timestamp")?.as_str()?)
+            .ok()?
+            .with_timezone(&Utc);
+
+        let session_id = record
+            .get("sessionId")
+            .or_else(|| record.get("session_id"))
+            .and_then(|s| s.as_str())
+            .unwrap_or("unknown")
+            .to_string();
+
+        let model = model_for_record.unwrap_or_else(|| "unknown".to_string());
+
+        let edit = EditRecord {
+            file_path,
+            timestamp,
+            model,
+            session_id,
+            is_create,
+            change_size,
+            agent_tool: Self::infer_agent_tool(path, record),
+            agent_version,
+            old_string,
+            new_string,
+            structured_patch,
+            create_content,
+            cell_id,
+            agent_id: crate::parsers::common::sidechain_agent_id(path, record),
+            replace_all: tool_result
+                .get("replaceAll")
+                .and_then(|r| r.as_bool())
+                .unwrap_or(false),
+        };
+
+        Some(PendingEdit {
+            record: edit,
+            parent_uuid,
+            tool_use_id,
+        })
+    }
+
+    /// Scan every file in `files` from its state (in parallel), then resolve models using
+    /// the combined cross-file UUID index. Files that fail to parse keep their old state.
+    fn scan_files(
+        &self,
+        files: &[std::path::PathBuf],
+        file_pattern: &str,
+        states: Vec<ClaudeParseState>,
+        warn: bool,
+    ) -> (Vec<EditRecord>, Vec<ClaudeParseState>) {
+        let inputs: Vec<_> = files.iter().zip(states).collect();
+        let scanned = crate::parallel::map_ordered(&inputs, |(file, state)| {
+            let mut state = state.clone();
+            let result = self.scan(file, file_pattern, &mut state);
+            (result, state)
+        });
+
+        let cross_file_models: HashMap<String, String> = scanned
+            .iter()
+            .flat_map(|(_, state)| state.models_by_uuid.iter())
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect();
+
+        let mut edits = Vec::new();
+        let mut states = Vec::with_capacity(scanned.len());
+        for ((file, old_state), (result, state)) in inputs.into_iter().zip(scanned) {
+            match result {
+                Ok(pending) => {
+                    edits.extend(resolve_pending(pending, &state, &cross_file_models));
+                    states.push(state);
+                }
+                Err(e) => {
+                    if warn {
+                        eprintln!("Warning: Failed to parse {:?}: {}", file, e);
                     }
+                    states.push(old_state);
                 }
             }
-            edits.push(p.record);
         }
+        (edits, states)
+    }
 
-        Ok(edits)
+    /// Saved state for each of `files` (`None` for new files), if every previously cached
+    /// file can be resumed.
+    ///
+    /// Returns `None` (full reparse needed) when a cached file has no saved state or was
+    /// rewritten rather than appended to.
+    fn resumable_states(
+        &self,
+        files: &[std::path::PathBuf],
+        cache: &crate::cache::CacheManager,
+    ) -> Result<Option<Vec<Option<ClaudeParseState>>>> {
+        let mut states = Vec::with_capacity(files.len());
+        for file in files {
+            let Some(json) = cache.get_parse_state(file)? else {
+                if cache.get_file_metadata(file)?.is_some() {
+                    // Cached before parse state was recorded.
+                    return Ok(None);
+                }
+                // New file: parse it from the start.
+                states.push(None);
+                continue;
+            };
+            let Ok(state) = serde_json::from_str::<ClaudeParseState>(&json) else {
+                return Ok(None);
+            };
+            let size = std::fs::metadata(file)?.len();
+            if size < state.offset || fingerprint_before(file, state.offset)? != state.fingerprint {
+                return Ok(None);
+            }
+            states.push(Some(state));
+        }
+        Ok(Some(states))
     }
 
     /// Infer the agent tool from trace path and record
@@ -384,46 +657,12 @@ impl TraceParser for ClaudeParser {
         file_pattern: &str,
         _repo_root: Option<&Path>,
     ) -> Result<Vec<EditRecord>> {
-        // Override default to handle cross-file UUID resolution
-        let mut all_edits = Vec::new();
+        // Override default to handle cross-file UUID resolution: every file is scanned
+        // (building its UUID → model index), then models are resolved across files.
         let files = self.collect_trace_files(dir)?;
-
-        // First pass: collect cross-file UUID → model mappings
-        let mut cross_file_models: std::collections::HashMap<String, String> =
-            std::collections::HashMap::new();
-        for file in &files {
-            let Ok(file_obj) = std::fs::File::open(file) else {
-                continue;
-            };
-            let reader = std::io::BufReader::new(file_obj);
-            for line in reader.lines() {
-                let Ok(line) = line else { continue };
-                let line = line.trim();
-                if line.is_empty() || !line.starts_with('{') {
-                    continue;
-                }
-                if let Ok(record) = serde_json::from_str::<Value>(line) {
-                    if let Some(model) = crate::parsers::common::extract_model_from_record(&record)
-                    {
-                        if let Some(uuid) = record.get("uuid").and_then(|u| u.as_str()) {
-                            cross_file_models.insert(uuid.to_string(), model.to_string());
-                        }
-                    }
-                }
-            }
-        }
-
-        // Second pass: parse each file with cross-file UUID index
-        for file in files {
-            match self.parse_file_with_cross_file_index(&file, file_pattern, &cross_file_models) {
-                Ok(mut edits) => all_edits.append(&mut edits),
-                Err(e) => {
-                    eprintln!("Warning: Failed to parse {:?}: {}", file, e);
-                }
-            }
-        }
-
-        Ok(all_edits)
+        let states = vec![ClaudeParseState::default(); files.len()];
+        let (edits, _) = self.scan_files(&files, file_pattern, states, true);
+        Ok(edits)
     }
 
     fn parse_directory_with_context_verbose(
@@ -434,72 +673,99 @@ impl TraceParser for ClaudeParser {
         verbose: u8,
     ) -> Result<Vec<EditRecord>> {
         // Override default to handle cross-file UUID resolution with verbose output
-        let mut all_edits = Vec::new();
         let files = self.collect_trace_files(dir)?;
 
         if verbose >= 3 {
-            eprintln!("[*] Claude parser: Building cross-file UUID index...");
+            eprintln!(
+                "[*] Claude parser: Scanning {} files and building cross-file UUID index...",
+                files.len()
+            );
         }
 
-        // First pass: collect cross-file UUID → model mappings
-        let mut cross_file_models: std::collections::HashMap<String, String> =
-            std::collections::HashMap::new();
-        for file in &files {
-            let Ok(file_obj) = std::fs::File::open(file) else {
-                continue;
-            };
-            let reader = std::io::BufReader::new(file_obj);
-            for line in reader.lines() {
-                let Ok(line) = line else { continue };
-                let line = line.trim();
-                if line.is_empty() || !line.starts_with('{') {
-                    continue;
-                }
-                if let Ok(record) = serde_json::from_str::<Value>(line) {
-                    if let Some(model) = crate::parsers::common::extract_model_from_record(&record)
-                    {
-                        if let Some(uuid) = record.get("uuid").and_then(|u| u.as_str()) {
-                            cross_file_models.insert(uuid.to_string(), model.to_string());
-                        }
-                    }
-                }
-            }
+        let start_time = Instant::now();
+        let states = vec![ClaudeParseState::default(); files.len()];
+        let (edits, _) = self.scan_files(&files, file_pattern, states, verbose >= 1);
+
+        if verbose >= 3 {
+            eprintln!(
+                "    ✓ Parsed {} claude files in {:.2}s ({} edits)",
+                files.len(),
+                start_time.elapsed().as_secs_f64(),
+                edits.len()
+            );
         }
 
-        // Second pass: parse each file with cross-file UUID index
-        for (idx, file) in files.iter().enumerate() {
-            let start_time = Instant::now();
+        Ok(edits)
+    }
 
-            if verbose >= 3 {
-                eprintln!(
-                    "[*] Parsing claude file {}/{}: {:?}",
-                    idx + 1,
-                    files.len(),
-                    file.file_name().unwrap_or_default()
-                );
-            }
+    /// Parse only what was appended to each trace file since the cache was written
+    ///
+    /// Previously cached edits for the directory are kept and the new tails are parsed
+    /// with each file's saved model indices. Falls back to a full parse when any file
+    /// was rewritten, removed, or cached without saved state.
+    fn parse_directory_with_cache(
+        &self,
+        dir: &Path,
+        file_pattern: &str,
+        _repo_root: Option<&Path>,
+        cache: &crate::cache::CacheManager,
+    ) -> Result<(Vec<EditRecord>, Vec<crate::cache::ParseStateUpdate>)> {
+        let files = self.collect_trace_files(dir)?;
 
-            match self.parse_file_with_cross_file_index(file, file_pattern, &cross_file_models) {
-                Ok(mut edits) => {
-                    if verbose >= 3 {
-                        let elapsed = start_time.elapsed();
-                        eprintln!(
-                            "    ✓ Parsed in {:.2}s ({} edits)",
-                            elapsed.as_secs_f64(),
-                            edits.len()
-                        );
-                    }
-                    all_edits.append(&mut edits);
-                }
-                Err(e) => {
-                    if verbose >= 1 {
-                        eprintln!("Warning: Failed to parse {:?}: {}", file, e);
+        // Edits from a removed trace file can't be picked out of the cached set.
+        let current: std::collections::HashSet<&std::path::PathBuf> = files.iter().collect();
+        let removed: Vec<std::path::PathBuf> = cache
+            .parse_state_files(self.info().name)?
+            .into_iter()
+            .filter(|saved| saved.starts_with(dir) && !current.contains(saved))
+            .collect();
+        let resumable = if removed.is_empty() {
+            self.resumable_states(&files, cache)?
+        } else {
+            cache.invalidate_files(&removed)?;
+            None
+        };
+
+        let (mut edits, states) = match resumable {
+            Some(states) => {
+                // All-or-nothing caching stores the directory's edits under one file.
+                let mut cached = Vec::new();
+                for file in &files {
+                    if let Some(mut file_edits) = cache.get_cached_edits(file)? {
+                        cached.append(&mut file_edits);
                     }
                 }
+                (cached, states)
             }
-        }
+            None => (Vec::new(), vec![None; files.len()]),
+        };
 
-        Ok(all_edits)
+        let saved_offsets: Vec<Option<u64>> = states
+            .iter()
+            .map(|s| s.as_ref().map(|s| s.offset))
+            .collect();
+        let states = states.into_iter().map(Option::unwrap_or_default).collect();
+
+        // Saved offsets cover every edit in a file, so scan without `file_pattern` and
+        // filter afterwards; otherwise a narrow run would leave edits out for good.
+        let (mut new_edits, states) = self.scan_files(&files, "", states, true);
+        edits.append(&mut new_edits);
+
+        // Only files read further have new state to save.
+        let mut updates = Vec::new();
+        for ((file, state), saved) in files.iter().zip(&states).zip(saved_offsets) {
+            if saved != Some(state.offset) {
+                updates.push(crate::cache::ParseStateUpdate {
+                    trace_file: file.clone(),
+                    byte_offset: state.offset,
+                    state: serde_json::to_string(state)?,
+                });
+            }
+        }
+        if !file_pattern.is_empty() {
+            edits.retain(|e| e.file_path.contains(file_pattern));
+        }
+        Ok((edits, updates))
     }
 
     fn parse_file(&self, path: &Path, file_pattern: &str) -> Result<Vec<EditRecord>> {
@@ -173,6 +173,9 @@ impl CodexParser {
                 new_string: new_content,
                 structured_patch: None,
                 create_content: content,
+                cell_id: None,
+                agent_id: None,
+                replace_all: false,
             };
 
             edits.push(edit);
@@ -336,6 +339,9 @@ impl CodexParser {
                                 new_string: None,
                                 structured_patch: None,
                                 create_content: Some(content),
+                                cell_id: None,
+                                agent_id: None,
+                                replace_all: false,
                             };
                             edits.push(edit);
                         } else {
@@ -386,6 +392,9 @@ impl CodexParser {
                                     new_string: Some(curr_text),
                                     structured_patch: None,
                                     create_content: None,
+                                    cell_id: None,
+                                    agent_id: None,
+                                    replace_all: false,
                                 };
                                 edits.push(edit);
                             }
@@ -532,6 +541,16 @@ impl TraceParser for CodexParser {
         }
     }
 
+    fn parse_directory_with_context(
+        &self,
+        dir: &Path,
+        file_pattern: &str,
+        repo_root: Option<&Path>,
+    ) -> Result<Vec<EditRecord>> {
+        // Share one git batch process across all workers instead of one per CLI session
+        self.parse_directory_with_batch(dir, file_pattern, repo_root)
+    }
+
     fn parse_directory_with_context_verbose(
         &self,
         dir: &Path,
@@ -540,42 +559,42 @@ impl TraceParser for CodexParser {
         verbose: u8,
     ) -> Result<Vec<EditRecord>> {
         // Override default to provide per-file progress for Codex
-        let mut all_edits = Vec::new();
         let files = self.collect_trace_files(dir)?;
+        let batch_reader = repo_root.and_then(|root| BatchGitReader::new(root).ok());
+        let counter = std::sync::atomic::AtomicUsize::new(0);
 
-        for (idx, file) in files.iter().enumerate() {
+        let results = crate::parallel::map_ordered(&files, |file| {
             let start_time = Instant::now();
+            let result =
+                self.parse_file_with_batch(file, file_pattern, repo_root, batch_reader.as_ref());
 
             if verbose >= 3 {
-                eprintln!(
-                    "[*] Parsing codex file {}/{}: {:?}",
-                    idx + 1,
-                    files.len(),
-                    file.file_name().unwrap_or_default()
-                );
-            }
-
-            match self.parse_file_with_context(file, file_pattern, repo_root) {
-                Ok(mut edits) => {
-                    if verbose >= 3 {
-                        let elapsed = start_time.elapsed();
-                        eprintln!(
-                            "    ✓ Parsed in {:.2}s ({} edits)",
-                            elapsed.as_secs_f64(),
-                            edits.len()
-                        );
-                    }
-                    all_edits.append(&mut edits);
-                }
-                Err(e) => {
-                    if verbose >= 1 {
-                        eprintln!("Warning: Failed to parse {:?}: {}", file, e);
-                    }
+                let idx = counter.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                match &result {
+                    Ok(edits) => eprintln!(
+                        "[*] Parsed codex file {}/{}: {:?} in {:.2}s ({} edits)",
+                        idx + 1,
+                        files.len(),
+                        file.file_name().unwrap_or_default(),
+                        start_time.elapsed().as_secs_f64(),
+                        edits.len()
+                    ),
+                    Err(_) => eprintln!(
+                        "[*] Failed codex file {}/{}: {:?}",
+                        idx + 1,
+                        files.len(),
+                        file.file_name().unwrap_or_default()
+                    ),
                 }
             }
-        }
-
-        Ok(all_edits)
+            result
+        });
+
+        Ok(crate::parsers::merge_file_results(
+            &files,
+            results,
+            verbose >= 1,
+        ))
     }
 
     /// Override collect_trace_files to only return Codex-parseable files
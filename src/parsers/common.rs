@@ -1,5 +1,6 @@
 /// Shared parsing utilities used by multiple parsers
 use serde_json::Value;
+use std::path::Path;
 
 /// Extract model from a JSON record
 pub fn extract_model_from_record(record: &Value) -> Option<&str> {
@@ -52,6 +53,28 @@ pub fn extract_tool_use_ids_from_record(record: &Value) -> Vec<String> {
     out
 }
 
+/// The subagent ID of a Claude sidechain record: its `agentId`, or for older traces the
+/// `agent-<id>.jsonl` file name. `None` for main-conversation records.
+pub fn sidechain_agent_id(path: &Path, record: &Value) -> Option<String> {
+    if !record
+        .get("isSidechain")
+        .and_then(|s| s.as_bool())
+        .unwrap_or(false)
+    {
+        return None;
+    }
+    record
+        .get("agentId")
+        .and_then(|a| a.as_str())
+        .map(|s| s.to_string())
+        .or_else(|| {
+            path.file_stem()
+                .and_then(|s| s.to_str())
+                .and_then(|s| s.strip_prefix("agent-"))
+                .map(|s| s.to_string())
+        })
+}
+
 /// Normalize file paths to be relative to repository root
 pub fn normalize_path(abs_path: &str, repo_root: Option<&str>) -> String {
     let repo_root = if let Some(r) = repo_root {
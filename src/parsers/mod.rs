@@ -8,6 +8,26 @@ pub mod claude;
 pub mod codex;
 pub mod common;
 
+/// Concatenate per-file parse results in file order, warning about files that failed.
+pub(crate) fn merge_file_results(
+    files: &[std::path::PathBuf],
+    results: Vec<Result<Vec<EditRecord>>>,
+    warn: bool,
+) -> Vec<EditRecord> {
+    let mut all_edits = Vec::new();
+    for (file, result) in files.iter().zip(results) {
+        match result {
+            Ok(mut edits) => all_edits.append(&mut edits),
+            Err(e) => {
+                if warn {
+                    eprintln!("Warning: Failed to parse {:?}: {}", file, e);
+                }
+            }
+        }
+    }
+    all_edits
+}
+
 /// Metadata about a trace parser
 #[derive(Debug, Clone)]
 pub struct ParserInfo {
@@ -52,7 +72,8 @@ pub trait TraceParser: Send + Sync {
 
     /// Parse all trace files in a directory tree with verbose progress reporting
     ///
-    /// Default implementation uses collect_trace_files() + parse_file_with_context().
+    /// Default implementation uses collect_trace_files() + parse_file_with_context(),
+    /// parsing files on the shared worker pool and merging results in file order.
     /// Parsers can override for custom directory handling (e.g., cross-file UUID resolution).
     /// Verbose levels: 0 = silent, 1 = basic, 2+ = detailed per-file progress
     fn parse_directory_with_context_verbose(
@@ -62,11 +83,12 @@ pub trait TraceParser: Send + Sync {
         repo_root: Option<&Path>,
         verbose: u8,
     ) -> Result<Vec<EditRecord>> {
-        let mut all_edits = Vec::new();
         let files = self.collect_trace_files(dir)?;
+        let counter = std::sync::atomic::AtomicUsize::new(0);
 
-        for (idx, file) in files.iter().enumerate() {
+        let results = crate::parallel::map_ordered(&files, |file| {
             if verbose >= 3 {
+                let idx = counter.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
                 eprintln!(
                     "[*] Parsing {} file {}/{}: {:?}",
                     self.info().name,
@@ -75,23 +97,16 @@ pub trait TraceParser: Send + Sync {
                     file.file_name().unwrap_or_default()
                 );
             }
+            self.parse_file_with_context(file, file_pattern, repo_root)
+        });
 
-            match self.parse_file_with_context(file, file_pattern, repo_root) {
-                Ok(mut edits) => all_edits.append(&mut edits),
-                Err(e) => {
-                    if verbose >= 1 {
-                        eprintln!("Warning: Failed to parse {:?}: {}", file, e);
-                    }
-                }
-            }
-        }
-
-        Ok(all_edits)
+        Ok(merge_file_results(&files, results, verbose >= 1))
     }
 
     /// Parse all trace files in a directory tree
     ///
-    /// Default implementation uses collect_trace_files() + parse_file_with_context().
+    /// Default implementation uses collect_trace_files() + parse_file_with_context(),
+    /// parsing files on the shared worker pool and merging results in file order.
     /// Parsers can override for custom directory handling (e.g., cross-file UUID resolution).
     fn parse_directory_with_context(
         &self,
@@ -99,19 +114,31 @@ pub trait TraceParser: Send + Sync {
         file_pattern: &str,
         repo_root: Option<&Path>,
     ) -> Result<Vec<EditRecord>> {
-        let mut all_edits = Vec::new();
         let files = self.collect_trace_files(dir)?;
+        let results = crate::parallel::map_ordered(&files, |file| {
+            self.parse_file_with_context(file, file_pattern, repo_root)
+        });
+        Ok(merge_file_results(&files, results, true))
+    }
 
-        for file in files {
-            match self.parse_file_with_context(&file, file_pattern, repo_root) {
-                Ok(mut edits) => all_edits.append(&mut edits),
-                Err(e) => {
-                    eprintln!("Warning: Failed to parse {:?}: {}", file, e);
-                }
-            }
-        }
-
-        Ok(all_edits)
+    /// Parse a directory whose cached edits are stale, returning its complete edit list
+    /// and the per-file state to save with it
+    ///
+    /// Parsers that keep per-file state in the cache (e.g. how far an append-only trace
+    /// was read) override this to parse only what changed since the last run. The state
+    /// is not saved here: the caller stores it together with the edits (see
+    /// [`crate::cache::CacheManager::store_directory_edits`]), so it can't get ahead of them.
+    /// Default implementation ignores the cache and calls parse_directory_with_context().
+    fn parse_directory_with_cache(
+        &self,
+        dir: &Path,
+        file_pattern: &str,
+        repo_root: Option<&Path>,
+        cache: &crate::cache::CacheManager,
+    ) -> Result<(Vec<EditRecord>, Vec<crate::cache::ParseStateUpdate>)> {
+        let _ = cache; // Default: no incremental state
+        let edits = self.parse_directory_with_context(dir, file_pattern, repo_root)?;
+        Ok((edits, Vec::new()))
     }
 
     /// Parse all trace files in a directory tree
@@ -139,28 +166,21 @@ pub trait TraceParser: Send + Sync {
     /// Parse all trace files in a directory tree with batch git reader
     ///
     /// Default implementation creates a batch reader if repo_root is provided,
-    /// then calls parse_file_with_batch for each file.
+    /// then calls parse_file_with_batch for each file on the shared worker pool.
     fn parse_directory_with_batch(
         &self,
         dir: &Path,
         file_pattern: &str,
         repo_root: Option<&Path>,
     ) -> Result<Vec<EditRecord>> {
-        // Create batch reader if repo_root available
+        // Create batch reader if repo_root available (shared by all workers)
         let batch_reader = repo_root.and_then(|root| BatchGitReader::new(root).ok());
 
         let files = self.collect_trace_files(dir)?;
-        let mut all_edits = Vec::new();
-
-        for file in files {
-            match self.parse_file_with_batch(&file, file_pattern, repo_root, batch_reader.as_ref())
-            {
-                Ok(mut edits) => all_edits.append(&mut edits),
-                Err(e) => eprintln!("Warning: Failed to parse {:?}: {}", file, e),
-            }
-        }
-
-        Ok(all_edits)
+        let results = crate::parallel::map_ordered(&files, |file| {
+            self.parse_file_with_batch(file, file_pattern, repo_root, batch_reader.as_ref())
+        });
+        Ok(merge_file_results(&files, results, true))
     }
 
     /// Collect trace files from a directory tree
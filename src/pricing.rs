@@ -0,0 +1,425 @@
+//! Token pricing: estimated cost of agent sessions in US dollars.
+//!
+//! A [`PriceTable`] maps model names to per-token rates. The built-in table covers the
+//! Claude and OpenAI models agents commonly use; the `pricing` section of
+//! `.ai-blame.yaml` overrides or extends it:
+//!
+//! ```yaml
+//! pricing:
+//!   - model: claude-sonnet-4      # prefix of the model name
+//!     input: 3.0                  # USD per million tokens
+//!     output: 15.0
+//!     cache_read: 0.3             # default: the input rate
+//!     cache_creation: 3.75        # default: the input rate
+//! ```
+
+use crate::models::OutputConfig;
+use crate::transcript::{ContentBlock, FileOpType, TokenUsage, Transcript, TranscriptMessage};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::path::Path;
+
+/// Rates for one model, in US dollars per million tokens.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ModelPrice {
+    /// Model name prefix the rates apply to (case-insensitive); the longest match wins.
+    pub model: String,
+    /// Uncached input tokens.
+    pub input: f64,
+    /// Output tokens, including reasoning.
+    pub output: f64,
+    /// Input tokens read from the prompt cache (default: the input rate).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_read: Option<f64>,
+    /// Input tokens written to the prompt cache (default: the input rate).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_creation: Option<f64>,
+}
+
+impl ModelPrice {
+    fn new(model: &str, input: f64, output: f64, cache_read: f64, cache_creation: f64) -> Self {
+        Self {
+            model: model.to_string(),
+            input,
+            output,
+            cache_read: Some(cache_read),
+            cache_creation: Some(cache_creation),
+        }
+    }
+
+    /// Cost of `usage` at these rates.
+    pub fn cost(&self, usage: &TokenUsage) -> f64 {
+        let tokens = |t: Option<u64>| t.unwrap_or(0) as f64;
+        (tokens(usage.input_tokens) * self.input
+            + tokens(usage.output_tokens) * self.output
+            + tokens(usage.cache_read_tokens) * self.cache_read.unwrap_or(self.input)
+            + tokens(usage.cache_creation_tokens) * self.cache_creation.unwrap_or(self.input))
+            / 1_000_000.0
+    }
+}
+
+/// Model prices, looked up by model name.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PriceTable {
+    prices: Vec<ModelPrice>,
+}
+
+impl Default for PriceTable {
+    fn default() -> Self {
+        Self::builtin()
+    }
+}
+
+impl PriceTable {
+    /// List prices of the models agents commonly use (standard tier, short context,
+    /// five-minute cache writes).
+    pub fn builtin() -> Self {
+        let p = ModelPrice::new;
+        Self {
+            prices: vec![
+                // Anthropic
+                p("claude-opus-4-5", 5.0, 25.0, 0.5, 6.25),
+                p("claude-opus-4", 15.0, 75.0, 1.5, 18.75),
+                p("claude-sonnet-4", 3.0, 15.0, 0.3, 3.75),
+                p("claude-haiku-4", 1.0, 5.0, 0.1, 1.25),
+                p("claude-3-opus", 15.0, 75.0, 1.5, 18.75),
+                p("claude-3-7-sonnet", 3.0, 15.0, 0.3, 3.75),
+                p("claude-3-5-sonnet", 3.0, 15.0, 0.3, 3.75),
+                p("claude-3-5-haiku", 0.8, 4.0, 0.08, 1.0),
+                p("claude-3-haiku", 0.25, 1.25, 0.03, 0.3),
+                // OpenAI (cache writes are not charged separately)
+                p("gpt-5.2", 1.75, 14.0, 0.175, 1.75),
+                p("gpt-5", 1.25, 10.0, 0.125, 1.25),
+                p("gpt-5-mini", 0.25, 2.0, 0.025, 0.25),
+                p("gpt-5-nano", 0.05, 0.4, 0.005, 0.05),
+                p("gpt-4.1", 2.0, 8.0, 0.5, 2.0),
+                p("gpt-4.1-mini", 0.4, 1.6, 0.1, 0.4),
+                p("gpt-4o", 2.5, 10.0, 1.25, 2.5),
+                p("o3", 2.0, 8.0, 0.5, 2.0),
+                p("o4-mini", 1.1, 4.4, 0.275, 1.1),
+                p("codex-mini", 1.5, 6.0, 0.375, 1.5),
+            ],
+        }
+    }
+
+    /// This table with `overrides` replacing the entries for the same model, and added
+    /// where there are none.
+    pub fn with_overrides(mut self, overrides: &[ModelPrice]) -> Self {
+        for price in overrides {
+            match self
+                .prices
+                .iter_mut()
+                .find(|p| p.model.eq_ignore_ascii_case(&price.model))
+            {
+                Some(existing) => *existing = price.clone(),
+                None => self.prices.push(price.clone()),
+            }
+        }
+        self
+    }
+
+    /// The built-in table with the config's `pricing` section applied.
+    pub fn from_config(config: &OutputConfig) -> Self {
+        Self::builtin().with_overrides(&config.pricing)
+    }
+
+    /// The built-in prices plus the `pricing` section of the `.ai-blame.yaml` found from
+    /// `start_dir` (default: the current directory).
+    pub fn load(start_dir: Option<&Path>) -> Result<Self> {
+        match crate::config::find_config(start_dir) {
+            Some(path) => Ok(Self::from_config(&crate::config::load_config(&path)?)),
+            None => Ok(Self::builtin()),
+        }
+    }
+
+    /// All entries, built-in and overridden.
+    pub fn prices(&self) -> &[ModelPrice] {
+        &self.prices
+    }
+
+    /// The price of `model`: the entry whose name is the longest prefix of it.
+    ///
+    /// Provider prefixes such as `anthropic/` or Bedrock's `us.anthropic.` are ignored.
+    pub fn price_for(&self, model: &str) -> Option<&ModelPrice> {
+        let model = model.to_lowercase();
+        let model = model.rsplit('/').next().unwrap_or(&model);
+        let model = model
+            .rsplit_once("anthropic.")
+            .map_or(model, |(_, name)| name);
+        self.prices
+            .iter()
+            .filter(|p| model.starts_with(&p.model.to_lowercase()))
+            .max_by_key(|p| p.model.len())
+    }
+
+    /// Cost of `usage` by `model`, or `None` if the model has no price.
+    pub fn usage_cost(&self, model: Option<&str>, usage: &TokenUsage) -> Option<f64> {
+        model
+            .and_then(|m| self.price_for(m))
+            .map(|price| price.cost(usage))
+    }
+
+    /// Estimated cost of a transcript: the sum over its messages with token usage and a
+    /// priced model, or `None` if there are none.
+    pub fn transcript_cost(&self, transcript: &Transcript) -> Option<f64> {
+        billed_messages(transcript)
+            .into_iter()
+            .filter_map(|m| self.usage_cost(m.model, m.usage))
+            .reduce(|a, b| a + b)
+    }
+}
+
+/// A message that reported token usage, with the model that produced it.
+pub struct BilledMessage<'a> {
+    /// Position of the message in the transcript.
+    pub index: usize,
+    pub message: &'a TranscriptMessage,
+    /// The message's model, or the latest model named before it in the transcript.
+    pub model: Option<&'a str>,
+    pub usage: &'a TokenUsage,
+}
+
+/// The messages of `transcript` that carry token usage.
+pub fn billed_messages(transcript: &Transcript) -> Vec<BilledMessage<'_>> {
+    let mut model = None;
+    let mut billed = Vec::new();
+    for (index, message) in transcript.messages.iter().enumerate() {
+        if let Some(m) = message.model.as_deref() {
+            model = Some(m);
+        }
+        if let Some(usage) = &message.usage {
+            billed.push(BilledMessage {
+                index,
+                message,
+                model,
+                usage,
+            });
+        }
+    }
+    billed
+}
+
+/// How a session's spend is divided between the files it changed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Attribution {
+    /// In proportion to the characters each file's edits changed.
+    #[default]
+    ChangeSize,
+    /// Each response's spend goes to the next edit: the work that led to it. Spend after
+    /// the last edit goes to that edit.
+    Messages,
+}
+
+/// How [`cost_report`] groups spend.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CostGroup {
+    Session,
+    /// UTC day of each message.
+    Day,
+    Model,
+    Agent,
+    /// Files the session created, edited or deleted.
+    File(Attribution),
+}
+
+/// Spend of one group in a [`cost_report`].
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct CostRow {
+    pub key: String,
+    /// Sessions contributing to the group (a session's subagent transcripts share its ID).
+    pub sessions: usize,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub cache_read_tokens: u64,
+    pub cache_creation_tokens: u64,
+    /// Estimated cost in US dollars of the tokens with a priced model.
+    pub cost: f64,
+    /// Tokens whose model has no price, and so are not part of `cost`.
+    pub unpriced_tokens: u64,
+    /// Models without a price.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub unpriced_models: Vec<String>,
+}
+
+/// Label of rows for messages whose model is unknown, or sessions that touched no files.
+pub const NO_KEY: &str = "(none)";
+
+/// Group the token spend of `transcripts` by `group`: days in order, other groups most
+/// expensive first.
+///
+/// With [`CostGroup::File`] each session's spend is divided between the files it changed
+/// (see [`Attribution`]), so the rows add up to the total spend.
+pub fn cost_report(
+    transcripts: &[Transcript],
+    prices: &PriceTable,
+    group: CostGroup,
+) -> Vec<CostRow> {
+    #[derive(Default)]
+    struct Acc<'a> {
+        row: CostRow,
+        usage: [f64; 4],
+        sessions: HashSet<&'a str>,
+        unpriced: std::collections::BTreeSet<String>,
+    }
+
+    let mut groups: BTreeMap<String, Acc> = BTreeMap::new();
+    for transcript in transcripts {
+        let billed = billed_messages(transcript);
+        let mut file_shares = match group {
+            CostGroup::File(attribution) => file_shares(transcript, &billed, attribution),
+            _ => Vec::new(),
+        }
+        .into_iter();
+        for billed in &billed {
+            let shares: Vec<(String, f64)> = match group {
+                CostGroup::Session => vec![(transcript.meta.session_id.clone(), 1.0)],
+                CostGroup::Day => vec![(day(billed.message.timestamp), 1.0)],
+                CostGroup::Model => vec![(billed.model.unwrap_or(NO_KEY).to_string(), 1.0)],
+                CostGroup::Agent => vec![(transcript.meta.agent_tool.clone(), 1.0)],
+                CostGroup::File(_) => match file_shares.next() {
+                    Some(shares) if !shares.is_empty() => shares,
+                    _ => vec![(NO_KEY.to_string(), 1.0)],
+                },
+            };
+            let cost = prices.usage_cost(billed.model, billed.usage);
+            for (key, share) in shares {
+                let acc = groups.entry(key).or_default();
+                acc.sessions.insert(&transcript.meta.session_id);
+                let counts = [
+                    billed.usage.input_tokens,
+                    billed.usage.output_tokens,
+                    billed.usage.cache_read_tokens,
+                    billed.usage.cache_creation_tokens,
+                ];
+                for (total, count) in acc.usage.iter_mut().zip(counts) {
+                    *total += count.unwrap_or(0) as f64 * share;
+                }
+                match cost {
+                    Some(cost) => acc.row.cost += cost * share,
+                    None if !billed.usage.is_empty() => {
+                        acc.row.unpriced_tokens +=
+                            (billed.usage.total() as f64 * share).round() as u64;
+                        acc.unpriced
+                            .insert(billed.model.unwrap_or(NO_KEY).to_string());
+                    }
+                    None => {}
+                }
+            }
+        }
+    }
+
+    let mut rows: Vec<CostRow> = groups
+        .into_iter()
+        .map(|(key, acc)| {
+            let [input, output, cache_read, cache_creation] = acc.usage.map(|t| t.round() as u64);
+            CostRow {
+                key,
+                sessions: acc.sessions.len(),
+                input_tokens: input,
+                output_tokens: output,
+                cache_read_tokens: cache_read,
+                cache_creation_tokens: cache_creation,
+                unpriced_models: acc.unpriced.into_iter().collect(),
+                ..acc.row
+            }
+        })
+        .collect();
+    if group != CostGroup::Day {
+        rows.sort_by(|a, b| b.cost.total_cmp(&a.cost).then_with(|| a.key.cmp(&b.key)));
+    }
+    rows
+}
+
+fn day(timestamp: DateTime<Utc>) -> String {
+    timestamp.format("%Y-%m-%d").to_string()
+}
+
+/// A file change found in a transcript.
+struct FileEdit {
+    /// Position of the message with the change.
+    message: usize,
+    /// Path relative to the current directory where possible.
+    path: String,
+    /// Characters changed, at least 1.
+    size: usize,
+}
+
+/// Creates, edits and deletes in `transcript`, in order.
+fn file_edits(transcript: &Transcript) -> Vec<FileEdit> {
+    let mut edits = Vec::new();
+    for (index, message) in transcript.messages.iter().enumerate() {
+        for block in &message.content {
+            let ContentBlock::FileOperation {
+                operation,
+                file_path,
+                content,
+                old_content,
+            } = block
+            else {
+                continue;
+            };
+            let len = |s: &Option<String>| s.as_ref().map_or(0, |s| s.len());
+            let size = match operation {
+                FileOpType::Read => continue,
+                FileOpType::Create => len(content),
+                FileOpType::Delete => len(old_content),
+                // As for edit records: the length difference plus the longer side.
+                FileOpType::Edit => {
+                    len(content).abs_diff(len(old_content)) + len(content).max(len(old_content))
+                }
+            };
+            edits.push(FileEdit {
+                message: index,
+                path: crate::extractor::normalize_path(file_path, None),
+                size: size.max(1),
+            });
+        }
+    }
+    edits
+}
+
+/// For each of `billed`, the files its spend goes to and their shares (summing to 1);
+/// empty lists when the transcript changed no files.
+fn file_shares(
+    transcript: &Transcript,
+    billed: &[BilledMessage],
+    attribution: Attribution,
+) -> Vec<Vec<(String, f64)>> {
+    let edits = file_edits(transcript);
+    match attribution {
+        Attribution::ChangeSize => {
+            let shares = shares_by_size(&edits);
+            vec![shares; billed.len()]
+        }
+        Attribution::Messages => billed
+            .iter()
+            .map(|b| {
+                // The edits of the first message with any, at or after this one.
+                let next = edits
+                    .iter()
+                    .find(|e| e.message >= b.index)
+                    .or(edits.last())
+                    .map(|e| e.message);
+                let leading: Vec<&FileEdit> =
+                    edits.iter().filter(|e| Some(e.message) == next).collect();
+                shares_by_size(&leading)
+            })
+            .collect(),
+    }
+}
+
+/// Each file's share of the total change size of `edits`.
+fn shares_by_size<E: std::borrow::Borrow<FileEdit>>(edits: &[E]) -> Vec<(String, f64)> {
+    let mut sizes: BTreeMap<&str, usize> = BTreeMap::new();
+    for edit in edits {
+        let edit = edit.borrow();
+        *sizes.entry(&edit.path).or_default() += edit.size;
+    }
+    let total: usize = sizes.values().sum();
+    sizes
+        .into_iter()
+        .map(|(path, size)| (path.to_string(), size as f64 / total as f64))
+        .collect()
+}
@@ -0,0 +1,360 @@
+//! Redaction of secrets and personal data in transcripts and edit payloads.
+//!
+//! A [`Redactor`] replaces every match of its detectors with `[REDACTED:<name>]`. The
+//! built-in detectors find cloud credentials, API tokens, private keys and email
+//! addresses; the `redaction` section of `.ai-blame.yaml` turns detectors off or adds
+//! patterns:
+//!
+//! ```yaml
+//! redaction:
+//!   disable: [email]
+//!   patterns:
+//!     - name: customer_id
+//!       regex: 'CUST-[0-9]{6}'
+//!     - name: db_password          # only the `secret` group is replaced
+//!       regex: 'postgres://[^:]+:(?P<secret>[^@]+)@'
+//! ```
+
+use crate::models::{EditRecord, OutputConfig};
+use crate::transcript::{ContentBlock, SearchResult, Transcript, TranscriptMeta};
+use anyhow::{bail, Context, Result};
+use regex::{Captures, Regex};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::cell::Cell;
+use std::path::Path;
+
+/// The `redaction` section of `.ai-blame.yaml`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RedactionConfig {
+    /// Names of built-in detectors to turn off.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub disable: Vec<String>,
+    /// Additional patterns; one named like a built-in detector replaces it.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub patterns: Vec<RedactionPattern>,
+}
+
+impl RedactionConfig {
+    pub fn is_empty(&self) -> bool {
+        self.disable.is_empty() && self.patterns.is_empty()
+    }
+}
+
+/// A named regular expression whose matches are redacted.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RedactionPattern {
+    /// Name shown in the `[REDACTED:<name>]` marker.
+    pub name: String,
+    /// Pattern to match; if it has a group named `secret`, only that group is replaced.
+    pub regex: String,
+}
+
+/// Built-in detectors, as (name, pattern).
+const BUILTIN_DETECTORS: &[(&str, &str)] = &[
+    (
+        "private_key",
+        r"-----BEGIN [A-Z0-9 ]*PRIVATE KEY( BLOCK)?-----(?s:.*?)(?:-----END [A-Z0-9 ]*PRIVATE KEY( BLOCK)?-----|\z)",
+    ),
+    ("aws_access_key", r"\b(?:AKIA|ASIA|ABIA|ACCA)[0-9A-Z]{16}\b"),
+    (
+        "aws_secret_key",
+        r#"(?i)aws_?secret_?(?:access_?)?key["']?\s*[:=]\s*["']?(?P<secret>[A-Za-z0-9/+=]{40})"#,
+    ),
+    (
+        "github_token",
+        r"\b(?:gh[pousr]_[A-Za-z0-9]{36,}|github_pat_[A-Za-z0-9_]{22,})",
+    ),
+    ("api_key", r"\bsk-(?:ant-|proj-)?[A-Za-z0-9_-]{20,}"),
+    ("slack_token", r"\bxox[abposr]-[A-Za-z0-9-]{10,}"),
+    (
+        "jwt",
+        r"\beyJ[A-Za-z0-9_-]+\.eyJ[A-Za-z0-9_-]+\.[A-Za-z0-9_-]*",
+    ),
+    (
+        "env_secret",
+        r#"\b[A-Z0-9_]*(?:SECRET|TOKEN|PASSWORD|PASSWD|API_KEY|APIKEY)[A-Z0-9_]*\s*[=:]\s*["']?(?P<secret>[^\s"'\[][^\s"']{7,})"#,
+    ),
+    (
+        "email",
+        r"\b[A-Za-z0-9._%+-]+@[A-Za-z0-9-]+(?:\.[A-Za-z0-9-]+)*\.[A-Za-z]{2,}\b",
+    ),
+];
+
+#[derive(Debug, Clone)]
+struct Detector {
+    name: String,
+    regex: Regex,
+    marker: String,
+}
+
+impl Detector {
+    fn new(name: &str, pattern: &str) -> Result<Self> {
+        let regex =
+            Regex::new(pattern).with_context(|| format!("Invalid redaction pattern '{}'", name))?;
+        Ok(Self {
+            name: name.to_string(),
+            regex,
+            marker: format!("[REDACTED:{}]", name),
+        })
+    }
+
+    /// `text` with this detector's matches replaced, counting them in `count`.
+    fn replace<'t>(&self, text: &'t str, count: &Cell<usize>) -> Cow<'t, str> {
+        self.regex.replace_all(text, |caps: &Captures| {
+            count.set(count.get() + 1);
+            let whole = caps.get(0).expect("group 0 always matches");
+            match caps.name("secret") {
+                Some(secret) => format!(
+                    "{}{}{}",
+                    &text[whole.start()..secret.start()],
+                    self.marker,
+                    &text[secret.end()..whole.end()]
+                ),
+                None => self.marker.clone(),
+            }
+        })
+    }
+}
+
+/// Replaces secrets and personal data with `[REDACTED:<name>]` markers.
+#[derive(Debug, Clone)]
+pub struct Redactor {
+    detectors: Vec<Detector>,
+}
+
+impl Default for Redactor {
+    fn default() -> Self {
+        Self::builtin()
+    }
+}
+
+impl Redactor {
+    /// Names of the built-in detectors.
+    pub fn builtin_names() -> impl Iterator<Item = &'static str> {
+        BUILTIN_DETECTORS.iter().map(|(name, _)| *name)
+    }
+
+    /// All built-in detectors.
+    pub fn builtin() -> Self {
+        Self::from_redaction_config(&RedactionConfig::default())
+            .expect("built-in redaction patterns are valid")
+    }
+
+    /// The built-in detectors adjusted by a `redaction` section.
+    pub fn from_redaction_config(config: &RedactionConfig) -> Result<Self> {
+        for name in &config.disable {
+            if !Self::builtin_names().any(|b| b == name) {
+                bail!(
+                    "Unknown redaction detector '{}' in disable (built-in: {})",
+                    name,
+                    Self::builtin_names().collect::<Vec<_>>().join(", ")
+                );
+            }
+        }
+        let mut detectors = BUILTIN_DETECTORS
+            .iter()
+            .filter(|(name, _)| !config.disable.iter().any(|d| d == name))
+            .map(|(name, pattern)| Detector::new(name, pattern))
+            .collect::<Result<Vec<_>>>()?;
+        for pattern in &config.patterns {
+            if pattern.name.is_empty() {
+                bail!("Redaction pattern '{}' has no name", pattern.regex);
+            }
+            let detector = Detector::new(&pattern.name, &pattern.regex)?;
+            match detectors.iter_mut().find(|d| d.name == pattern.name) {
+                Some(existing) => *existing = detector,
+                None => detectors.push(detector),
+            }
+        }
+        Ok(Self { detectors })
+    }
+
+    /// The redactor for a loaded config file.
+    pub fn from_config(config: &OutputConfig) -> Result<Self> {
+        Self::from_redaction_config(&config.redaction)
+    }
+
+    /// The redactor for the `.ai-blame.yaml` found from `start_dir` (default: the current
+    /// directory), or the built-in one if there is none. A config that can't be read is
+    /// an error rather than a reason to show secrets.
+    pub fn load(start_dir: Option<&Path>) -> Result<Self> {
+        match crate::config::find_config(start_dir) {
+            Some(path) => Self::from_config(&crate::config::load_config(&path)?)
+                .with_context(|| format!("Invalid redaction section in {:?}", path)),
+            None => Ok(Self::builtin()),
+        }
+    }
+
+    /// Names of the active detectors, in the order they are applied.
+    pub fn detector_names(&self) -> Vec<&str> {
+        self.detectors.iter().map(|d| d.name.as_str()).collect()
+    }
+
+    /// `text` with every match redacted.
+    pub fn redact_str<'t>(&self, text: &'t str) -> Cow<'t, str> {
+        self.redact_counted(text, &Cell::new(0))
+    }
+
+    fn redact_counted<'t>(&self, text: &'t str, count: &Cell<usize>) -> Cow<'t, str> {
+        let mut out = Cow::Borrowed(text);
+        for detector in &self.detectors {
+            let replaced = match detector.replace(&out, count) {
+                Cow::Owned(replaced) => replaced,
+                Cow::Borrowed(_) => continue,
+            };
+            out = Cow::Owned(replaced);
+        }
+        out
+    }
+
+    /// Redact a string in place. Returns the number of redactions.
+    pub fn redact_text(&self, text: &mut String) -> usize {
+        let count = Cell::new(0);
+        self.redact_string(text, &count);
+        count.get()
+    }
+
+    fn redact_string(&self, text: &mut String, count: &Cell<usize>) {
+        let replaced = match self.redact_counted(text, count) {
+            Cow::Owned(replaced) => replaced,
+            Cow::Borrowed(_) => return,
+        };
+        *text = replaced;
+    }
+
+    fn redact_option(&self, text: &mut Option<String>, count: &Cell<usize>) {
+        if let Some(text) = text {
+            self.redact_string(text, count);
+        }
+    }
+
+    fn redact_json(&self, value: &mut serde_json::Value, count: &Cell<usize>) {
+        match value {
+            serde_json::Value::String(s) => self.redact_string(s, count),
+            serde_json::Value::Array(items) => {
+                for item in items {
+                    self.redact_json(item, count);
+                }
+            }
+            serde_json::Value::Object(map) => {
+                for item in map.values_mut() {
+                    self.redact_json(item, count);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Redact the strings in a JSON value (keys are left alone). Returns the number of
+    /// redactions.
+    pub fn redact_value(&self, value: &mut serde_json::Value) -> usize {
+        let count = Cell::new(0);
+        self.redact_json(value, &count);
+        count.get()
+    }
+
+    fn redact_block_counted(&self, block: &mut ContentBlock, count: &Cell<usize>) {
+        match block {
+            ContentBlock::Text { text } => self.redact_string(text, count),
+            ContentBlock::Thinking { thinking } => self.redact_string(thinking, count),
+            ContentBlock::ToolUse { input, .. } => self.redact_json(input, count),
+            ContentBlock::ToolResult { content, .. } => self.redact_string(content, count),
+            ContentBlock::Code { code, .. } => self.redact_string(code, count),
+            ContentBlock::FileOperation {
+                file_path,
+                content,
+                old_content,
+                ..
+            } => {
+                self.redact_string(file_path, count);
+                self.redact_option(content, count);
+                self.redact_option(old_content, count);
+            }
+            ContentBlock::Command {
+                command, output, ..
+            } => {
+                self.redact_string(command, count);
+                self.redact_option(output, count);
+            }
+        }
+    }
+
+    /// Redact a content block in place. Returns the number of redactions.
+    pub fn redact_block(&self, block: &mut ContentBlock) -> usize {
+        let count = Cell::new(0);
+        self.redact_block_counted(block, &count);
+        count.get()
+    }
+
+    /// Redact every message of a transcript and its subagents, and its working directory,
+    /// branch and slug, in place. Returns the number of redactions.
+    pub fn redact_transcript(&self, transcript: &mut Transcript) -> usize {
+        let count = Cell::new(0);
+        self.redact_transcript_counted(transcript, &count);
+        count.get()
+    }
+
+    fn redact_transcript_counted(&self, transcript: &mut Transcript, count: &Cell<usize>) {
+        self.redact_meta_counted(&mut transcript.meta, count);
+        for message in &mut transcript.messages {
+            for block in &mut message.content {
+                self.redact_block_counted(block, count);
+            }
+        }
+        for subagent in &mut transcript.subagents {
+            self.redact_transcript_counted(subagent, count);
+        }
+    }
+
+    /// Redact the working directory, branch and slug of a transcript (but not its
+    /// messages) in place. Returns the number of redactions.
+    pub fn redact_transcript_meta(&self, meta: &mut TranscriptMeta) -> usize {
+        let count = Cell::new(0);
+        self.redact_meta_counted(meta, &count);
+        count.get()
+    }
+
+    fn redact_meta_counted(&self, meta: &mut TranscriptMeta, count: &Cell<usize>) {
+        self.redact_option(&mut meta.cwd, count);
+        self.redact_option(&mut meta.git_branch, count);
+        self.redact_option(&mut meta.slug, count);
+    }
+
+    /// Redact the snippets of search results, and the slug and last message preview of
+    /// each matching transcript, in place. Returns the number of redactions.
+    pub fn redact_search_result(&self, result: &mut SearchResult) -> usize {
+        let count = Cell::new(0);
+        for found in &mut result.matching_transcripts {
+            self.redact_option(&mut found.transcript.slug, &count);
+            self.redact_option(&mut found.transcript.last_message_preview, &count);
+            for snippet in &mut found.matches {
+                self.redact_string(&mut snippet.snippet, &count);
+            }
+        }
+        count.get()
+    }
+
+    /// Redact the payloads of an edit (old and new text, patch and created content) in
+    /// place. Returns the number of redactions.
+    pub fn redact_edit(&self, edit: &mut EditRecord) -> usize {
+        let count = Cell::new(0);
+        self.redact_option(&mut edit.old_string, &count);
+        self.redact_option(&mut edit.new_string, &count);
+        self.redact_option(&mut edit.create_content, &count);
+        if let Some(patch) = &mut edit.structured_patch {
+            // Redact inside the JSON so a match can't swallow its quoting.
+            match serde_json::from_str::<serde_json::Value>(patch) {
+                Ok(mut value) => {
+                    let before = count.get();
+                    self.redact_json(&mut value, &count);
+                    if count.get() > before {
+                        *patch = value.to_string();
+                    }
+                }
+                Err(_) => self.redact_string(patch, &count),
+            }
+        }
+        count.get()
+    }
+}
@@ -0,0 +1,250 @@
+//! Reconstruct a file as it was at a point in the agent's history.
+//!
+//! When a creation with content is on record, edits are replayed forward from it
+//! (`old_string` → `new_string`). Otherwise the current file is rewound by undoing later
+//! edits in reverse. Every step is checked, and steps that don't fit the content (usually
+//! because of human edits in between) are reported as divergences, as are notebook cell
+//! edits, which don't describe text in the file.
+
+use crate::models::EditRecord;
+use anyhow::{bail, Result};
+use chrono::{DateTime, Utc};
+
+/// A point in history to reconstruct the file at.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReplayPoint {
+    /// After every edit at or before this time.
+    Time(DateTime<Utc>),
+    /// After the last edit of the session whose id starts with this prefix.
+    Session(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReplayStrategy {
+    /// Replayed forward from a recorded creation.
+    Forward,
+    /// Rewound from the current file by undoing later edits.
+    Backward,
+}
+
+/// An edit that could not be applied (or undone) cleanly.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Divergence {
+    pub timestamp: DateTime<Utc>,
+    pub session_id: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct Replay {
+    /// Reconstructed content, or `None` if the file did not exist yet at that point.
+    pub content: Option<String>,
+    pub strategy: ReplayStrategy,
+    /// The resolved cutoff: edits at or before it are included.
+    pub cutoff: DateTime<Utc>,
+    /// Edits applied (forward) or undone (backward) cleanly.
+    pub applied: usize,
+    pub divergences: Vec<Divergence>,
+    /// For forward replays: whether replaying every edit reproduces the current file.
+    pub matches_working_tree: Option<bool>,
+}
+
+/// Resolve a replay point to a timestamp cutoff.
+pub fn resolve_cutoff(edits: &[EditRecord], point: &ReplayPoint) -> Result<DateTime<Utc>> {
+    match point {
+        ReplayPoint::Time(t) => Ok(*t),
+        ReplayPoint::Session(prefix) => match edits
+            .iter()
+            .filter(|e| e.session_id.starts_with(prefix.as_str()))
+            .map(|e| e.timestamp)
+            .max()
+        {
+            Some(t) => Ok(t),
+            None => bail!("No edits to this file from a session matching {:?}", prefix),
+        },
+    }
+}
+
+fn divergence(edit: &EditRecord, reason: impl Into<String>) -> Divergence {
+    Divergence {
+        timestamp: edit.timestamp,
+        session_id: edit.session_id.clone(),
+        reason: reason.into(),
+    }
+}
+
+/// Why a step could not be applied.
+enum StepError {
+    EmptySearch,
+    NotFound,
+    /// Found this many times, with nothing on record saying which one was meant.
+    Ambiguous(usize),
+}
+
+/// Apply one edit to `content`, replacing `from` with `to`.
+///
+/// The agent's edit tool rejects ambiguous matches unless it was asked to replace every
+/// occurrence, so several matches of a single replacement mean the content has diverged.
+fn replace_step(
+    content: &str,
+    from: &str,
+    to: &str,
+    replace_all: bool,
+) -> Result<String, StepError> {
+    if from.is_empty() {
+        return Err(StepError::EmptySearch);
+    }
+    match content.matches(from).count() {
+        0 => Err(StepError::NotFound),
+        _ if replace_all => Ok(content.replace(from, to)),
+        1 => Ok(content.replacen(from, to, 1)),
+        n => Err(StepError::Ambiguous(n)),
+    }
+}
+
+/// Apply `edits` (oldest first) to `content`, recording steps that don't fit.
+fn replay_edits(
+    mut content: String,
+    edits: &[&EditRecord],
+    divergences: &mut Vec<Divergence>,
+) -> (String, usize) {
+    let mut applied = 0;
+    for edit in edits {
+        if edit.is_cell_edit() {
+            divergences.push(divergence(
+                edit,
+                "notebook cell edit cannot be replayed as text",
+            ));
+            continue;
+        }
+        if edit.is_create {
+            match edit
+                .create_content
+                .as_deref()
+                .or(edit.new_string.as_deref())
+            {
+                Some(created) => {
+                    content = created.to_string();
+                    applied += 1;
+                }
+                None => {
+                    divergences.push(divergence(edit, "file rewritten without recorded content"))
+                }
+            }
+            continue;
+        }
+        let (Some(old), Some(new)) = (edit.old_string.as_deref(), edit.new_string.as_deref())
+        else {
+            divergences.push(divergence(edit, "edit has no recorded old/new text"));
+            continue;
+        };
+        match replace_step(&content, old, new, edit.replace_all) {
+            Ok(next) => {
+                content = next;
+                applied += 1;
+            }
+            Err(StepError::EmptySearch) => {
+                divergences.push(divergence(edit, "edit has an empty search string"))
+            }
+            Err(StepError::NotFound) => {
+                divergences.push(divergence(edit, "text to replace not found"))
+            }
+            Err(StepError::Ambiguous(n)) => divergences.push(divergence(
+                edit,
+                format!("text to replace found {} times", n),
+            )),
+        }
+    }
+    (content, applied)
+}
+
+/// Reconstruct the file after every edit at or before `cutoff`.
+///
+/// `current` is the file as it is now (if it exists); it seeds backward replays and is
+/// compared against forward replays.
+pub fn reconstruct(current: Option<&str>, edits: &[EditRecord], cutoff: DateTime<Utc>) -> Replay {
+    let mut sorted: Vec<&EditRecord> = edits.iter().collect();
+    sorted.sort_by_key(|e| e.timestamp);
+
+    let replayable_create = |e: &&EditRecord| {
+        e.is_create && !e.is_cell_edit() && (e.create_content.is_some() || e.new_string.is_some())
+    };
+    let start = sorted
+        .iter()
+        .rposition(|e| e.timestamp <= cutoff && replayable_create(e));
+
+    if let Some(start) = start {
+        let until = sorted.partition_point(|e| e.timestamp <= cutoff);
+        let mut divergences = Vec::new();
+        let (content, applied) =
+            replay_edits(String::new(), &sorted[start..until], &mut divergences);
+
+        // Keep going to the latest edit to see whether the history explains the file on disk.
+        let mut later = Vec::new();
+        let (latest, _) = replay_edits(content.clone(), &sorted[until..], &mut later);
+        let matches_working_tree = current.map(|c| c == latest);
+
+        return Replay {
+            content: Some(content),
+            strategy: ReplayStrategy::Forward,
+            cutoff,
+            applied,
+            divergences,
+            matches_working_tree,
+        };
+    }
+
+    // No creation on record: rewind the current file, newest edit first.
+    let mut divergences = Vec::new();
+    let mut applied = 0;
+    let mut content = current.map(|c| c.to_string());
+    for edit in sorted.iter().rev().take_while(|e| e.timestamp > cutoff) {
+        let Some(text) = content.as_deref() else {
+            break;
+        };
+        if edit.is_cell_edit() {
+            divergences.push(divergence(
+                edit,
+                "notebook cell edit cannot be undone as text",
+            ));
+            continue;
+        }
+        if edit.is_create {
+            // Created after the cutoff, so it didn't exist yet.
+            content = None;
+            applied += 1;
+            continue;
+        }
+        let (Some(old), Some(new)) = (edit.old_string.as_deref(), edit.new_string.as_deref())
+        else {
+            divergences.push(divergence(edit, "edit has no recorded old/new text"));
+            continue;
+        };
+        match replace_step(text, new, old, edit.replace_all) {
+            Ok(prev) => {
+                content = Some(prev);
+                applied += 1;
+            }
+            Err(StepError::EmptySearch) => divergences.push(divergence(
+                edit,
+                "deletion cannot be undone (position not recorded)",
+            )),
+            Err(StepError::NotFound) => {
+                divergences.push(divergence(edit, "inserted text not found in later version"))
+            }
+            Err(StepError::Ambiguous(n)) => divergences.push(divergence(
+                edit,
+                format!("inserted text found {} times in later version", n),
+            )),
+        }
+    }
+
+    Replay {
+        content,
+        strategy: ReplayStrategy::Backward,
+        cutoff,
+        applied,
+        divergences,
+        matches_working_tree: None,
+    }
+}
@@ -0,0 +1,469 @@
+//! Central provenance store for the `store` output policy.
+//!
+//! Instead of touching each file (or writing a sidecar next to it), histories are
+//! kept in one place and keyed by the file's path relative to the project root:
+//!
+//! - a directory tree, `<store>/<rel>.yaml` (the default, `.provenance/`)
+//! - a single JSON-lines file, one `FileHistory` per line (`*.jsonl`)
+//! - a DuckDB database with a `provenance_events` table (`*.ddb`, `*.duckdb`)
+//!
+//! The same store can be read back by `report --store`. It keeps events, not edit
+//! contents, so it can't drive line-level `blame`.
+
+use crate::models::{
+    CurationAction, CurationEvent, FileHistory, FileRule, HistoriesByFile, OutputConfig,
+    OutputPolicy,
+};
+use crate::updater::merge_events;
+use crate::utils::write_atomic;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use duckdb::Connection;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+/// Store location used when a `store` rule doesn't set `store_path`.
+pub const DEFAULT_STORE_PATH: &str = ".provenance";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StoreKind {
+    /// `<store>/<rel>.yaml`, one file per tracked file.
+    Directory,
+    /// A single JSON-lines file.
+    Jsonl,
+    /// A DuckDB database.
+    DuckDb,
+}
+
+impl StoreKind {
+    /// Backend for a store path, chosen by its extension.
+    pub fn from_path(path: &Path) -> Self {
+        match path
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_ascii_lowercase())
+            .as_deref()
+        {
+            Some("jsonl") => Self::Jsonl,
+            Some("ddb") | Some("duckdb") => Self::DuckDb,
+            _ => Self::Directory,
+        }
+    }
+}
+
+/// The store a rule writes to.
+pub fn store_path_for_rule(rule: &FileRule) -> PathBuf {
+    PathBuf::from(rule.store_path.as_deref().unwrap_or(DEFAULT_STORE_PATH))
+}
+
+/// Resolve the store paths of `config`'s `store` rules against the project root, so
+/// the store lands in the project (`--dir`) rather than wherever the command runs.
+pub fn resolve_store_paths(config: &mut OutputConfig, root: &Path) {
+    for rule in config.rules.iter_mut().chain(config.defaults.as_mut()) {
+        if rule.policy == OutputPolicy::Store {
+            let path = root.join(store_path_for_rule(rule));
+            rule.store_path = Some(path.to_string_lossy().into_owned());
+        }
+    }
+}
+
+/// Normalize a history key to a `/`-separated relative path, dropping `.` components.
+///
+/// Returns `None` for paths the store can't key unambiguously: empty, absolute, or
+/// containing `..`.
+pub fn store_key(file_path: &str) -> Option<String> {
+    let mut parts = Vec::new();
+    for component in Path::new(file_path).components() {
+        match component {
+            Component::Normal(s) => parts.push(s.to_string_lossy().into_owned()),
+            Component::CurDir => {}
+            Component::ParentDir | Component::RootDir | Component::Prefix(_) => return None,
+        }
+    }
+    if parts.is_empty() {
+        return None;
+    }
+    Some(parts.join("/"))
+}
+
+/// The file that holds `key`'s history inside `store`.
+///
+/// For the directory backend this is `<store>/<key>.yaml`; the single-file backends
+/// keep everything in `store` itself. `None` if `key` is not a valid store key.
+pub fn entry_path(store: &Path, key: &str) -> Option<PathBuf> {
+    let key = store_key(key)?;
+    Some(match StoreKind::from_path(store) {
+        StoreKind::Directory => store.join(format!("{}.yaml", key)),
+        StoreKind::Jsonl | StoreKind::DuckDb => store.to_path_buf(),
+    })
+}
+
+/// Merge `history` into the store, deduplicating events by timestamp.
+pub fn write_history(store: &Path, history: &FileHistory, dry_run: bool) -> Result<(bool, String)> {
+    let mut results = write_histories(store, &[history], dry_run)?;
+    Ok(results.remove(0))
+}
+
+/// Merge several histories into the store at once, with one `(ok, message)` per history.
+///
+/// The single-file backends are read and written once for the whole batch rather than
+/// once per history.
+pub fn write_histories(
+    store: &Path,
+    histories: &[&FileHistory],
+    dry_run: bool,
+) -> Result<Vec<(bool, String)>> {
+    let kind = StoreKind::from_path(store);
+    // One connection serves the whole batch (a dry run never creates the database).
+    let conn = match kind {
+        StoreKind::DuckDb if !dry_run || store.is_file() => Some(open_duckdb(store)?),
+        _ => None,
+    };
+    let mut existing = match (kind, &conn) {
+        (StoreKind::Jsonl, _) => read_jsonl(store)?,
+        (StoreKind::DuckDb, Some(conn)) => query_duckdb(conn, None)?,
+        _ => BTreeMap::new(),
+    };
+
+    let mut results = Vec::with_capacity(histories.len());
+    let mut merged_keys = Vec::new();
+    for history in histories {
+        let Some(key) = store_key(&history.file_path) else {
+            results.push((
+                false,
+                format!("Cannot store history for path {:?}", history.file_path),
+            ));
+            continue;
+        };
+
+        let previous = match kind {
+            StoreKind::Directory => read_directory_entry(store, &key)?,
+            StoreKind::Jsonl | StoreKind::DuckDb => existing.remove(&key),
+        };
+        let merged = FileHistory {
+            file_path: key.clone(),
+            events: merge_events(
+                previous.map(|h| h.events).unwrap_or_default(),
+                &history.events,
+            ),
+        };
+
+        if dry_run {
+            results.push((
+                true,
+                format!("Would store history for {} in {:?}", key, store),
+            ));
+            continue;
+        }
+        if kind == StoreKind::Directory {
+            write_directory_entry(store, &merged)?;
+        }
+        results.push((true, format!("Stored: {} -> {:?}", key, store)));
+        merged_keys.push(key.clone());
+        existing.insert(key, merged);
+    }
+
+    if dry_run || merged_keys.is_empty() {
+        return Ok(results);
+    }
+    match (kind, &conn) {
+        (StoreKind::Jsonl, _) => write_jsonl(store, &existing)?,
+        (StoreKind::DuckDb, Some(conn)) => {
+            let changed: Vec<&FileHistory> = merged_keys.iter().map(|k| &existing[k]).collect();
+            write_duckdb_entries(conn, &changed)
+                .with_context(|| format!("Failed to write store database: {:?}", store))?;
+        }
+        _ => {}
+    }
+    Ok(results)
+}
+
+/// History for one file, or `None` if the store has nothing for it.
+pub fn read_history(store: &Path, file_path: &str) -> Result<Option<FileHistory>> {
+    let Some(key) = store_key(file_path) else {
+        return Ok(None);
+    };
+    match StoreKind::from_path(store) {
+        StoreKind::Directory => read_directory_entry(store, &key),
+        StoreKind::Jsonl => Ok(read_jsonl(store)?.remove(&key)),
+        StoreKind::DuckDb => Ok(read_duckdb(store, Some(&key))?.remove(&key)),
+    }
+}
+
+/// Every history in the store, keyed by relative path.
+pub fn read_all(store: &Path) -> Result<HistoriesByFile> {
+    let histories = match StoreKind::from_path(store) {
+        StoreKind::Directory => read_directory(store)?,
+        StoreKind::Jsonl => read_jsonl(store)?,
+        StoreKind::DuckDb => read_duckdb(store, None)?,
+    };
+    Ok(histories.into_iter().collect())
+}
+
+// ---------------------------------------------------------------------------
+// Directory backend
+// ---------------------------------------------------------------------------
+
+fn write_directory_entry(store: &Path, history: &FileHistory) -> Result<()> {
+    let path = store.join(format!("{}.yaml", history.file_path));
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create store directory: {:?}", parent))?;
+    }
+
+    let mut data = serde_yaml::Mapping::new();
+    data.insert(
+        serde_yaml::Value::String("source_file".to_string()),
+        serde_yaml::Value::String(history.file_path.clone()),
+    );
+    data.insert(
+        serde_yaml::Value::String("edit_history".to_string()),
+        serde_yaml::to_value(&history.events)?,
+    );
+    write_atomic(&path, serde_yaml::to_string(&data)?)
+        .with_context(|| format!("Failed to write store entry: {:?}", path))
+}
+
+fn parse_directory_entry(path: &Path, fallback_key: &str) -> Result<FileHistory> {
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read store entry: {:?}", path))?;
+    let data: serde_yaml::Value = serde_yaml::from_str(&content)
+        .with_context(|| format!("Failed to parse store entry: {:?}", path))?;
+    let key = data
+        .get("source_file")
+        .and_then(|v| v.as_str())
+        .and_then(store_key)
+        .unwrap_or_else(|| fallback_key.to_string());
+    let events = match data.get("edit_history") {
+        Some(v) => serde_yaml::from_value::<Vec<CurationEvent>>(v.clone())
+            .with_context(|| format!("Invalid edit_history in store entry: {:?}", path))?,
+        None => Vec::new(),
+    };
+    Ok(FileHistory {
+        file_path: key,
+        events,
+    })
+}
+
+fn read_directory_entry(store: &Path, key: &str) -> Result<Option<FileHistory>> {
+    let path = store.join(format!("{}.yaml", key));
+    if !path.is_file() {
+        return Ok(None);
+    }
+    parse_directory_entry(&path, key).map(Some)
+}
+
+fn read_directory(store: &Path) -> Result<BTreeMap<String, FileHistory>> {
+    let mut histories = BTreeMap::new();
+    if !store.is_dir() {
+        return Ok(histories);
+    }
+
+    let mut pending = vec![store.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        for entry in fs::read_dir(&dir)
+            .with_context(|| format!("Failed to read store directory: {:?}", dir))?
+        {
+            let path = entry?.path();
+            if path.is_dir() {
+                pending.push(path);
+                continue;
+            }
+            if path.extension().and_then(|e| e.to_str()) != Some("yaml") {
+                continue;
+            }
+            let rel = path.strip_prefix(store).unwrap_or(&path).with_extension("");
+            let history = parse_directory_entry(
+                &path,
+                &store_key(&rel.to_string_lossy()).unwrap_or_default(),
+            )?;
+            histories.insert(history.file_path.clone(), history);
+        }
+    }
+    Ok(histories)
+}
+
+// ---------------------------------------------------------------------------
+// JSONL backend
+// ---------------------------------------------------------------------------
+
+fn read_jsonl(store: &Path) -> Result<BTreeMap<String, FileHistory>> {
+    let mut histories = BTreeMap::new();
+    if !store.is_file() {
+        return Ok(histories);
+    }
+
+    let content =
+        fs::read_to_string(store).with_context(|| format!("Failed to read store: {:?}", store))?;
+    for (i, line) in content.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let mut history: FileHistory = serde_json::from_str(line)
+            .with_context(|| format!("Failed to parse {:?} line {}", store, i + 1))?;
+        history.file_path = store_key(&history.file_path).with_context(|| {
+            format!(
+                "Invalid path {:?} in {:?} line {}",
+                history.file_path,
+                store,
+                i + 1
+            )
+        })?;
+        histories.insert(history.file_path.clone(), history);
+    }
+    Ok(histories)
+}
+
+fn write_jsonl(store: &Path, histories: &BTreeMap<String, FileHistory>) -> Result<()> {
+    // Rewritten whole (sorted by path) so the file diffs cleanly under version control.
+    let mut content = String::new();
+    for history in histories.values() {
+        content.push_str(&serde_json::to_string(history)?);
+        content.push('\n');
+    }
+
+    if let Some(parent) = store.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent)?;
+    }
+    write_atomic(store, content).with_context(|| format!("Failed to write store: {:?}", store))
+}
+
+// ---------------------------------------------------------------------------
+// DuckDB backend
+// ---------------------------------------------------------------------------
+
+fn open_duckdb(store: &Path) -> Result<Connection> {
+    if let Some(parent) = store.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent)?;
+    }
+    let conn = Connection::open(store)
+        .with_context(|| format!("Failed to open store database: {:?}", store))?;
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS provenance_events (
+            file_path TEXT NOT NULL,
+            timestamp TEXT NOT NULL,
+            model TEXT,
+            action TEXT,
+            description TEXT,
+            agent_tool TEXT,
+            agent_version TEXT,
+            cell_id TEXT
+        );",
+    )?;
+    Ok(conn)
+}
+
+fn write_duckdb_entries(conn: &Connection, histories: &[&FileHistory]) -> Result<()> {
+    conn.execute("BEGIN TRANSACTION", [])?;
+
+    let result = (|| -> Result<()> {
+        let mut delete = conn.prepare("DELETE FROM provenance_events WHERE file_path = ?1")?;
+        let mut insert = conn.prepare(
+            "INSERT INTO provenance_events
+             (file_path, timestamp, model, action, description, agent_tool, agent_version, cell_id)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        )?;
+        for history in histories {
+            delete.execute([&history.file_path])?;
+            for event in &history.events {
+                let action = event.action.as_ref().map(|a| match a {
+                    CurationAction::Created => "CREATED",
+                    CurationAction::Edited => "EDITED",
+                });
+                insert.execute(duckdb::params![
+                    &history.file_path,
+                    event.timestamp.to_rfc3339(),
+                    &event.model,
+                    action,
+                    &event.description,
+                    &event.agent_tool,
+                    &event.agent_version,
+                    &event.cell_id,
+                ])?;
+            }
+        }
+        Ok(())
+    })();
+
+    match result {
+        Ok(()) => {
+            conn.execute("COMMIT", [])?;
+            Ok(())
+        }
+        Err(e) => {
+            let _ = conn.execute("ROLLBACK", []);
+            Err(e)
+        }
+    }
+}
+
+fn read_duckdb(store: &Path, key: Option<&str>) -> Result<BTreeMap<String, FileHistory>> {
+    if !store.is_file() {
+        return Ok(BTreeMap::new());
+    }
+    query_duckdb(&open_duckdb(store)?, key)
+}
+
+fn query_duckdb(conn: &Connection, key: Option<&str>) -> Result<BTreeMap<String, FileHistory>> {
+    let mut histories = BTreeMap::new();
+    let mut stmt = conn.prepare(
+        "SELECT file_path, timestamp, model, action, description, agent_tool, agent_version, cell_id
+         FROM provenance_events
+         WHERE ?1 IS NULL OR file_path = ?1
+         ORDER BY file_path, timestamp",
+    )?;
+
+    type EventRow = (
+        String,
+        String,
+        Option<String>,
+        Option<String>,
+        Option<String>,
+        Option<String>,
+        Option<String>,
+        Option<String>,
+    );
+    let rows = stmt.query_map([key], |row| -> duckdb::Result<EventRow> {
+        Ok((
+            row.get(0)?,
+            row.get(1)?,
+            row.get(2)?,
+            row.get(3)?,
+            row.get(4)?,
+            row.get(5)?,
+            row.get(6)?,
+            row.get(7)?,
+        ))
+    })?;
+
+    for row in rows {
+        let (file_path, timestamp, model, action, description, agent_tool, agent_version, cell_id) =
+            row?;
+        let timestamp = DateTime::parse_from_rfc3339(&timestamp)
+            .with_context(|| format!("Invalid timestamp in store: {:?}", timestamp))?
+            .with_timezone(&Utc);
+        let action = match action.as_deref() {
+            Some("CREATED") => Some(CurationAction::Created),
+            Some("EDITED") => Some(CurationAction::Edited),
+            _ => None,
+        };
+        histories
+            .entry(file_path.clone())
+            .or_insert_with(|| FileHistory {
+                file_path,
+                events: Vec::new(),
+            })
+            .events
+            .push(CurationEvent {
+                timestamp,
+                model,
+                action,
+                description,
+                agent_tool,
+                agent_version,
+                cell_id,
+            });
+    }
+    Ok(histories)
+}
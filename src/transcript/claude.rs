@@ -92,6 +92,7 @@ impl ClaudeTranscriptParser {
                         tool_use_id,
                         content,
                         is_error,
+                        agent_id: None,
                     });
                 }
                 _ => {}
@@ -230,6 +231,10 @@ impl TranscriptParser for ClaudeTranscriptParser {
         let mut messages: Vec<TranscriptMessage> = Vec::new();
         let mut files_touched: HashSet<String> = HashSet::new();
         let mut seen_uuids: HashSet<String> = HashSet::new();
+        let mut seen_message_ids: HashSet<String> = HashSet::new();
+        // UUIDs the records point back to, in file order; one not in this file is where
+        // an earlier file of the conversation left off.
+        let mut references: Vec<String> = Vec::new();
 
         for line in reader.lines() {
             let line = line?;
@@ -248,6 +253,14 @@ impl TranscriptParser for ClaudeTranscriptParser {
                 continue;
             }
 
+            // Summary records of a resumed session name the last message before the resume;
+            // compaction boundaries name the last message before the compaction.
+            for key in ["leafUuid", "logicalParentUuid", "parentUuid"] {
+                if let Some(uuid) = record.get(key).and_then(|u| u.as_str()) {
+                    references.push(uuid.to_string());
+                }
+            }
+
             // Extract metadata from first user message if not set
             if meta.is_none() {
                 if let Some(session_id) = record.get("sessionId").and_then(|s| s.as_str()) {
@@ -274,6 +287,10 @@ impl TranscriptParser for ClaudeTranscriptParser {
                         .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
                         .map(|dt| dt.with_timezone(&Utc))
                         .unwrap_or_else(Utc::now);
+                    let agent_id = crate::parsers::common::sidechain_agent_id(path, &record);
+                    // Sidechain records carry the session ID of the session that started
+                    // the subagent.
+                    let parent_session_id = agent_id.as_ref().map(|_| session_id.to_string());
 
                     meta = Some(TranscriptMeta {
                         session_id: session_id.to_string(),
@@ -285,6 +302,11 @@ impl TranscriptParser for ClaudeTranscriptParser {
                         start_time: timestamp,
                         end_time: None,
                         source_file: Some(path.to_string_lossy().to_string()),
+                        agent_id,
+                        parent_session_id,
+                        parent_tool_use_id: None,
+                        continues_from: None,
+                        continuations: Vec::new(),
                     });
                 }
             }
@@ -339,6 +361,14 @@ impl TranscriptParser for ClaudeTranscriptParser {
             // For user messages with tool results, add file operation info
             if role == Role::User {
                 if let Some(tool_result) = record.get("toolUseResult") {
+                    // A Task call's result names the subagent that ran it
+                    if let Some(id) = tool_result.get("agentId").and_then(|a| a.as_str()) {
+                        for block in &mut content_blocks {
+                            if let ContentBlock::ToolResult { agent_id, .. } = block {
+                                *agent_id = Some(id.to_string());
+                            }
+                        }
+                    }
                     if let Some(file_op) = Self::extract_file_operation(tool_result) {
                         // Track file touched
                         if let ContentBlock::FileOperation { file_path, .. } = &file_op {
@@ -354,8 +384,17 @@ impl TranscriptParser for ClaudeTranscriptParser {
                 continue;
             }
 
-            // Extract usage
-            let usage = Self::extract_usage(msg);
+            // Extract usage. Claude Code writes one record per content block of an API
+            // response, each repeating the response's usage; count it only once.
+            let first_of_response = match msg.get("id").and_then(|i| i.as_str()) {
+                Some(id) => seen_message_ids.insert(id.to_string()),
+                None => true,
+            };
+            let usage = if first_of_response {
+                Self::extract_usage(msg)
+            } else {
+                None
+            };
 
             messages.push(TranscriptMessage {
                 id: uuid,
@@ -368,7 +407,7 @@ impl TranscriptParser for ClaudeTranscriptParser {
         }
 
         // Create transcript with default meta if none found
-        let meta = meta.unwrap_or_else(|| {
+        let mut meta = meta.unwrap_or_else(|| {
             let filename = path
                 .file_stem()
                 .and_then(|s| s.to_str())
@@ -383,9 +422,18 @@ impl TranscriptParser for ClaudeTranscriptParser {
                 start_time: Utc::now(),
                 end_time: None,
                 source_file: Some(path.to_string_lossy().to_string()),
+                agent_id: None,
+                parent_session_id: None,
+                parent_tool_use_id: None,
+                continues_from: None,
+                continuations: Vec::new(),
             }
         });
 
+        if meta.agent_id.is_none() {
+            meta.continues_from = references.into_iter().find(|u| !seen_uuids.contains(u));
+        }
+
         let mut transcript = Transcript::new(meta);
         for msg in messages {
             transcript.add_message(msg);
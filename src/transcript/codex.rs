@@ -21,6 +21,47 @@ impl CodexTranscriptParser {
         Self
     }
 
+    /// The command a shell tool call ran, with its exit code.
+    ///
+    /// The output itself stays in the tool result. Codex reports the exit code either as an
+    /// `Exit code: N` first line or, in older sessions, as JSON `metadata.exit_code`.
+    fn shell_command(tool_use: &ContentBlock, output: &str) -> Option<ContentBlock> {
+        let ContentBlock::ToolUse { name, input, .. } = tool_use else {
+            return None;
+        };
+        if !name.starts_with("shell") {
+            return None;
+        }
+        let command = match input.get("command")? {
+            Value::String(command) => command.clone(),
+            Value::Array(parts) => parts
+                .iter()
+                .filter_map(|p| p.as_str())
+                .collect::<Vec<_>>()
+                .join(" "),
+            _ => return None,
+        };
+
+        let exit_code = output
+            .lines()
+            .next()
+            .and_then(|line| line.strip_prefix("Exit code: "))
+            .and_then(|code| code.trim().parse().ok())
+            .or_else(|| {
+                serde_json::from_str::<Value>(output)
+                    .ok()?
+                    .pointer("/metadata/exit_code")?
+                    .as_i64()
+                    .map(|code| code as i32)
+            });
+
+        Some(ContentBlock::Command {
+            command,
+            output: None,
+            exit_code,
+        })
+    }
+
     /// Extract content blocks from Codex message content.
     fn extract_content_from_message(payload: &Value) -> Vec<ContentBlock> {
         let mut blocks = Vec::new();
@@ -48,16 +89,35 @@ impl CodexTranscriptParser {
         blocks
     }
 
-    /// Extract token usage from a token_count event.
-    fn extract_usage(info: &Value) -> Option<TokenUsage> {
-        let usage = info.get("total_token_usage")?;
-
-        Some(TokenUsage {
-            input_tokens: usage.get("input_tokens").and_then(|t| t.as_u64()),
-            output_tokens: usage.get("output_tokens").and_then(|t| t.as_u64()),
-            cache_read_tokens: usage.get("cached_input_tokens").and_then(|t| t.as_u64()),
+    /// Read a Codex usage object (`total_token_usage` or `last_token_usage`).
+    ///
+    /// Codex counts cached input as part of `input_tokens`; it is moved to
+    /// `cache_read_tokens` so usage means the same as for Claude.
+    fn read_usage(usage: &Value) -> TokenUsage {
+        let get = |key: &str| usage.get(key).and_then(|t| t.as_u64());
+        let cached = get("cached_input_tokens");
+        TokenUsage {
+            input_tokens: get("input_tokens").map(|i| i.saturating_sub(cached.unwrap_or(0))),
+            output_tokens: get("output_tokens"),
+            cache_read_tokens: cached,
             cache_creation_tokens: None,
-        })
+        }
+    }
+
+    /// Token usage of the model response a token_count event reports.
+    ///
+    /// Events carry a running total and may be repeated, so the usage is the growth of
+    /// the total since `previous_total`; `None` when it did not grow.
+    fn extract_usage(info: &Value, previous_total: &mut Option<TokenUsage>) -> Option<TokenUsage> {
+        let Some(total) = info.get("total_token_usage").map(Self::read_usage) else {
+            return info.get("last_token_usage").map(Self::read_usage);
+        };
+        let usage = match previous_total.as_ref() {
+            Some(previous) => total.minus(previous),
+            None => total.clone(),
+        };
+        *previous_total = Some(total);
+        (!usage.is_empty()).then_some(usage)
     }
 }
 
@@ -137,7 +197,10 @@ impl TranscriptParser for CodexTranscriptParser {
         let mut files_touched: HashSet<String> = HashSet::new();
         let mut message_id_counter = 0;
         let mut pending_tool_calls: Vec<(String, ContentBlock)> = Vec::new();
+        // Usage reported since the last assistant message, attached to the next one.
         let mut last_usage: Option<TokenUsage> = None;
+        let mut total_usage: Option<TokenUsage> = None;
+        let mut current_model: Option<String> = None;
 
         for line in reader.lines() {
             let line = line?;
@@ -191,6 +254,11 @@ impl TranscriptParser for CodexTranscriptParser {
                             start_time: timestamp.unwrap_or_else(Utc::now),
                             end_time: None,
                             source_file: Some(path.to_string_lossy().to_string()),
+                            agent_id: None,
+                            parent_session_id: None,
+                            parent_tool_use_id: None,
+                            continues_from: None,
+                            continuations: Vec::new(),
                         });
                     }
                 }
@@ -219,8 +287,14 @@ impl TranscriptParser for CodexTranscriptParser {
                                         role,
                                         timestamp: timestamp.unwrap_or_else(Utc::now),
                                         content: content_blocks,
-                                        model: None,
-                                        usage: last_usage.take(),
+                                        model: (role == Role::Assistant)
+                                            .then(|| current_model.clone())
+                                            .flatten(),
+                                        usage: if role == Role::Assistant {
+                                            last_usage.take()
+                                        } else {
+                                            None
+                                        },
                                     });
                                 }
                             }
@@ -265,6 +339,7 @@ impl TranscriptParser for CodexTranscriptParser {
                                     pending_tool_calls.iter().position(|(id, _)| id == &call_id)
                                 {
                                     let (_, tool_use) = pending_tool_calls.remove(idx);
+                                    let command = Self::shell_command(&tool_use, &output);
 
                                     // Create assistant message with tool use
                                     message_id_counter += 1;
@@ -273,8 +348,8 @@ impl TranscriptParser for CodexTranscriptParser {
                                         role: Role::Assistant,
                                         timestamp: timestamp.unwrap_or_else(Utc::now),
                                         content: vec![tool_use],
-                                        model: None,
-                                        usage: None,
+                                        model: current_model.clone(),
+                                        usage: last_usage.take(),
                                     });
 
                                     // Create tool result
@@ -283,11 +358,14 @@ impl TranscriptParser for CodexTranscriptParser {
                                         id: format!("msg_{}", message_id_counter),
                                         role: Role::User,
                                         timestamp: timestamp.unwrap_or_else(Utc::now),
-                                        content: vec![ContentBlock::ToolResult {
+                                        content: std::iter::once(ContentBlock::ToolResult {
                                             tool_use_id: call_id,
                                             content: output,
                                             is_error: false,
-                                        }],
+                                            agent_id: None,
+                                        })
+                                        .chain(command)
+                                        .collect(),
                                         model: None,
                                         usage: None,
                                     });
@@ -311,8 +389,8 @@ impl TranscriptParser for CodexTranscriptParser {
                                         content: vec![ContentBlock::Thinking {
                                             thinking: summary.to_string(),
                                         }],
-                                        model: None,
-                                        usage: None,
+                                        model: current_model.clone(),
+                                        usage: last_usage.take(),
                                     });
                                 }
                             }
@@ -320,13 +398,27 @@ impl TranscriptParser for CodexTranscriptParser {
                         }
                     }
                 }
+                "turn_context" => {
+                    if let Some(model) = record
+                        .get("payload")
+                        .and_then(|p| p.get("model"))
+                        .and_then(|m| m.as_str())
+                    {
+                        current_model = Some(model.to_string());
+                    }
+                }
                 "event_msg" => {
                     if let Some(payload) = record.get("payload") {
                         let event_type = payload.get("type").and_then(|t| t.as_str()).unwrap_or("");
 
                         if event_type == "token_count" {
-                            if let Some(info) = payload.get("info") {
-                                last_usage = Self::extract_usage(info);
+                            if let Some(usage) = payload
+                                .get("info")
+                                .and_then(|info| Self::extract_usage(info, &mut total_usage))
+                            {
+                                last_usage
+                                    .get_or_insert_with(TokenUsage::default)
+                                    .add(&usage);
                             }
                         }
                     }
@@ -422,6 +514,11 @@ impl TranscriptParser for CodexTranscriptParser {
                                 start_time: timestamp.unwrap_or_else(Utc::now),
                                 end_time: None,
                                 source_file: Some(path.to_string_lossy().to_string()),
+                                agent_id: None,
+                                parent_session_id: None,
+                                parent_tool_use_id: None,
+                                continues_from: None,
+                                continuations: Vec::new(),
                             });
                         }
                     }
@@ -446,9 +543,27 @@ impl TranscriptParser for CodexTranscriptParser {
                 start_time: Utc::now(),
                 end_time: None,
                 source_file: Some(path.to_string_lossy().to_string()),
+                agent_id: None,
+                parent_session_id: None,
+                parent_tool_use_id: None,
+                continues_from: None,
+                continuations: Vec::new(),
             }
         });
 
+        // Usage reported after the last assistant message belongs to it.
+        if let Some(usage) = last_usage {
+            if let Some(msg) = messages
+                .iter_mut()
+                .rev()
+                .find(|m| m.role == Role::Assistant)
+            {
+                msg.usage
+                    .get_or_insert_with(TokenUsage::default)
+                    .add(&usage);
+            }
+        }
+
         let mut transcript = Transcript::new(meta);
         for msg in messages {
             transcript.add_message(msg);
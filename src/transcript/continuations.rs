@@ -0,0 +1,193 @@
+//! Conversations that continue across trace files.
+//!
+//! Resuming a Claude Code session, or compacting its context, can carry the conversation on
+//! in a new JSONL file with a new session ID. The new file either points back at the last
+//! message of the earlier one (a `summary` record's `leafUuid`, a compaction boundary's
+//! `logicalParentUuid`, or the first record's `parentUuid`) or replays the earlier
+//! messages with their original UUIDs. [`link_continuations`] uses both to merge the
+//! fragments back into one transcript per conversation.
+
+use super::{
+    link_subagents, parse_transcript_with_subagents, parse_transcripts_from_directory,
+    ContentBlock, Continuation, Transcript,
+};
+use chrono::{DateTime, Utc};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+/// Merge transcripts that continue one another into one transcript per conversation.
+///
+/// Each conversation keeps the metadata of its first file; the messages of later files
+/// are added (leaving out replayed ones) and the files are listed in
+/// `meta.continuations`. Other transcripts are returned unchanged, in their original order.
+pub fn link_continuations(transcripts: Vec<Transcript>) -> Vec<Transcript> {
+    let predecessors = find_predecessors(&transcripts);
+    if predecessors.iter().all(Option::is_none) {
+        return transcripts;
+    }
+
+    // Walk each transcript back to the first file of its conversation.
+    let first_file = |mut i: usize| {
+        while let Some(p) = predecessors[i] {
+            i = p;
+        }
+        i
+    };
+    let mut fragments: HashMap<usize, Vec<usize>> = HashMap::new();
+    for i in 0..transcripts.len() {
+        let first = first_file(i);
+        if first != i {
+            fragments.entry(first).or_default().push(i);
+        }
+    }
+
+    let mut slots: Vec<Option<Transcript>> = transcripts.into_iter().map(Some).collect();
+    let mut merged = Vec::new();
+    for i in 0..slots.len() {
+        if predecessors[i].is_some() {
+            continue;
+        }
+        let Some(mut transcript) = slots[i].take() else {
+            continue;
+        };
+        let mut later: Vec<Transcript> = fragments
+            .remove(&i)
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|j| slots[j].take())
+            .collect();
+        later.sort_by(|a, b| order_key(a).cmp(&order_key(b)));
+        for fragment in later {
+            append_fragment(&mut transcript, fragment);
+        }
+        merged.push(transcript);
+    }
+    merged
+}
+
+/// For each transcript, the index of the transcript it continues.
+///
+/// Only Claude Code sessions (not subagents) take part. A file continues the one holding
+/// the message it points back at, or else the one that ended last before it among those
+/// holding its first message. Either must have ended earlier, so there are no cycles.
+fn find_predecessors(transcripts: &[Transcript]) -> Vec<Option<usize>> {
+    let linkable = |t: &Transcript| t.meta.agent_tool == "claude-code" && t.meta.agent_id.is_none();
+
+    let mut holders: HashMap<&str, Vec<usize>> = HashMap::new();
+    for (i, transcript) in transcripts.iter().enumerate() {
+        if linkable(transcript) {
+            for message in &transcript.messages {
+                holders.entry(message.id.as_str()).or_default().push(i);
+            }
+        }
+    }
+
+    transcripts
+        .iter()
+        .enumerate()
+        .map(|(i, transcript)| {
+            if !linkable(transcript) {
+                return None;
+            }
+            let earlier = |uuid: &str| {
+                holders
+                    .get(uuid)
+                    .into_iter()
+                    .flatten()
+                    .copied()
+                    .filter(|&j| j != i && order_key(&transcripts[j]) < order_key(transcript))
+                    .max_by_key(|&j| order_key(&transcripts[j]))
+            };
+            transcript
+                .meta
+                .continues_from
+                .as_deref()
+                .and_then(earlier)
+                .or_else(|| earlier(transcript.messages.first()?.id.as_str()))
+        })
+        .collect()
+}
+
+/// Order of the files of a conversation: a continuation ends after what it continues.
+fn order_key(t: &Transcript) -> (DateTime<Utc>, DateTime<Utc>, Option<&str>) {
+    (
+        t.meta.end_time.unwrap_or(t.meta.start_time),
+        t.meta.start_time,
+        t.meta.source_file.as_deref(),
+    )
+}
+
+/// Add a later file of the conversation to `transcript`.
+fn append_fragment(transcript: &mut Transcript, fragment: Transcript) {
+    let mut seen: HashSet<String> = transcript.messages.iter().map(|m| m.id.clone()).collect();
+    let new_messages: Vec<_> = fragment
+        .messages
+        .into_iter()
+        .filter(|m| seen.insert(m.id.clone()))
+        .collect();
+
+    transcript.meta.continuations.push(Continuation {
+        session_id: fragment.meta.session_id,
+        source_file: fragment.meta.source_file,
+        start_time: new_messages
+            .iter()
+            .map(|m| m.timestamp)
+            .min()
+            .unwrap_or(fragment.meta.start_time),
+    });
+    transcript
+        .meta
+        .continuations
+        .extend(fragment.meta.continuations);
+    for message in new_messages {
+        transcript.add_message(message);
+    }
+    transcript.sort_messages();
+    transcript.meta.continuations.sort_by_key(|c| c.start_time);
+
+    transcript.subagents.extend(fragment.subagents);
+    transcript.subagents.sort_by_key(|s| s.meta.start_time);
+
+    let files: HashSet<&str> = transcript
+        .messages
+        .iter()
+        .flat_map(|m| &m.content)
+        .filter_map(|block| match block {
+            ContentBlock::FileOperation { file_path, .. } => Some(file_path.as_str()),
+            _ => None,
+        })
+        .collect();
+    transcript.stats.files_touched = files.len();
+}
+
+/// Parse the transcript in `path` as part of its whole conversation: with its subagents
+/// and the other trace files of the conversation in the same directory.
+pub fn parse_conversation(path: &Path) -> anyhow::Result<Transcript> {
+    let transcript = parse_transcript_with_subagents(path)?;
+    if transcript.meta.agent_tool != "claude-code" || transcript.meta.agent_id.is_some() {
+        return Ok(transcript);
+    }
+
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let Ok(target) = path.canonicalize() else {
+        return Ok(transcript);
+    };
+    let is_target = |file: &Option<String>| {
+        file.as_ref()
+            .and_then(|f| Path::new(f).canonicalize().ok())
+            .is_some_and(|f| f == target)
+    };
+    let conversation = link_continuations(link_subagents(parse_transcripts_from_directory(dir)?))
+        .into_iter()
+        .find(|t| {
+            is_target(&t.meta.source_file)
+                || t.meta
+                    .continuations
+                    .iter()
+                    .any(|c| is_target(&c.source_file))
+        });
+    Ok(conversation.unwrap_or(transcript))
+}
@@ -0,0 +1,648 @@
+//! Standalone HTML rendering of transcripts, for `transcript view --format html`.
+//!
+//! The page is a single file with its styles inline and no scripts, so it can be attached
+//! to a pull request or archived as is. Thinking, tool calls, tool results, commands and
+//! subagents are collapsible `<details>` blocks; file edits are shown as diffs and code
+//! blocks are highlighted by a small built-in highlighter. Every message has an anchor
+//! (`#m12`, or `#<agent>-m3` inside a subagent) and shows its token usage.
+
+use super::{ContentBlock, FileOpType, Role, TokenUsage, Transcript};
+use crate::diff::{diff_lines, DiffLine};
+use crate::models::CommentSyntax;
+use crate::pricing::{billed_messages, PriceTable};
+use std::collections::HashMap;
+use std::path::Path;
+
+const STYLE: &str = r#"
+:root { --fg: #1f2328; --muted: #656d76; --border: #d0d7de; --bg: #ffffff; --panel: #f6f8fa;
+  --user: #ddf4ff; --add: #e6ffec; --del: #ffebe9; --accent: #0969da; }
+@media (prefers-color-scheme: dark) {
+  :root { --fg: #e6edf3; --muted: #8d96a0; --border: #30363d; --bg: #0d1117; --panel: #161b22;
+    --user: #0c2d4b; --add: #12361f; --del: #4c1d1d; --accent: #4493f8; }
+}
+body { margin: 0 auto; max-width: 60rem; padding: 1.5rem; color: var(--fg); background: var(--bg);
+  font: 15px/1.5 -apple-system, BlinkMacSystemFont, "Segoe UI", Helvetica, Arial, sans-serif; }
+h1 { font-size: 1.4rem; margin: 0 0 .75rem; }
+dl.meta { display: grid; grid-template-columns: max-content 1fr; gap: .2rem 1rem; margin: 0 0 1.5rem;
+  padding: .75rem 1rem; background: var(--panel); border: 1px solid var(--border); border-radius: 6px; }
+dl.meta dt { color: var(--muted); }
+dl.meta dd { margin: 0; overflow-wrap: anywhere; }
+section.message { border: 1px solid var(--border); border-radius: 6px; margin: 0 0 1rem; padding: .5rem .9rem; }
+section.message.user { background: var(--user); }
+.message-head { display: flex; flex-wrap: wrap; gap: .6rem; align-items: baseline; font-size: .85rem;
+  color: var(--muted); margin-bottom: .35rem; }
+.message-head .role { font-weight: 600; color: var(--fg); }
+.message-head .usage { margin-left: auto; }
+a.anchor { color: var(--muted); text-decoration: none; }
+a.anchor:hover { color: var(--accent); }
+.text { white-space: pre-wrap; overflow-wrap: anywhere; margin: .35rem 0; }
+pre { background: var(--panel); border: 1px solid var(--border); border-radius: 6px; padding: .6rem .8rem;
+  overflow-x: auto; font: 13px/1.45 ui-monospace, SFMono-Regular, Menlo, Consolas, monospace; margin: .35rem 0; }
+details { margin: .35rem 0; }
+details > summary { cursor: pointer; color: var(--muted); font-size: .9rem; }
+details > summary b, details > summary code { color: var(--fg); }
+details.subagent { border-left: 3px solid var(--accent); padding-left: .8rem; }
+details.subagent > summary { color: var(--accent); font-weight: 600; }
+.file-op { font-size: .9rem; }
+.error { color: #cf222e; }
+.diff .add { background: var(--add); display: block; }
+.diff .del { background: var(--del); display: block; }
+.diff .ctx { display: block; }
+.stat .add { color: #1a7f37; }
+.stat .del { color: #cf222e; }
+.code .kw { color: #cf222e; }
+.code .str { color: #0a3069; }
+.code .com { color: #6e7781; font-style: italic; }
+.code .num { color: #0550ae; }
+@media (prefers-color-scheme: dark) {
+  .code .kw { color: #ff7b72; } .code .str { color: #a5d6ff; } .code .com { color: #8b949e; }
+  .code .num { color: #79c0ff; }
+}
+.resumed { text-align: center; color: var(--muted); border-top: 1px dashed var(--border);
+  margin: 1.5rem 0 1rem; padding-top: .4rem; font-size: .9rem; }
+"#;
+
+/// Render `transcript` (with its subagents and continuations) as a standalone HTML page,
+/// with costs estimated from `prices`.
+pub fn render_html(transcript: &Transcript, prices: &PriceTable) -> String {
+    let mut out = String::new();
+    out.push_str("<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n");
+    out.push_str("<meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n");
+    out.push_str(&format!(
+        "<title>Transcript {}</title>\n",
+        escape(&transcript.meta.session_id)
+    ));
+    out.push_str(&format!("<style>{}</style>\n</head>\n<body>\n", STYLE));
+    render_header(&mut out, transcript, prices);
+    out.push_str("<main>\n");
+    render_messages(&mut out, transcript, "m", prices);
+    out.push_str("</main>\n</body>\n</html>\n");
+    out
+}
+
+fn render_header(out: &mut String, transcript: &Transcript, prices: &PriceTable) {
+    let meta = &transcript.meta;
+    let summary = transcript.summary(prices);
+    out.push_str(&format!(
+        "<header>\n<h1>Transcript <code>{}</code></h1>\n<dl class=\"meta\">\n",
+        escape(&meta.session_id)
+    ));
+    let mut row = |label: &str, value: String| {
+        out.push_str(&format!("<dt>{}</dt><dd>{}</dd>\n", label, value));
+    };
+    if let Some(slug) = &meta.slug {
+        row("Slug", escape(slug));
+    }
+    let agent = match &meta.agent_version {
+        Some(version) => format!("{} {}", meta.agent_tool, version),
+        None => meta.agent_tool.clone(),
+    };
+    row("Agent", escape(&agent));
+    if !summary.all_models.is_empty() {
+        row("Models", escape(&summary.all_models.join(", ")));
+    }
+    if let Some(cwd) = &meta.cwd {
+        row("Working Directory", format!("<code>{}</code>", escape(cwd)));
+    }
+    if let Some(branch) = &meta.git_branch {
+        row("Branch", format!("<code>{}</code>", escape(branch)));
+    }
+    if let Some(source_file) = &meta.source_file {
+        row(
+            "Trace File",
+            format!("<code>{}</code>", escape(source_file)),
+        );
+    }
+    row("Start", format_time(meta.start_time));
+    if let Some(end) = meta.end_time {
+        row("End", format_time(end));
+    }
+    for resumed in &meta.continuations {
+        row(
+            "Resumed",
+            format!(
+                "{} as session <code>{}</code>",
+                format_time(resumed.start_time),
+                escape(&resumed.session_id)
+            ),
+        );
+    }
+    row("Duration", format_duration(summary.duration_secs));
+    row("Messages", transcript.stats.message_count.to_string());
+    row("Files Touched", transcript.stats.files_touched.to_string());
+    if !transcript.subagents.is_empty() {
+        row("Subagents", transcript.subagents.len().to_string());
+    }
+    let total = TokenUsage {
+        input_tokens: transcript.stats.total_input_tokens,
+        output_tokens: transcript.stats.total_output_tokens,
+        ..Default::default()
+    };
+    if !total.is_empty() {
+        row("Tokens", format_usage(&total, summary.cost));
+    }
+    out.push_str("</dl>\n</header>\n");
+}
+
+/// Render the messages of `transcript`, anchored as `<prefix><n>`, with each subagent
+/// after the tool call that started it.
+fn render_messages(out: &mut String, transcript: &Transcript, prefix: &str, prices: &PriceTable) {
+    let costs: HashMap<usize, Option<f64>> = billed_messages(transcript)
+        .into_iter()
+        .map(|m| (m.index, prices.usage_cost(m.model, m.usage)))
+        .collect();
+    let mut resumes = transcript.meta.continuations.iter().peekable();
+
+    for (index, message) in transcript.messages.iter().enumerate() {
+        while let Some(resumed) = resumes.next_if(|c| c.start_time <= message.timestamp) {
+            out.push_str(&format!(
+                "<div class=\"resumed\">Resumed as session <code>{}</code> at {}</div>\n",
+                escape(&resumed.session_id),
+                format_time(resumed.start_time)
+            ));
+        }
+
+        let (class, role) = match message.role {
+            Role::User => ("user", "User"),
+            Role::Assistant => ("assistant", "Assistant"),
+            Role::System => ("system", "System"),
+        };
+        let anchor = escape(&format!("{}{}", prefix, index + 1));
+        out.push_str(&format!(
+            "<section class=\"message {}\" id=\"{}\">\n<div class=\"message-head\">\
+             <a class=\"anchor\" href=\"#{}\">#{}</a> <span class=\"role\">{}</span> \
+             <time datetime=\"{}\">{}</time>",
+            class,
+            anchor,
+            anchor,
+            index + 1,
+            role,
+            message.timestamp.to_rfc3339(),
+            format_time(message.timestamp)
+        ));
+        if let Some(model) = &message.model {
+            out.push_str(&format!(" <span class=\"model\">{}</span>", escape(model)));
+        }
+        if let Some(usage) = &message.usage {
+            let cost = costs.get(&index).copied().flatten();
+            out.push_str(&format!(
+                " <span class=\"usage\">{}</span>",
+                format_usage(usage, cost)
+            ));
+        }
+        out.push_str("</div>\n");
+
+        for block in &message.content {
+            render_block(out, block);
+            if let ContentBlock::ToolUse { id, .. } = block {
+                for subagent in transcript.subagents_started_by(id) {
+                    render_subagent(out, subagent, prices);
+                }
+            }
+        }
+        out.push_str("</section>\n");
+    }
+
+    for subagent in transcript.unplaced_subagents() {
+        render_subagent(out, subagent, prices);
+    }
+}
+
+fn render_subagent(out: &mut String, subagent: &Transcript, prices: &PriceTable) {
+    let id = subagent.meta.agent_id.as_deref().unwrap_or("subagent");
+    out.push_str(&format!(
+        "<details class=\"subagent\">\n<summary>Subagent <code>{}</code> · {} messages</summary>\n",
+        escape(id),
+        subagent.stats.message_count
+    ));
+    render_messages(out, subagent, &format!("{}-m", id), prices);
+    out.push_str("</details>\n");
+}
+
+fn render_block(out: &mut String, block: &ContentBlock) {
+    match block {
+        ContentBlock::Text { text } => {
+            out.push_str(&format!("<div class=\"text\">{}</div>\n", escape(text)));
+        }
+        ContentBlock::Thinking { thinking } => {
+            out.push_str(&format!(
+                "<details class=\"thinking\"><summary>Thinking</summary>\
+                 <div class=\"text\">{}</div></details>\n",
+                escape(thinking)
+            ));
+        }
+        ContentBlock::ToolUse { id, name, input } => {
+            let brief = tool_brief(input)
+                .map(|b| format!(" {}", escape(&crate::utils::safe_truncate(b, 80))))
+                .unwrap_or_default();
+            out.push_str(&format!(
+                "<details class=\"tool-use\"><summary>Tool <b>{}</b>{} <code>{}</code></summary>\
+                 <pre>{}</pre></details>\n",
+                escape(name),
+                brief,
+                escape(id),
+                escape(&serde_json::to_string_pretty(input).unwrap_or_default())
+            ));
+        }
+        ContentBlock::ToolResult {
+            content, is_error, ..
+        } => {
+            let label = if *is_error {
+                "<span class=\"error\">Error</span>"
+            } else {
+                "Result"
+            };
+            if content.is_empty() {
+                out.push_str(&format!(
+                    "<div class=\"tool-result\">{} (empty)</div>\n",
+                    label
+                ));
+            } else {
+                out.push_str(&format!(
+                    "<details class=\"tool-result\"><summary>{} · {} lines</summary>\
+                     <pre>{}</pre></details>\n",
+                    label,
+                    content.lines().count(),
+                    escape(content)
+                ));
+            }
+        }
+        ContentBlock::FileOperation {
+            operation,
+            file_path,
+            content,
+            old_content,
+        } => render_file_operation(out, operation, file_path, content, old_content),
+        ContentBlock::Command {
+            command,
+            output,
+            exit_code,
+        } => {
+            let status = match exit_code {
+                Some(code) if *code != 0 => format!(" <span class=\"error\">exit {}</span>", code),
+                _ => String::new(),
+            };
+            let summary = format!("<code>$ {}</code>{}", escape(command), status);
+            match output {
+                Some(output) if !output.is_empty() => out.push_str(&format!(
+                    "<details class=\"command\"><summary>{}</summary><pre>{}</pre></details>\n",
+                    summary,
+                    escape(output)
+                )),
+                _ => out.push_str(&format!("<div class=\"command\">{}</div>\n", summary)),
+            }
+        }
+        ContentBlock::Code { code, language } => {
+            out.push_str(&format!(
+                "<pre class=\"code\"><code data-lang=\"{}\">{}</code></pre>\n",
+                escape(language.as_deref().unwrap_or("text")),
+                highlight(code, language.as_deref())
+            ));
+        }
+    }
+}
+
+fn render_file_operation(
+    out: &mut String,
+    operation: &FileOpType,
+    file_path: &str,
+    content: &Option<String>,
+    old_content: &Option<String>,
+) {
+    let path = format!("<code>{}</code>", escape(file_path));
+    let lines = match (operation, content) {
+        (FileOpType::Create, Some(content)) => content
+            .lines()
+            .map(|l| DiffLine::Added(l.to_string()))
+            .collect(),
+        (FileOpType::Edit, Some(content)) => {
+            diff_lines(old_content.as_deref().unwrap_or(""), content)
+        }
+        _ => {
+            let verb = match operation {
+                FileOpType::Create => "Created",
+                FileOpType::Edit => "Edited",
+                FileOpType::Read => "Read",
+                FileOpType::Delete => "Deleted",
+            };
+            out.push_str(&format!("<div class=\"file-op\">{} {}</div>\n", verb, path));
+            return;
+        }
+    };
+
+    let added = lines
+        .iter()
+        .filter(|l| matches!(l, DiffLine::Added(_)))
+        .count();
+    let removed = lines
+        .iter()
+        .filter(|l| matches!(l, DiffLine::Removed(_)))
+        .count();
+    let verb = if *operation == FileOpType::Create {
+        "Created"
+    } else {
+        "Edited"
+    };
+    out.push_str(&format!(
+        "<details class=\"file-op\" open><summary>{} {} <span class=\"stat\">\
+         <span class=\"add\">+{}</span> <span class=\"del\">-{}</span></span></summary>\
+         <pre class=\"diff\">",
+        verb, path, added, removed
+    ));
+    for line in &lines {
+        let (class, sign, text) = match line {
+            DiffLine::Context(text) => ("ctx", ' ', text),
+            DiffLine::Removed(text) => ("del", '-', text),
+            DiffLine::Added(text) => ("add", '+', text),
+        };
+        out.push_str(&format!(
+            "<span class=\"{}\">{}{}</span>",
+            class,
+            sign,
+            escape(text)
+        ));
+    }
+    out.push_str("</pre></details>\n");
+}
+
+/// The most telling string argument of a tool call, shown next to its name.
+fn tool_brief(input: &serde_json::Value) -> Option<&str> {
+    [
+        "description",
+        "command",
+        "file_path",
+        "notebook_path",
+        "pattern",
+        "path",
+        "url",
+        "query",
+    ]
+    .iter()
+    .find_map(|key| input.get(key).and_then(|v| v.as_str()))
+}
+
+/// `1,234 in · 56 out · 10,000 cached · 500 cache write · $0.0123`
+fn format_usage(usage: &TokenUsage, cost: Option<f64>) -> String {
+    let mut parts = Vec::new();
+    let mut part = |tokens: Option<u64>, label: &str| {
+        if let Some(n) = tokens.filter(|&n| n > 0) {
+            parts.push(format!("{} {}", thousands(n), label));
+        }
+    };
+    part(usage.input_tokens, "in");
+    part(usage.output_tokens, "out");
+    part(usage.cache_read_tokens, "cached");
+    part(usage.cache_creation_tokens, "cache write");
+    if let Some(cost) = cost {
+        parts.push(if cost < 0.01 {
+            format!("${:.4}", cost)
+        } else {
+            format!("${:.2}", cost)
+        });
+    }
+    parts.join(" · ")
+}
+
+fn thousands(n: u64) -> String {
+    let digits = n.to_string();
+    let mut out = String::new();
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            out.push(',');
+        }
+        out.push(c);
+    }
+    out
+}
+
+fn format_time(time: chrono::DateTime<chrono::Utc>) -> String {
+    time.format("%Y-%m-%d %H:%M:%S").to_string()
+}
+
+fn format_duration(secs: i64) -> String {
+    let secs = secs.max(0);
+    if secs >= 3600 {
+        format!("{}h {:02}m", secs / 3600, secs % 3600 / 60)
+    } else if secs >= 60 {
+        format!("{}m {:02}s", secs / 60, secs % 60)
+    } else {
+        format!("{}s", secs)
+    }
+}
+
+/// Escape text for HTML element content and attribute values.
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// Keywords highlighted in code blocks, across the common languages.
+const KEYWORDS: &[&str] = &[
+    "abstract",
+    "and",
+    "as",
+    "async",
+    "await",
+    "break",
+    "case",
+    "catch",
+    "class",
+    "const",
+    "continue",
+    "crate",
+    "def",
+    "default",
+    "defer",
+    "del",
+    "do",
+    "dyn",
+    "elif",
+    "else",
+    "enum",
+    "except",
+    "export",
+    "extends",
+    "extern",
+    "false",
+    "False",
+    "finally",
+    "fn",
+    "for",
+    "from",
+    "func",
+    "function",
+    "go",
+    "if",
+    "impl",
+    "implements",
+    "import",
+    "in",
+    "interface",
+    "is",
+    "lambda",
+    "let",
+    "loop",
+    "match",
+    "mod",
+    "move",
+    "mut",
+    "new",
+    "nil",
+    "None",
+    "not",
+    "null",
+    "or",
+    "package",
+    "pass",
+    "private",
+    "protected",
+    "pub",
+    "public",
+    "raise",
+    "ref",
+    "return",
+    "self",
+    "Self",
+    "static",
+    "struct",
+    "super",
+    "switch",
+    "this",
+    "throw",
+    "trait",
+    "true",
+    "True",
+    "try",
+    "type",
+    "typeof",
+    "undefined",
+    "unsafe",
+    "use",
+    "var",
+    "void",
+    "where",
+    "while",
+    "with",
+    "yield",
+];
+
+/// Comment syntax of a code block's language, from its name or file extension.
+fn language_syntax(language: &str) -> Option<CommentSyntax> {
+    let language = language.to_ascii_lowercase();
+    let extension = match language.as_str() {
+        "rust" => "rs",
+        "python" | "python3" => "py",
+        "javascript" | "node" => "js",
+        "typescript" => "ts",
+        "shell" | "console" | "shellscript" => "sh",
+        "golang" => "go",
+        "c++" => "cpp",
+        "csharp" | "c#" => "cs",
+        "ruby" => "rb",
+        "kotlin" => "kt",
+        "haskell" => "hs",
+        "docker" => "dockerfile",
+        other => other,
+    };
+    CommentSyntax::from_path(Path::new(&format!("code.{}", extension)))
+}
+
+/// `code` as escaped HTML with keywords, strings, numbers and comments in `<span>`s.
+/// Text with no known language is only escaped.
+fn highlight(code: &str, language: Option<&str>) -> String {
+    let Some(language) = language.filter(|l| !matches!(*l, "" | "text" | "plaintext")) else {
+        return escape(code);
+    };
+    let syntax = language_syntax(language);
+    let line_comment = syntax.as_ref().and_then(|s| s.line_prefix());
+    let block_comment = syntax.as_ref().and_then(|s| s.block_delimiters());
+
+    let mut out = String::with_capacity(code.len() * 2);
+    let span = |out: &mut String, class: &str, text: &str| {
+        out.push_str(&format!(
+            "<span class=\"{}\">{}</span>",
+            class,
+            escape(text)
+        ));
+    };
+    let mut i = 0;
+    while i < code.len() {
+        let rest = &code[i..];
+        if let Some(prefix) = line_comment.filter(|p| rest.starts_with(p)) {
+            let end = rest.find('\n').unwrap_or(rest.len()).max(prefix.len());
+            span(&mut out, "com", &rest[..end]);
+            i += end;
+            continue;
+        }
+        if let Some((open, close)) = block_comment.filter(|(open, _)| rest.starts_with(open)) {
+            let end = rest[open.len()..]
+                .find(close)
+                .map_or(rest.len(), |e| open.len() + e + close.len());
+            span(&mut out, "com", &rest[..end]);
+            i += end;
+            continue;
+        }
+
+        let c = rest.chars().next().expect("rest is not empty");
+        let end = if matches!(c, '"' | '\'' | '`') {
+            string_end(rest, c).map(|end| ("str", end))
+        } else if c.is_ascii_digit() {
+            let end = rest
+                .find(|ch: char| !(ch.is_ascii_alphanumeric() || ch == '.' || ch == '_'))
+                .unwrap_or(rest.len());
+            Some(("num", end))
+        } else if c.is_alphabetic() || c == '_' {
+            let end = rest
+                .find(|ch: char| !(ch.is_alphanumeric() || ch == '_'))
+                .unwrap_or(rest.len());
+            if KEYWORDS.contains(&&rest[..end]) {
+                Some(("kw", end))
+            } else {
+                out.push_str(&escape(&rest[..end]));
+                i += end;
+                continue;
+            }
+        } else {
+            None
+        };
+        match end {
+            Some((class, end)) => {
+                span(&mut out, class, &rest[..end]);
+                i += end;
+            }
+            None => {
+                out.push_str(&escape(&rest[..c.len_utf8()]));
+                i += c.len_utf8();
+            }
+        }
+    }
+    out
+}
+
+/// End of the string literal opening `text` with `quote`, if it closes (on the same line,
+/// except for backtick strings).
+fn string_end(text: &str, quote: char) -> Option<usize> {
+    let mut escaped = false;
+    for (i, c) in text.char_indices().skip(1) {
+        if escaped {
+            escaped = false;
+        } else if c == '\\' {
+            escaped = true;
+        } else if c == quote {
+            return Some(i + c.len_utf8());
+        } else if c == '\n' && quote != '`' {
+            return None;
+        }
+    }
+    None
+}
@@ -6,6 +6,13 @@
 
 pub mod claude;
 pub mod codex;
+pub mod continuations;
+pub mod html;
+pub mod query;
+pub mod subagents;
+
+pub use continuations::{link_continuations, parse_conversation};
+pub use subagents::{link_subagents, parse_transcript_with_subagents, subagent_files};
 
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
@@ -53,6 +60,9 @@ pub enum ContentBlock {
         content: String,
         #[serde(default)]
         is_error: bool,
+        /// Subagent that produced the result (Claude Code `Task` calls).
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        agent_id: Option<String>,
     },
 
     /// Code block with optional language.
@@ -128,7 +138,10 @@ pub struct TranscriptMessage {
 }
 
 /// Token usage information for a message.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+///
+/// `input_tokens` excludes input read from or written to the prompt cache, which is
+/// counted in `cache_read_tokens` and `cache_creation_tokens`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct TokenUsage {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub input_tokens: Option<u64>,
@@ -140,6 +153,50 @@ pub struct TokenUsage {
     pub cache_creation_tokens: Option<u64>,
 }
 
+impl TokenUsage {
+    /// Add `other`'s counts to this usage.
+    pub fn add(&mut self, other: &TokenUsage) {
+        fn add(a: &mut Option<u64>, b: Option<u64>) {
+            if let Some(b) = b {
+                *a.get_or_insert(0) += b;
+            }
+        }
+        add(&mut self.input_tokens, other.input_tokens);
+        add(&mut self.output_tokens, other.output_tokens);
+        add(&mut self.cache_read_tokens, other.cache_read_tokens);
+        add(&mut self.cache_creation_tokens, other.cache_creation_tokens);
+    }
+
+    /// The counts by which this usage exceeds `earlier` (never negative).
+    pub fn minus(&self, earlier: &TokenUsage) -> TokenUsage {
+        let sub = |a: Option<u64>, b: Option<u64>| a.map(|a| a.saturating_sub(b.unwrap_or(0)));
+        TokenUsage {
+            input_tokens: sub(self.input_tokens, earlier.input_tokens),
+            output_tokens: sub(self.output_tokens, earlier.output_tokens),
+            cache_read_tokens: sub(self.cache_read_tokens, earlier.cache_read_tokens),
+            cache_creation_tokens: sub(self.cache_creation_tokens, earlier.cache_creation_tokens),
+        }
+    }
+
+    /// Whether no tokens were counted.
+    pub fn is_empty(&self) -> bool {
+        self.total() == 0
+    }
+
+    /// All tokens counted, of every kind.
+    pub fn total(&self) -> u64 {
+        [
+            self.input_tokens,
+            self.output_tokens,
+            self.cache_read_tokens,
+            self.cache_creation_tokens,
+        ]
+        .iter()
+        .map(|t| t.unwrap_or(0))
+        .sum()
+    }
+}
+
 /// Metadata about a transcript session.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TranscriptMeta {
@@ -175,6 +232,42 @@ pub struct TranscriptMeta {
     /// Source file path for this transcript.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub source_file: Option<String>,
+
+    /// Subagent ID, if this is a subagent's transcript (a Claude Code sidechain).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub agent_id: Option<String>,
+
+    /// Session that started this subagent.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent_session_id: Option<String>,
+
+    /// The parent's tool call that started this subagent (set by [`link_subagents`]).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent_tool_use_id: Option<String>,
+
+    /// UUID of the message in an earlier trace file that this one continues, when a
+    /// Claude Code session was resumed or compacted into a new file.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub continues_from: Option<String>,
+
+    /// Later trace files of the conversation merged into this transcript (set by
+    /// [`link_continuations`]).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub continuations: Vec<Continuation>,
+}
+
+/// A later trace file of a conversation, merged into the transcript it continues.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Continuation {
+    /// Session ID of the later file.
+    pub session_id: String,
+
+    /// Path of the later file.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source_file: Option<String>,
+
+    /// When the conversation was resumed: the first message the later file added.
+    pub start_time: DateTime<Utc>,
 }
 
 /// A complete transcript representing a conversation session.
@@ -188,6 +281,10 @@ pub struct Transcript {
 
     /// Summary statistics.
     pub stats: TranscriptStats,
+
+    /// Transcripts of the subagents this session started (set by [`link_subagents`]).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub subagents: Vec<Transcript>,
 }
 
 /// Statistics about a transcript.
@@ -226,6 +323,10 @@ pub struct TranscriptSummary {
     /// Agent tool.
     pub agent_tool: String,
 
+    /// Subagent ID, if this is a subagent's transcript.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub agent_id: Option<String>,
+
     /// Session slug (if available).
     #[serde(skip_serializing_if = "Option::is_none")]
     pub slug: Option<String>,
@@ -237,6 +338,15 @@ pub struct TranscriptSummary {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub end_time: Option<DateTime<Utc>>,
 
+    /// Time spent in the conversation, in seconds: the breaks before it was resumed don't
+    /// count.
+    #[serde(default)]
+    pub duration_secs: i64,
+
+    /// Session IDs of the later trace files merged into this conversation.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub continued_in: Vec<String>,
+
     /// Number of messages.
     pub message_count: usize,
 
@@ -255,6 +365,10 @@ pub struct TranscriptSummary {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub last_message_preview: Option<String>,
 
+    /// Estimated cost in US dollars (see [`crate::pricing`]), if any usage was priced.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cost: Option<f64>,
+
     /// Source file path.
     pub source_file: String,
 }
@@ -266,6 +380,7 @@ impl Transcript {
             meta,
             messages: Vec::new(),
             stats: TranscriptStats::default(),
+            subagents: Vec::new(),
         }
     }
 
@@ -314,8 +429,8 @@ impl Transcript {
         self.messages.sort_by_key(|m| m.timestamp);
     }
 
-    /// Get a summary of this transcript.
-    pub fn summary(&self) -> TranscriptSummary {
+    /// Get a summary of this transcript, with its cost estimated from `prices`.
+    pub fn summary(&self, prices: &crate::pricing::PriceTable) -> TranscriptSummary {
         let primary_model = self
             .messages
             .iter()
@@ -356,18 +471,60 @@ impl Transcript {
         TranscriptSummary {
             session_id: self.meta.session_id.clone(),
             agent_tool: self.meta.agent_tool.clone(),
+            agent_id: self.meta.agent_id.clone(),
             slug: self.meta.slug.clone(),
             start_time: self.meta.start_time,
             end_time: self.meta.end_time,
+            duration_secs: self.duration().num_seconds(),
+            continued_in: self
+                .meta
+                .continuations
+                .iter()
+                .map(|c| c.session_id.clone())
+                .collect(),
             message_count: self.stats.message_count,
             files_touched: self.stats.files_touched,
             primary_model,
             all_models,
             last_message_preview,
+            cost: prices.transcript_cost(self),
             source_file: self.meta.source_file.clone().unwrap_or_default(),
         }
     }
 
+    /// Time from the first to the last message, leaving out the breaks before each
+    /// continuation.
+    pub fn duration(&self) -> chrono::Duration {
+        let mut total = chrono::Duration::zero();
+        let mut span: Option<(DateTime<Utc>, DateTime<Utc>)> = None;
+        let mut resumes = self
+            .meta
+            .continuations
+            .iter()
+            .map(|c| c.start_time)
+            .peekable();
+        for message in &self.messages {
+            let t = message.timestamp;
+            let mut resumed = false;
+            while resumes.next_if(|&r| r <= t).is_some() {
+                resumed = true;
+            }
+            if resumed {
+                if let Some((start, end)) = span.take() {
+                    total += end - start;
+                }
+            }
+            span = Some(match span {
+                Some((start, _)) => (start, t),
+                None => (t, t),
+            });
+        }
+        if let Some((start, end)) = span {
+            total += end - start;
+        }
+        total
+    }
+
     /// Get messages in a given time range.
     pub fn messages_in_range(
         &self,
@@ -426,13 +583,45 @@ pub trait TranscriptParser: Send + Sync {
 }
 
 /// Parse transcripts from a directory, automatically detecting the format.
+///
+/// Transcripts are cached in the directory's `.ai-blame.ddb`; only files that changed since
+/// they were cached are parsed again. Set `AI_BLAME_NO_CACHE` to always parse every file.
 pub fn parse_transcripts_from_directory(dir: &Path) -> anyhow::Result<Vec<Transcript>> {
-    let claude_parser = claude::ClaudeTranscriptParser::new();
-    let codex_parser = codex::CodexTranscriptParser::new();
+    let files = collect_transcript_files(dir)?;
+    let cache = open_transcript_cache(dir);
 
     let mut all_transcripts = Vec::new();
+    let mut stale_files = files.clone();
+    if let Some(cache) = &cache {
+        match load_fresh_transcripts(cache, &files) {
+            Ok((fresh, stale)) => {
+                all_transcripts.extend(fresh);
+                stale_files = stale;
+            }
+            Err(e) => eprintln!("Warning: Failed to read cached transcripts: {:#}", e),
+        }
+    }
 
-    // Collect all .jsonl files
+    let parsed = parse_transcript_files(&stale_files);
+    if let Some(cache) = &cache {
+        let entries: Vec<_> = stale_files
+            .iter()
+            .map(|f| f.as_path())
+            .zip(parsed.iter().map(|t| t.as_ref()))
+            .collect();
+        if let Err(e) = cache.store_transcripts(&entries) {
+            eprintln!("Warning: Failed to cache transcripts: {:#}", e);
+        }
+    }
+    all_transcripts.extend(parsed.into_iter().flatten());
+
+    // Sort by start time, newest first
+    all_transcripts.sort_by_key(|t| std::cmp::Reverse(t.meta.start_time));
+    Ok(all_transcripts)
+}
+
+/// All `.jsonl` files under `dir`, sorted.
+fn collect_transcript_files(dir: &Path) -> anyhow::Result<Vec<std::path::PathBuf>> {
     fn collect_jsonl_files(dir: &Path, files: &mut Vec<std::path::PathBuf>) -> anyhow::Result<()> {
         if !dir.is_dir() {
             return Ok(());
@@ -451,27 +640,104 @@ pub fn parse_transcripts_from_directory(dir: &Path) -> anyhow::Result<Vec<Transc
 
     let mut files = Vec::new();
     collect_jsonl_files(dir, &mut files)?;
+    files.sort();
+    Ok(files)
+}
+
+/// The transcript cache for `dir`, or `None` when caching is disabled or unavailable.
+fn open_transcript_cache(dir: &Path) -> Option<crate::cache::CacheManager> {
+    if !dir.is_dir() || std::env::var("AI_BLAME_NO_CACHE").is_ok() {
+        return None;
+    }
+    match crate::cache::CacheManager::open(dir) {
+        Ok(cache) => Some(cache),
+        Err(e) => {
+            eprintln!(
+                "Warning: Cache unavailable for {:?}, parsing without it: {:#}",
+                dir, e
+            );
+            None
+        }
+    }
+}
 
-    for file in files {
+/// Bring cached transcripts (and their search index) in line with `files`: parse files that
+/// changed and forget files that no longer exist.
+fn refresh_transcript_cache(
+    cache: &crate::cache::CacheManager,
+    files: &[std::path::PathBuf],
+) -> anyhow::Result<()> {
+    let metadata = cache.transcript_file_metadata()?;
+    let present: std::collections::HashSet<&std::path::PathBuf> = files.iter().collect();
+    let removed: Vec<_> = metadata
+        .keys()
+        .filter(|f| !present.contains(f))
+        .cloned()
+        .collect();
+    if !removed.is_empty() {
+        cache.invalidate_files(&removed)?;
+    }
+
+    let stale: Vec<_> = files
+        .iter()
+        .filter(|f| crate::cache::is_transcript_stale(f, metadata.get(*f)))
+        .cloned()
+        .collect();
+    let parsed = parse_transcript_files(&stale);
+    let entries: Vec<_> = stale
+        .iter()
+        .map(|f| f.as_path())
+        .zip(parsed.iter().map(|t| t.as_ref()))
+        .collect();
+    cache.store_transcripts(&entries)
+}
+
+/// Split `files` into transcripts loaded from the cache and files that need parsing.
+fn load_fresh_transcripts(
+    cache: &crate::cache::CacheManager,
+    files: &[std::path::PathBuf],
+) -> anyhow::Result<(Vec<Transcript>, Vec<std::path::PathBuf>)> {
+    let metadata = cache.transcript_file_metadata()?;
+    let (stale, fresh): (Vec<_>, Vec<_>) = files
+        .iter()
+        .cloned()
+        .partition(|file| crate::cache::is_transcript_stale(file, metadata.get(file)));
+
+    let mut cached = cache.get_cached_transcripts(&fresh)?;
+    let mut transcripts = Vec::new();
+    let mut stale = stale;
+    for file in fresh {
+        match cached.remove(&file) {
+            Some(transcript) => transcripts.extend(transcript),
+            // Listed as fresh but its rows are gone: parse it again.
+            None => stale.push(file),
+        }
+    }
+    Ok((transcripts, stale))
+}
+
+/// Parse files on the shared worker pool; results come back in file order, `None` for files
+/// no transcript parser understands.
+fn parse_transcript_files(files: &[std::path::PathBuf]) -> Vec<Option<Transcript>> {
+    let claude_parser = claude::ClaudeTranscriptParser::new();
+    let codex_parser = codex::CodexTranscriptParser::new();
+
+    crate::parallel::map_ordered(files, |file| {
         // Try Claude parser first
-        if claude_parser.can_parse(&file).unwrap_or(false) {
-            if let Ok(transcript) = claude_parser.parse(&file) {
-                all_transcripts.push(transcript);
-                continue;
+        if claude_parser.can_parse(file).unwrap_or(false) {
+            if let Ok(transcript) = claude_parser.parse(file) {
+                return Some(transcript);
             }
         }
 
         // Try Codex parser
-        if codex_parser.can_parse(&file).unwrap_or(false) {
-            if let Ok(transcript) = codex_parser.parse(&file) {
-                all_transcripts.push(transcript);
+        if codex_parser.can_parse(file).unwrap_or(false) {
+            if let Ok(transcript) = codex_parser.parse(file) {
+                return Some(transcript);
             }
         }
-    }
-
-    // Sort by start time, newest first
-    all_transcripts.sort_by_key(|t| std::cmp::Reverse(t.meta.start_time));
-    Ok(all_transcripts)
+        None
+    })
 }
 
 /// Parse a single transcript file, auto-detecting the format.
@@ -493,11 +759,11 @@ pub fn parse_transcript(path: &Path) -> anyhow::Result<Transcript> {
 /// Search criteria for transcript search
 #[derive(Debug, Clone, Default)]
 pub struct TranscriptSearchCriteria {
-    /// Full-text search in message content
+    /// Full-text search in message content, in the [`query`] syntax
     pub query: Option<String>,
-    /// Treat query as a regex pattern (default: substring match)
+    /// Treat query as a regex pattern and scan every message instead of using the index
     pub use_regex: bool,
-    /// Case-sensitive search (default: case-insensitive)
+    /// Case-sensitive substring scan instead of the (case-insensitive) index
     pub case_sensitive: bool,
     /// Filter by session ID pattern
     pub session_id_pattern: Option<String>,
@@ -509,6 +775,98 @@ pub struct TranscriptSearchCriteria {
     pub since: Option<DateTime<Utc>>,
     /// Filter by start time (before)
     pub until: Option<DateTime<Utc>>,
+    /// Only transcripts with a file operation on a path containing this (case-insensitive)
+    pub touched_file: Option<String>,
+    /// Only transcripts that called this tool (case-insensitive)
+    pub used_tool: Option<String>,
+    /// Only transcripts with a command that exited non-zero
+    pub failed_command: bool,
+    /// Only transcripts with a tool result flagged as an error
+    pub tool_error: bool,
+    /// Filter by git branch pattern
+    pub git_branch: Option<String>,
+    /// Filter by working directory pattern
+    pub cwd: Option<String>,
+    /// Minimum total input + output tokens
+    pub min_tokens: Option<u64>,
+    /// Maximum total input + output tokens
+    pub max_tokens: Option<u64>,
+}
+
+impl TranscriptSearchCriteria {
+    /// Whether a transcript passes the filters on its content and metadata (everything
+    /// except the query).
+    fn matches_transcript(&self, t: &Transcript) -> bool {
+        let contains = |value: Option<&str>, pattern: &Option<String>| match pattern {
+            Some(pattern) => value.is_some_and(|v| v.contains(pattern.as_str())),
+            None => true,
+        };
+        let contains_lower = |value: &str, pattern: &Option<String>| match pattern {
+            Some(pattern) => value.to_lowercase().contains(&pattern.to_lowercase()),
+            None => true,
+        };
+
+        if !contains(Some(&t.meta.session_id), &self.session_id_pattern)
+            || !contains_lower(&t.meta.agent_tool, &self.agent_tool)
+            || !contains(t.meta.git_branch.as_deref(), &self.git_branch)
+            || !contains(t.meta.cwd.as_deref(), &self.cwd)
+        {
+            return false;
+        }
+        if self.since.is_some_and(|since| t.meta.start_time < since)
+            || self.until.is_some_and(|until| t.meta.start_time > until)
+        {
+            return false;
+        }
+
+        let tokens =
+            t.stats.total_input_tokens.unwrap_or(0) + t.stats.total_output_tokens.unwrap_or(0);
+        if self.min_tokens.is_some_and(|min| tokens < min)
+            || self.max_tokens.is_some_and(|max| tokens > max)
+        {
+            return false;
+        }
+
+        if self.model.is_some()
+            && !t.messages.iter().any(|msg| {
+                msg.model
+                    .as_deref()
+                    .is_some_and(|m| contains_lower(m, &self.model))
+            })
+        {
+            return false;
+        }
+
+        let blocks = || t.messages.iter().flat_map(|m| m.content.iter());
+        if self.touched_file.is_some()
+            && !blocks().any(|b| {
+                matches!(b, ContentBlock::FileOperation { file_path, .. }
+                    if contains_lower(file_path, &self.touched_file))
+            })
+        {
+            return false;
+        }
+        if let Some(tool) = &self.used_tool {
+            if !blocks().any(
+                |b| matches!(b, ContentBlock::ToolUse { name, .. } if name.eq_ignore_ascii_case(tool)),
+            ) {
+                return false;
+            }
+        }
+        if self.failed_command
+            && !blocks().any(
+                |b| matches!(b, ContentBlock::Command { exit_code: Some(code), .. } if *code != 0),
+            )
+        {
+            return false;
+        }
+        if self.tool_error
+            && !blocks().any(|b| matches!(b, ContentBlock::ToolResult { is_error: true, .. }))
+        {
+            return false;
+        }
+        true
+    }
 }
 
 /// A single match within a transcript
@@ -518,6 +876,9 @@ pub struct SearchMatch {
     pub transcript: TranscriptSummary,
     /// Matching snippets with context
     pub matches: Vec<MatchSnippet>,
+    /// Relevance score of an indexed search (higher is better)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub score: Option<f64>,
 }
 
 /// A snippet showing matched content with context
@@ -709,11 +1070,122 @@ fn extract_matching_snippets(transcript: &Transcript, matcher: &QueryMatcher) ->
     snippets
 }
 
-/// Search transcripts in a directory matching the given criteria
+/// Search transcripts in a directory matching the given criteria.
+///
+/// Queries go through the full-text index kept in the directory's cache (an in-memory one
+/// when caching is disabled) and results are ranked by relevance; without a query,
+/// transcripts passing the filters are returned newest first. Regex and case-sensitive
+/// searches fall back to scanning every message. Session costs are estimated from `prices`.
 pub fn search_transcripts(
     dir: &Path,
     criteria: &TranscriptSearchCriteria,
     limit: usize,
+    prices: &crate::pricing::PriceTable,
+) -> anyhow::Result<SearchResult> {
+    let query = match &criteria.query {
+        Some(_) if criteria.use_regex || criteria.case_sensitive => {
+            return scan_transcripts(dir, criteria, limit, prices);
+        }
+        Some(text) => Some(query::QueryNode::parse(text)?),
+        None => None,
+    };
+
+    let files = collect_transcript_files(dir)?;
+    let cache = match open_transcript_cache(dir) {
+        Some(cache) => cache,
+        None => crate::cache::CacheManager::open_in_memory()?,
+    };
+    refresh_transcript_cache(&cache, &files)?;
+
+    let hits =
+        cache.search_transcript_index(query.as_ref(), criteria, MAX_SNIPPETS_PER_TRANSCRIPT)?;
+    let total_matches = hits.len();
+    let shown = if limit == 0 {
+        &hits[..]
+    } else {
+        &hits[..limit.min(hits.len())]
+    };
+
+    let paths: Vec<_> = shown.iter().map(|h| h.source_file.clone()).collect();
+    let mut transcripts = cache.get_cached_transcripts(&paths)?;
+    let terms = query
+        .as_ref()
+        .map(|q| q.ranking_terms())
+        .unwrap_or_default();
+    let mut matching_transcripts = Vec::new();
+    for hit in shown {
+        let Some(Some(transcript)) = transcripts.remove(&hit.source_file) else {
+            continue;
+        };
+        let matches = hit
+            .blocks
+            .iter()
+            .filter_map(|&(message, block)| {
+                let msg = transcript.messages.get(message)?;
+                Some(index_snippet(msg, msg.content.get(block)?, &terms))
+            })
+            .collect();
+        matching_transcripts.push(SearchMatch {
+            transcript: transcript.summary(prices),
+            matches,
+            score: query.as_ref().map(|_| hit.score),
+        });
+    }
+
+    Ok(SearchResult {
+        matching_transcripts,
+        total_matches,
+    })
+}
+
+/// Snippet for a block found by the index, centred on the first query word it contains.
+fn index_snippet(
+    msg: &TranscriptMessage,
+    block: &ContentBlock,
+    terms: &[&query::QueryNode],
+) -> MatchSnippet {
+    let block_type = match block {
+        ContentBlock::Text { .. } => "text",
+        ContentBlock::Thinking { .. } => "thinking",
+        ContentBlock::Code { .. } => "code",
+        ContentBlock::ToolUse { .. } => "tool",
+        ContentBlock::FileOperation { .. } => "file",
+        ContentBlock::Command { .. } => "command",
+        ContentBlock::ToolResult { .. } => "result",
+    };
+    let text = query::block_search_text(block);
+    let words = terms.iter().filter_map(|term| match term {
+        query::QueryNode::Term(word) | query::QueryNode::Prefix(word) => Some(word.as_str()),
+        query::QueryNode::Phrase(words) => words.first().map(|w| w.as_str()),
+        _ => None,
+    });
+    // Filter-only queries have no words; show the start of the block instead.
+    let snippet = words
+        .chain([""])
+        .find_map(|word| {
+            QueryMatcher::Substring {
+                query: word.to_string(),
+                case_sensitive: false,
+            }
+            .extract_snippet(&text, SNIPPET_CONTEXT_CHARS)
+        })
+        .unwrap_or_default();
+
+    MatchSnippet {
+        role: msg.role.to_string(),
+        timestamp: msg.timestamp,
+        block_type: block_type.to_string(),
+        snippet,
+    }
+}
+
+/// Search by parsing every transcript and scanning its messages (regex and case-sensitive
+/// queries, which the index cannot answer).
+fn scan_transcripts(
+    dir: &Path,
+    criteria: &TranscriptSearchCriteria,
+    limit: usize,
+    prices: &crate::pricing::PriceTable,
 ) -> anyhow::Result<SearchResult> {
     // Build matcher if query is present
     let matcher = if let Some(ref query) = criteria.query {
@@ -730,54 +1202,7 @@ pub fn search_transcripts(
     let mut transcripts = parse_transcripts_from_directory(dir)?;
 
     // Apply metadata filters first
-    transcripts.retain(|t| {
-        // Filter by session ID pattern
-        if let Some(ref pattern) = criteria.session_id_pattern {
-            if !t.meta.session_id.contains(pattern) {
-                return false;
-            }
-        }
-
-        // Filter by agent tool
-        if let Some(ref tool) = criteria.agent_tool {
-            if !t
-                .meta
-                .agent_tool
-                .to_lowercase()
-                .contains(&tool.to_lowercase())
-            {
-                return false;
-            }
-        }
-
-        // Filter by timestamp range
-        if let Some(since) = criteria.since {
-            if t.meta.start_time < since {
-                return false;
-            }
-        }
-        if let Some(until) = criteria.until {
-            if t.meta.start_time > until {
-                return false;
-            }
-        }
-
-        // Filter by model
-        if let Some(ref model) = criteria.model {
-            let model_lower = model.to_lowercase();
-            let has_model = t.messages.iter().any(|msg| {
-                msg.model
-                    .as_ref()
-                    .map(|m| m.to_lowercase().contains(&model_lower))
-                    .unwrap_or(false)
-            });
-            if !has_model {
-                return false;
-            }
-        }
-
-        true
-    });
+    transcripts.retain(|t| criteria.matches_transcript(t));
 
     // Now search for matches and collect snippets
     let mut matches: Vec<SearchMatch> = Vec::new();
@@ -787,15 +1212,17 @@ pub fn search_transcripts(
             let snippets = extract_matching_snippets(&transcript, matcher);
             if !snippets.is_empty() {
                 matches.push(SearchMatch {
-                    transcript: transcript.summary(),
+                    transcript: transcript.summary(prices),
                     matches: snippets,
+                    score: None,
                 });
             }
         } else {
             // No query, just return the transcript with no snippets
             matches.push(SearchMatch {
-                transcript: transcript.summary(),
+                transcript: transcript.summary(prices),
                 matches: Vec::new(),
+                score: None,
             });
         }
     }
@@ -831,6 +1258,11 @@ mod tests {
             start_time: Utc::now(),
             end_time: None,
             source_file: None,
+            agent_id: None,
+            parent_session_id: None,
+            parent_tool_use_id: None,
+            continues_from: None,
+            continuations: Vec::new(),
         };
 
         let mut transcript = Transcript::new(meta);
@@ -0,0 +1,472 @@
+//! Query language for indexed transcript search.
+//!
+//! A query is matched against individual content blocks (a message's text, a tool call, a
+//! command, ...). Supported syntax:
+//!
+//! - `word` matches blocks containing the word; `migrat*` matches any word with that prefix
+//! - `"exact phrase"` matches consecutive words
+//! - `a b` and `a AND b` require both, `a OR b` either, `NOT a` or `-a` excludes
+//! - parentheses group sub-queries
+//! - `role:user`, `tool:Bash`, `file:src/main.rs` and `block:thinking` filter on the block
+//!
+//! Words are compared after [`tokenize`]: lowercased and split on anything that is not a
+//! letter, digit or underscore.
+
+use super::{ContentBlock, Role};
+use anyhow::{anyhow, bail, Result};
+
+/// Longest token kept in the index; longer runs (hashes, base64) are truncated.
+const MAX_TOKEN_CHARS: usize = 64;
+
+/// Split text into lowercase search tokens.
+pub fn tokenize(text: &str) -> Vec<String> {
+    text.split(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .filter(|t| !t.is_empty())
+        .map(|t| {
+            t.chars()
+                .take(MAX_TOKEN_CHARS)
+                .collect::<String>()
+                .to_lowercase()
+        })
+        .collect()
+}
+
+/// The text of a content block that search looks at.
+pub fn block_search_text(block: &ContentBlock) -> String {
+    match block {
+        ContentBlock::Text { text } => text.clone(),
+        ContentBlock::Thinking { thinking } => thinking.clone(),
+        ContentBlock::Code { code, .. } => code.clone(),
+        ContentBlock::ToolUse { name, input, .. } => format!("{}\n{}", name, input),
+        ContentBlock::FileOperation { file_path, .. } => file_path.clone(),
+        ContentBlock::Command {
+            command, output, ..
+        } => match output {
+            Some(output) => format!("{}\n{}", command, output),
+            None => command.clone(),
+        },
+        ContentBlock::ToolResult { content, .. } => content.clone(),
+    }
+}
+
+/// Block types accepted by `block:`, as stored in the cache.
+pub const BLOCK_TYPES: &[&str] = &[
+    "text",
+    "thinking",
+    "tool_use",
+    "tool_result",
+    "code",
+    "file_operation",
+    "command",
+];
+
+/// A field filter on a content block.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FieldFilter {
+    /// Role of the message the block belongs to.
+    Role(Role),
+    /// Tool name of a tool call (case-insensitive).
+    Tool(String),
+    /// Substring of the path of a file operation (case-insensitive).
+    File(String),
+    /// Block type, one of [`BLOCK_TYPES`].
+    Block(&'static str),
+}
+
+/// A parsed search query.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QueryNode {
+    /// A single token.
+    Term(String),
+    /// Any token starting with this prefix.
+    Prefix(String),
+    /// Consecutive tokens.
+    Phrase(Vec<String>),
+    Field(FieldFilter),
+    And(Vec<QueryNode>),
+    Or(Vec<QueryNode>),
+    Not(Box<QueryNode>),
+}
+
+impl QueryNode {
+    /// Parse a query string.
+    pub fn parse(query: &str) -> Result<Self> {
+        let tokens = lex(query)?;
+        let mut parser = Parser { tokens, pos: 0 };
+        let node = parser.parse_or()?;
+        if let Some(token) = parser.tokens.get(parser.pos) {
+            bail!("Unexpected {} in search query", token.describe());
+        }
+        let node = node.ok_or_else(|| anyhow!("Search query has no searchable words"))?;
+        if !node.has_positive_clause() {
+            bail!("Search query needs at least one word or filter that is not negated");
+        }
+        Ok(node)
+    }
+
+    /// Whether the query can match without relying only on exclusions.
+    fn has_positive_clause(&self) -> bool {
+        match self {
+            QueryNode::Not(_) => false,
+            QueryNode::And(nodes) => nodes.iter().any(|n| n.has_positive_clause()),
+            QueryNode::Or(nodes) => nodes.iter().all(|n| n.has_positive_clause()),
+            _ => true,
+        }
+    }
+
+    /// Terms, prefixes and phrases that count towards ranking (everything not under `NOT`).
+    pub fn ranking_terms(&self) -> Vec<&QueryNode> {
+        let mut out = Vec::new();
+        self.collect_ranking_terms(&mut out);
+        out
+    }
+
+    fn collect_ranking_terms<'a>(&'a self, out: &mut Vec<&'a QueryNode>) {
+        match self {
+            QueryNode::Term(_) | QueryNode::Prefix(_) | QueryNode::Phrase(_) => out.push(self),
+            QueryNode::And(nodes) | QueryNode::Or(nodes) => {
+                for node in nodes {
+                    node.collect_ranking_terms(out);
+                }
+            }
+            QueryNode::Field(_) | QueryNode::Not(_) => {}
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Word(String),
+    Phrase(String),
+    Field(String, String),
+    And,
+    Or,
+    Not,
+    LParen,
+    RParen,
+}
+
+impl Token {
+    fn describe(&self) -> String {
+        match self {
+            Token::Word(w) => format!("'{}'", w),
+            Token::Phrase(p) => format!("\"{}\"", p),
+            Token::Field(f, v) => format!("'{}:{}'", f, v),
+            Token::And => "AND".to_string(),
+            Token::Or => "OR".to_string(),
+            Token::Not => "NOT".to_string(),
+            Token::LParen => "'('".to_string(),
+            Token::RParen => "')'".to_string(),
+        }
+    }
+}
+
+const FIELDS: &[&str] = &["role", "tool", "file", "block"];
+
+fn lex(query: &str) -> Result<Vec<Token>> {
+    let chars: Vec<char> = query.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    let read_quoted = |start: usize| -> Result<(String, usize)> {
+        let end = chars[start + 1..]
+            .iter()
+            .position(|&c| c == '"')
+            .map(|p| start + 1 + p)
+            .ok_or_else(|| anyhow!("Unterminated quote in search query"))?;
+        Ok((chars[start + 1..end].iter().collect(), end + 1))
+    };
+
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c == '(' {
+            tokens.push(Token::LParen);
+            i += 1;
+        } else if c == ')' {
+            tokens.push(Token::RParen);
+            i += 1;
+        } else if c == '"' {
+            let (phrase, next) = read_quoted(i)?;
+            tokens.push(Token::Phrase(phrase));
+            i = next;
+        } else if c == '-' && chars.get(i + 1).is_some_and(|n| !n.is_whitespace()) {
+            tokens.push(Token::Not);
+            i += 1;
+        } else {
+            let word_end = |from: usize| {
+                let mut end = from;
+                while end < chars.len()
+                    && !chars[end].is_whitespace()
+                    && !matches!(chars[end], '(' | ')')
+                {
+                    end += 1;
+                }
+                end
+            };
+
+            // `field:value` or `field:"quoted value"`
+            let name_end = (i..chars.len())
+                .find(|&j| !chars[j].is_ascii_alphabetic())
+                .unwrap_or(chars.len());
+            let name: String = chars[i..name_end].iter().collect::<String>().to_lowercase();
+            if chars.get(name_end) == Some(&':') && FIELDS.contains(&name.as_str()) {
+                let (value, next) = if chars.get(name_end + 1) == Some(&'"') {
+                    read_quoted(name_end + 1)?
+                } else {
+                    let end = word_end(name_end + 1);
+                    (chars[name_end + 1..end].iter().collect(), end)
+                };
+                if value.is_empty() {
+                    bail!("Missing value after '{}:' in search query", name);
+                }
+                tokens.push(Token::Field(name, value));
+                i = next;
+                continue;
+            }
+
+            let end = word_end(i);
+            let word: String = chars[i..end].iter().collect();
+            tokens.push(match word.as_str() {
+                "AND" => Token::And,
+                "OR" => Token::Or,
+                "NOT" => Token::Not,
+                _ => Token::Word(word),
+            });
+            i = end;
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn parse_or(&mut self) -> Result<Option<QueryNode>> {
+        let mut nodes: Vec<QueryNode> = self.parse_and()?.into_iter().collect();
+        while self.peek() == Some(&Token::Or) {
+            self.pos += 1;
+            match self.parse_and()? {
+                Some(node) => nodes.push(node),
+                None => bail!("Missing search term after OR"),
+            }
+        }
+        Ok(match nodes.len() {
+            0 => None,
+            1 => nodes.pop(),
+            _ => Some(QueryNode::Or(nodes)),
+        })
+    }
+
+    fn parse_and(&mut self) -> Result<Option<QueryNode>> {
+        let mut nodes = Vec::new();
+        loop {
+            match self.peek() {
+                None | Some(Token::Or) | Some(Token::RParen) => break,
+                Some(Token::And) => {
+                    self.pos += 1;
+                    if matches!(self.peek(), None | Some(Token::Or) | Some(Token::RParen)) {
+                        bail!("Missing search term after AND");
+                    }
+                }
+                _ => nodes.extend(self.parse_unary()?),
+            }
+        }
+        Ok(match nodes.len() {
+            0 => None,
+            1 => nodes.pop(),
+            _ => Some(QueryNode::And(nodes)),
+        })
+    }
+
+    fn parse_unary(&mut self) -> Result<Option<QueryNode>> {
+        if self.peek() == Some(&Token::Not) {
+            self.pos += 1;
+            return match self.parse_unary()? {
+                Some(node) => Ok(Some(QueryNode::Not(Box::new(node)))),
+                None => bail!("Missing search term after NOT"),
+            };
+        }
+        self.parse_primary()
+    }
+
+    fn parse_primary(&mut self) -> Result<Option<QueryNode>> {
+        let Some(token) = self.tokens.get(self.pos).cloned() else {
+            return Ok(None);
+        };
+        self.pos += 1;
+        match token {
+            Token::LParen => {
+                let inner = self.parse_or()?;
+                if self.peek() != Some(&Token::RParen) {
+                    bail!("Missing ')' in search query");
+                }
+                self.pos += 1;
+                Ok(inner)
+            }
+            Token::Word(word) => Ok(word_node(&word)),
+            Token::Phrase(phrase) => Ok(phrase_node(tokenize(&phrase))),
+            Token::Field(field, value) => Ok(Some(QueryNode::Field(field_filter(&field, &value)?))),
+            other => bail!("Unexpected {} in search query", other.describe()),
+        }
+    }
+}
+
+fn word_node(word: &str) -> Option<QueryNode> {
+    if let Some(prefix) = word.strip_suffix('*') {
+        let tokens = tokenize(prefix);
+        if let [token] = tokens.as_slice() {
+            return Some(QueryNode::Prefix(token.clone()));
+        }
+    }
+    phrase_node(tokenize(word))
+}
+
+/// One token is a term; several (e.g. `src/main.rs`) must appear together.
+fn phrase_node(mut tokens: Vec<String>) -> Option<QueryNode> {
+    match tokens.len() {
+        0 => None,
+        1 => tokens.pop().map(QueryNode::Term),
+        _ => Some(QueryNode::Phrase(tokens)),
+    }
+}
+
+fn field_filter(field: &str, value: &str) -> Result<FieldFilter> {
+    let lower = value.to_lowercase();
+    match field {
+        "role" => match lower.as_str() {
+            "user" => Ok(FieldFilter::Role(Role::User)),
+            "assistant" => Ok(FieldFilter::Role(Role::Assistant)),
+            "system" => Ok(FieldFilter::Role(Role::System)),
+            _ => bail!(
+                "Unknown role '{}' (expected user, assistant or system)",
+                value
+            ),
+        },
+        "tool" => Ok(FieldFilter::Tool(lower)),
+        "file" => Ok(FieldFilter::File(lower)),
+        "block" => {
+            let canonical = match lower.as_str() {
+                "tool" => "tool_use",
+                "result" => "tool_result",
+                "file" => "file_operation",
+                other => other,
+            };
+            BLOCK_TYPES
+                .iter()
+                .find(|t| **t == canonical)
+                .map(|t| FieldFilter::Block(t))
+                .ok_or_else(|| {
+                    anyhow!(
+                        "Unknown block type '{}' (expected one of: {})",
+                        value,
+                        BLOCK_TYPES.join(", ")
+                    )
+                })
+        }
+        _ => unreachable!("lexer only produces known fields"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn term(t: &str) -> QueryNode {
+        QueryNode::Term(t.to_string())
+    }
+
+    #[test]
+    fn test_parse_boolean_queries() {
+        assert_eq!(QueryNode::parse("Cache").unwrap(), term("cache"));
+        assert_eq!(
+            QueryNode::parse("cache migration").unwrap(),
+            QueryNode::And(vec![term("cache"), term("migration")])
+        );
+        assert_eq!(
+            QueryNode::parse("(cache OR index) AND -duckdb").unwrap(),
+            QueryNode::And(vec![
+                QueryNode::Or(vec![term("cache"), term("index")]),
+                QueryNode::Not(Box::new(term("duckdb"))),
+            ])
+        );
+        assert_eq!(
+            QueryNode::parse("\"schema version\" migrat*").unwrap(),
+            QueryNode::And(vec![
+                QueryNode::Phrase(vec!["schema".to_string(), "version".to_string()]),
+                QueryNode::Prefix("migrat".to_string()),
+            ])
+        );
+        // Words that split into several tokens must appear together.
+        assert_eq!(
+            QueryNode::parse("src/main.rs").unwrap(),
+            QueryNode::Phrase(vec!["src".into(), "main".into(), "rs".into()])
+        );
+    }
+
+    #[test]
+    fn test_parse_field_filters() {
+        assert_eq!(
+            QueryNode::parse("role:User tool:Bash file:\"my dir/a.rs\" block:result").unwrap(),
+            QueryNode::And(vec![
+                QueryNode::Field(FieldFilter::Role(Role::User)),
+                QueryNode::Field(FieldFilter::Tool("bash".to_string())),
+                QueryNode::Field(FieldFilter::File("my dir/a.rs".to_string())),
+                QueryNode::Field(FieldFilter::Block("tool_result")),
+            ])
+        );
+        // Unknown prefixes are ordinary words.
+        assert_eq!(
+            QueryNode::parse("http://example.com").unwrap(),
+            QueryNode::Phrase(vec!["http".into(), "example".into(), "com".into()])
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        for query in [
+            "",
+            "!!!",
+            "NOT cache",
+            "-cache -index",
+            "cache OR",
+            "(cache",
+            "cache)",
+            "\"cache",
+            "role:robot",
+            "block:image",
+            "tool:",
+        ] {
+            assert!(
+                QueryNode::parse(query).is_err(),
+                "{:?} should not parse",
+                query
+            );
+        }
+    }
+
+    #[test]
+    fn test_tokenize() {
+        assert_eq!(
+            tokenize("Fix cache_path() in src/cache/mod.rs — größer!"),
+            vec![
+                "fix",
+                "cache_path",
+                "in",
+                "src",
+                "cache",
+                "mod",
+                "rs",
+                "größer"
+            ]
+        );
+    }
+}
@@ -0,0 +1,205 @@
+//! Subagent transcripts and the sessions that started them.
+//!
+//! Claude Code runs each subagent (a `Task` tool call) in a sidechain: a separate JSONL
+//! file, `agent-<id>.jsonl` next to the session's file or `<session>/subagents/` below it,
+//! whose records carry the parent's session ID. [`link_subagents`] puts those transcripts
+//! back under the session that started them and finds the tool call that did, either from
+//! the `agentId` on the call's result or by matching the call's prompt with the subagent's
+//! first message.
+
+use super::{ContentBlock, Role, Transcript};
+use std::collections::HashSet;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+
+/// Attach subagent transcripts to the sessions that started them.
+///
+/// Subagent transcripts whose parent session is among `transcripts` move into that
+/// transcript's `subagents`, in start order, with `parent_tool_use_id` set when the
+/// starting tool call is found. Subagents of sessions that aren't present stay at the top
+/// level.
+pub fn link_subagents(transcripts: Vec<Transcript>) -> Vec<Transcript> {
+    let (subagents, mut sessions): (Vec<_>, Vec<_>) = transcripts
+        .into_iter()
+        .partition(|t| t.meta.agent_id.is_some());
+
+    let mut orphans = Vec::new();
+    for mut subagent in subagents {
+        let parent_session = subagent
+            .meta
+            .parent_session_id
+            .as_ref()
+            .unwrap_or(&subagent.meta.session_id);
+        let candidates: Vec<usize> = (0..sessions.len())
+            .filter(|&i| &sessions[i].meta.session_id == parent_session)
+            .collect();
+        if candidates.is_empty() {
+            orphans.push(subagent);
+            continue;
+        }
+
+        // A resumed session can have several files: prefer the one with the starting call,
+        // then the last one started before the subagent.
+        let started_by = candidates
+            .iter()
+            .find_map(|&i| starting_tool_use(&sessions[i], &subagent).map(|id| (i, id)));
+        let parent = match started_by {
+            Some((i, tool_use_id)) => {
+                subagent.meta.parent_tool_use_id = Some(tool_use_id);
+                i
+            }
+            None => candidates
+                .iter()
+                .copied()
+                .filter(|&i| sessions[i].meta.start_time <= subagent.meta.start_time)
+                .max_by_key(|&i| sessions[i].meta.start_time)
+                .unwrap_or(candidates[0]),
+        };
+        sessions[parent].subagents.push(subagent);
+    }
+
+    for session in &mut sessions {
+        session.subagents.sort_by_key(|s| s.meta.start_time);
+    }
+    sessions.extend(orphans);
+    sessions
+}
+
+impl Transcript {
+    /// Subagents of this session started by the tool call `tool_use_id`.
+    pub fn subagents_started_by<'a>(
+        &'a self,
+        tool_use_id: &'a str,
+    ) -> impl Iterator<Item = &'a Transcript> {
+        self.subagents
+            .iter()
+            .filter(move |s| s.meta.parent_tool_use_id.as_deref() == Some(tool_use_id))
+    }
+
+    /// Subagents whose starting tool call isn't among this session's messages; views show
+    /// them after the conversation.
+    pub fn unplaced_subagents(&self) -> impl Iterator<Item = &Transcript> {
+        let tool_use_ids: HashSet<&str> = self
+            .messages
+            .iter()
+            .flat_map(|m| &m.content)
+            .filter_map(|block| match block {
+                ContentBlock::ToolUse { id, .. } => Some(id.as_str()),
+                _ => None,
+            })
+            .collect();
+        self.subagents.iter().filter(move |s| {
+            !s.meta
+                .parent_tool_use_id
+                .as_deref()
+                .is_some_and(|id| tool_use_ids.contains(id))
+        })
+    }
+}
+
+/// The ID of the tool call in `parent` that started `subagent`.
+fn starting_tool_use(parent: &Transcript, subagent: &Transcript) -> Option<String> {
+    let blocks = || parent.messages.iter().flat_map(|m| &m.content);
+
+    let agent_id = subagent.meta.agent_id.as_deref()?;
+    let by_result = blocks().find_map(|block| match block {
+        ContentBlock::ToolResult {
+            tool_use_id,
+            agent_id: Some(id),
+            ..
+        } if id == agent_id => Some(tool_use_id.clone()),
+        _ => None,
+    });
+    if by_result.is_some() {
+        return by_result;
+    }
+
+    // Older traces don't record the agent ID: the subagent's first message is the prompt.
+    let prompt = subagent
+        .messages
+        .iter()
+        .find(|m| m.role == Role::User)?
+        .content
+        .iter()
+        .find_map(|block| match block {
+            ContentBlock::Text { text } => Some(text.trim()),
+            _ => None,
+        })?;
+    blocks().find_map(|block| match block {
+        ContentBlock::ToolUse { id, input, .. }
+            if input.get("prompt").and_then(|p| p.as_str()).map(str::trim) == Some(prompt) =>
+        {
+            Some(id.clone())
+        }
+        _ => None,
+    })
+}
+
+/// Files that may hold subagent transcripts of the session in `transcript_path`:
+/// `agent-*.jsonl` files beside it and the files in `<session>/subagents/`.
+pub fn subagent_files(transcript_path: &Path) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    let Some(dir) = transcript_path.parent() else {
+        return files;
+    };
+    let mut dirs = vec![(dir.to_path_buf(), true)];
+    if let Some(stem) = transcript_path.file_stem() {
+        dirs.push((dir.join(stem).join("subagents"), false));
+    }
+    for (dir, agent_prefix_only) in dirs {
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            if path.extension().and_then(|e| e.to_str()) == Some("jsonl")
+                && path != transcript_path
+                && (!agent_prefix_only || name.starts_with("agent-"))
+            {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+    files
+}
+
+/// Parse the transcript in `path` with the subagents it started linked in.
+pub fn parse_transcript_with_subagents(path: &Path) -> anyhow::Result<Transcript> {
+    let transcript = super::parse_transcript(path)?;
+    if transcript.meta.agent_id.is_some() {
+        return Ok(transcript);
+    }
+
+    // Only parse files whose records belong to this session.
+    let files: Vec<PathBuf> = subagent_files(path)
+        .into_iter()
+        .filter(|f| first_session_id(f).as_deref() == Some(transcript.meta.session_id.as_str()))
+        .collect();
+    let mut all = vec![transcript];
+    all.extend(
+        super::parse_transcript_files(&files)
+            .into_iter()
+            .flatten()
+            .filter(|t| t.meta.agent_id.is_some()),
+    );
+    Ok(link_subagents(all).swap_remove(0))
+}
+
+/// The `sessionId` of the first record in a Claude trace file that has one.
+fn first_session_id(path: &Path) -> Option<String> {
+    let file = std::fs::File::open(path).ok()?;
+    BufReader::new(file)
+        .lines()
+        .take(20)
+        .map_while(Result::ok)
+        .find_map(|line| {
+            let record: serde_json::Value = serde_json::from_str(line.trim()).ok()?;
+            record
+                .get("sessionId")
+                .and_then(|s| s.as_str())
+                .map(|s| s.to_string())
+        })
+}
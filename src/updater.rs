@@ -1,11 +1,14 @@
 use crate::config::resolve_sidecar_path;
 use crate::models::*;
-use anyhow::{Context, Result};
+use crate::store;
+use crate::utils::write_atomic;
+use anyhow::{bail, Context, Result};
 use regex::Regex;
+use serde::Serialize;
 use serde_yaml;
 use std::collections::HashMap;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::OnceLock;
 
 pub fn generate_curation_yaml(history: &FileHistory) -> Result<String> {
@@ -26,6 +29,33 @@ pub fn generate_curation_json(history: &FileHistory) -> Result<String> {
     Ok(json)
 }
 
+/// Remove a top-level `edit_history:` key (and its indented/list body) from YAML text.
+fn strip_yaml_edit_history(content: &str) -> String {
+    if !content.contains("edit_history:") {
+        return content.to_string();
+    }
+
+    let mut new_lines = Vec::new();
+    let mut in_curation = false;
+
+    for line in content.split('\n') {
+        if line.starts_with("edit_history:") {
+            in_curation = true;
+            continue;
+        }
+        if in_curation {
+            // Check if this is a new top-level key (not indented and not empty)
+            if !line.is_empty() && !line.starts_with(' ') && !line.starts_with('-') {
+                in_curation = false;
+                new_lines.push(line);
+            }
+            continue;
+        }
+        new_lines.push(line);
+    }
+    new_lines.join("\n")
+}
+
 pub fn append_yaml(
     file_path: &Path,
     history: &FileHistory,
@@ -35,32 +65,11 @@ pub fn append_yaml(
         return Ok((false, format!("File not found: {:?}", file_path)));
     }
 
-    let mut content = fs::read_to_string(file_path)
+    let content = fs::read_to_string(file_path)
         .with_context(|| format!("Failed to read file: {:?}", file_path))?;
 
     // Check if edit_history already exists and remove it
-    if content.contains("edit_history:") {
-        let lines: Vec<&str> = content.split('\n').collect();
-        let mut new_lines = Vec::new();
-        let mut in_curation = false;
-
-        for line in lines {
-            if line.starts_with("edit_history:") {
-                in_curation = true;
-                continue;
-            }
-            if in_curation {
-                // Check if this is a new top-level key (not indented and not empty)
-                if !line.is_empty() && !line.starts_with(' ') && !line.starts_with('-') {
-                    in_curation = false;
-                    new_lines.push(line);
-                }
-                continue;
-            }
-            new_lines.push(line);
-        }
-        content = new_lines.join("\n");
-    }
+    let mut content = strip_yaml_edit_history(&content);
 
     // Generate new curation history
     let curation_yaml = generate_curation_yaml(history)?;
@@ -81,7 +90,7 @@ pub fn append_yaml(
         return Ok((true, new_content));
     }
 
-    fs::write(file_path, new_content)
+    write_atomic(file_path, new_content)
         .with_context(|| format!("Failed to write file: {:?}", file_path))?;
     Ok((true, format!("Updated: {:?}", file_path)))
 }
@@ -113,37 +122,285 @@ pub fn append_json(
         return Ok((true, new_content));
     }
 
-    fs::write(file_path, new_content)
+    write_atomic(file_path, new_content)
         .with_context(|| format!("Failed to write file: {:?}", file_path))?;
     Ok((true, format!("Updated: {:?}", file_path)))
 }
 
-pub fn write_sidecar(
+fn toml_string(value: &str) -> String {
+    // JSON string escapes are a subset of TOML basic-string escapes.
+    serde_json::to_string(value).unwrap_or_else(|_| "\"\"".to_string())
+}
+
+pub fn generate_curation_toml(history: &FileHistory) -> String {
+    let mut out = String::new();
+    for (i, event) in history.events.iter().enumerate() {
+        if i > 0 {
+            out.push('\n');
+        }
+        out.push_str("[[tool.ai-blame.edit_history]]\n");
+        out.push_str(&format!(
+            "timestamp = {}\n",
+            event
+                .timestamp
+                .to_rfc3339_opts(chrono::SecondsFormat::AutoSi, true)
+        ));
+        if let Some(model) = &event.model {
+            out.push_str(&format!("model = {}\n", toml_string(model)));
+        }
+        if let Some(action) = &event.action {
+            let action = match action {
+                CurationAction::Created => "CREATED",
+                CurationAction::Edited => "EDITED",
+            };
+            out.push_str(&format!("action = {}\n", toml_string(action)));
+        }
+        if let Some(description) = &event.description {
+            out.push_str(&format!("description = {}\n", toml_string(description)));
+        }
+        if let Some(agent_tool) = &event.agent_tool {
+            out.push_str(&format!("agent_tool = {}\n", toml_string(agent_tool)));
+        }
+        if let Some(agent_version) = &event.agent_version {
+            out.push_str(&format!("agent_version = {}\n", toml_string(agent_version)));
+        }
+    }
+    out
+}
+
+/// Remove all `[[tool.ai-blame.edit_history]]` tables from TOML text.
+///
+/// Other keys under `[tool.ai-blame]` are left untouched.
+fn strip_toml_edit_history(content: &str) -> String {
+    let mut new_lines = Vec::new();
+    let mut in_history = false;
+
+    for line in content.split('\n') {
+        let trimmed = line.trim();
+        if trimmed.starts_with('[') {
+            // Headers may carry a trailing `# comment`.
+            let header: String = trimmed
+                .split('#')
+                .next()
+                .unwrap_or("")
+                .chars()
+                .filter(|c| !c.is_whitespace())
+                .collect();
+            in_history = header == "[[tool.ai-blame.edit_history]]";
+            if in_history {
+                continue;
+            }
+        }
+        if !in_history {
+            new_lines.push(line);
+        }
+    }
+    new_lines.join("\n")
+}
+
+pub fn append_toml(
     file_path: &Path,
     history: &FileHistory,
-    sidecar_pattern: &str,
     dry_run: bool,
 ) -> Result<(bool, String)> {
-    let sidecar_path = resolve_sidecar_path(file_path, sidecar_pattern);
+    if !file_path.exists() {
+        return Ok((false, format!("File not found: {:?}", file_path)));
+    }
 
-    // Merge with existing sidecar if it exists
-    let mut existing_events: Vec<CurationEvent> = Vec::new();
-    if sidecar_path.exists() {
-        let existing_content = fs::read_to_string(&sidecar_path)?;
-        if let Ok(existing_data) = serde_yaml::from_str::<serde_yaml::Value>(&existing_content) {
-            if let Some(edit_history) = existing_data.get("edit_history") {
-                if let Ok(events) =
-                    serde_yaml::from_value::<Vec<CurationEvent>>(edit_history.clone())
-                {
-                    existing_events = events;
+    let content = fs::read_to_string(file_path)
+        .with_context(|| format!("Failed to read file: {:?}", file_path))?;
+
+    let mut content = strip_toml_edit_history(&content);
+    let trimmed_len = content.trim_end_matches('\n').len();
+    content.truncate(trimmed_len);
+    if !content.is_empty() {
+        content.push_str("\n\n");
+    }
+
+    let new_content = format!("{}{}", content, generate_curation_toml(history));
+
+    if dry_run {
+        return Ok((true, new_content));
+    }
+
+    write_atomic(file_path, new_content)
+        .with_context(|| format!("Failed to write file: {:?}", file_path))?;
+    Ok((true, format!("Updated: {:?}", file_path)))
+}
+
+/// Split a document into its YAML front matter (without delimiters) and body.
+///
+/// Returns `None` when the document does not start with a `---` delimiter line.
+fn split_front_matter(content: &str) -> Option<(&str, &str)> {
+    let rest = content
+        .strip_prefix("---\n")
+        .or_else(|| content.strip_prefix("---\r\n"))?;
+
+    let mut offset = 0;
+    for line in rest.split_inclusive('\n') {
+        let trimmed = line.trim_end();
+        if trimmed == "---" || trimmed == "..." {
+            return Some((&rest[..offset], &rest[offset + line.len()..]));
+        }
+        offset += line.len();
+    }
+    None
+}
+
+/// Write `edit_history` into the YAML front matter of a Markdown/Quarto/Jekyll document.
+///
+/// Documents without front matter get a new front matter block at the top.
+pub fn append_front_matter(
+    file_path: &Path,
+    history: &FileHistory,
+    dry_run: bool,
+) -> Result<(bool, String)> {
+    if !file_path.exists() {
+        return Ok((false, format!("File not found: {:?}", file_path)));
+    }
+
+    let content = fs::read_to_string(file_path)
+        .with_context(|| format!("Failed to read file: {:?}", file_path))?;
+
+    let curation_yaml = generate_curation_yaml(history)?;
+
+    let new_content = match split_front_matter(&content) {
+        Some((front, body)) => {
+            let mut front = strip_yaml_edit_history(front);
+            let trimmed_len = front.trim_end_matches('\n').len();
+            front.truncate(trimmed_len);
+            if !front.is_empty() {
+                front.push('\n');
+            }
+            format!("---\n{}{}---\n{}", front, curation_yaml, body)
+        }
+        None => format!("---\n{}---\n\n{}", curation_yaml, content),
+    };
+
+    if dry_run {
+        return Ok((true, new_content));
+    }
+
+    write_atomic(file_path, new_content)
+        .with_context(|| format!("Failed to write file: {:?}", file_path))?;
+    Ok((true, format!("Updated: {:?}", file_path)))
+}
+
+/// Serialize a notebook the way nbformat does: sorted keys, one-space indent.
+fn to_notebook_json(notebook: &serde_json::Value) -> Result<String> {
+    let mut buf = Vec::new();
+    let formatter = serde_json::ser::PrettyFormatter::with_indent(b" ");
+    let mut serializer = serde_json::Serializer::with_formatter(&mut buf, formatter);
+    notebook.serialize(&mut serializer)?;
+    Ok(format!("{}\n", String::from_utf8(buf)?))
+}
+
+/// Annotate a Jupyter notebook in place.
+///
+/// File-level history goes under `metadata.ai_blame.edit_history` (a top-level
+/// `edit_history` key would fail nbformat validation). With `cell_history`, events
+/// carrying a `cell_id` are also written to the matching cell's `metadata.ai_blame`.
+pub fn append_ipynb(
+    file_path: &Path,
+    history: &FileHistory,
+    cell_history: bool,
+    dry_run: bool,
+) -> Result<(bool, String)> {
+    if !file_path.exists() {
+        return Ok((false, format!("File not found: {:?}", file_path)));
+    }
+
+    let content = fs::read_to_string(file_path)
+        .with_context(|| format!("Failed to read file: {:?}", file_path))?;
+
+    let mut notebook: serde_json::Value = serde_json::from_str(&content)
+        .with_context(|| format!("Failed to parse notebook: {:?}", file_path))?;
+    let Some(nb) = notebook.as_object_mut() else {
+        return Ok((false, format!("Not a notebook object: {:?}", file_path)));
+    };
+
+    let events_data = serde_json::to_value(&history.events)?;
+    let metadata = nb
+        .entry("metadata")
+        .or_insert_with(|| serde_json::json!({}));
+    if let Some(metadata) = metadata.as_object_mut() {
+        metadata.insert(
+            "ai_blame".to_string(),
+            serde_json::json!({ "edit_history": events_data }),
+        );
+    }
+
+    if cell_history {
+        if let Some(cells) = nb.get_mut("cells").and_then(|c| c.as_array_mut()) {
+            for cell in cells {
+                let Some(cell_id) = cell.get("id").and_then(|id| id.as_str()) else {
+                    // nbformat < 4.5 cells have no stable id to attribute against.
+                    continue;
+                };
+                let cell_events: Vec<CurationEvent> = history
+                    .events
+                    .iter()
+                    .filter(|e| e.cell_id.as_deref() == Some(cell_id))
+                    .map(|e| CurationEvent {
+                        cell_id: None,
+                        ..e.clone()
+                    })
+                    .collect();
+
+                let Some(cell) = cell.as_object_mut() else {
+                    continue;
+                };
+                let cell_metadata = cell
+                    .entry("metadata")
+                    .or_insert_with(|| serde_json::json!({}));
+                if let Some(cell_metadata) = cell_metadata.as_object_mut() {
+                    if cell_events.is_empty() {
+                        cell_metadata.remove("ai_blame");
+                    } else {
+                        cell_metadata.insert(
+                            "ai_blame".to_string(),
+                            serde_json::json!({ "edit_history": cell_events }),
+                        );
+                    }
                 }
             }
         }
     }
 
-    // Merge and deduplicate by timestamp
-    let mut all_events = existing_events;
-    all_events.extend(history.events.clone());
+    let new_content = to_notebook_json(&notebook)?;
+
+    if dry_run {
+        return Ok((true, new_content));
+    }
+
+    write_atomic(file_path, new_content)
+        .with_context(|| format!("Failed to write file: {:?}", file_path))?;
+    Ok((true, format!("Updated: {:?}", file_path)))
+}
+
+/// Dispatch an in-place append based on `FileRule.format`.
+fn append_for_format(
+    file_path: &Path,
+    history: &FileHistory,
+    rule: &FileRule,
+    dry_run: bool,
+) -> Result<(bool, String)> {
+    match rule.format.as_str() {
+        "json" => append_json(file_path, history, dry_run),
+        "ipynb" => append_ipynb(file_path, history, rule.cell_history, dry_run),
+        "toml" => append_toml(file_path, history, dry_run),
+        "front-matter" | "frontmatter" => append_front_matter(file_path, history, dry_run),
+        _ => append_yaml(file_path, history, dry_run),
+    }
+}
+
+/// Merge previously written events with new ones, deduplicated by timestamp and sorted.
+pub(crate) fn merge_events(
+    existing: Vec<CurationEvent>,
+    new_events: &[CurationEvent],
+) -> Vec<CurationEvent> {
+    let mut all_events = existing;
+    all_events.extend(new_events.iter().cloned());
 
     let mut seen_timestamps = std::collections::HashSet::new();
     let mut merged_events = Vec::new();
@@ -157,6 +414,33 @@ pub fn write_sidecar(
 
     // Sort by timestamp
     merged_events.sort_by_key(|e| e.timestamp);
+    merged_events
+}
+
+pub fn write_sidecar(
+    file_path: &Path,
+    history: &FileHistory,
+    sidecar_pattern: &str,
+    dry_run: bool,
+) -> Result<(bool, String)> {
+    let sidecar_path = resolve_sidecar_path(file_path, sidecar_pattern);
+
+    // Merge with existing sidecar if it exists
+    let mut existing_events: Vec<CurationEvent> = Vec::new();
+    if sidecar_path.exists() {
+        let existing_content = fs::read_to_string(&sidecar_path)?;
+        if let Ok(existing_data) = serde_yaml::from_str::<serde_yaml::Value>(&existing_content) {
+            if let Some(edit_history) = existing_data.get("edit_history") {
+                if let Ok(events) =
+                    serde_yaml::from_value::<Vec<CurationEvent>>(edit_history.clone())
+                {
+                    existing_events = events;
+                }
+            }
+        }
+    }
+
+    let merged_events = merge_events(existing_events, &history.events);
 
     // Include source file reference
     let mut sidecar_data = serde_yaml::Mapping::new();
@@ -188,14 +472,284 @@ pub fn write_sidecar(
     if let Some(parent) = sidecar_path.parent() {
         fs::create_dir_all(parent)?;
     }
-    fs::write(&sidecar_path, new_content)?;
+    write_atomic(&sidecar_path, new_content)
+        .with_context(|| format!("Failed to write sidecar: {:?}", sidecar_path))?;
     Ok((true, format!("Wrote sidecar: {:?}", sidecar_path)))
 }
 
+/// Render a comment template once per event.
+///
+/// Supported placeholders: `{timestamp}`, `{date}`, `{model}`, `{action}`, `{agent_tool}`,
+/// `{agent_version}`, `{description}` and `{cell_id}`. Missing values render as empty strings.
+/// The template is scanned once, so placeholder-like text inside a value is kept as is;
+/// an unknown placeholder is an error.
+pub fn render_comment_template(template: &str, history: &FileHistory) -> Result<String> {
+    static PLACEHOLDER_RE: OnceLock<Regex> = OnceLock::new();
+    let placeholder_re = PLACEHOLDER_RE.get_or_init(|| {
+        Regex::new(r"\{([A-Za-z0-9_]+)\}").expect("placeholder regex must compile")
+    });
+    const PLACEHOLDERS: [&str; 8] = [
+        "timestamp",
+        "date",
+        "model",
+        "action",
+        "agent_tool",
+        "agent_version",
+        "description",
+        "cell_id",
+    ];
+    if let Some(unknown) = placeholder_re
+        .captures_iter(template)
+        .map(|c| c.get(1).map_or("", |m| m.as_str()))
+        .find(|name| !PLACEHOLDERS.contains(name))
+    {
+        bail!("Unknown placeholder {{{}}} in comment template", unknown);
+    }
+
+    let mut out = String::new();
+    for event in &history.events {
+        let rendered =
+            placeholder_re.replace_all(template, |caps: &regex::Captures| match &caps[1] {
+                "timestamp" => event
+                    .timestamp
+                    .to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+                "date" => event.timestamp.format("%Y-%m-%d").to_string(),
+                "model" => event.model.clone().unwrap_or_default(),
+                "action" => match event.action {
+                    Some(CurationAction::Created) => "CREATED".to_string(),
+                    Some(CurationAction::Edited) => "EDITED".to_string(),
+                    None => String::new(),
+                },
+                "agent_tool" => event.agent_tool.clone().unwrap_or_default(),
+                "agent_version" => event.agent_version.clone().unwrap_or_default(),
+                "description" => event.description.clone().unwrap_or_default(),
+                "cell_id" => event.cell_id.clone().unwrap_or_default(),
+                _ => unreachable!("placeholders are checked above"),
+            });
+        out.push_str(rendered.trim_end());
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+/// Wrap the history body in an `edit_history` comment block for `syntax`.
+fn format_comment_block(body: &str, syntax: &CommentSyntax) -> String {
+    if let Some(prefix) = syntax.line_prefix() {
+        let commented: Vec<String> = body
+            .lines()
+            .map(|line| {
+                if line.is_empty() {
+                    prefix.to_string()
+                } else {
+                    format!("{} {}", prefix, line)
+                }
+            })
+            .collect();
+        return format!(
+            "{p} --- edit_history ---\n{}\n{p} --- end edit_history ---\n",
+            commented.join("\n"),
+            p = prefix
+        );
+    }
+
+    match syntax.block_delimiters() {
+        Some(("<!--", close)) => format!("<!-- edit_history\n{}{}\n", body, close),
+        Some((open, close)) => format!(
+            "{} --- edit_history ---\n{}--- end edit_history --- {}\n",
+            open, body, close
+        ),
+        None => body.to_string(),
+    }
+}
+
+/// Remove an `edit_history` comment block (marker-based or `<!-- edit_history -->`),
+/// together with the blank line that separated it from the following content.
+fn remove_comment_block(content: &str) -> String {
+    let mut new_lines = Vec::new();
+    let mut end_marker: Option<&str> = None;
+    let mut drop_blank = false;
+
+    for line in content.split('\n') {
+        if let Some(end) = end_marker {
+            if line.contains(end) {
+                end_marker = None;
+                drop_blank = true;
+            }
+            continue;
+        }
+        if line.contains("--- edit_history ---") {
+            end_marker = Some("--- end edit_history ---");
+            continue;
+        }
+        if line.trim_start().starts_with("<!-- edit_history") {
+            if line.contains("-->") {
+                drop_blank = true;
+            } else {
+                end_marker = Some("-->");
+            }
+            continue;
+        }
+        if std::mem::take(&mut drop_blank) && line.is_empty() {
+            continue;
+        }
+        new_lines.push(line);
+    }
+    new_lines.join("\n")
+}
+
+/// Index of the first line after the preamble that must stay at the very top of a file:
+/// YAML/TOML front matter, shebang, XML/PHP/doctype declarations, encoding cookie and Go
+/// build constraints.
+fn preamble_end(lines: &[&str]) -> usize {
+    static CODING_RE: OnceLock<Regex> = OnceLock::new();
+    let coding_re = CODING_RE.get_or_init(|| {
+        Regex::new(r"^[ \t\f]*#.*?coding[:=][ \t]*[-_.a-zA-Z0-9]+")
+            .expect("encoding cookie regex must compile")
+    });
+
+    // Jekyll, Hugo and Quarto only recognize front matter on the very first line.
+    if let Some(fence) = lines
+        .first()
+        .map(|l| l.trim_end())
+        .filter(|l| matches!(*l, "---" | "+++"))
+    {
+        if let Some(close) = lines[1..].iter().position(|l| {
+            let l = l.trim_end();
+            l == fence || (fence == "---" && l == "...")
+        }) {
+            return close + 2;
+        }
+    }
+
+    let mut i = 0;
+    if lines.first().is_some_and(|l| l.starts_with("#!")) {
+        i = 1;
+    }
+    while i < lines.len() {
+        let line = lines[i].trim_start();
+        if line.starts_with("<?xml")
+            || line.starts_with("<?php")
+            || line.to_ascii_lowercase().starts_with("<!doctype")
+        {
+            i += 1;
+        } else {
+            break;
+        }
+    }
+    // PEP 263: the encoding cookie must be on the first or second line.
+    if i < 2 && i < lines.len() && coding_re.is_match(lines[i]) {
+        i += 1;
+    }
+    // Go build constraints must be followed by a blank line before the package clause.
+    let mut j = i;
+    while j < lines.len()
+        && (lines[j].starts_with("//go:build") || lines[j].starts_with("// +build"))
+    {
+        j += 1;
+    }
+    if j > i {
+        i = j;
+        if i < lines.len() && lines[i].trim().is_empty() {
+            i += 1;
+        }
+    }
+    i
+}
+
+/// End (exclusive) of the comment block starting at line `start`, if there is one.
+fn leading_comment_end(lines: &[&str], start: usize) -> Option<usize> {
+    let first = lines.get(start)?.trim_start();
+    for (open, close) in [("/*", "*/"), ("(*", "*)"), ("<!--", "-->")] {
+        if let Some(rest) = first.strip_prefix(open) {
+            if rest.contains(close) {
+                return Some(start + 1);
+            }
+            return lines[start + 1..]
+                .iter()
+                .position(|line| line.contains(close))
+                .map(|offset| start + offset + 2);
+        }
+    }
+    for prefix in ["//", "#", "--", "%", ";"] {
+        if first.starts_with(prefix) {
+            let len = lines[start..]
+                .iter()
+                .take_while(|line| line.trim_start().starts_with(prefix))
+                .count();
+            return Some(start + len);
+        }
+    }
+    None
+}
+
+/// Index of the first line after the preamble, a license/SPDX/copyright header comment
+/// and a `package` declaration.
+fn after_license_end(lines: &[&str], start: usize) -> usize {
+    let skip_blank = |from: usize| {
+        from + lines[from..]
+            .iter()
+            .take_while(|l| l.trim().is_empty())
+            .count()
+    };
+
+    let mut end = start;
+    let i = skip_blank(start);
+    if let Some(comment_end) = leading_comment_end(lines, i) {
+        let is_license = lines[i..comment_end].iter().any(|line| {
+            let line = line.to_ascii_lowercase();
+            line.contains("spdx-license-identifier")
+                || line.contains("license")
+                || line.contains("copyright")
+        });
+        if is_license {
+            end = comment_end;
+        }
+    }
+    let i = skip_blank(end);
+    if lines.get(i).is_some_and(|l| l.starts_with("package ")) {
+        end = i + 1;
+    }
+    end
+}
+
+/// Insert a comment block near the top of the file according to `placement`.
+fn insert_header_block(content: &str, block: &str, placement: CommentPlacement) -> String {
+    let lines: Vec<&str> = content.split('\n').collect();
+    let preamble = preamble_end(&lines);
+    let idx = match placement {
+        CommentPlacement::AfterLicense => after_license_end(&lines, preamble),
+        _ => preamble,
+    };
+
+    let mut out = String::new();
+    for line in &lines[..idx] {
+        out.push_str(line);
+        out.push('\n');
+    }
+    // Separate the block from a license header or package line above it.
+    if idx > preamble && !lines[idx - 1].trim().is_empty() {
+        out.push('\n');
+    }
+    out.push_str(block);
+
+    let rest = lines[idx..].join("\n");
+    if !rest.is_empty() {
+        // Keep a blank line between the block and the code (so e.g. Go doesn't treat it
+        // as a package doc comment).
+        if !rest.starts_with('\n') {
+            out.push('\n');
+        }
+        out.push_str(&rest);
+    }
+    out
+}
+
 pub fn write_comment(
     file_path: &Path,
     history: &FileHistory,
     syntax: &CommentSyntax,
+    template: Option<&str>,
+    placement: CommentPlacement,
     dry_run: bool,
 ) -> Result<(bool, String)> {
     if !file_path.exists() {
@@ -205,97 +759,72 @@ pub fn write_comment(
     let mut content = fs::read_to_string(file_path)
         .with_context(|| format!("Failed to read file: {:?}", file_path))?;
 
-    // Generate the curation history YAML (without the outer key)
-    let history_yaml = serde_yaml::to_string(&history.events)?;
-
-    // Format as comment block based on syntax
-    let comment_block = match syntax {
-        CommentSyntax::Hash => {
-            let commented: Vec<String> = history_yaml
-                .lines()
-                .map(|line| {
-                    if line.is_empty() {
-                        "#".to_string()
-                    } else {
-                        format!("# {}", line)
-                    }
-                })
-                .collect();
-            format!(
-                "# --- edit_history ---\n{}\n# --- end edit_history ---\n",
-                commented.join("\n")
-            )
-        }
-        CommentSyntax::Slash => {
-            let commented: Vec<String> = history_yaml
-                .lines()
-                .map(|line| {
-                    if line.is_empty() {
-                        "//".to_string()
-                    } else {
-                        format!("// {}", line)
-                    }
-                })
-                .collect();
-            format!(
-                "// --- edit_history ---\n{}\n// --- end edit_history ---\n",
-                commented.join("\n")
-            )
-        }
-        CommentSyntax::Html => {
-            format!("<!-- edit_history\n{}-->\n", history_yaml)
-        }
+    // Either the user's template or the curation history YAML (without the outer key)
+    let body = match template {
+        Some(template) => render_comment_template(template, history)?,
+        None => serde_yaml::to_string(&history.events)?,
     };
-
-    // Remove existing edit_history comment block if present
-    if content.contains("edit_history") {
-        if matches!(syntax, CommentSyntax::Html) {
-            // Remove <!-- edit_history ... -->
-            static RE: OnceLock<Regex> = OnceLock::new();
-            let re = RE.get_or_init(|| {
-                Regex::new(r"<!--\s*edit_history.*?-->\n?")
-                    .expect("HTML edit_history regex must compile")
-            });
-            content = re.replace_all(&content, "").to_string();
-        } else {
-            // Remove marker-based blocks
-            let lines: Vec<&str> = content.split('\n').collect();
-            let mut new_lines = Vec::new();
-            let mut in_block = false;
-
-            for line in lines {
-                if line.contains("--- edit_history ---") {
-                    in_block = true;
-                    continue;
-                }
-                if line.contains("--- end edit_history ---") {
-                    in_block = false;
-                    continue;
-                }
-                if !in_block {
-                    new_lines.push(line);
-                }
-            }
-            content = new_lines.join("\n");
+    // A closing delimiter in the body would end the comment early and leave the rest of
+    // the history as code.
+    if let Some((_, close)) = syntax.block_delimiters() {
+        if body.contains(close) {
+            return Ok((
+                false,
+                format!(
+                    "Skipped {:?}: edit history contains {:?}, which would close the comment",
+                    file_path, close
+                ),
+            ));
         }
     }
+    let comment_block = format_comment_block(&body, syntax);
 
-    // Ensure content ends with newline
-    if !content.is_empty() && !content.ends_with('\n') {
-        content.push('\n');
+    // Remove existing edit_history comment block, wherever it sits
+    if content.contains("edit_history") {
+        content = remove_comment_block(&content);
     }
 
-    let new_content = format!("{}\n{}", content, comment_block);
+    let new_content = match placement {
+        CommentPlacement::Bottom => {
+            // Ensure content ends with newline
+            if !content.is_empty() && !content.ends_with('\n') {
+                content.push('\n');
+            }
+            format!("{}\n{}", content, comment_block)
+        }
+        CommentPlacement::Top | CommentPlacement::AfterLicense => {
+            insert_header_block(&content, &comment_block, placement)
+        }
+    };
 
     if dry_run {
         return Ok((true, new_content));
     }
 
-    fs::write(file_path, new_content)
+    write_atomic(file_path, new_content)
         .with_context(|| format!("Failed to write file: {:?}", file_path))?;
     Ok((true, format!("Updated: {:?}", file_path)))
 }
 
+/// The file `apply_rule` writes to for `file_path`: the file itself, its sidecar, the
+/// provenance store entry, or nothing for `skip`.
+pub fn output_path(file_path: &Path, history: &FileHistory, rule: &FileRule) -> Option<PathBuf> {
+    match rule.policy {
+        OutputPolicy::Skip => None,
+        OutputPolicy::Sidecar => {
+            let pattern = rule
+                .sidecar_pattern
+                .as_deref()
+                .unwrap_or("{stem}.history.yaml");
+            Some(resolve_sidecar_path(file_path, pattern))
+        }
+        OutputPolicy::Store => {
+            store::entry_path(&store::store_path_for_rule(rule), &history.file_path)
+        }
+        OutputPolicy::Append | OutputPolicy::Comment => Some(file_path.to_path_buf()),
+    }
+}
+
 pub fn apply_rule(
     file_path: &Path,
     history: &FileHistory,
@@ -306,16 +835,10 @@ pub fn apply_rule(
         OutputPolicy::Skip => Ok((true, format!("Skipped (policy=skip): {:?}", file_path))),
         OutputPolicy::Append => {
             if dry_run {
-                let res = if rule.format == "json" {
-                    append_json(file_path, history, true)
-                } else {
-                    append_yaml(file_path, history, true)
-                }?;
+                let res = append_for_format(file_path, history, rule, true)?;
                 Ok((res.0, format!("Would update: {:?}", file_path)))
-            } else if rule.format == "json" {
-                append_json(file_path, history, false)
             } else {
-                append_yaml(file_path, history, false)
+                append_for_format(file_path, history, rule, false)
             }
         }
         OutputPolicy::Sidecar => {
@@ -330,18 +853,33 @@ pub fn apply_rule(
                 write_sidecar(file_path, history, pattern, false)
             }
         }
+        OutputPolicy::Store => {
+            store::write_history(&store::store_path_for_rule(rule), history, dry_run)
+        }
         OutputPolicy::Comment => {
-            if let Some(ref syntax) = rule.comment_syntax {
+            let syntax = rule
+                .comment_syntax
+                .clone()
+                .or_else(|| CommentSyntax::from_path(file_path));
+            let template = rule.template.as_deref();
+            if let Some(ref syntax) = syntax {
                 if dry_run {
-                    let res = write_comment(file_path, history, syntax, true)?;
-                    Ok((res.0, format!("Would update: {:?}", file_path)))
+                    let res =
+                        write_comment(file_path, history, syntax, template, rule.placement, true)?;
+                    if !res.0 {
+                        return Ok(res);
+                    }
+                    Ok((true, format!("Would update: {:?}", file_path)))
                 } else {
-                    write_comment(file_path, history, syntax, false)
+                    write_comment(file_path, history, syntax, template, rule.placement, false)
                 }
             } else {
                 Ok((
                     false,
-                    format!("Comment policy requires comment_syntax for {:?}", file_path),
+                    format!(
+                        "Comment policy requires comment_syntax for {:?} (no built-in syntax for this file type)",
+                        file_path
+                    ),
                 ))
             }
         }
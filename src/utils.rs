@@ -45,6 +45,46 @@ pub fn validate_safe_path(path: &str) -> Result<std::path::PathBuf, String> {
     Ok(path_buf)
 }
 
+/// Write `contents` to `path` atomically: write a temp file next to it, then rename it over
+/// the original, so an interrupted write never leaves a half-written file behind.
+///
+/// Permissions of an existing file are preserved, and symlinks are written through.
+pub fn write_atomic(path: &std::path::Path, contents: impl AsRef<[u8]>) -> std::io::Result<()> {
+    use std::io::Write;
+
+    let is_symlink = std::fs::symlink_metadata(path)
+        .map(|m| m.file_type().is_symlink())
+        .unwrap_or(false);
+    let path = if is_symlink {
+        std::fs::canonicalize(path)?
+    } else {
+        path.to_path_buf()
+    };
+
+    let file_name = path.file_name().ok_or_else(|| {
+        std::io::Error::new(std::io::ErrorKind::InvalidInput, "path has no file name")
+    })?;
+    let tmp_path = path.with_file_name(format!(
+        ".{}.ai-blame-tmp-{}",
+        file_name.to_string_lossy(),
+        std::process::id()
+    ));
+
+    let result = (|| {
+        let mut file = std::fs::File::create(&tmp_path)?;
+        file.write_all(contents.as_ref())?;
+        file.sync_all()?;
+        if let Ok(meta) = std::fs::metadata(&path) {
+            std::fs::set_permissions(&tmp_path, meta.permissions())?;
+        }
+        std::fs::rename(&tmp_path, &path)
+    })();
+    if result.is_err() {
+        let _ = std::fs::remove_file(&tmp_path);
+    }
+    result
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -77,4 +117,17 @@ mod tests {
         assert!(validate_safe_path("dir/../../../etc/passwd").is_err());
         assert!(validate_safe_path("file\0.txt").is_err());
     }
+
+    #[test]
+    fn test_write_atomic_replaces_contents_and_leaves_no_temp_file() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let path = temp.path().join("file.txt");
+        std::fs::write(&path, "old").unwrap();
+
+        write_atomic(&path, "new").unwrap();
+
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "new");
+        let entries: Vec<_> = std::fs::read_dir(temp.path()).unwrap().collect();
+        assert_eq!(entries.len(), 1);
+    }
 }
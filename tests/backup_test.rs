@@ -0,0 +1,43 @@
+use ai_blame::backup::{load_manifest, undo_run, BackupRun, UndoSummary};
+use std::fs;
+use tempfile::TempDir;
+
+#[test]
+fn test_backup_and_undo_restores_modified_and_removes_created_files() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path();
+    fs::create_dir_all(root.join("src")).unwrap();
+    let existing = root.join("src/main.py");
+    let sidecar = root.join("src/main.history.yaml");
+    fs::write(&existing, "print('hi')\n").unwrap();
+
+    let mut run = BackupRun::create(root).unwrap();
+    run.snapshot(&existing).unwrap();
+    run.snapshot(&sidecar).unwrap();
+    fs::write(&existing, "print('hi')\n# --- edit_history ---\n").unwrap();
+    fs::write(&sidecar, "edit_history: []\n").unwrap();
+    // A second snapshot of an already-written file must not clobber the original copy.
+    run.snapshot(&existing).unwrap();
+
+    let run_id = run.run_id().to_string();
+    let manifest = load_manifest(root, &run_id).unwrap();
+    assert_eq!(manifest.entries.len(), 2);
+
+    let summary = undo_run(root, &run_id).unwrap();
+    assert_eq!(
+        summary,
+        UndoSummary {
+            restored: 1,
+            removed: 1
+        }
+    );
+    assert_eq!(fs::read_to_string(&existing).unwrap(), "print('hi')\n");
+    assert!(!sidecar.exists());
+}
+
+#[test]
+fn test_undo_rejects_unknown_or_unsafe_run_ids() {
+    let temp = TempDir::new().unwrap();
+    assert!(undo_run(temp.path(), "20990101T000000Z").is_err());
+    assert!(undo_run(temp.path(), "../outside").is_err());
+}
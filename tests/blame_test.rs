@@ -1,6 +1,10 @@
-use ai_blame::blame::{compute_line_blame, group_blocks};
+use ai_blame::blame::{
+    compute_line_blame, compute_reverse_blame, compute_span_blame, detect_moves, group_blocks,
+    removed_lines, MoveKind, SpanGranularity,
+};
 use ai_blame::models::EditRecord;
 use chrono::{TimeZone, Utc};
+use std::collections::HashMap;
 
 fn mk_edit(
     ts: (i32, u32, u32, u32, u32, u32),
@@ -15,14 +19,12 @@ fn mk_edit(
             .unwrap(),
         model: "claude-test".to_string(),
         session_id: "s1".to_string(),
-        is_create: false,
         change_size: 1,
         agent_tool: "claude-code".to_string(),
-        agent_version: None,
         old_string: Some(old.to_string()),
         new_string: Some(new_.to_string()),
         structured_patch: structured_patch.map(|s| s.to_string()),
-        create_content: None,
+        ..Default::default()
     }
 }
 
@@ -79,3 +81,218 @@ fn test_group_blocks_splits_on_meta_changes() {
     assert_eq!(blocks[2].start_line, 3);
     assert_eq!(blocks[2].end_line, 3);
 }
+
+#[test]
+fn test_reverse_blame_finds_most_recent_removal() {
+    let mut readd = mk_edit(
+        (2025, 12, 1, 9, 0, 0),
+        "def f(x):\n    return x",
+        "def f(x):\n    if x is None:\n        return 0\n    return x",
+        None,
+    );
+    readd.session_id = "s-add".to_string();
+    let mut removal = mk_edit(
+        (2025, 12, 1, 10, 0, 0),
+        "    if x is None:\n        return 0\n    return x",
+        "    return x",
+        None,
+    );
+    removal.session_id = "s-remove".to_string();
+    let edits = vec![readd, removal];
+
+    let lines = vec![
+        (1, "if x is None:".to_string()),
+        (2, "    return x".to_string()),
+    ];
+    let result = compute_reverse_blame(&lines, &edits);
+    assert_eq!(
+        result[0].meta.as_ref().map(|m| m.session_id.as_str()),
+        Some("s-remove")
+    );
+    // Still present after the edit, so nothing removed it.
+    assert!(result[1].meta.is_none());
+}
+
+#[test]
+fn test_removed_lines_ignores_indentation_and_blank_lines() {
+    let old = "fn main() {\n    guard();\n\n    run();\n}\n";
+    let new = "fn main() {\n  run();\n}\n";
+    assert_eq!(
+        removed_lines(old, new),
+        vec![(2, "    guard();".to_string())]
+    );
+}
+
+const HELPER: &str = "fn helper() {\n    let x = 1;\n    x + 1\n}";
+
+#[test]
+fn test_blame_ignores_notebook_cell_edits() {
+    let create = EditRecord {
+        is_create: true,
+        old_string: None,
+        new_string: None,
+        create_content: Some("x = 1\n".to_string()),
+        ..mk_edit((2025, 12, 1, 8, 0, 0), "", "", None)
+    };
+    // The cell source happens to match a line of the file, but describes a cell.
+    let cell = EditRecord {
+        session_id: "s2".to_string(),
+        old_string: None,
+        cell_id: Some("cell-1".to_string()),
+        ..mk_edit((2025, 12, 1, 9, 0, 0), "", "x = 1", None)
+    };
+    let edits = vec![create, cell];
+
+    let lines = compute_line_blame("x = 1\n", &edits).unwrap();
+    assert_eq!(lines[0].meta.as_ref().unwrap().session_id, "s1");
+
+    let spans = compute_span_blame("x = 1\n", &edits, SpanGranularity::Word).unwrap();
+    assert!(spans[0]
+        .iter()
+        .all(|s| s.meta.as_ref().map(|m| m.session_id.as_str()) == Some("s1")));
+}
+
+fn edit_in(file: &str, session: &str, hour: u32, old: &str, new_: &str) -> EditRecord {
+    let mut e = mk_edit((2025, 12, 1, hour, 0, 0), old, new_, None);
+    e.file_path = file.to_string();
+    e.session_id = session.to_string();
+    e
+}
+
+#[test]
+fn test_detect_moves_keeps_original_author_across_files() {
+    let written = edit_in(
+        "src/a.rs",
+        "s-author",
+        8,
+        "// a",
+        &format!("// a\n{}", HELPER),
+    );
+    let cut = edit_in("src/a.rs", "s-mover", 9, HELPER, "");
+    let paste = edit_in(
+        "src/b.rs",
+        "s-mover",
+        10,
+        "// end",
+        &format!("// end\n{}", HELPER),
+    );
+    let sources = HashMap::from([
+        ("src/a.rs".to_string(), vec![written, cut]),
+        ("src/b.rs".to_string(), vec![paste.clone()]),
+    ]);
+
+    let current = format!("fn main() {{}}\n// end\n{}\n", HELPER);
+    let edits = vec![paste];
+    let mut blamed = compute_line_blame(&current, &edits).unwrap();
+    detect_moves(&mut blamed, &edits, &sources, 3);
+
+    // The anchor line is still the paste's own text.
+    assert_eq!(blamed[1].meta.as_ref().unwrap().session_id, "s-mover");
+    assert!(blamed[1].moved.is_none());
+    for line in &blamed[2..6] {
+        assert_eq!(line.meta.as_ref().unwrap().session_id, "s-author");
+        let moved = line.moved.as_ref().unwrap();
+        assert_eq!(moved.kind, MoveKind::Moved);
+        assert_eq!(moved.by.session_id, "s-mover");
+        assert_eq!(moved.from_file, "src/a.rs");
+    }
+}
+
+#[test]
+fn test_detect_moves_reports_copies_and_ignores_small_blocks() {
+    let written = edit_in(
+        "src/a.rs",
+        "s-author",
+        8,
+        "// a",
+        &format!("// a\n{}", HELPER),
+    );
+    let paste = edit_in(
+        "src/b.rs",
+        "s-copier",
+        10,
+        "// end",
+        &format!("// end\n{}", HELPER),
+    );
+    let sources = HashMap::from([("src/a.rs".to_string(), vec![written])]);
+    let current = format!("// end\n{}\n", HELPER);
+    let edits = vec![paste];
+
+    let mut blamed = compute_line_blame(&current, &edits).unwrap();
+    detect_moves(&mut blamed, &edits, &sources, 3);
+    assert_eq!(
+        blamed[1].moved.as_ref().map(|m| m.kind),
+        Some(MoveKind::Copied)
+    );
+    assert_eq!(blamed[1].meta.as_ref().unwrap().session_id, "s-author");
+
+    let mut blamed = compute_line_blame(&current, &edits).unwrap();
+    detect_moves(&mut blamed, &edits, &sources, 5);
+    assert!(blamed.iter().all(|l| l.moved.is_none()));
+}
+
+#[test]
+fn test_span_blame_attributes_changed_arguments() {
+    let mut create = mk_edit((2025, 12, 1, 8, 0, 0), "", "", None);
+    create.is_create = true;
+    create.session_id = "s-create".to_string();
+    let mut add_arg = mk_edit(
+        (2025, 12, 1, 9, 0, 0),
+        "    call(a);",
+        "    call(a, b);",
+        None,
+    );
+    add_arg.session_id = "s-arg".to_string();
+    let mut rename = mk_edit(
+        (2025, 12, 1, 10, 0, 0),
+        "    call(a, b);",
+        "    call(x, b);",
+        None,
+    );
+    rename.session_id = "s-rename".to_string();
+    let edits = vec![create, add_arg, rename];
+
+    let current = "fn main() {\n    call(x, b);\n}\n";
+    let spans = compute_span_blame(current, &edits, SpanGranularity::Word).unwrap();
+    assert_eq!(spans.len(), 3);
+
+    let owners: Vec<(usize, usize, &str)> = spans[1]
+        .iter()
+        .map(|s| (s.start, s.end, s.meta.as_ref().unwrap().session_id.as_str()))
+        .collect();
+    assert_eq!(
+        owners,
+        vec![
+            (0, 9, "s-create"),
+            (9, 10, "s-rename"),
+            (10, 13, "s-arg"),
+            (13, 15, "s-create"),
+        ]
+    );
+    // Line-level blame gives the whole line to the newest edit.
+    let lines = compute_line_blame(current, &edits).unwrap();
+    assert_eq!(lines[1].meta.as_ref().unwrap().session_id, "s-rename");
+}
+
+#[test]
+fn test_span_blame_char_granularity_and_unattributed_text() {
+    let edits = vec![mk_edit(
+        (2025, 12, 1, 9, 0, 0),
+        "value = 10",
+        "value = 12",
+        None,
+    )];
+    let current = "value = 12\nother\n";
+
+    let spans = compute_span_blame(current, &edits, SpanGranularity::Char).unwrap();
+    assert_eq!(spans[0].len(), 2);
+    assert!(spans[0][0].meta.is_none());
+    assert_eq!((spans[0][1].start, spans[0][1].end), (9, 10));
+    assert_eq!(spans[0][1].meta.as_ref().unwrap().session_id, "s1");
+    assert_eq!(spans[1].len(), 1);
+    assert!(spans[1][0].meta.is_none());
+
+    // At word level the whole number changed.
+    let spans = compute_span_blame(current, &edits, SpanGranularity::Word).unwrap();
+    assert_eq!((spans[0][1].start, spans[0][1].end), (8, 10));
+}
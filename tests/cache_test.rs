@@ -1,5 +1,7 @@
-use ai_blame::cache::CacheManager;
-use ai_blame::models::EditRecord;
+use ai_blame::cache::{CacheManager, SchemaUpgrade, CACHE_VERSION};
+use ai_blame::extractor::extract_edit_history;
+use ai_blame::models::{EditRecord, FilterConfig};
+use ai_blame::transcript::parse_transcripts_from_directory;
 use chrono::Utc;
 use std::fs;
 use tempfile::TempDir;
@@ -40,6 +42,9 @@ fn test_cache_round_trip() {
             new_string: None,
             structured_patch: None,
             create_content: Some("fn main() {}".to_string()),
+            cell_id: None,
+            agent_id: None,
+            replace_all: false,
         },
         EditRecord {
             file_path: "/test/file2.rs".to_string(),
@@ -54,6 +59,9 @@ fn test_cache_round_trip() {
             new_string: Some("new code".to_string()),
             structured_patch: Some("--- a\n+++ b".to_string()),
             create_content: None,
+            cell_id: None,
+            agent_id: None,
+            replace_all: false,
         },
     ];
 
@@ -150,6 +158,9 @@ fn test_multiple_files() {
         new_string: None,
         structured_patch: None,
         create_content: Some("a".to_string()),
+        cell_id: None,
+        agent_id: None,
+        replace_all: false,
     }];
 
     let edits2 = vec![EditRecord {
@@ -165,6 +176,9 @@ fn test_multiple_files() {
         new_string: None,
         structured_patch: None,
         create_content: Some("bb".to_string()),
+        cell_id: None,
+        agent_id: None,
+        replace_all: false,
     }];
 
     cache.store_edits(&file1, "claude", &edits1, 10).unwrap();
@@ -205,6 +219,9 @@ fn test_file_invalidation() {
         new_string: None,
         structured_patch: None,
         create_content: Some("test".to_string()),
+        cell_id: None,
+        agent_id: None,
+        replace_all: false,
     }];
 
     cache.store_edits(&file1, "claude", &edits, 10).unwrap();
@@ -247,6 +264,9 @@ fn test_special_chars_in_paths() {
             new_string: None,
             structured_patch: None,
             create_content: Some("test".to_string()),
+            cell_id: None,
+            agent_id: None,
+            replace_all: false,
         },
         EditRecord {
             file_path: "/path/with'quotes/file.rs".to_string(),
@@ -261,6 +281,9 @@ fn test_special_chars_in_paths() {
             new_string: None,
             structured_patch: None,
             create_content: Some("test".to_string()),
+            cell_id: None,
+            agent_id: None,
+            replace_all: false,
         },
     ];
 
@@ -294,6 +317,9 @@ fn test_persistence() {
         new_string: None,
         structured_patch: None,
         create_content: Some("test".to_string()),
+        cell_id: None,
+        agent_id: None,
+        replace_all: false,
     }];
 
     // Create cache and store data
@@ -342,6 +368,9 @@ fn test_large_edit_count() {
             } else {
                 None
             },
+            cell_id: None,
+            agent_id: None,
+            replace_all: false,
         });
     }
 
@@ -352,3 +381,484 @@ fn test_large_edit_count() {
 
     assert_eq!(retrieved.len(), 1000);
 }
+
+fn claude_edit_line(uuid: &str, ts: &str, file: &str, old: &str, new: &str) -> String {
+    format!(
+        r#"{{"uuid":"{uuid}","parentUuid":"parent-1","type":"user","timestamp":"{ts}","sessionId":"s1","toolUseResult":{{"filePath":"{file}","oldString":"{old}","newString":"{new}"}}}}"#
+    )
+}
+
+fn append(path: &std::path::Path, text: &str) {
+    use std::io::Write;
+    let mut f = fs::OpenOptions::new().append(true).open(path).unwrap();
+    f.write_all(text.as_bytes()).unwrap();
+}
+
+fn saved_offset(trace_dir: &std::path::Path, trace_file: &std::path::Path) -> u64 {
+    let cache = CacheManager::open(trace_dir).unwrap();
+    let state = cache.get_parse_state(trace_file).unwrap().unwrap();
+    let state: serde_json::Value = serde_json::from_str(&state).unwrap();
+    state["offset"].as_u64().unwrap()
+}
+
+/// Test that appended Claude trace lines are parsed on their own, resolving models
+/// against records parsed in earlier runs
+#[test]
+fn test_incremental_parse_of_appended_lines() {
+    let temp = TempDir::new().unwrap();
+    let trace_file = temp.path().join("session.jsonl");
+    fs::write(
+        &trace_file,
+        format!(
+            "{}\n{}\n",
+            r#"{"uuid":"parent-1","message":{"model":"claude-test-model"}}"#,
+            claude_edit_line("child-1", "2025-12-01T08:00:00Z", "/repo/a.rs", "a", "b")
+        ),
+    )
+    .unwrap();
+    let config = FilterConfig::default();
+    let count = |edits: &ai_blame::cache::EditsByFile| edits.values().map(Vec::len).sum::<usize>();
+
+    let edits = extract_edit_history(temp.path(), &config).unwrap();
+    assert_eq!(count(&edits), 1);
+
+    // Mark the cached edit so we can tell it was reused rather than reparsed.
+    {
+        let cache = CacheManager::open(temp.path()).unwrap();
+        let mut cached = cache.get_cached_edits(&trace_file).unwrap().unwrap();
+        cached[0].model = "from-cache".to_string();
+        cache
+            .store_edits(&trace_file, "claude", &cached, 0)
+            .unwrap();
+    }
+
+    // A second edit plus a line the agent is still writing.
+    let partial = claude_edit_line("child-3", "2025-12-01T10:00:00Z", "/repo/c.rs", "e", "f");
+    append(
+        &trace_file,
+        &format!(
+            "{}\n{}",
+            claude_edit_line("child-2", "2025-12-01T09:00:00Z", "/repo/b.rs", "c", "d"),
+            &partial[..40]
+        ),
+    );
+    let edits = extract_edit_history(temp.path(), &config).unwrap();
+    assert_eq!(count(&edits), 2);
+    // The parent message was parsed in the first run; its model comes from saved state.
+    assert_eq!(edits["/repo/b.rs"][0].model, "claude-test-model");
+    assert_eq!(edits["/repo/a.rs"][0].model, "from-cache");
+    let file_len = fs::metadata(&trace_file).unwrap().len();
+    assert_eq!(saved_offset(temp.path(), &trace_file), file_len - 40);
+
+    append(&trace_file, &format!("{}\n", &partial[40..]));
+    let edits = extract_edit_history(temp.path(), &config).unwrap();
+    assert_eq!(count(&edits), 3);
+    assert_eq!(edits["/repo/c.rs"][0].model, "claude-test-model");
+    let file_len = fs::metadata(&trace_file).unwrap().len();
+    assert_eq!(saved_offset(temp.path(), &trace_file), file_len);
+}
+
+/// Test that a rewritten (not appended) trace file is reparsed from the start
+#[test]
+fn test_incremental_parse_falls_back_on_rewrite() {
+    let temp = TempDir::new().unwrap();
+    let trace_file = temp.path().join("session.jsonl");
+    let parent = r#"{"uuid":"parent-1","message":{"model":"claude-test-model"}}"#;
+    fs::write(
+        &trace_file,
+        format!(
+            "{}\n{}\n",
+            parent,
+            claude_edit_line("child-1", "2025-12-01T08:00:00Z", "/repo/a.rs", "a", "b")
+        ),
+    )
+    .unwrap();
+    let config = FilterConfig::default();
+    extract_edit_history(temp.path(), &config).unwrap();
+
+    // Same prefix length, different content, plus more lines.
+    fs::write(
+        &trace_file,
+        format!(
+            "{}\n{}\n{}\n",
+            parent,
+            claude_edit_line("child-1", "2025-12-01T08:00:00Z", "/repo/x.rs", "a", "b"),
+            claude_edit_line("child-2", "2025-12-01T09:00:00Z", "/repo/y.rs", "c", "d")
+        ),
+    )
+    .unwrap();
+    let edits = extract_edit_history(temp.path(), &config).unwrap();
+    let mut files: Vec<_> = edits.keys().cloned().collect();
+    files.sort();
+    assert_eq!(files, vec!["/repo/x.rs", "/repo/y.rs"]);
+}
+
+/// Test that a run narrowed to one file still caches the other files' edits
+#[test]
+fn test_incremental_parse_after_narrow_run() {
+    let temp = TempDir::new().unwrap();
+    let trace_file = temp.path().join("session.jsonl");
+    fs::write(
+        &trace_file,
+        format!(
+            "{}\n{}\n{}\n",
+            r#"{"uuid":"parent-1","message":{"model":"claude-test-model"}}"#,
+            claude_edit_line("child-1", "2025-12-01T08:00:00Z", "/repo/a.rs", "a", "b"),
+            claude_edit_line("child-2", "2025-12-01T09:00:00Z", "/repo/b.rs", "c", "d")
+        ),
+    )
+    .unwrap();
+    let narrow = FilterConfig {
+        file_pattern: Some("a.rs".to_string()),
+        ..Default::default()
+    };
+    let edits = extract_edit_history(temp.path(), &narrow).unwrap();
+    let files: Vec<_> = edits.keys().cloned().collect();
+    assert_eq!(files, vec!["/repo/a.rs"]);
+
+    append(
+        &trace_file,
+        &format!(
+            "{}\n",
+            claude_edit_line("child-3", "2025-12-01T10:00:00Z", "/repo/c.rs", "e", "f")
+        ),
+    );
+    let edits = extract_edit_history(temp.path(), &FilterConfig::default()).unwrap();
+    let mut files: Vec<_> = edits.keys().cloned().collect();
+    files.sort();
+    assert_eq!(files, vec!["/repo/a.rs", "/repo/b.rs", "/repo/c.rs"]);
+
+    // A narrow run served from the cache still applies the pattern.
+    let edits = extract_edit_history(temp.path(), &narrow).unwrap();
+    let files: Vec<_> = edits.keys().cloned().collect();
+    assert_eq!(files, vec!["/repo/a.rs"]);
+}
+
+/// Test that a trailing line cut in the middle of a multibyte character is
+/// treated as partial rather than as invalid UTF-8
+#[test]
+fn test_incremental_parse_stops_at_line_cut_mid_character() {
+    let temp = TempDir::new().unwrap();
+    let trace_file = temp.path().join("session.jsonl");
+    fs::write(
+        &trace_file,
+        format!(
+            "{}\n{}\n",
+            r#"{"uuid":"parent-1","message":{"model":"claude-test-model"}}"#,
+            claude_edit_line("child-1", "2025-12-01T08:00:00Z", "/repo/a.rs", "a", "b")
+        ),
+    )
+    .unwrap();
+    let config = FilterConfig::default();
+    let count = |edits: &ai_blame::cache::EditsByFile| edits.values().map(Vec::len).sum::<usize>();
+    extract_edit_history(temp.path(), &config).unwrap();
+    let complete_len = fs::metadata(&trace_file).unwrap().len();
+
+    let line = claude_edit_line("child-2", "2025-12-01T09:00:00Z", "/repo/é.rs", "c", "d");
+    let cut = line.find('é').unwrap() + 1;
+    {
+        use std::io::Write;
+        let mut f = fs::OpenOptions::new()
+            .append(true)
+            .open(&trace_file)
+            .unwrap();
+        f.write_all(&line.as_bytes()[..cut]).unwrap();
+    }
+    let edits = extract_edit_history(temp.path(), &config).unwrap();
+    assert_eq!(count(&edits), 1);
+    assert_eq!(saved_offset(temp.path(), &trace_file), complete_len);
+
+    {
+        use std::io::Write;
+        let mut f = fs::OpenOptions::new()
+            .append(true)
+            .open(&trace_file)
+            .unwrap();
+        f.write_all(&line.as_bytes()[cut..]).unwrap();
+        f.write_all(b"\n").unwrap();
+    }
+    let edits = extract_edit_history(temp.path(), &config).unwrap();
+    assert_eq!(count(&edits), 2);
+    assert_eq!(edits["/repo/é.rs"][0].model, "claude-test-model");
+}
+
+/// Test that parsing alone does not advance the saved offset, so edits are
+/// never skipped when they fail to reach the cache
+#[test]
+fn test_parse_state_is_saved_with_cached_edits() {
+    use ai_blame::parsers::claude::ClaudeParser;
+    use ai_blame::parsers::TraceParser;
+
+    let temp = TempDir::new().unwrap();
+    let trace_file = temp.path().join("session.jsonl");
+    fs::write(
+        &trace_file,
+        format!(
+            "{}\n{}\n",
+            r#"{"uuid":"parent-1","message":{"model":"claude-test-model"}}"#,
+            claude_edit_line("child-1", "2025-12-01T08:00:00Z", "/repo/a.rs", "a", "b")
+        ),
+    )
+    .unwrap();
+    let config = FilterConfig::default();
+    extract_edit_history(temp.path(), &config).unwrap();
+    let first_offset = saved_offset(temp.path(), &trace_file);
+
+    append(
+        &trace_file,
+        &format!(
+            "{}\n",
+            claude_edit_line("child-2", "2025-12-01T09:00:00Z", "/repo/b.rs", "c", "d")
+        ),
+    );
+    {
+        let cache = CacheManager::open(temp.path()).unwrap();
+        let (edits, updates) = ClaudeParser::new()
+            .parse_directory_with_cache(temp.path(), "", None, &cache)
+            .unwrap();
+        assert_eq!(edits.len(), 2);
+        assert_eq!(updates.len(), 1);
+        assert_eq!(
+            updates[0].byte_offset,
+            fs::metadata(&trace_file).unwrap().len()
+        );
+    }
+    // The parse above was never stored, so the next run still picks up child-2.
+    assert_eq!(saved_offset(temp.path(), &trace_file), first_offset);
+    let edits = extract_edit_history(temp.path(), &config).unwrap();
+    assert!(edits.contains_key("/repo/b.rs"));
+}
+
+/// Create a cache as the first release wrote it: version "1", no cell_id column.
+fn write_v1_cache(trace_dir: &std::path::Path, trace_file: &std::path::Path) {
+    let conn = duckdb::Connection::open(CacheManager::cache_path(trace_dir)).unwrap();
+    conn.execute_batch(
+        "CREATE TABLE cache_metadata (key TEXT PRIMARY KEY, value TEXT NOT NULL);
+         INSERT INTO cache_metadata VALUES ('version', '1');
+         CREATE TABLE trace_files (
+             file_path TEXT PRIMARY KEY, provider TEXT NOT NULL, file_mtime_ns TEXT NOT NULL,
+             file_size_bytes TEXT NOT NULL, last_parsed_at TEXT NOT NULL,
+             record_count INTEGER NOT NULL DEFAULT 0, parse_duration_ms INTEGER);
+         CREATE TABLE edit_records (
+             trace_file_path TEXT NOT NULL, file_path TEXT NOT NULL, timestamp TEXT NOT NULL,
+             model TEXT NOT NULL, session_id TEXT NOT NULL, is_create BOOLEAN NOT NULL,
+             change_size INTEGER NOT NULL, agent_tool TEXT NOT NULL, agent_version TEXT,
+             old_string TEXT, new_string TEXT, structured_patch TEXT, create_content TEXT);
+         CREATE INDEX idx_edits_file_path ON edit_records(file_path);",
+    )
+    .unwrap();
+    let trace = trace_file.to_string_lossy();
+    conn.execute(
+        "INSERT INTO trace_files VALUES (?1, 'claude', '1', '2', '2025-01-01T00:00:00+00:00', 1, 5)",
+        [trace.as_ref()],
+    )
+    .unwrap();
+    conn.execute(
+        "INSERT INTO edit_records VALUES (?1, 'src/lib.rs', '2025-01-01T00:00:00+00:00',
+         'legacy-model', 's1', true, 10, 'claude-code', NULL, NULL, NULL, NULL, 'x')",
+        [trace.as_ref()],
+    )
+    .unwrap();
+}
+
+/// Test that a version 1 cache is migrated in place, and its edits parsed again
+#[test]
+fn test_migrates_v1_cache() {
+    let temp = TempDir::new().unwrap();
+    let trace_file = temp.path().join("session.jsonl");
+    fs::write(
+        &trace_file,
+        format!(
+            "{}\n{}\n",
+            r#"{"uuid":"parent-1","message":{"model":"claude-test-model"}}"#,
+            claude_edit_line("child-1", "2025-12-01T08:00:00Z", "/repo/a.rs", "a", "b")
+        ),
+    )
+    .unwrap();
+    write_v1_cache(temp.path(), &trace_file);
+
+    let cache = CacheManager::open(temp.path()).unwrap();
+    assert_eq!(
+        cache.schema_upgrade(),
+        &SchemaUpgrade::Migrated {
+            from: 1,
+            to: CACHE_VERSION
+        }
+    );
+    // Edits cached by version 1 lack the new edit record fields, so they are dropped.
+    assert!(cache.get_cached_edits(&trace_file).unwrap().is_none());
+    assert!(cache.get_file_metadata(&trace_file).unwrap().is_none());
+
+    // The new parse_state table exists after migration.
+    cache
+        .store_parse_state(&trace_file, "claude", 3, "{}")
+        .unwrap();
+    drop(cache);
+
+    let reopened = CacheManager::open(temp.path()).unwrap();
+    assert_eq!(
+        reopened.schema_upgrade(),
+        &SchemaUpgrade::UpToDate(CACHE_VERSION)
+    );
+    assert!(reopened.get_parse_state(&trace_file).unwrap().is_some());
+    drop(reopened);
+
+    // The next run parses the trace instead of reusing the legacy edit.
+    let edits = extract_edit_history(temp.path(), &FilterConfig::default()).unwrap();
+    let files: Vec<_> = edits.keys().cloned().collect();
+    assert_eq!(files, vec!["/repo/a.rs"]);
+    assert_eq!(edits["/repo/a.rs"][0].model, "claude-test-model");
+}
+
+/// Test that caches that cannot be migrated are rebuilt instead of failing
+#[test]
+fn test_rebuilds_unmigratable_cache() {
+    for version in ["99", "not-a-version"] {
+        let temp = TempDir::new().unwrap();
+        let trace_file = temp.path().join("session.jsonl");
+        fs::write(&trace_file, "{}\n").unwrap();
+        write_v1_cache(temp.path(), &trace_file);
+        {
+            let conn = duckdb::Connection::open(CacheManager::cache_path(temp.path())).unwrap();
+            conn.execute(
+                "UPDATE cache_metadata SET value = ?1 WHERE key = 'version'",
+                [version],
+            )
+            .unwrap();
+        }
+
+        let cache = CacheManager::open(temp.path()).unwrap();
+        assert!(
+            matches!(cache.schema_upgrade(), SchemaUpgrade::Rebuilt { to, .. } if *to == CACHE_VERSION),
+            "version {:?} gave {:?}",
+            version,
+            cache.schema_upgrade()
+        );
+        assert!(cache.get_cached_edits(&trace_file).unwrap().is_none());
+        assert_eq!(cache.info().unwrap().trace_files, 0);
+    }
+}
+
+/// Test cache info totals and that vacuum prunes deleted trace files
+#[test]
+fn test_cache_info_and_vacuum() {
+    let temp = TempDir::new().unwrap();
+    let cache = CacheManager::open(temp.path()).unwrap();
+    assert_eq!(
+        cache.schema_upgrade(),
+        &SchemaUpgrade::Created(CACHE_VERSION)
+    );
+
+    let kept = temp.path().join("kept.jsonl");
+    let deleted = temp.path().join("deleted.jsonl");
+    fs::write(&kept, "{}\n").unwrap();
+    fs::write(&deleted, "{}\n{}\n").unwrap();
+    let edit = |file: &str| EditRecord {
+        file_path: file.to_string(),
+        timestamp: Utc::now(),
+        model: "m".to_string(),
+        session_id: "s".to_string(),
+        is_create: false,
+        change_size: 1,
+        agent_tool: "claude-code".to_string(),
+        agent_version: None,
+        old_string: Some("a".to_string()),
+        new_string: Some("b".to_string()),
+        structured_patch: None,
+        create_content: None,
+        cell_id: None,
+        agent_id: None,
+        replace_all: false,
+    };
+    cache
+        .store_edits(&kept, "claude", &[edit("a.rs"), edit("b.rs")], 4)
+        .unwrap();
+    cache
+        .store_edits(&deleted, "codex", &[edit("c.rs")], 6)
+        .unwrap();
+
+    let info = cache.info().unwrap();
+    assert_eq!((info.trace_files, info.edit_records), (2, 3));
+    assert!(info.size_bytes > 0);
+    let providers: Vec<_> = info
+        .providers
+        .iter()
+        .map(|p| {
+            (
+                p.provider.as_str(),
+                p.trace_files,
+                p.record_count,
+                p.trace_bytes,
+            )
+        })
+        .collect();
+    assert_eq!(providers, vec![("claude", 1, 2, 3), ("codex", 1, 1, 6)]);
+
+    fs::remove_file(&deleted).unwrap();
+    let report = cache.vacuum().unwrap();
+    assert_eq!(report.pruned_files, 1);
+
+    let cache = CacheManager::open(temp.path()).unwrap();
+    let info = cache.info().unwrap();
+    assert_eq!((info.trace_files, info.edit_records), (1, 2));
+    assert_eq!(cache.get_cached_edits(&kept).unwrap().unwrap().len(), 2);
+    drop(cache);
+
+    assert!(CacheManager::remove(temp.path()).unwrap());
+    assert!(!CacheManager::cache_path(temp.path()).exists());
+    assert!(!CacheManager::remove(temp.path()).unwrap());
+}
+
+/// Test that transcripts are cached and only changed files are parsed again
+#[test]
+fn test_transcripts_are_cached() {
+    let temp = TempDir::new().unwrap();
+    let session = temp.path().join("session.jsonl");
+    fs::copy(
+        "tests/data/mixed-traces/claude-traces/2c965cfd-41e4-4e31-8339-2986ebced03c.jsonl",
+        &session,
+    )
+    .unwrap();
+    fs::write(temp.path().join("notes.jsonl"), "not json\n").unwrap();
+
+    let parsed = parse_transcripts_from_directory(temp.path()).unwrap();
+    assert_eq!(parsed.len(), 1);
+
+    {
+        let cache = CacheManager::open(temp.path()).unwrap();
+        assert_eq!(cache.info().unwrap().transcripts, 1);
+        let metadata = cache.transcript_file_metadata().unwrap();
+        assert_eq!(metadata.len(), 2);
+        assert!(metadata
+            .keys()
+            .all(|f| !ai_blame::cache::is_transcript_stale(f, metadata.get(f))));
+    }
+
+    // The second listing comes from the cache and matches the parse exactly.
+    let cached = parse_transcripts_from_directory(temp.path()).unwrap();
+    assert_eq!(
+        serde_json::to_value(&cached).unwrap(),
+        serde_json::to_value(&parsed).unwrap()
+    );
+
+    // A changed file is stale and parsed again.
+    let original = fs::read_to_string(&session).unwrap();
+    let lines: Vec<&str> = original.lines().filter(|l| !l.is_empty()).collect();
+    fs::write(&session, lines[..lines.len() - 1].join("\n") + "\n").unwrap();
+    let reparsed = parse_transcripts_from_directory(temp.path()).unwrap();
+    assert_eq!(reparsed.len(), 1);
+    assert!(reparsed[0].messages.len() < parsed[0].messages.len());
+
+    let cache = CacheManager::open(temp.path()).unwrap();
+    let cached = cache
+        .get_cached_transcripts(std::slice::from_ref(&session))
+        .unwrap();
+    let transcript = cached[&session].as_ref().unwrap();
+    assert_eq!(transcript.messages.len(), reparsed[0].messages.len());
+
+    cache
+        .invalidate_files(std::slice::from_ref(&session))
+        .unwrap();
+    assert!(cache.get_cached_transcripts(&[session]).unwrap().is_empty());
+    assert_eq!(cache.info().unwrap().transcripts, 0);
+}
@@ -210,7 +210,7 @@ fn test_timeline_command_extracts_edits() {
         .collect();
 
     // Sort by timestamp (most recent first)
-    all_edits.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
+    all_edits.sort_by_key(|e| std::cmp::Reverse(e.timestamp));
 
     // Should find both edits
     assert_eq!(all_edits.len(), 2, "Should find 2 edits");
@@ -0,0 +1,195 @@
+use ai_blame::pricing::PriceTable;
+use ai_blame::transcript::{link_continuations, parse_conversation, parse_transcript};
+use serde_json::json;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tempfile::TempDir;
+
+fn write_jsonl(path: &Path, records: &[serde_json::Value]) {
+    let lines: Vec<String> = records.iter().map(|r| r.to_string()).collect();
+    fs::write(path, lines.join("\n") + "\n").unwrap();
+}
+
+fn message(
+    session: &str,
+    uuid: &str,
+    parent: Option<&str>,
+    time: &str,
+    role: &str,
+    text: &str,
+) -> serde_json::Value {
+    json!({
+        "type": role,
+        "uuid": uuid,
+        "parentUuid": parent,
+        "sessionId": session,
+        "timestamp": format!("2025-03-01T{}Z", time),
+        "message": {"role": role, "model": "claude-opus-4-5", "content": [{"type": "text", "text": text}]},
+    })
+}
+
+fn edit(session: &str, uuid: &str, parent: &str, time: &str, file: &str) -> serde_json::Value {
+    json!({
+        "type": "user",
+        "uuid": uuid,
+        "parentUuid": parent,
+        "sessionId": session,
+        "timestamp": format!("2025-03-01T{}Z", time),
+        "toolUseResult": {"filePath": file, "oldString": "a", "newString": "b"},
+        "message": {"role": "user", "content": [{"type": "tool_result", "tool_use_id": uuid, "content": "ok"}]},
+    })
+}
+
+/// The first session, 10:00 to 10:05, editing `/repo/a.rs`.
+fn write_first(dir: &Path) -> PathBuf {
+    let path = dir.join("s1.jsonl");
+    write_jsonl(
+        &path,
+        &[
+            message("s1", "u0", None, "10:00:00", "user", "Refactor the parser"),
+            message(
+                "s1",
+                "u1",
+                Some("u0"),
+                "10:01:00",
+                "assistant",
+                "Editing a.rs",
+            ),
+            edit("s1", "u2", "u1", "10:02:00", "/repo/a.rs"),
+            message(
+                "s1",
+                "u3",
+                Some("u2"),
+                "10:05:00",
+                "assistant",
+                "Done with a.rs",
+            ),
+        ],
+    );
+    path
+}
+
+#[test]
+fn test_resumed_session_points_back() {
+    let temp = TempDir::new().unwrap();
+    let first = write_first(temp.path());
+    // Resumed hours later: a summary record, then messages continuing from u3.
+    let resumed = temp.path().join("s2.jsonl");
+    write_jsonl(
+        &resumed,
+        &[
+            json!({"type": "summary", "summary": "Parser refactor", "leafUuid": "u3"}),
+            message("s2", "v0", Some("u3"), "14:00:00", "user", "Now b.rs"),
+            edit("s2", "v1", "v0", "14:05:00", "/repo/b.rs"),
+            edit("s2", "v2", "v1", "14:06:00", "/repo/a.rs"),
+            message(
+                "s2",
+                "v3",
+                Some("v2"),
+                "14:10:00",
+                "assistant",
+                "Done with b.rs",
+            ),
+        ],
+    );
+
+    let second = parse_transcript(&resumed).unwrap();
+    assert_eq!(second.meta.continues_from.as_deref(), Some("u3"));
+    assert!(parse_transcript(&first)
+        .unwrap()
+        .meta
+        .continues_from
+        .is_none());
+
+    let linked = link_continuations(vec![second, parse_transcript(&first).unwrap()]);
+    assert_eq!(linked.len(), 1);
+    let conversation = &linked[0];
+    assert_eq!(conversation.meta.session_id, "s1");
+    assert_eq!(conversation.meta.continuations.len(), 1);
+    assert_eq!(conversation.meta.continuations[0].session_id, "s2");
+    assert_eq!(conversation.messages.len(), 8);
+    assert_eq!(conversation.messages[4].id, "v0");
+
+    let summary = conversation.summary(&PriceTable::builtin());
+    assert_eq!(summary.message_count, 8);
+    assert_eq!(summary.files_touched, 2);
+    assert_eq!(summary.continued_in, ["s2"]);
+    // 5 minutes before the resume and 10 after; the break doesn't count.
+    assert_eq!(summary.duration_secs, 15 * 60);
+    assert_eq!(
+        summary.end_time.unwrap().to_rfc3339(),
+        "2025-03-01T14:10:00+00:00"
+    );
+
+    // Viewing either file shows the whole conversation.
+    for path in [&first, &resumed] {
+        let conversation = parse_conversation(path).unwrap();
+        assert_eq!(conversation.meta.session_id, "s1");
+        assert_eq!(conversation.stats.message_count, 8);
+    }
+}
+
+#[test]
+fn test_replayed_history_shown_once() {
+    let temp = TempDir::new().unwrap();
+    let first = write_first(temp.path());
+    // Older releases copy the conversation into the new file before continuing it.
+    let resumed = temp.path().join("s2.jsonl");
+    write_jsonl(
+        &resumed,
+        &[
+            message("s2", "u0", None, "10:00:00", "user", "Refactor the parser"),
+            message(
+                "s2",
+                "u1",
+                Some("u0"),
+                "10:01:00",
+                "assistant",
+                "Editing a.rs",
+            ),
+            edit("s2", "u2", "u1", "10:02:00", "/repo/a.rs"),
+            message(
+                "s2",
+                "u3",
+                Some("u2"),
+                "10:05:00",
+                "assistant",
+                "Done with a.rs",
+            ),
+            message("s2", "v0", Some("u3"), "11:00:00", "user", "Add tests"),
+            message("s2", "v1", Some("v0"), "11:02:00", "assistant", "Added"),
+        ],
+    );
+    let unrelated = temp.path().join("s3.jsonl");
+    write_jsonl(
+        &unrelated,
+        &[message("s3", "w0", None, "12:00:00", "user", "Unrelated")],
+    );
+
+    let linked = link_continuations(
+        [&first, &resumed, &unrelated]
+            .iter()
+            .map(|p| parse_transcript(p).unwrap())
+            .collect(),
+    );
+    assert_eq!(linked.len(), 2);
+    let conversation = &linked[0];
+    let ids: Vec<_> = conversation
+        .messages
+        .iter()
+        .map(|m| m.id.as_str())
+        .collect();
+    assert_eq!(ids, ["u0", "u1", "u2", "u3", "v0", "v1"]);
+    assert_eq!(conversation.stats.message_count, 6);
+    // Resumed with the first message the new file added.
+    assert_eq!(
+        conversation.meta.continuations[0].start_time.to_rfc3339(),
+        "2025-03-01T11:00:00+00:00"
+    );
+    assert_eq!(
+        conversation.summary(&PriceTable::builtin()).duration_secs,
+        7 * 60
+    );
+    assert_eq!(linked[1].meta.session_id, "s3");
+    assert!(linked[1].meta.continuations.is_empty());
+}
@@ -0,0 +1,86 @@
+use ai_blame::diff::{diff_lines, edit_hunks, parse_structured_patch, render_patch, DiffLine};
+use ai_blame::models::EditRecord;
+use chrono::{TimeZone, Utc};
+
+fn edit(old: Option<&str>, new: Option<&str>, patch: Option<&str>) -> EditRecord {
+    EditRecord {
+        file_path: "src/main.rs".to_string(),
+        timestamp: Utc.with_ymd_and_hms(2025, 12, 1, 8, 3, 42).unwrap(),
+        model: "claude-opus-4-5-20251101".to_string(),
+        session_id: "s1".to_string(),
+        agent_tool: "claude-code".to_string(),
+        old_string: old.map(str::to_string),
+        new_string: new.map(str::to_string),
+        structured_patch: patch.map(str::to_string),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_diff_lines_keeps_common_lines_as_context() {
+    let lines = diff_lines("fn main() {\n    old();\n}", "fn main() {\n    new();\n}");
+    assert_eq!(
+        lines,
+        vec![
+            DiffLine::Context("fn main() {".to_string()),
+            DiffLine::Removed("    old();".to_string()),
+            DiffLine::Added("    new();".to_string()),
+            DiffLine::Context("}".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn test_parse_structured_patch_json_and_text() {
+    let json =
+        r#"[{"oldStart":3,"oldLines":2,"newStart":3,"newLines":2,"lines":[" a","-b","+c"]}]"#;
+    let hunks = parse_structured_patch(json);
+    assert_eq!(hunks.len(), 1);
+    assert_eq!(hunks[0].old_start, 3);
+    assert_eq!(hunks[0].lines.len(), 3);
+
+    let text = "@@ -10,2 +10,3 @@\n x\n-y\n+y1\n+y2\n";
+    let hunks = parse_structured_patch(text);
+    assert_eq!(hunks[0].header(), "@@ -10,2 +10,3 @@");
+    assert_eq!(hunks[0].lines[3], DiffLine::Added("y2".to_string()));
+}
+
+#[test]
+fn test_edit_hunks_uses_patch_line_numbers_for_snippet_diff() {
+    let hunks = edit_hunks(&edit(Some("a"), Some("b\nc"), Some("@@ -7 +7,2 @@")));
+    assert_eq!(hunks.len(), 1);
+    assert_eq!(hunks[0].header(), "@@ -7 +7,2 @@");
+    assert_eq!(
+        render_patch("src/main.rs", false, &hunks, false),
+        "--- a/src/main.rs\n+++ b/src/main.rs\n@@ -7 +7,2 @@\n-a\n+b\n+c\n"
+    );
+}
+
+#[test]
+fn test_edit_hunks_for_create_and_missing_content() {
+    let mut create = edit(None, None, None);
+    create.is_create = true;
+    create.create_content = Some("one\ntwo\n".to_string());
+    let hunks = edit_hunks(&create);
+    assert_eq!(hunks[0].header(), "@@ -0,0 +1,2 @@");
+    assert!(render_patch("src/main.rs", true, &hunks, false).starts_with("--- /dev/null\n"));
+
+    assert!(edit_hunks(&edit(None, None, Some("@@ -1 +1 @@"))).is_empty());
+}
+
+#[test]
+fn test_edit_hunks_skips_notebook_cell_edits() {
+    let mut cell = edit(None, Some("import pandas as pd"), None);
+    cell.file_path = "analysis.ipynb".to_string();
+    cell.cell_id = Some("cell-1".to_string());
+    assert!(edit_hunks(&cell).is_empty());
+}
+
+#[test]
+fn test_render_patch_colors_only_when_enabled() {
+    let hunks = edit_hunks(&edit(Some("a"), Some("b"), None));
+    let colored = render_patch("f.txt", false, &hunks, true);
+    assert!(colored.contains("\x1b[31m-a\x1b[0m"));
+    assert!(colored.contains("\x1b[32m+b\x1b[0m"));
+    assert!(!render_patch("f.txt", false, &hunks, false).contains('\x1b'));
+}
@@ -0,0 +1,233 @@
+use ai_blame::export::{Export, ExportFormat};
+use ai_blame::extractor::timeline_events;
+use ai_blame::models::EditRecord;
+use ai_blame::transcript::{
+    ContentBlock, Role, TokenUsage, Transcript, TranscriptMessage, TranscriptMeta,
+};
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::fs;
+use tempfile::TempDir;
+
+fn ts(s: &str) -> DateTime<Utc> {
+    DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+}
+
+fn edits() -> Vec<EditRecord> {
+    vec![
+        EditRecord {
+            file_path: "src/a.rs".to_string(),
+            timestamp: ts("2025-03-01T10:00:00Z"),
+            model: "opus".to_string(),
+            session_id: "s1".to_string(),
+            is_create: true,
+            change_size: 12,
+            agent_tool: "claude-code".to_string(),
+            agent_version: Some("1.0.0".to_string()),
+            old_string: None,
+            new_string: None,
+            structured_patch: None,
+            create_content: Some("fn main() {}".to_string()),
+            cell_id: None,
+            agent_id: None,
+            replace_all: false,
+        },
+        EditRecord {
+            file_path: "src/a.rs".to_string(),
+            timestamp: ts("2025-03-02T09:30:00Z"),
+            model: "opus".to_string(),
+            session_id: "s2".to_string(),
+            is_create: false,
+            change_size: 4,
+            agent_tool: "claude-code".to_string(),
+            agent_version: None,
+            old_string: Some("main".to_string()),
+            new_string: Some("run, \"x\"".to_string()),
+            structured_patch: None,
+            create_content: None,
+            cell_id: None,
+            agent_id: None,
+            replace_all: false,
+        },
+    ]
+}
+
+/// Read an exported Parquet file back as (column names, rows rendered as text).
+fn read_parquet(path: &std::path::Path) -> (Vec<String>, Vec<Vec<Option<String>>>) {
+    let conn = duckdb::Connection::open_in_memory().unwrap();
+    let mut stmt = conn
+        .prepare(&format!("SELECT * FROM read_parquet('{}')", path.display()))
+        .unwrap();
+    let mut rows = stmt.query([]).unwrap();
+    let columns = rows.as_ref().unwrap().column_names();
+    let mut out = Vec::new();
+    while let Some(row) = rows.next().unwrap() {
+        out.push(
+            (0..columns.len())
+                .map(|i| {
+                    let value: duckdb::types::Value = row.get(i).unwrap();
+                    match value {
+                        duckdb::types::Value::Null => None,
+                        duckdb::types::Value::Text(s) => Some(s),
+                        other => Some(format!("{:?}", other)),
+                    }
+                })
+                .collect(),
+        );
+    }
+    (columns, out)
+}
+
+#[test]
+fn test_export_edits_parquet_with_and_without_payloads() {
+    let temp = TempDir::new().unwrap();
+
+    let full = temp.path().join("edits.parquet");
+    Export::edits(&edits(), true)
+        .unwrap()
+        .write(ExportFormat::Parquet, &full)
+        .unwrap();
+    let (columns, rows) = read_parquet(&full);
+    assert_eq!(columns.len(), 14);
+    assert_eq!(columns.last().unwrap(), "create_content");
+    assert_eq!(rows.len(), 2);
+    assert_eq!(rows[0][13].as_deref(), Some("fn main() {}"));
+
+    let slim = temp.path().join("slim.parquet");
+    let export = Export::edits(&edits(), false).unwrap();
+    export.write(ExportFormat::Parquet, &slim).unwrap();
+    assert_eq!(export.row_count().unwrap(), 2);
+    let (columns, _) = read_parquet(&slim);
+    assert_eq!(
+        columns,
+        vec![
+            "file_path",
+            "timestamp",
+            "model",
+            "session_id",
+            "agent_tool",
+            "agent_version",
+            "is_create",
+            "change_size",
+            "cell_id",
+            "agent_id"
+        ]
+    );
+
+    // No rows still yields the full schema.
+    let empty = temp.path().join("empty.parquet");
+    Export::edits(&[], false)
+        .unwrap()
+        .write(ExportFormat::Parquet, &empty)
+        .unwrap();
+    let (empty_columns, empty_rows) = read_parquet(&empty);
+    assert_eq!(empty_columns, columns);
+    assert!(empty_rows.is_empty());
+}
+
+#[test]
+fn test_export_edits_csv_and_jsonl() {
+    let temp = TempDir::new().unwrap();
+    let export = Export::edits(&edits(), true).unwrap();
+
+    let csv = temp.path().join("edits.csv");
+    export.write(ExportFormat::Csv, &csv).unwrap();
+    let text = fs::read_to_string(&csv).unwrap();
+    let lines: Vec<&str> = text.lines().collect();
+    assert_eq!(lines.len(), 3);
+    assert!(lines[0].starts_with("file_path,timestamp,model,session_id,"));
+    assert!(lines[2].contains("\"run, \"\"x\"\"\""));
+
+    let jsonl = temp.path().join("edits.jsonl");
+    export.write(ExportFormat::Jsonl, &jsonl).unwrap();
+    let text = fs::read_to_string(&jsonl).unwrap();
+    let lines: Vec<&str> = text.lines().collect();
+    assert_eq!(lines.len(), 2);
+    assert!(lines[0].starts_with("{\"file_path\":\"src/a.rs\",\"timestamp\":"));
+    let first: serde_json::Value = serde_json::from_str(lines[0]).unwrap();
+    assert_eq!(first["is_create"], true);
+    assert_eq!(first["change_size"], 12);
+    assert_eq!(first["old_string"], serde_json::Value::Null);
+    let second: serde_json::Value = serde_json::from_str(lines[1]).unwrap();
+    assert_eq!(second["new_string"], "run, \"x\"");
+    assert_eq!(second["timestamp"], "2025-03-02T09:30:00+00:00");
+}
+
+#[test]
+fn test_export_timeline_and_transcripts() {
+    let temp = TempDir::new().unwrap();
+
+    let mut by_file = HashMap::new();
+    by_file.insert("src/a.rs".to_string(), edits());
+    let timeline = temp.path().join("timeline.jsonl");
+    Export::timeline(&timeline_events(&by_file))
+        .unwrap()
+        .write(ExportFormat::Jsonl, &timeline)
+        .unwrap();
+    let text = fs::read_to_string(&timeline).unwrap();
+    let events: Vec<serde_json::Value> = text
+        .lines()
+        .map(|l| serde_json::from_str(l).unwrap())
+        .collect();
+    assert_eq!(events[0]["action"], "EDITED");
+    assert_eq!(events[1]["action"], "CREATED");
+
+    let mut transcript = Transcript::new(TranscriptMeta {
+        session_id: "s1".to_string(),
+        agent_tool: "claude-code".to_string(),
+        agent_version: None,
+        cwd: Some("/repo".to_string()),
+        git_branch: Some("main".to_string()),
+        slug: None,
+        start_time: ts("2025-03-01T09:59:00Z"),
+        end_time: None,
+        source_file: Some("/traces/s1.jsonl".to_string()),
+        agent_id: None,
+        parent_session_id: None,
+        parent_tool_use_id: None,
+        continues_from: None,
+        continuations: Vec::new(),
+    });
+    transcript.add_message(TranscriptMessage {
+        id: "m1".to_string(),
+        role: Role::User,
+        timestamp: ts("2025-03-01T09:59:00Z"),
+        content: vec![ContentBlock::Text {
+            text: "Add a main".to_string(),
+        }],
+        model: None,
+        usage: None,
+    });
+    transcript.add_message(TranscriptMessage {
+        id: "m2".to_string(),
+        role: Role::Assistant,
+        timestamp: ts("2025-03-01T10:00:00Z"),
+        content: vec![ContentBlock::Text {
+            text: "Done".to_string(),
+        }],
+        model: Some("opus".to_string()),
+        usage: Some(TokenUsage {
+            input_tokens: Some(100),
+            output_tokens: Some(20),
+            cache_read_tokens: None,
+            cache_creation_tokens: None,
+        }),
+    });
+
+    let csv = temp.path().join("transcripts.csv");
+    Export::transcripts(&[transcript])
+        .unwrap()
+        .write(ExportFormat::Csv, &csv)
+        .unwrap();
+    let text = fs::read_to_string(&csv).unwrap();
+    let mut lines = text.lines();
+    assert_eq!(
+        lines.next().unwrap(),
+        "session_id,agent_tool,agent_version,slug,cwd,git_branch,start_time,end_time,\
+         primary_model,models,message_count,user_message_count,assistant_message_count,\
+         tool_use_count,files_touched,total_input_tokens,total_output_tokens,source_file"
+    );
+    let row = lines.next().unwrap();
+    assert!(row.starts_with("s1,claude-code,,,/repo,main,"));
+    assert!(row.ends_with(",opus,opus,2,1,1,0,0,100,20,/traces/s1.jsonl"));
+}
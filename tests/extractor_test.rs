@@ -79,6 +79,32 @@ fn test_extract_edit_history_resolves_model_across_trace_files() {
     assert_eq!(edits[0].agent_tool, "claude-code-agent");
 }
 
+#[test]
+fn test_extract_edit_history_attributes_notebook_edit_to_cell() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut trace = std::fs::File::create(dir.path().join("session.jsonl")).unwrap();
+
+    // The tool result omits notebook_path, so it must be resolved from the tool_use input.
+    writeln!(
+        trace,
+        r#"{{"uuid":"assistant-nb","type":"assistant","message":{{"model":"claude-notebook","content":[{{"type":"tool_use","id":"toolu_nb","name":"NotebookEdit","input":{{"notebook_path":"/repo/analysis.ipynb","cell_id":"cell-2","new_source":"df.describe()","edit_mode":"replace"}}}}]}}}}"#
+    )
+    .unwrap();
+    writeln!(
+        trace,
+        r#"{{"uuid":"result-nb","parentUuid":"assistant-nb","type":"user","timestamp":"2025-12-02T09:00:00Z","sessionId":"s-nb","message":{{"role":"user","content":[{{"type":"tool_result","tool_use_id":"toolu_nb","content":"Updated cell cell-2"}}]}},"toolUseResult":{{"new_source":"df.describe()","cell_type":"code","language":"python","edit_mode":"replace","error":""}}}}"#
+    )
+    .unwrap();
+
+    let edits_by_file = extract_edit_history(dir.path(), &Default::default()).unwrap();
+    let edits = edits_by_file.get("/repo/analysis.ipynb").unwrap();
+    assert_eq!(edits.len(), 1);
+    assert_eq!(edits[0].model, "claude-notebook");
+    assert_eq!(edits[0].cell_id.as_deref(), Some("cell-2"));
+    assert_eq!(edits[0].new_string.as_deref(), Some("df.describe()"));
+    assert!(!edits[0].is_create);
+}
+
 #[test]
 fn test_parse_trace_file_treats_content_without_explicit_type_as_create() {
     let mut tmp = tempfile::NamedTempFile::new().unwrap();
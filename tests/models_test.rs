@@ -21,6 +21,7 @@ fn test_file_history_first_last_edit() {
         description: None,
         agent_tool: Some("claude-code".to_string()),
         agent_version: None,
+        cell_id: None,
     });
 
     history.events.push(CurationEvent {
@@ -30,6 +31,7 @@ fn test_file_history_first_last_edit() {
         description: None,
         agent_tool: Some("claude-code".to_string()),
         agent_version: None,
+        cell_id: None,
     });
 
     assert!(history.first_edit().is_some());
@@ -55,3 +57,42 @@ fn test_curation_action_serialization() {
     let serialized = serde_json::to_string(&action).unwrap();
     assert_eq!(serialized, "\"EDITED\"");
 }
+
+#[test]
+fn test_comment_syntax_from_path() {
+    use std::path::Path;
+
+    assert_eq!(
+        CommentSyntax::from_path(Path::new("src/main.py")),
+        Some(CommentSyntax::Hash)
+    );
+    assert_eq!(
+        CommentSyntax::from_path(Path::new("lib/util.C")),
+        Some(CommentSyntax::Slash)
+    );
+    assert_eq!(
+        CommentSyntax::from_path(Path::new("lib/util.h")),
+        CommentSyntax::from_path(Path::new("lib/util.c"))
+    );
+    assert_eq!(
+        CommentSyntax::from_path(Path::new("style.css")),
+        Some(CommentSyntax::Block)
+    );
+    assert_eq!(
+        CommentSyntax::from_path(Path::new("parser.ml")),
+        Some(CommentSyntax::Paren)
+    );
+    assert_eq!(
+        CommentSyntax::from_path(Path::new("schema.sql")),
+        Some(CommentSyntax::Dash)
+    );
+    assert_eq!(
+        CommentSyntax::from_path(Path::new("paper.tex")),
+        Some(CommentSyntax::Percent)
+    );
+    assert_eq!(
+        CommentSyntax::from_path(Path::new("Dockerfile")),
+        Some(CommentSyntax::Hash)
+    );
+    assert_eq!(CommentSyntax::from_path(Path::new("data.bin")), None);
+}
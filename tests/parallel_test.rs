@@ -0,0 +1,45 @@
+use ai_blame::parallel::{map_ordered, JOBS_ENV};
+use ai_blame::parsers::claude::ClaudeParser;
+use ai_blame::parsers::TraceParser;
+use ai_blame::transcript::parse_transcripts_from_directory;
+use std::path::Path;
+
+#[test]
+fn test_map_ordered_keeps_input_order() {
+    let items: Vec<u64> = (0..200).collect();
+    // Uneven work so workers finish out of order.
+    let results = map_ordered(&items, |n| {
+        std::thread::sleep(std::time::Duration::from_micros((200 - n) * 10));
+        n * 2
+    });
+    assert_eq!(results, items.iter().map(|n| n * 2).collect::<Vec<_>>());
+    assert!(map_ordered(&Vec::<u64>::new(), |n| *n).is_empty());
+}
+
+#[test]
+fn test_parallel_parse_matches_sequential() {
+    let dir = Path::new("tests/data/traces");
+    let parse = || {
+        let edits = ClaudeParser::new()
+            .parse_directory_with_context(dir, "", None)
+            .unwrap();
+        let transcripts: Vec<String> = parse_transcripts_from_directory(dir)
+            .unwrap()
+            .iter()
+            .map(|t| t.meta.session_id.clone())
+            .collect();
+        (format!("{:?}", edits), transcripts)
+    };
+
+    // Parse every time rather than reading transcripts back from the cache.
+    std::env::set_var("AI_BLAME_NO_CACHE", "1");
+    std::env::set_var(JOBS_ENV, "1");
+    let sequential = parse();
+    std::env::set_var(JOBS_ENV, "4");
+    let parallel = parse();
+    std::env::remove_var(JOBS_ENV);
+    std::env::remove_var("AI_BLAME_NO_CACHE");
+
+    assert!(!sequential.1.is_empty());
+    assert_eq!(sequential, parallel);
+}
@@ -0,0 +1,331 @@
+use ai_blame::models::OutputConfig;
+use ai_blame::pricing::{cost_report, Attribution, CostGroup, PriceTable, NO_KEY};
+use ai_blame::transcript::{parse_transcript, TokenUsage};
+use serde_json::{json, Value};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tempfile::TempDir;
+
+fn usage(input: u64, output: u64, cache_read: u64, cache_creation: u64) -> TokenUsage {
+    TokenUsage {
+        input_tokens: Some(input),
+        output_tokens: Some(output),
+        cache_read_tokens: Some(cache_read),
+        cache_creation_tokens: Some(cache_creation),
+    }
+}
+
+fn assert_close(actual: f64, expected: f64) {
+    assert!(
+        (actual - expected).abs() < 1e-9,
+        "expected {}, got {}",
+        expected,
+        actual
+    );
+}
+
+#[test]
+fn test_price_lookup_and_overrides() {
+    let table = PriceTable::builtin();
+    let model = |name: &str| table.price_for(name).map(|p| p.model.as_str());
+
+    // Longest prefix wins.
+    assert_eq!(model("claude-opus-4-5-20251101"), Some("claude-opus-4-5"));
+    assert_eq!(model("claude-opus-4-1-20250805"), Some("claude-opus-4"));
+    assert_eq!(model("gpt-5-mini-2025-08-07"), Some("gpt-5-mini"));
+    assert_eq!(model("gpt-5.2-codex"), Some("gpt-5.2"));
+    // Case and provider prefixes are ignored.
+    assert_eq!(model("Claude-Sonnet-4-5"), Some("claude-sonnet-4"));
+    assert_eq!(model("anthropic/claude-haiku-4-5"), Some("claude-haiku-4"));
+    assert_eq!(
+        model("us.anthropic.claude-sonnet-4-20250514-v1:0"),
+        Some("claude-sonnet-4")
+    );
+    assert_eq!(model("<synthetic>"), None);
+
+    // 1M input at $3, 1M output at $15, 1M cache reads at $0.30, 1M cache writes at $3.75.
+    let sonnet = table.price_for("claude-sonnet-4-5").unwrap();
+    assert_close(
+        sonnet.cost(&usage(1_000_000, 1_000_000, 1_000_000, 1_000_000)),
+        22.05,
+    );
+
+    let config: OutputConfig = serde_yaml::from_str(
+        "pricing:
+  - model: claude-sonnet-4
+    input: 2.0
+    output: 10.0
+  - model: local-llama
+    input: 0.0
+    output: 0.0
+",
+    )
+    .unwrap();
+    let table = PriceTable::from_config(&config);
+    assert_eq!(
+        table.prices().len(),
+        PriceTable::builtin().prices().len() + 1
+    );
+    // Cache rates default to the input rate.
+    let sonnet = table.price_for("claude-sonnet-4-5").unwrap();
+    assert_close(sonnet.cost(&usage(500_000, 100_000, 500_000, 0)), 3.0);
+    assert_eq!(
+        table.usage_cost(Some("local-llama-3"), &usage(10, 10, 0, 0)),
+        Some(0.0)
+    );
+    assert_eq!(table.usage_cost(None, &usage(10, 10, 0, 0)), None);
+}
+
+/// A Claude session whose single API response was written as two records.
+fn write_claude_session(dir: &Path, session_id: &str, model: &str, file: &str) -> PathBuf {
+    let record = |i: usize, role: &str, message: Value, extra: Value| {
+        let mut record = json!({
+            "type": role,
+            "uuid": format!("{}-{}", session_id, i),
+            "sessionId": session_id,
+            "timestamp": format!("2025-03-0{}T23:5{}:00Z", 1 + i / 2, i),
+            "message": message,
+        });
+        record
+            .as_object_mut()
+            .unwrap()
+            .extend(extra.as_object().unwrap().clone());
+        record.to_string()
+    };
+    let response_usage = json!({
+        "input_tokens": 1000, "output_tokens": 2000,
+        "cache_read_input_tokens": 10000, "cache_creation_input_tokens": 4000,
+    });
+    let lines = [
+        record(
+            0,
+            "user",
+            json!({"role": "user", "content": "Write the file"}),
+            json!({}),
+        ),
+        record(
+            1,
+            "assistant",
+            json!({"id": "resp-1", "role": "assistant", "model": model, "usage": response_usage,
+                   "content": [{"type": "text", "text": "Writing it"}]}),
+            json!({}),
+        ),
+        record(
+            2,
+            "assistant",
+            json!({"id": "resp-1", "role": "assistant", "model": model, "usage": response_usage,
+                   "content": [{"type": "tool_use", "id": "t1", "name": "Write",
+                                "input": {"file_path": file, "content": "x"}}]}),
+            json!({}),
+        ),
+        record(
+            3,
+            "user",
+            json!({"role": "user", "content": [
+                {"type": "tool_result", "tool_use_id": "t1", "content": "ok"}]}),
+            json!({"toolUseResult": {"type": "create", "filePath": file, "content": "x"}}),
+        ),
+    ];
+    let path = dir.join(format!("{}.jsonl", session_id));
+    fs::write(&path, lines.join("\n") + "\n").unwrap();
+    path
+}
+
+#[test]
+fn test_transcript_cost_counts_each_response_once() {
+    let temp = TempDir::new().unwrap();
+    let path = write_claude_session(temp.path(), "s1", "claude-sonnet-4-5", "/repo/a.rs");
+    let transcript = parse_transcript(&path).unwrap();
+
+    assert_eq!(transcript.stats.total_input_tokens, Some(1000));
+    assert_eq!(transcript.stats.total_output_tokens, Some(2000));
+    // 1000 * 3 + 2000 * 15 + 10000 * 0.3 + 4000 * 3.75 = 51000 per million.
+    let cost = PriceTable::builtin().transcript_cost(&transcript).unwrap();
+    assert_close(cost, 0.051);
+    assert_eq!(transcript.summary(&PriceTable::builtin()).cost, Some(cost));
+
+    // Summaries are priced with the table they are given, such as the project's config.
+    fs::write(
+        temp.path().join(".ai-blame.yaml"),
+        "pricing:\n  - model: claude-sonnet-4\n    input: 0.0\n    output: 0.0\n",
+    )
+    .unwrap();
+    let project = PriceTable::load(Some(temp.path())).unwrap();
+    assert_eq!(transcript.summary(&project).cost, Some(0.0));
+
+    let unpriced = write_claude_session(temp.path(), "s2", "mystery-model", "/repo/a.rs");
+    let unpriced = parse_transcript(&unpriced).unwrap();
+    assert_eq!(PriceTable::builtin().transcript_cost(&unpriced), None);
+}
+
+#[test]
+fn test_codex_usage_is_per_turn_with_model() {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join(
+        "tests/data/mixed-traces/codex-sessions/2025/12/29/\
+         rollout-2025-12-29T11-54-27-019b6b08-58f3-7580-9dca-fe3f30a10650.jsonl",
+    );
+    let transcript = parse_transcript(&path).unwrap();
+
+    // Running totals (repeated in duplicate events) add up to the final total, with
+    // cached input split out of the input count.
+    let mut total = TokenUsage::default();
+    for message in &transcript.messages {
+        if let Some(usage) = &message.usage {
+            total.add(usage);
+            assert_eq!(message.model.as_deref(), Some("gpt-5.2-codex"));
+        }
+    }
+    assert_eq!(total.input_tokens, Some(44962 - 40320));
+    assert_eq!(total.cache_read_tokens, Some(40320));
+    assert_eq!(total.output_tokens, Some(628));
+    assert!(transcript
+        .summary(&PriceTable::builtin())
+        .all_models
+        .contains(&"gpt-5.2-codex".to_string()));
+}
+
+#[test]
+fn test_cost_report_groups() {
+    let temp = TempDir::new().unwrap();
+    let dir = temp.path();
+    let transcripts: Vec<_> = [
+        write_claude_session(dir, "s1", "claude-sonnet-4-5", "/repo/a.rs"),
+        write_claude_session(dir, "s2", "claude-opus-4-5", "/repo/b.rs"),
+        write_claude_session(dir, "s3", "mystery-model", "/repo/a.rs"),
+    ]
+    .iter()
+    .map(|p| parse_transcript(p).unwrap())
+    .collect();
+    let prices = PriceTable::builtin();
+    let report = |group| cost_report(&transcripts, &prices, group);
+
+    let by_session = report(CostGroup::Session);
+    let keys: Vec<&str> = by_session.iter().map(|r| r.key.as_str()).collect();
+    assert_eq!(keys, vec!["s2", "s1", "s3"]);
+    // 1000 * 5 + 2000 * 25 + 10000 * 0.5 + 4000 * 6.25 = 85000 per million.
+    assert_close(by_session[0].cost, 0.085);
+    assert_eq!(by_session[0].cache_creation_tokens, 4000);
+    assert_close(by_session[2].cost, 0.0);
+    assert_eq!(by_session[2].unpriced_tokens, 17000);
+    assert_eq!(by_session[2].unpriced_models, vec!["mystery-model"]);
+
+    let by_model = report(CostGroup::Model);
+    assert_eq!(by_model[0].key, "claude-opus-4-5");
+    assert_eq!(by_model.len(), 3);
+
+    let by_agent = report(CostGroup::Agent);
+    assert_eq!(by_agent.len(), 1);
+    assert_eq!(by_agent[0].sessions, 3);
+    assert_close(by_agent[0].cost, 0.136);
+
+    let by_file = report(CostGroup::File(Attribution::ChangeSize));
+    assert_eq!(by_file[0].key, "/repo/b.rs");
+    assert_eq!(by_file[1].key, "/repo/a.rs");
+    assert_eq!(by_file[1].sessions, 2);
+    assert_eq!(by_file[1].unpriced_tokens, 17000);
+
+    // Usage is dated by the message reporting it, not the session's last message.
+    let by_day = report(CostGroup::Day);
+    let days: Vec<&str> = by_day.iter().map(|r| r.key.as_str()).collect();
+    assert_eq!(days, vec!["2025-03-01"]);
+    assert_eq!(by_day[0].sessions, 3);
+}
+
+/// A session that writes a 10-character file after one response and then edits another,
+/// changing 30 characters, after a second response, then wraps up with a third.
+fn two_file_session(dir: &Path) -> PathBuf {
+    let mut lines = Vec::new();
+    let mut i = 0;
+    let mut push = |role: &str, message: Value, extra: Value| {
+        let mut record = json!({
+            "type": role,
+            "uuid": format!("u{}", i),
+            "sessionId": "two",
+            "timestamp": format!("2025-03-01T10:{:02}:00Z", i),
+            "message": message,
+        });
+        record
+            .as_object_mut()
+            .unwrap()
+            .extend(extra.as_object().unwrap().clone());
+        lines.push(record.to_string());
+        i += 1;
+    };
+    let response = |id: &str, output: u64| {
+        json!({"id": id, "role": "assistant", "model": "claude-sonnet-4-5",
+               "usage": {"input_tokens": 0, "output_tokens": output},
+               "content": [{"type": "text", "text": "working"}]})
+    };
+    let result = json!({"role": "user", "content": [
+        {"type": "tool_result", "tool_use_id": "t", "content": "ok"}]});
+
+    push("assistant", response("r1", 100_000), json!({}));
+    push(
+        "user",
+        result.clone(),
+        json!({"toolUseResult": {"type": "create", "filePath": "/repo/new.rs",
+                                 "content": "0123456789"}}),
+    );
+    push("assistant", response("r2", 300_000), json!({}));
+    push(
+        "user",
+        result,
+        json!({"toolUseResult": {"filePath": "/repo/old.rs",
+                                 "oldString": "0123456789", "newString": "01234567890123456789"}}),
+    );
+    push("assistant", response("r3", 600_000), json!({}));
+
+    let path = dir.join("two.jsonl");
+    fs::write(&path, lines.join("\n") + "\n").unwrap();
+    path
+}
+
+#[test]
+fn test_file_attribution_weights() {
+    let temp = TempDir::new().unwrap();
+    let transcripts = vec![parse_transcript(&two_file_session(temp.path())).unwrap()];
+    let prices = PriceTable::builtin();
+    let costs = |attribution| {
+        let rows = cost_report(&transcripts, &prices, CostGroup::File(attribution));
+        let mut costs: Vec<(String, f64)> = rows.into_iter().map(|r| (r.key, r.cost)).collect();
+        costs.sort_by(|a, b| a.0.cmp(&b.0));
+        costs
+    };
+    // 1M output tokens at $15.
+    let total = 15.0;
+
+    // Change sizes: 10 for the new file, 10 + 20 for the edit.
+    let by_size = costs(Attribution::ChangeSize);
+    assert_eq!(by_size[0].0, "/repo/new.rs");
+    assert_close(by_size[0].1, total * 0.25);
+    assert_close(by_size[1].1, total * 0.75);
+
+    // The first response led to the new file; the second, and the wrap-up after the last
+    // edit, to the edited one.
+    let by_messages = costs(Attribution::Messages);
+    assert_close(by_messages[0].1, total * 0.1);
+    assert_close(by_messages[1].1, total * 0.9);
+
+    // Sessions without edits are reported together.
+    let temp = TempDir::new().unwrap();
+    write_session_without_edits(temp.path());
+    let transcripts = vec![parse_transcript(&temp.path().join("plain.jsonl")).unwrap()];
+    let rows = cost_report(
+        &transcripts,
+        &prices,
+        CostGroup::File(Attribution::Messages),
+    );
+    assert_eq!(rows.len(), 1);
+    assert_eq!(rows[0].key, NO_KEY);
+}
+
+fn write_session_without_edits(dir: &Path) {
+    let record = json!({
+        "type": "assistant", "uuid": "p0", "sessionId": "plain",
+        "timestamp": "2025-03-01T10:00:00Z",
+        "message": {"role": "assistant", "model": "claude-sonnet-4-5",
+                    "usage": {"input_tokens": 10, "output_tokens": 10},
+                    "content": [{"type": "text", "text": "Nothing to change"}]},
+    });
+    fs::write(dir.join("plain.jsonl"), record.to_string() + "\n").unwrap();
+}
@@ -0,0 +1,146 @@
+use ai_blame::cache::query::QuerySession;
+use ai_blame::cache::CacheManager;
+use ai_blame::models::EditRecord;
+use chrono::{DateTime, Utc};
+use serde_json::json;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tempfile::TempDir;
+
+fn edit(file: &str, ts: &str, model: &str, session: &str, agent: &str, size: usize) -> EditRecord {
+    EditRecord {
+        file_path: file.to_string(),
+        timestamp: DateTime::parse_from_rfc3339(ts)
+            .unwrap()
+            .with_timezone(&Utc),
+        model: model.to_string(),
+        session_id: session.to_string(),
+        change_size: size,
+        agent_tool: agent.to_string(),
+        old_string: Some("a".to_string()),
+        new_string: Some("b".to_string()),
+        ..Default::default()
+    }
+}
+
+/// Write a cache into `dir` holding `edits` under a single trace file.
+fn write_cache(dir: &Path, provider: &str, edits: &[EditRecord]) -> PathBuf {
+    fs::create_dir_all(dir).unwrap();
+    let trace_file = dir.join("session.jsonl");
+    fs::write(&trace_file, "{}\n").unwrap();
+    let cache = CacheManager::open(dir).unwrap();
+    cache.store_edits(&trace_file, provider, edits, 1).unwrap();
+    cache.db_path().to_path_buf()
+}
+
+fn two_caches(temp: &TempDir) -> Vec<PathBuf> {
+    vec![
+        write_cache(
+            &temp.path().join("claude"),
+            "claude",
+            &[
+                edit(
+                    "a.rs",
+                    "2025-03-01T10:00:00Z",
+                    "opus",
+                    "s1",
+                    "claude-code",
+                    30,
+                ),
+                edit(
+                    "a.rs",
+                    "2025-03-01T11:00:00Z",
+                    "opus",
+                    "s1",
+                    "claude-code",
+                    10,
+                ),
+                edit(
+                    "b.rs",
+                    "2025-03-02T09:00:00Z",
+                    "sonnet",
+                    "s2",
+                    "claude-code",
+                    5,
+                ),
+            ],
+        ),
+        write_cache(
+            &temp.path().join("codex"),
+            "codex",
+            &[edit(
+                "a.rs",
+                "2025-03-02T12:00:00Z",
+                "gpt-5",
+                "s3",
+                "codex",
+                100,
+            )],
+        ),
+    ]
+}
+
+#[test]
+fn test_query_spans_caches_and_predefined_views() {
+    let temp = TempDir::new().unwrap();
+    let session = QuerySession::open(&two_caches(&temp)).unwrap();
+
+    let totals = session
+        .run("SELECT COUNT(*) AS edits, COUNT(DISTINCT provider) AS providers FROM edit_records JOIN trace_files ON trace_file_path = trace_files.file_path")
+        .unwrap();
+    assert_eq!(totals.columns, vec!["edits", "providers"]);
+    assert_eq!(totals.rows, vec![vec![json!(4), json!(2)]]);
+
+    let per_day = session
+        .run("SELECT CAST(day AS VARCHAR), model, edits, chars_changed FROM edits_per_day")
+        .unwrap();
+    assert_eq!(
+        per_day.rows,
+        vec![
+            vec![json!("2025-03-01"), json!("opus"), json!(2), json!(40)],
+            vec![json!("2025-03-02"), json!("gpt-5"), json!(1), json!(100)],
+            vec![json!("2025-03-02"), json!("sonnet"), json!(1), json!(5)],
+        ]
+    );
+
+    let share = session
+        .run("SELECT agent_tool, edits, edit_share FROM file_agent_share WHERE file_path = 'a.rs'")
+        .unwrap();
+    assert_eq!(
+        share.rows,
+        vec![
+            vec![json!("claude-code"), json!(2), json!(0.667)],
+            vec![json!("codex"), json!(1), json!(0.333)],
+        ]
+    );
+
+    let sessions = session
+        .run("SELECT session_id, files, started FROM session_sizes LIMIT 2")
+        .unwrap();
+    assert_eq!(sessions.rows[0][0], json!("s3"));
+    assert_eq!(sessions.rows[1][..2], [json!("s1"), json!(1)]);
+    assert_eq!(sessions.rows[1][2], json!("2025-03-01T10:00:00+00:00"));
+
+    // Caches are attached read-only.
+    assert!(session.run("DELETE FROM cache_0.edit_records").is_err());
+}
+
+#[test]
+fn test_query_writes_parquet() {
+    let temp = TempDir::new().unwrap();
+    let session = QuerySession::open(&two_caches(&temp)).unwrap();
+
+    let output = temp.path().join("days.parquet");
+    session
+        .write_parquet("SELECT * FROM edits_per_day;", &output)
+        .unwrap();
+    assert!(output.exists());
+
+    let read_back = session
+        .run(&format!(
+            "SELECT SUM(edits) FROM read_parquet('{}')",
+            output.display()
+        ))
+        .unwrap();
+    assert_eq!(read_back.rows, vec![vec![json!(4)]]);
+}
@@ -0,0 +1,177 @@
+use ai_blame::models::EditRecord;
+use ai_blame::replay::{reconstruct, resolve_cutoff, ReplayPoint, ReplayStrategy};
+use chrono::{DateTime, TimeZone, Utc};
+
+fn at(hour: u32) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2025, 12, 1, hour, 0, 0).unwrap()
+}
+
+fn edit(hour: u32, session: &str, old: &str, new: &str) -> EditRecord {
+    EditRecord {
+        file_path: "src/app.py".to_string(),
+        timestamp: at(hour),
+        model: "claude-opus-4-5-20251101".to_string(),
+        session_id: session.to_string(),
+        change_size: new.len(),
+        agent_tool: "claude-code".to_string(),
+        old_string: Some(old.to_string()),
+        new_string: Some(new.to_string()),
+        ..Default::default()
+    }
+}
+
+fn create(hour: u32, session: &str, content: &str) -> EditRecord {
+    EditRecord {
+        is_create: true,
+        old_string: None,
+        new_string: None,
+        create_content: Some(content.to_string()),
+        ..edit(hour, session, "", "")
+    }
+}
+
+fn history() -> Vec<EditRecord> {
+    vec![
+        create(8, "session-a", "def main():\n    pass\n"),
+        edit(9, "session-a", "    pass\n", "    print('v1')\n"),
+        edit(10, "session-b", "print('v1')", "print('v2')"),
+    ]
+}
+
+#[test]
+fn test_forward_replay_at_timestamp_and_session() {
+    let edits = history();
+    let current = "def main():\n    print('v2')\n";
+
+    let replay = reconstruct(Some(current), &edits, at(9));
+    assert_eq!(replay.strategy, ReplayStrategy::Forward);
+    assert_eq!(
+        replay.content.as_deref(),
+        Some("def main():\n    print('v1')\n")
+    );
+    assert_eq!(replay.applied, 2);
+    assert!(replay.divergences.is_empty());
+    assert_eq!(replay.matches_working_tree, Some(true));
+
+    let cutoff = resolve_cutoff(&edits, &ReplayPoint::Session("session-b".to_string())).unwrap();
+    assert_eq!(cutoff, at(10));
+    assert!(resolve_cutoff(&edits, &ReplayPoint::Session("nope".to_string())).is_err());
+}
+
+#[test]
+fn test_forward_replay_reports_divergence_from_human_edits() {
+    let mut edits = history();
+    // A human renamed the print before session-b's edit, so its old_string no longer matches.
+    edits[2] = edit(10, "session-b", "print('v1') # human", "print('v2')");
+
+    let replay = reconstruct(Some("something else\n"), &edits, at(10));
+    assert_eq!(replay.applied, 2);
+    assert_eq!(replay.divergences.len(), 1);
+    assert_eq!(replay.divergences[0].session_id, "session-b");
+    assert_eq!(replay.matches_working_tree, Some(false));
+}
+
+#[test]
+fn test_backward_replay_without_creation() {
+    let edits = vec![
+        edit(9, "session-a", "x = 1", "x = 2"),
+        edit(10, "session-b", "y = 1", "y = 2"),
+    ];
+    let current = "x = 2\ny = 2\n";
+
+    let replay = reconstruct(Some(current), &edits, at(9));
+    assert_eq!(replay.strategy, ReplayStrategy::Backward);
+    assert_eq!(replay.content.as_deref(), Some("x = 2\ny = 1\n"));
+    assert_eq!(replay.applied, 1);
+
+    let replay = reconstruct(Some("x = 2\ny = 3\n"), &edits, at(8));
+    assert_eq!(replay.content.as_deref(), Some("x = 1\ny = 3\n"));
+    assert_eq!(replay.divergences.len(), 1);
+    assert_eq!(replay.divergences[0].session_id, "session-b");
+}
+
+#[test]
+fn test_replay_before_creation_has_no_content() {
+    let edits = history();
+    let replay = reconstruct(Some("def main():\n    print('v2')\n"), &edits, at(7));
+    assert_eq!(replay.strategy, ReplayStrategy::Backward);
+    assert_eq!(replay.content, None);
+}
+
+#[test]
+fn test_replay_reports_notebook_cell_edits_as_divergences() {
+    let cell = |hour: u32| EditRecord {
+        old_string: None,
+        cell_id: Some("cell-1".to_string()),
+        ..edit(hour, "session-b", "", "print('cell')")
+    };
+    let mut edits = history();
+    edits.push(cell(11));
+
+    let replay = reconstruct(None, &edits, at(11));
+    assert_eq!(replay.strategy, ReplayStrategy::Forward);
+    assert_eq!(
+        replay.content.as_deref(),
+        Some("def main():\n    print('v2')\n")
+    );
+    assert_eq!(
+        replay.divergences[0].reason,
+        "notebook cell edit cannot be replayed as text"
+    );
+
+    let current = "def main():\n    print('cell')\n";
+    let replay = reconstruct(Some(current), &[cell(11)], at(10));
+    assert_eq!(replay.content.as_deref(), Some(current));
+    assert_eq!(
+        replay.divergences[0].reason,
+        "notebook cell edit cannot be undone as text"
+    );
+}
+
+#[test]
+fn test_forward_replay_ambiguous_match_is_divergence() {
+    let replace_all = |mut e: EditRecord| {
+        e.replace_all = true;
+        e
+    };
+    let edits = vec![
+        create(8, "session-a", "a = 1\nb = 1\n"),
+        // A single replacement can't say which `= 1` it meant.
+        edit(9, "session-a", "= 1", "= 2"),
+        replace_all(edit(10, "session-b", "= 1", "= 3")),
+    ];
+
+    let replay = reconstruct(None, &edits, at(9));
+    assert_eq!(replay.content.as_deref(), Some("a = 1\nb = 1\n"));
+    assert_eq!(replay.applied, 1);
+    assert_eq!(replay.divergences.len(), 1);
+    assert_eq!(
+        replay.divergences[0].reason,
+        "text to replace found 2 times"
+    );
+
+    let replay = reconstruct(None, &edits, at(10));
+    assert_eq!(replay.content.as_deref(), Some("a = 3\nb = 3\n"));
+    assert_eq!(replay.applied, 2);
+}
+
+#[test]
+fn test_backward_replay_ambiguous_match_is_divergence() {
+    let mut rename = edit(10, "session-b", "old_name", "new_name");
+    let edits = vec![rename.clone()];
+    let current = "new_name()\nnew_name()\n";
+
+    // Only one call was renamed, but the trace doesn't say which.
+    let replay = reconstruct(Some(current), &edits, at(9));
+    assert_eq!(replay.content.as_deref(), Some(current));
+    assert_eq!(replay.applied, 0);
+    assert_eq!(
+        replay.divergences[0].reason,
+        "inserted text found 2 times in later version"
+    );
+
+    rename.replace_all = true;
+    let replay = reconstruct(Some(current), &[rename], at(9));
+    assert_eq!(replay.content.as_deref(), Some("old_name()\nold_name()\n"));
+    assert!(replay.divergences.is_empty());
+}
@@ -0,0 +1,230 @@
+use ai_blame::config::get_default_config;
+use ai_blame::models::{
+    CommentPlacement, CurationAction, CurationEvent, FileHistory, FileRule, OutputConfig,
+    OutputPolicy,
+};
+use ai_blame::store;
+use ai_blame::updater::{apply_rule, output_path};
+use chrono::{TimeZone, Utc};
+use std::fs;
+use std::path::Path;
+use tempfile::TempDir;
+
+fn event(hour: u32, action: CurationAction, model: &str) -> CurationEvent {
+    CurationEvent {
+        timestamp: Utc.with_ymd_and_hms(2025, 12, 1, hour, 0, 0).unwrap(),
+        model: Some(model.to_string()),
+        action: Some(action),
+        description: None,
+        agent_tool: Some("claude-code".to_string()),
+        agent_version: None,
+        cell_id: None,
+    }
+}
+
+fn history(path: &str, events: Vec<CurationEvent>) -> FileHistory {
+    FileHistory {
+        file_path: path.to_string(),
+        events,
+    }
+}
+
+fn store_rule(store_path: &Path) -> FileRule {
+    FileRule {
+        pattern: "*".to_string(),
+        policy: OutputPolicy::Store,
+        format: "yaml".to_string(),
+        comment_syntax: None,
+        template: None,
+        placement: CommentPlacement::Bottom,
+        sidecar_pattern: None,
+        store_path: Some(store_path.to_string_lossy().to_string()),
+        cell_history: false,
+    }
+}
+
+/// Write two runs for two files and check the store merges and reads them back.
+fn assert_round_trip(store_path: &Path) {
+    let temp = TempDir::new().unwrap();
+    let source = temp.path().join("main.py");
+    fs::write(&source, "print('hi')\n").unwrap();
+    let rule = store_rule(store_path);
+
+    let first = history(
+        "src/main.py",
+        vec![event(8, CurationAction::Created, "claude-opus-4-5")],
+    );
+    let second = history(
+        "src/main.py",
+        vec![
+            event(8, CurationAction::Created, "claude-opus-4-5"),
+            event(9, CurationAction::Edited, "claude-sonnet-4-5"),
+        ],
+    );
+    let other = history(
+        "docs/readme.md",
+        vec![event(10, CurationAction::Edited, "gpt-5")],
+    );
+
+    assert!(apply_rule(&source, &first, &rule, false).unwrap().0);
+    assert!(apply_rule(&source, &second, &rule, false).unwrap().0);
+    assert!(apply_rule(&source, &other, &rule, false).unwrap().0);
+
+    // The source file itself is never touched.
+    assert_eq!(fs::read_to_string(&source).unwrap(), "print('hi')\n");
+
+    let stored = store::read_history(store_path, "./src/main.py")
+        .unwrap()
+        .unwrap();
+    assert_eq!(stored.file_path, "src/main.py");
+    assert_eq!(stored.events.len(), 2);
+    assert_eq!(stored.events[1].model.as_deref(), Some("claude-sonnet-4-5"));
+    assert_eq!(stored.events[0].action, Some(CurationAction::Created));
+
+    let all = store::read_all(store_path).unwrap();
+    let mut keys: Vec<_> = all.keys().cloned().collect();
+    keys.sort();
+    assert_eq!(keys, vec!["docs/readme.md", "src/main.py"]);
+}
+
+#[test]
+fn test_store_directory_backend() {
+    let temp = TempDir::new().unwrap();
+    let store_path = temp.path().join(".provenance");
+    assert_round_trip(&store_path);
+
+    let entry = store_path.join("src/main.py.yaml");
+    let content = fs::read_to_string(&entry).unwrap();
+    assert!(content.starts_with("source_file: src/main.py\nedit_history:\n"));
+}
+
+#[test]
+fn test_store_jsonl_backend() {
+    let temp = TempDir::new().unwrap();
+    let store_path = temp.path().join("provenance.jsonl");
+    assert_round_trip(&store_path);
+
+    let content = fs::read_to_string(&store_path).unwrap();
+    let lines: Vec<_> = content.lines().collect();
+    assert_eq!(lines.len(), 2);
+    // Sorted by path for stable diffs.
+    assert!(lines[0].contains("\"docs/readme.md\""));
+    assert!(lines[1].contains("\"src/main.py\""));
+}
+
+#[test]
+fn test_store_duckdb_backend() {
+    let temp = TempDir::new().unwrap();
+    assert_round_trip(&temp.path().join("provenance.ddb"));
+}
+
+#[test]
+fn test_store_batch_write_merges_existing_histories() {
+    for name in ["provenance.jsonl", "provenance.ddb"] {
+        let temp = TempDir::new().unwrap();
+        let store_path = temp.path().join(name);
+        let main = history(
+            "src/main.py",
+            vec![event(8, CurationAction::Created, "claude-opus-4-5")],
+        );
+        store::write_history(&store_path, &main, false).unwrap();
+
+        let main_again = history(
+            "src/main.py",
+            vec![event(9, CurationAction::Edited, "claude-sonnet-4-5")],
+        );
+        let other = history(
+            "docs/readme.md",
+            vec![event(10, CurationAction::Edited, "gpt-5")],
+        );
+        let invalid = history("/", vec![event(11, CurationAction::Edited, "gpt-5")]);
+        let results =
+            store::write_histories(&store_path, &[&main_again, &other, &invalid], false).unwrap();
+        let oks: Vec<bool> = results.iter().map(|(ok, _)| *ok).collect();
+        assert_eq!(oks, vec![true, true, false], "{}", name);
+
+        let all = store::read_all(&store_path).unwrap();
+        assert_eq!(all.len(), 2, "{}", name);
+        assert_eq!(all["src/main.py"].events.len(), 2, "{}", name);
+        assert_eq!(all["docs/readme.md"].events.len(), 1, "{}", name);
+    }
+}
+
+#[test]
+fn test_store_paths_resolve_against_project_root() {
+    let root = Path::new("/work/project");
+    let mut absolute = store_rule(Path::new("/var/provenance.jsonl"));
+    absolute.pattern = "*.md".to_string();
+    let mut config = OutputConfig {
+        rules: vec![
+            absolute,
+            FileRule {
+                pattern: "*.py".to_string(),
+                store_path: None,
+                ..store_rule(Path::new(""))
+            },
+        ],
+        defaults: Some(store_rule(Path::new("provenance.ddb"))),
+        ..get_default_config()
+    };
+    store::resolve_store_paths(&mut config, root);
+
+    let paths: Vec<_> = config
+        .rules
+        .iter()
+        .chain(config.defaults.as_ref())
+        .map(store::store_path_for_rule)
+        .collect();
+    assert_eq!(
+        paths,
+        vec![
+            Path::new("/var/provenance.jsonl").to_path_buf(),
+            root.join(store::DEFAULT_STORE_PATH),
+            root.join("provenance.ddb"),
+        ]
+    );
+}
+
+#[test]
+fn test_store_dry_run_and_output_path() {
+    let temp = TempDir::new().unwrap();
+    let store_path = temp.path().join(".provenance");
+    let rule = store_rule(&store_path);
+    let h = history(
+        "src/lib.rs",
+        vec![event(8, CurationAction::Created, "claude-opus-4-5")],
+    );
+
+    let (ok, msg) = apply_rule(Path::new("src/lib.rs"), &h, &rule, true).unwrap();
+    assert!(ok);
+    assert!(msg.contains("Would store history for src/lib.rs"));
+    assert!(!store_path.exists());
+
+    assert_eq!(
+        output_path(Path::new("src/lib.rs"), &h, &rule),
+        Some(store_path.join("src/lib.rs.yaml"))
+    );
+}
+
+#[test]
+fn test_store_rejects_paths_outside_the_project() {
+    assert_eq!(store::store_key("./src/x.rs").as_deref(), Some("src/x.rs"));
+    assert_eq!(store::store_key("../x.rs"), None);
+    assert_eq!(store::store_key("src/../x.rs"), None);
+    assert_eq!(store::store_key("/abs/x.rs"), None);
+
+    let temp = TempDir::new().unwrap();
+    let store_path = temp.path().join("provenance.jsonl");
+    let outside = history(
+        "../x.rs",
+        vec![event(8, CurationAction::Created, "claude-opus-4-5")],
+    );
+    let (ok, msg) = store::write_history(&store_path, &outside, false).unwrap();
+    assert!(!ok);
+    assert!(msg.contains("Cannot store history"));
+    assert!(!store_path.exists());
+    assert_eq!(
+        output_path(Path::new("x.rs"), &outside, &store_rule(&store_path)),
+        None
+    );
+}
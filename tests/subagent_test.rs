@@ -0,0 +1,228 @@
+use ai_blame::extractor::parse_trace_file;
+use ai_blame::transcript::{
+    link_subagents, parse_transcript, parse_transcript_with_subagents, subagent_files,
+};
+use serde_json::json;
+use std::fs;
+use std::path::Path;
+use tempfile::TempDir;
+
+const FIXTURE_DIR: &str = "tests/data/mixed-traces/claude-traces";
+const FIXTURE_SESSION: &str = "2c965cfd-41e4-4e31-8339-2986ebced03c";
+
+fn write_jsonl(path: &Path, records: &[serde_json::Value]) {
+    let lines: Vec<String> = records.iter().map(|r| r.to_string()).collect();
+    fs::write(path, lines.join("\n") + "\n").unwrap();
+}
+
+/// A record of session `session-1`, from the subagent `agent` if given.
+fn record(
+    uuid: &str,
+    minute: u32,
+    agent: Option<&str>,
+    body: serde_json::Value,
+) -> serde_json::Value {
+    let mut record = json!({
+        "uuid": uuid,
+        "sessionId": "session-1",
+        "timestamp": format!("2025-03-01T10:{:02}:00Z", minute),
+        "cwd": "/repo",
+    });
+    if let Some(agent) = agent {
+        record["isSidechain"] = json!(true);
+        record["agentId"] = json!(agent);
+    }
+    for (key, value) in body.as_object().unwrap() {
+        record[key] = value.clone();
+    }
+    record
+}
+
+fn task_call(uuid: &str, minute: u32, tool_use_id: &str, prompt: &str) -> serde_json::Value {
+    record(
+        uuid,
+        minute,
+        None,
+        json!({"type": "assistant", "message": {"role": "assistant", "model": "claude-opus-4-5",
+            "content": [{"type": "tool_use", "id": tool_use_id, "name": "Task",
+                         "input": {"description": "Explore", "prompt": prompt}}]}}),
+    )
+}
+
+/// A session that starts two subagents: the result of the first names it, the second is
+/// only recognisable by its prompt.
+fn write_session(dir: &Path) -> std::path::PathBuf {
+    let session = dir.join("session-1.jsonl");
+    write_jsonl(
+        &session,
+        &[
+            record(
+                "u0",
+                0,
+                None,
+                json!({"type": "user", "message": {"role": "user", "content": "Fix the parser"}}),
+            ),
+            task_call("u1", 1, "toolu_a", "Find the parser"),
+            record(
+                "u2",
+                5,
+                None,
+                json!({"type": "user", "toolUseResult": {"status": "completed", "agentId": "a1"},
+                    "message": {"role": "user", "content": [
+                        {"type": "tool_result", "tool_use_id": "toolu_a", "content": "src/parser.rs"}]}}),
+            ),
+            task_call("u3", 6, "toolu_b", "Fix the off-by-one in src/parser.rs"),
+        ],
+    );
+
+    write_jsonl(
+        &dir.join("agent-a1.jsonl"),
+        &[
+            record(
+                "a1-0",
+                2,
+                Some("a1"),
+                json!({"type": "user", "message": {"role": "user", "content": "Find the parser"}}),
+            ),
+            record(
+                "a1-1",
+                3,
+                Some("a1"),
+                json!({"type": "assistant", "message": {"role": "assistant", "model": "claude-haiku-4-5",
+                    "content": [{"type": "text", "text": "It is in src/parser.rs"}]}}),
+            ),
+        ],
+    );
+
+    let nested = dir.join("session-1").join("subagents");
+    fs::create_dir_all(&nested).unwrap();
+    write_jsonl(
+        &nested.join("agent-a2.jsonl"),
+        &[
+            record(
+                "a2-0",
+                7,
+                Some("a2"),
+                json!({"type": "user", "message": {"role": "user",
+                    "content": "Fix the off-by-one in src/parser.rs"}}),
+            ),
+            record(
+                "a2-1",
+                8,
+                Some("a2"),
+                json!({"type": "assistant", "message": {"role": "assistant", "model": "claude-haiku-4-5",
+                    "content": [{"type": "tool_use", "id": "toolu_edit", "name": "Edit",
+                        "input": {"file_path": "/repo/src/parser.rs", "old_string": "i + 1", "new_string": "i"}}]}}),
+            ),
+            record(
+                "a2-2",
+                9,
+                Some("a2"),
+                json!({"type": "user", "parentUuid": "a2-1",
+                    "toolUseResult": {"filePath": "/repo/src/parser.rs", "oldString": "i + 1", "newString": "i"},
+                    "message": {"role": "user", "content": [
+                        {"type": "tool_result", "tool_use_id": "toolu_edit", "content": "ok"}]}}),
+            ),
+        ],
+    );
+
+    // A subagent of another session in the same directory.
+    let mut other = record(
+        "x-0",
+        4,
+        Some("x1"),
+        json!({"type": "user", "message": {"role": "user", "content": "Warmup"}}),
+    );
+    other["sessionId"] = json!("session-2");
+    write_jsonl(&dir.join("agent-x1.jsonl"), &[other]);
+
+    session
+}
+
+#[test]
+fn test_fixture_sidechains_name_their_session() {
+    let agent = parse_transcript(&Path::new(FIXTURE_DIR).join("agent-ad47cca.jsonl")).unwrap();
+    assert_eq!(agent.meta.agent_id.as_deref(), Some("ad47cca"));
+    assert_eq!(
+        agent.meta.parent_session_id.as_deref(),
+        Some(FIXTURE_SESSION)
+    );
+    assert!(agent.meta.parent_tool_use_id.is_none());
+
+    // Warmup subagents have no starting call, but still belong to the session.
+    let session = parse_transcript_with_subagents(
+        &Path::new(FIXTURE_DIR).join(format!("{}.jsonl", FIXTURE_SESSION)),
+    )
+    .unwrap();
+    assert!(session.meta.agent_id.is_none());
+    let agent_ids: Vec<_> = session
+        .subagents
+        .iter()
+        .map(|s| s.meta.agent_id.as_deref().unwrap())
+        .collect();
+    assert_eq!(agent_ids.len(), 2);
+    assert!(agent_ids.contains(&"ad47cca") && agent_ids.contains(&"ae9a854"));
+}
+
+#[test]
+fn test_subagents_linked_to_starting_tool_call() {
+    let temp = TempDir::new().unwrap();
+    let session_path = write_session(temp.path());
+
+    let files = subagent_files(&session_path);
+    let names: Vec<_> = files
+        .iter()
+        .map(|f| f.file_name().unwrap().to_string_lossy().into_owned())
+        .collect();
+    assert_eq!(
+        names,
+        ["agent-a1.jsonl", "agent-x1.jsonl", "agent-a2.jsonl"]
+    );
+
+    let session = parse_transcript_with_subagents(&session_path).unwrap();
+    let linked: Vec<_> = session
+        .subagents
+        .iter()
+        .map(|s| {
+            (
+                s.meta.agent_id.as_deref().unwrap(),
+                s.meta.parent_tool_use_id.as_deref(),
+            )
+        })
+        .collect();
+    // a1 by the agent ID on its result, a2 by its prompt; x1 isn't this session's.
+    assert_eq!(linked, [("a1", Some("toolu_a")), ("a2", Some("toolu_b"))]);
+
+    // Subagents of sessions that aren't loaded stay at the top level.
+    let all: Vec<_> = [session_path.clone(), temp.path().join("agent-x1.jsonl")]
+        .iter()
+        .map(|p| parse_transcript(p).unwrap())
+        .collect();
+    let linked = link_subagents(all);
+    assert_eq!(linked.len(), 2);
+    assert_eq!(linked[1].meta.agent_id.as_deref(), Some("x1"));
+
+    // The JSON form nests the subagents.
+    let json = serde_json::to_value(&session).unwrap();
+    assert_eq!(json["subagents"][1]["meta"]["agent_id"], "a2");
+}
+
+#[test]
+fn test_subagent_edits_attributed_to_session() {
+    let temp = TempDir::new().unwrap();
+    write_session(temp.path());
+
+    let edits = parse_trace_file(
+        &temp
+            .path()
+            .join("session-1")
+            .join("subagents")
+            .join("agent-a2.jsonl"),
+        "",
+    )
+    .unwrap();
+    assert_eq!(edits.len(), 1);
+    assert_eq!(edits[0].session_id, "session-1");
+    assert_eq!(edits[0].agent_id.as_deref(), Some("a2"));
+    assert_eq!(edits[0].model, "claude-haiku-4-5");
+}
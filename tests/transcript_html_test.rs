@@ -0,0 +1,144 @@
+use ai_blame::pricing::PriceTable;
+use ai_blame::transcript::html::render_html;
+use ai_blame::transcript::{
+    ContentBlock, FileOpType, Role, TokenUsage, Transcript, TranscriptMessage, TranscriptMeta,
+};
+use chrono::{DateTime, Utc};
+use serde_json::json;
+
+fn ts(s: &str) -> DateTime<Utc> {
+    DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+}
+
+fn meta(session_id: &str, agent_id: Option<&str>) -> TranscriptMeta {
+    TranscriptMeta {
+        session_id: session_id.to_string(),
+        agent_tool: "claude-code".to_string(),
+        agent_version: Some("2.0.1".to_string()),
+        cwd: Some("/repo".to_string()),
+        git_branch: Some("main".to_string()),
+        slug: None,
+        start_time: ts("2025-03-01T10:00:00Z"),
+        end_time: None,
+        source_file: Some("/traces/s1.jsonl".to_string()),
+        agent_id: agent_id.map(str::to_string),
+        parent_session_id: None,
+        parent_tool_use_id: agent_id.map(|_| "toolu_task".to_string()),
+        continues_from: None,
+        continuations: Vec::new(),
+    }
+}
+
+fn message(id: &str, role: Role, minute: u32, content: Vec<ContentBlock>) -> TranscriptMessage {
+    TranscriptMessage {
+        id: id.to_string(),
+        role,
+        timestamp: ts(&format!("2025-03-01T10:{:02}:00Z", minute)),
+        content,
+        model: None,
+        usage: None,
+    }
+}
+
+fn transcript() -> Transcript {
+    let mut transcript = Transcript::new(meta("s1", None));
+    transcript.add_message(message(
+        "u0",
+        Role::User,
+        0,
+        vec![ContentBlock::Text {
+            text: "Why does <script>alert(1)</script> run?".to_string(),
+        }],
+    ));
+    let mut reply = message(
+        "u1",
+        Role::Assistant,
+        1,
+        vec![
+            ContentBlock::Thinking {
+                thinking: "Look at the parser first".to_string(),
+            },
+            ContentBlock::Code {
+                code: "fn main() { // entry\n    let s = \"hi\";\n}".to_string(),
+                language: Some("rust".to_string()),
+            },
+            ContentBlock::ToolUse {
+                id: "toolu_task".to_string(),
+                name: "Task".to_string(),
+                input: json!({"description": "Find the parser", "prompt": "Find it"}),
+            },
+            ContentBlock::FileOperation {
+                operation: FileOpType::Edit,
+                file_path: "src/parser.rs".to_string(),
+                content: Some("let x = i;\nreturn x;".to_string()),
+                old_content: Some("let x = i + 1;\nreturn x;".to_string()),
+            },
+            ContentBlock::Command {
+                command: "cargo test".to_string(),
+                output: Some("1 failed".to_string()),
+                exit_code: Some(101),
+            },
+        ],
+    );
+    reply.model = Some("claude-opus-4-5".to_string());
+    reply.usage = Some(TokenUsage {
+        input_tokens: Some(1234),
+        output_tokens: Some(56),
+        cache_read_tokens: Some(10_000),
+        cache_creation_tokens: None,
+    });
+    transcript.add_message(reply);
+
+    let mut subagent = Transcript::new(meta("s1", Some("a1")));
+    subagent.add_message(message(
+        "a1-0",
+        Role::User,
+        2,
+        vec![ContentBlock::Text {
+            text: "Find it".to_string(),
+        }],
+    ));
+    transcript.subagents.push(subagent);
+    transcript
+}
+
+#[test]
+fn test_html_is_standalone_with_anchors_and_collapsible_blocks() {
+    let html = render_html(&transcript(), &PriceTable::builtin());
+
+    assert!(html.starts_with("<!DOCTYPE html>"));
+    assert!(html.contains("<style>"));
+    assert!(!html.contains("<script"));
+    assert!(html.contains("&lt;script&gt;alert(1)&lt;/script&gt;"));
+
+    assert!(html.contains("id=\"m1\""));
+    assert!(html.contains("href=\"#m2\""));
+    assert!(html.contains("<details class=\"thinking\">"));
+    assert!(html.contains("<details class=\"tool-use\"><summary>Tool <b>Task</b> Find the parser"));
+
+    // The subagent follows the call that started it, with its own anchors.
+    let call = html.find("<details class=\"tool-use\">").unwrap();
+    let subagent = html.find("<details class=\"subagent\">").unwrap();
+    let edit = html.find("<details class=\"file-op\" open>").unwrap();
+    assert!(call < subagent && subagent < edit);
+    assert!(html.contains("id=\"a1-m1\""));
+
+    assert!(html.contains("<span class=\"error\">exit 101</span>"));
+}
+
+#[test]
+fn test_html_diffs_highlighting_and_usage() {
+    let html = render_html(&transcript(), &PriceTable::builtin());
+
+    assert!(html.contains("<span class=\"add\">+1</span> <span class=\"del\">-1</span>"));
+    assert!(html.contains("<span class=\"del\">-let x = i + 1;</span>"));
+    assert!(html.contains("<span class=\"add\">+let x = i;</span>"));
+    assert!(html.contains("<span class=\"ctx\"> return x;</span>"));
+
+    assert!(html.contains("<code data-lang=\"rust\"><span class=\"kw\">fn</span> main()"));
+    assert!(html.contains("<span class=\"com\">// entry</span>"));
+    assert!(html.contains("<span class=\"str\">&quot;hi&quot;</span>"));
+
+    assert!(html.contains("1,234 in · 56 out · 10,000 cached · $"));
+    assert!(html.contains("<span class=\"model\">claude-opus-4-5</span>"));
+}
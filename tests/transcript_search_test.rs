@@ -0,0 +1,390 @@
+use ai_blame::pricing::PriceTable;
+use ai_blame::transcript::{search_transcripts, SearchResult, TranscriptSearchCriteria};
+use serde_json::{json, Value};
+use std::fs;
+use std::path::Path;
+use tempfile::TempDir;
+
+/// Write a Claude transcript with one message per `(role, content)` pair.
+fn write_session(dir: &Path, session_id: &str, day: u32, messages: &[(&str, Value)]) {
+    let lines: Vec<String> = messages
+        .iter()
+        .enumerate()
+        .map(|(i, (role, content))| {
+            json!({
+                "type": role,
+                "uuid": format!("{}-{}", session_id, i),
+                "sessionId": session_id,
+                "cwd": "/repo",
+                "version": "2.0.0",
+                "timestamp": format!("2025-03-{:02}T10:{:02}:00Z", day, i),
+                "message": {"role": role, "model": "claude-opus", "content": content},
+            })
+            .to_string()
+        })
+        .collect();
+    fs::write(
+        dir.join(format!("{}.jsonl", session_id)),
+        lines.join("\n") + "\n",
+    )
+    .unwrap();
+}
+
+fn fixture() -> TempDir {
+    let temp = TempDir::new().unwrap();
+    write_session(
+        temp.path(),
+        "alpha",
+        1,
+        &[
+            ("user", json!("The cache migration failed again")),
+            (
+                "assistant",
+                json!([
+                    {"type": "thinking", "thinking": "Planning the schema upgrade first"},
+                    {"type": "text", "text": "I will look at the schema version."},
+                ]),
+            ),
+        ],
+    );
+    write_session(
+        temp.path(),
+        "beta",
+        2,
+        &[
+            ("user", json!("cache cache cache index")),
+            (
+                "assistant",
+                json!([
+                    {"type": "tool_use", "id": "t1", "name": "Bash",
+                     "input": {"command": "cargo test --workspace"}},
+                    {"type": "tool_use", "id": "t2", "name": "Edit",
+                     "input": {"file_path": "/repo/src/lib.rs", "old_string": "a", "new_string": "b"}},
+                ]),
+            ),
+        ],
+    );
+    write_session(
+        temp.path(),
+        "gamma",
+        3,
+        &[("user", json!("Something unrelated about parquet"))],
+    );
+    temp
+}
+
+fn search(dir: &Path, query: &str) -> SearchResult {
+    let criteria = TranscriptSearchCriteria {
+        query: Some(query.to_string()),
+        ..Default::default()
+    };
+    search_transcripts(dir, &criteria, 0, &PriceTable::builtin()).unwrap()
+}
+
+fn sessions(result: &SearchResult) -> Vec<&str> {
+    result
+        .matching_transcripts
+        .iter()
+        .map(|m| m.transcript.session_id.as_str())
+        .collect()
+}
+
+#[test]
+fn test_indexed_search_ranks_and_combines_terms() {
+    let temp = fixture();
+    let dir = temp.path();
+
+    // More occurrences in a block of the same length ranks higher.
+    let result = search(dir, "Cache");
+    assert_eq!(sessions(&result), vec!["beta", "alpha"]);
+    assert_eq!(result.total_matches, 2);
+    let scores: Vec<f64> = result
+        .matching_transcripts
+        .iter()
+        .map(|m| m.score.unwrap())
+        .collect();
+    assert!(scores[0] > scores[1] && scores[1] > 0.0);
+    assert_eq!(
+        result.matching_transcripts[1].matches[0].snippet,
+        "The cache migration failed again"
+    );
+
+    assert_eq!(sessions(&search(dir, "cache -migration")), vec!["beta"]);
+    assert_eq!(sessions(&search(dir, "cache NOT index")), vec!["alpha"]);
+    assert_eq!(sessions(&search(dir, "migrat*")), vec!["alpha"]);
+    assert_eq!(sessions(&search(dir, "\"cache migration\"")), vec!["alpha"]);
+    assert!(search(dir, "\"migration cache\"")
+        .matching_transcripts
+        .is_empty());
+    assert_eq!(search(dir, "parquet OR (schema version)").total_matches, 2);
+    // Whole words only: "cach" is not a word anywhere.
+    assert!(search(dir, "cach").matching_transcripts.is_empty());
+
+    assert!(search_transcripts(
+        dir,
+        &TranscriptSearchCriteria {
+            query: Some("(cache".to_string()),
+            ..Default::default()
+        },
+        0,
+        &PriceTable::builtin()
+    )
+    .is_err());
+}
+
+#[test]
+fn test_indexed_search_field_filters() {
+    let temp = fixture();
+    let dir = temp.path();
+
+    let result = search(dir, "tool:bash");
+    assert_eq!(sessions(&result), vec!["beta"]);
+    let snippet = &result.matching_transcripts[0].matches[0];
+    assert_eq!(snippet.block_type, "tool");
+    assert_eq!(snippet.role, "assistant");
+
+    assert_eq!(sessions(&search(dir, "tool:bash workspace")), vec!["beta"]);
+    assert!(search(dir, "tool:bash role:user")
+        .matching_transcripts
+        .is_empty());
+    assert_eq!(sessions(&search(dir, "file:src/lib.rs")), vec!["beta"]);
+    assert_eq!(
+        sessions(&search(dir, "block:thinking schema")),
+        vec!["alpha"]
+    );
+    assert_eq!(sessions(&search(dir, "role:user")).len(), 3);
+
+    // Metadata filters combine with the query.
+    let criteria = TranscriptSearchCriteria {
+        query: Some("cache".to_string()),
+        session_id_pattern: Some("alp".to_string()),
+        ..Default::default()
+    };
+    assert_eq!(
+        sessions(&search_transcripts(dir, &criteria, 0, &PriceTable::builtin()).unwrap()),
+        vec!["alpha"]
+    );
+}
+
+#[test]
+fn test_indexed_search_follows_file_changes() {
+    let temp = fixture();
+    let dir = temp.path();
+    assert_eq!(search(dir, "parquet").total_matches, 1);
+
+    fs::remove_file(dir.join("gamma.jsonl")).unwrap();
+    write_session(
+        dir,
+        "alpha",
+        1,
+        &[("user", json!("Now about parquet exports"))],
+    );
+    assert_eq!(sessions(&search(dir, "parquet")), vec!["alpha"]);
+    assert!(search(dir, "migration").matching_transcripts.is_empty());
+}
+
+#[test]
+fn test_search_without_query_and_scan_fallback() {
+    let temp = fixture();
+    let dir = temp.path();
+
+    // No query lists filtered transcripts newest first, with a limit.
+    let result = search_transcripts(
+        dir,
+        &TranscriptSearchCriteria::default(),
+        2,
+        &PriceTable::builtin(),
+    )
+    .unwrap();
+    assert_eq!(result.total_matches, 3);
+    assert_eq!(sessions(&result), vec!["gamma", "beta"]);
+    assert!(result.matching_transcripts[0].score.is_none());
+
+    // Case-sensitive searches scan messages for substrings.
+    let criteria = TranscriptSearchCriteria {
+        query: Some("The cach".to_string()),
+        case_sensitive: true,
+        ..Default::default()
+    };
+    let result = search_transcripts(dir, &criteria, 0, &PriceTable::builtin()).unwrap();
+    assert_eq!(sessions(&result), vec!["alpha"]);
+    assert!(result.matching_transcripts[0].score.is_none());
+}
+
+/// A Claude session with a file operation, a failed tool call and token usage, a Codex
+/// session whose `ls` failed, and a plain session.
+fn structured_fixture() -> TempDir {
+    let temp = TempDir::new().unwrap();
+    let record = |i: usize, role: &str, message: Value, extra: Value| {
+        let mut record = json!({
+            "type": role,
+            "uuid": format!("delta-{}", i),
+            "sessionId": "delta",
+            "cwd": "/repo",
+            "gitBranch": "feature/parser",
+            "version": "2.0.0",
+            "timestamp": format!("2025-03-04T10:{:02}:00Z", i),
+            "message": message,
+        });
+        record
+            .as_object_mut()
+            .unwrap()
+            .extend(extra.as_object().unwrap().clone());
+        record.to_string()
+    };
+    let lines = [
+        record(
+            0,
+            "user",
+            json!({"role": "user", "content": "Add a parser module"}),
+            json!({}),
+        ),
+        record(
+            1,
+            "assistant",
+            json!({"role": "assistant", "model": "claude-opus",
+                   "usage": {"input_tokens": 1200, "output_tokens": 300},
+                   "content": [{"type": "tool_use", "id": "t1", "name": "Write",
+                                "input": {"file_path": "/repo/src/parser.rs", "content": "fn p() {}"}}]}),
+            json!({}),
+        ),
+        record(
+            2,
+            "user",
+            json!({"role": "user", "content": [
+                {"type": "tool_result", "tool_use_id": "t1", "content": "ok"}]}),
+            json!({"toolUseResult": {"type": "create", "filePath": "/repo/src/parser.rs",
+                                     "content": "fn p() {}"}}),
+        ),
+        record(
+            3,
+            "user",
+            json!({"role": "user", "content": [
+                {"type": "tool_result", "tool_use_id": "t2", "content": "denied",
+                 "is_error": true}]}),
+            json!({}),
+        ),
+    ];
+    fs::write(temp.path().join("delta.jsonl"), lines.join("\n") + "\n").unwrap();
+
+    let codex = Path::new(env!("CARGO_MANIFEST_DIR")).join(
+        "tests/data/mixed-traces/codex-sessions/2025/12/29/\
+         rollout-2025-12-29T11-54-27-019b6b08-58f3-7580-9dca-fe3f30a10650.jsonl",
+    );
+    fs::copy(codex, temp.path().join("codex.jsonl")).unwrap();
+
+    write_session(
+        temp.path(),
+        "gamma",
+        3,
+        &[("user", json!("Something unrelated about parquet"))],
+    );
+    temp
+}
+
+#[test]
+fn test_structured_filters_on_index_and_scan() {
+    let temp = structured_fixture();
+    let dir = temp.path();
+
+    let check = |criteria: TranscriptSearchCriteria, expected: &[&str]| {
+        // Indexed (no query) and scanned (case-sensitive query) searches must agree.
+        let indexed = search_transcripts(dir, &criteria, 0, &PriceTable::builtin()).unwrap();
+        let scanned = search_transcripts(
+            dir,
+            &TranscriptSearchCriteria {
+                query: Some("".to_string()),
+                case_sensitive: true,
+                ..criteria.clone()
+            },
+            0,
+            &PriceTable::builtin(),
+        )
+        .unwrap();
+        for result in [indexed, scanned] {
+            let mut found: Vec<String> = result
+                .matching_transcripts
+                .iter()
+                .map(|m| {
+                    if m.transcript.agent_tool.contains("codex") {
+                        "codex".to_string()
+                    } else {
+                        m.transcript.session_id.clone()
+                    }
+                })
+                .collect();
+            found.sort();
+            assert_eq!(found, expected, "{:?}", criteria);
+        }
+    };
+
+    check(
+        TranscriptSearchCriteria {
+            touched_file: Some("SRC/parser.rs".to_string()),
+            ..Default::default()
+        },
+        &["delta"],
+    );
+    check(
+        TranscriptSearchCriteria {
+            used_tool: Some("write".to_string()),
+            ..Default::default()
+        },
+        &["delta"],
+    );
+    check(
+        TranscriptSearchCriteria {
+            used_tool: Some("shell_command".to_string()),
+            failed_command: true,
+            ..Default::default()
+        },
+        &["codex"],
+    );
+    check(
+        TranscriptSearchCriteria {
+            tool_error: true,
+            ..Default::default()
+        },
+        &["delta"],
+    );
+    check(
+        TranscriptSearchCriteria {
+            git_branch: Some("feature/".to_string()),
+            ..Default::default()
+        },
+        &["delta"],
+    );
+    check(
+        TranscriptSearchCriteria {
+            cwd: Some("ai-blame-test".to_string()),
+            ..Default::default()
+        },
+        &["codex"],
+    );
+    check(
+        TranscriptSearchCriteria {
+            min_tokens: Some(1500),
+            max_tokens: Some(1500),
+            ..Default::default()
+        },
+        &["delta"],
+    );
+    check(
+        TranscriptSearchCriteria {
+            max_tokens: Some(0),
+            ..Default::default()
+        },
+        &["gamma"],
+    );
+
+    // Filters combine with a query.
+    let criteria = TranscriptSearchCriteria {
+        query: Some("parser".to_string()),
+        tool_error: true,
+        ..Default::default()
+    };
+    assert_eq!(
+        sessions(&search_transcripts(dir, &criteria, 0, &PriceTable::builtin()).unwrap()),
+        vec!["delta"]
+    );
+}
@@ -0,0 +1,326 @@
+use ai_blame::models::{
+    CommentPlacement, CurationAction, CurationEvent, FileHistory, FileRule, OutputPolicy,
+};
+use ai_blame::updater::apply_rule;
+use chrono::{TimeZone, Utc};
+use std::fs;
+use tempfile::TempDir;
+
+fn sample_history(path: &str) -> FileHistory {
+    FileHistory {
+        file_path: path.to_string(),
+        events: vec![CurationEvent {
+            timestamp: Utc.with_ymd_and_hms(2025, 12, 1, 8, 3, 42).unwrap(),
+            model: Some("claude-opus-4-5-20251101".to_string()),
+            action: Some(CurationAction::Created),
+            description: None,
+            agent_tool: Some("claude-code".to_string()),
+            agent_version: None,
+            cell_id: None,
+        }],
+    }
+}
+
+fn append_rule(format: &str) -> FileRule {
+    FileRule {
+        policy: OutputPolicy::Append,
+        format: format.to_string(),
+        ..comment_rule()
+    }
+}
+
+fn comment_rule() -> FileRule {
+    FileRule {
+        pattern: "*".to_string(),
+        policy: OutputPolicy::Comment,
+        format: "yaml".to_string(),
+        comment_syntax: None,
+        template: None,
+        placement: CommentPlacement::Bottom,
+        sidecar_pattern: None,
+        store_path: None,
+        cell_history: false,
+    }
+}
+
+#[test]
+fn test_append_toml_writes_tool_table_and_replaces_existing() {
+    let temp = TempDir::new().unwrap();
+    let path = temp.path().join("pyproject.toml");
+    fs::write(
+        &path,
+        "[project]\nname = \"demo\"\n\n[tool.ai-blame]\nenabled = true\n",
+    )
+    .unwrap();
+
+    let history = sample_history("pyproject.toml");
+    let rule = append_rule("toml");
+    apply_rule(&path, &history, &rule, false).unwrap();
+    apply_rule(&path, &history, &rule, false).unwrap();
+
+    let content = fs::read_to_string(&path).unwrap();
+    assert!(content.starts_with("[project]\nname = \"demo\""));
+    assert!(content.contains("[tool.ai-blame]\nenabled = true"));
+    assert_eq!(content.matches("[[tool.ai-blame.edit_history]]").count(), 1);
+    assert!(content.contains("timestamp = 2025-12-01T08:03:42Z"));
+    assert!(content.contains("model = \"claude-opus-4-5-20251101\""));
+    assert!(content.contains("action = \"CREATED\""));
+}
+
+#[test]
+fn test_append_toml_replaces_history_headers_with_comments() {
+    let temp = TempDir::new().unwrap();
+    let path = temp.path().join("Cargo.toml");
+    fs::write(
+        &path,
+        "[package]\nname = \"demo\"\n\n\
+         [[tool.ai-blame.edit_history]]  # managed by ai-blame\n\
+         timestamp = 2025-11-30T08:00:00Z\n\
+         model = \"old-model\"\n\n\
+         [dependencies] # none yet\n",
+    )
+    .unwrap();
+
+    let history = sample_history("Cargo.toml");
+    apply_rule(&path, &history, &append_rule("toml"), false).unwrap();
+
+    let content = fs::read_to_string(&path).unwrap();
+    assert!(!content.contains("old-model"));
+    assert!(content.contains("[dependencies] # none yet\n"));
+    assert_eq!(content.matches("[[tool.ai-blame.edit_history]]").count(), 1);
+}
+
+#[test]
+fn test_append_front_matter_updates_existing_block() {
+    let temp = TempDir::new().unwrap();
+    let path = temp.path().join("page.qmd");
+    fs::write(&path, "---\ntitle: Demo\n---\n\n# Demo\n").unwrap();
+
+    let history = sample_history("page.qmd");
+    let rule = append_rule("front-matter");
+    apply_rule(&path, &history, &rule, false).unwrap();
+    apply_rule(&path, &history, &rule, false).unwrap();
+
+    let content = fs::read_to_string(&path).unwrap();
+    assert!(content.starts_with("---\ntitle: Demo\nedit_history:\n"));
+    assert_eq!(content.matches("edit_history:").count(), 1);
+    assert!(content.ends_with("---\n\n# Demo\n"));
+}
+
+#[test]
+fn test_append_front_matter_creates_block_when_missing() {
+    let temp = TempDir::new().unwrap();
+    let path = temp.path().join("notes.md");
+    fs::write(&path, "# Notes\n").unwrap();
+
+    let history = sample_history("notes.md");
+    apply_rule(&path, &history, &append_rule("front-matter"), false).unwrap();
+
+    let content = fs::read_to_string(&path).unwrap();
+    assert!(content.starts_with("---\nedit_history:\n"));
+    assert!(content.ends_with("---\n\n# Notes\n"));
+}
+
+#[test]
+fn test_append_ipynb_writes_notebook_and_cell_metadata() {
+    let temp = TempDir::new().unwrap();
+    let path = temp.path().join("analysis.ipynb");
+    fs::write(
+        &path,
+        r#"{"cells":[{"cell_type":"code","id":"cell-1","metadata":{},"source":["import pandas"]},{"cell_type":"code","id":"cell-2","metadata":{"ai_blame":{"edit_history":[]}},"source":["df"]}],"metadata":{"kernelspec":{"name":"python3"}},"nbformat":4,"nbformat_minor":5}"#,
+    )
+    .unwrap();
+
+    let mut history = sample_history("analysis.ipynb");
+    history.events[0].cell_id = Some("cell-1".to_string());
+    let mut rule = append_rule("ipynb");
+    rule.cell_history = true;
+    apply_rule(&path, &history, &rule, false).unwrap();
+
+    let notebook: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+    assert!(notebook.get("edit_history").is_none());
+    assert_eq!(notebook["metadata"]["kernelspec"]["name"], "python3");
+    assert_eq!(
+        notebook["metadata"]["ai_blame"]["edit_history"][0]["cell_id"],
+        "cell-1"
+    );
+
+    let cells = notebook["cells"].as_array().unwrap();
+    let cell_history = &cells[0]["metadata"]["ai_blame"]["edit_history"];
+    assert_eq!(cell_history[0]["model"], "claude-opus-4-5-20251101");
+    assert!(cell_history[0].get("cell_id").is_none());
+    // Stale per-cell history from an earlier run is dropped.
+    assert!(cells[1]["metadata"].get("ai_blame").is_none());
+}
+
+#[test]
+fn test_comment_detects_block_syntax_and_replaces_existing_block() {
+    let temp = TempDir::new().unwrap();
+    let path = temp.path().join("style.css");
+    fs::write(&path, "body { margin: 0; }\n").unwrap();
+
+    let history = sample_history("style.css");
+    apply_rule(&path, &history, &comment_rule(), false).unwrap();
+    apply_rule(&path, &history, &comment_rule(), false).unwrap();
+
+    let content = fs::read_to_string(&path).unwrap();
+    assert!(content.starts_with("body { margin: 0; }\n"));
+    assert_eq!(content.matches("/* --- edit_history ---").count(), 1);
+    assert!(content.ends_with("--- end edit_history --- */\n"));
+}
+
+#[test]
+fn test_comment_skips_history_that_would_close_block_comment() {
+    let temp = TempDir::new().unwrap();
+    let path = temp.path().join("style.css");
+    fs::write(&path, "body { margin: 0; }\n").unwrap();
+
+    let mut history = sample_history("style.css");
+    history.events[0].description = Some("Strip stray */ from header".to_string());
+    let (changed, message) = apply_rule(&path, &history, &comment_rule(), false).unwrap();
+
+    assert!(!changed);
+    assert!(message.contains("*/"));
+    assert_eq!(fs::read_to_string(&path).unwrap(), "body { margin: 0; }\n");
+}
+
+#[test]
+fn test_comment_renders_template() {
+    let temp = TempDir::new().unwrap();
+    let path = temp.path().join("schema.sql");
+    fs::write(&path, "SELECT 1;\n").unwrap();
+
+    let mut rule = comment_rule();
+    rule.template = Some("{date} {action} by {model} ({agent_tool})".to_string());
+    apply_rule(&path, &sample_history("schema.sql"), &rule, false).unwrap();
+
+    let content = fs::read_to_string(&path).unwrap();
+    assert_eq!(
+        content,
+        "SELECT 1;\n\n-- --- edit_history ---\n\
+         -- 2025-12-01 CREATED by claude-opus-4-5-20251101 (claude-code)\n\
+         -- --- end edit_history ---\n"
+    );
+}
+
+#[test]
+fn test_comment_template_keeps_placeholders_in_values_and_rejects_unknown() {
+    let temp = TempDir::new().unwrap();
+    let path = temp.path().join("schema.sql");
+    fs::write(&path, "SELECT 1;\n").unwrap();
+
+    let mut history = sample_history("schema.sql");
+    history.events[0].description = Some("Document {model} and {date}".to_string());
+    let mut rule = comment_rule();
+    rule.template = Some("{description} ({model})".to_string());
+    apply_rule(&path, &history, &rule, false).unwrap();
+
+    let content = fs::read_to_string(&path).unwrap();
+    assert!(content.contains("-- Document {model} and {date} (claude-opus-4-5-20251101)\n"));
+
+    rule.template = Some("{date} {modle}".to_string());
+    let err = apply_rule(&path, &history, &rule, false).unwrap_err();
+    assert!(err.to_string().contains("{modle}"));
+}
+
+fn placed_rule(placement: CommentPlacement) -> FileRule {
+    FileRule {
+        template: Some("{action} by {model}".to_string()),
+        placement,
+        ..comment_rule()
+    }
+}
+
+#[test]
+fn test_comment_top_placement_skips_shebang_and_encoding_cookie() {
+    let temp = TempDir::new().unwrap();
+    let path = temp.path().join("tool.py");
+    fs::write(
+        &path,
+        "#!/usr/bin/env python\n# -*- coding: utf-8 -*-\nimport sys\n",
+    )
+    .unwrap();
+
+    let history = sample_history("tool.py");
+    let rule = placed_rule(CommentPlacement::Top);
+    apply_rule(&path, &history, &rule, false).unwrap();
+    apply_rule(&path, &history, &rule, false).unwrap();
+
+    let content = fs::read_to_string(&path).unwrap();
+    assert_eq!(
+        content,
+        "#!/usr/bin/env python\n# -*- coding: utf-8 -*-\n\
+         # --- edit_history ---\n\
+         # CREATED by claude-opus-4-5-20251101\n\
+         # --- end edit_history ---\n\
+         \n\
+         import sys\n"
+    );
+}
+
+#[test]
+fn test_comment_top_placement_skips_front_matter() {
+    let temp = TempDir::new().unwrap();
+    let path = temp.path().join("post.md");
+    fs::write(&path, "---\ntitle: Hello\n---\n\n# Hello\n").unwrap();
+
+    let history = sample_history("post.md");
+    let rule = placed_rule(CommentPlacement::Top);
+    apply_rule(&path, &history, &rule, false).unwrap();
+    apply_rule(&path, &history, &rule, false).unwrap();
+
+    let content = fs::read_to_string(&path).unwrap();
+    assert_eq!(
+        content,
+        "---\ntitle: Hello\n---\n\
+         <!-- edit_history\n\
+         CREATED by claude-opus-4-5-20251101\n\
+         -->\n\
+         \n\
+         # Hello\n"
+    );
+}
+
+#[test]
+fn test_comment_after_license_placement_skips_spdx_build_tags_and_package() {
+    let temp = TempDir::new().unwrap();
+    let path = temp.path().join("main.go");
+    fs::write(
+        &path,
+        "//go:build linux\n\n// SPDX-License-Identifier: MIT\n// Copyright 2025 Example\n\npackage main\n\nfunc main() {}\n",
+    )
+    .unwrap();
+
+    let history = sample_history("main.go");
+    let rule = placed_rule(CommentPlacement::AfterLicense);
+    apply_rule(&path, &history, &rule, false).unwrap();
+    apply_rule(&path, &history, &rule, false).unwrap();
+
+    let content = fs::read_to_string(&path).unwrap();
+    assert_eq!(
+        content,
+        "//go:build linux\n\n// SPDX-License-Identifier: MIT\n// Copyright 2025 Example\n\npackage main\n\n\
+         // --- edit_history ---\n\
+         // CREATED by claude-opus-4-5-20251101\n\
+         // --- end edit_history ---\n\
+         \n\
+         func main() {}\n"
+    );
+}
+
+#[test]
+fn test_comment_block_moves_when_placement_changes() {
+    let temp = TempDir::new().unwrap();
+    let path = temp.path().join("lib.rs");
+    fs::write(&path, "pub fn f() {}\n").unwrap();
+
+    let history = sample_history("lib.rs");
+    apply_rule(&path, &history, &comment_rule(), false).unwrap();
+    apply_rule(&path, &history, &placed_rule(CommentPlacement::Top), false).unwrap();
+
+    let content = fs::read_to_string(&path).unwrap();
+    assert!(content.starts_with("// --- edit_history ---\n"));
+    assert!(content.ends_with("// --- end edit_history ---\n\npub fn f() {}\n"));
+    assert_eq!(content.matches("--- edit_history ---").count(), 1);
+}
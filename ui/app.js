@@ -246,6 +246,93 @@ function renderFileList() {
   });
 }
 
+function showBlameDetails(title, meta) {
+  if (blameDetailTitle) blameDetailTitle.textContent = title;
+  if (blameDetailModel) blameDetailModel.textContent = `Model: ${meta?.model || 'Unknown'}`;
+  if (blameDetailTimestamp) blameDetailTimestamp.textContent = `Timestamp: ${meta?.timestamp ?? '—'}`;
+
+  // Make session ID clickable to view transcript
+  if (blameDetailTrace) {
+    const sessionId = meta?.session_id;
+    if (sessionId && sessionId !== '—') {
+      blameDetailTrace.innerHTML = `Session: <a href="#" class="blame-session-link" data-session="${sessionId.replace(/"/g, '&quot;')}" style="color: #4a90e2; text-decoration: none; cursor: pointer;">${sessionId.slice(0, 8)}...</a>`;
+      // Add click handler
+      const link = blameDetailTrace.querySelector('.blame-session-link');
+      if (link) {
+        link.addEventListener('click', (e) => {
+          e.preventDefault();
+          const sid = link.getAttribute('data-session');
+          navigateToTranscript(sid).catch(console.error);
+        });
+      }
+    } else {
+      blameDetailTrace.textContent = `Session: —`;
+    }
+  }
+
+  // Show details panel
+  if (blameDetailsPanel) {
+    blameDetailsPanel.classList.add('is-visible');
+  }
+}
+
+const SPAN_COLOR_COUNT = 6;
+
+// Stable color slot per session so the same session is highlighted alike on every line.
+function spanColorIndex(sessionId) {
+  let hash = 0;
+  for (const ch of sessionId) hash = (hash * 31 + ch.codePointAt(0)) >>> 0;
+  return hash % SPAN_COLOR_COUNT;
+}
+
+// Render a line's text, highlighting spans written by a different session than the bulk
+// of the line (e.g. a renamed argument). Span offsets count characters, not UTF-16 units.
+function renderLineSpans(code, line, row) {
+  const spans = line.spans ?? [];
+  const sessionOf = (span) => span.meta?.session_id ?? null;
+  if (new Set(spans.map(sessionOf)).size < 2) {
+    code.textContent = line.text;
+    return;
+  }
+
+  const widths = new Map();
+  spans.forEach((span) => {
+    const key = sessionOf(span);
+    widths.set(key, (widths.get(key) ?? 0) + (span.end - span.start));
+  });
+  const base = [...widths.entries()].sort((a, b) => b[1] - a[1])[0][0];
+
+  const chars = Array.from(line.text);
+  spans.forEach((span) => {
+    const text = chars.slice(span.start, span.end).join('');
+    const session = sessionOf(span);
+    if (session === base) {
+      code.appendChild(document.createTextNode(text));
+      return;
+    }
+    const mark = document.createElement('mark');
+    mark.className = 'blame-span';
+    if (session) mark.classList.add(`blame-span-${spanColorIndex(session)}`);
+    mark.textContent = text;
+    mark.title = span.meta
+      ? `${span.meta.model} · ${session.slice(0, 8)} · ${span.meta.timestamp}`
+      : 'Unattributed';
+    mark.addEventListener('click', (e) => {
+      e.stopPropagation();
+      selectBlameRow(row);
+      showBlameDetails(`Line ${line.line_no}, columns ${span.start + 1}-${span.end}`, span.meta);
+    });
+    code.appendChild(mark);
+  });
+}
+
+function selectBlameRow(row) {
+  document
+    .querySelectorAll('.code-line.is-selected')
+    .forEach((el) => el.classList.remove('is-selected'));
+  row.classList.add('is-selected');
+}
+
 function renderBlameLines(lines) {
   if (!blameCodeBlock) return;
   blameCodeBlock.textContent = '';
@@ -273,46 +360,15 @@ function renderBlameLines(lines) {
     badge.textContent = line.meta ? model.split(/[\\s-]/)[0] : 'Unknown';
 
     const code = document.createElement('code');
-    code.textContent = line.text;
+    renderLineSpans(code, line, row);
 
     row.appendChild(no);
     row.appendChild(badge);
     row.appendChild(code);
 
     row.addEventListener('click', () => {
-      document
-        .querySelectorAll('.code-line.is-selected')
-        .forEach((el) => el.classList.remove('is-selected'));
-      row.classList.add('is-selected');
-
-      if (blameDetailTitle) blameDetailTitle.textContent = `Line ${line.line_no}`;
-      if (blameDetailModel) blameDetailModel.textContent = `Model: ${model}`;
-      if (blameDetailTimestamp)
-        blameDetailTimestamp.textContent = `Timestamp: ${line.meta?.timestamp ?? '—'}`;
-
-      // Make session ID clickable to view transcript
-      if (blameDetailTrace) {
-        const sessionId = line.meta?.session_id;
-        if (sessionId && sessionId !== '—') {
-          blameDetailTrace.innerHTML = `Session: <a href="#" class="blame-session-link" data-session="${sessionId.replace(/"/g, '&quot;')}" style="color: #4a90e2; text-decoration: none; cursor: pointer;">${sessionId.slice(0, 8)}...</a>`;
-          // Add click handler
-          const link = blameDetailTrace.querySelector('.blame-session-link');
-          if (link) {
-            link.addEventListener('click', (e) => {
-              e.preventDefault();
-              const sid = link.getAttribute('data-session');
-              navigateToTranscript(sid).catch(console.error);
-            });
-          }
-        } else {
-          blameDetailTrace.textContent = `Session: —`;
-        }
-      }
-
-      // Show details panel
-      if (blameDetailsPanel) {
-        blameDetailsPanel.classList.add('is-visible');
-      }
+      selectBlameRow(row);
+      showBlameDetails(`Line ${line.line_no}`, line.meta);
     });
 
     blameCodeBlock.appendChild(row);